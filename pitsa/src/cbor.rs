@@ -0,0 +1,105 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Minimal CBOR encoding.
+//!
+//! Only the subset of [RFC 8949](https://www.rfc-editor.org/rfc/rfc8949)
+//! needed for COSE structures with deterministic encoding of definite length
+//! items.
+
+/// Major types.
+mod major {
+    /// Unsigned integer.
+    pub const UNSIGNED: u8 = 0;
+    /// Negative integer.
+    pub const NEGATIVE: u8 = 1;
+    /// Byte string.
+    pub const BYTES: u8 = 2;
+    /// Text string.
+    pub const TEXT: u8 = 3;
+    /// Array of data items.
+    pub const ARRAY: u8 = 4;
+    /// Map of pairs of data items.
+    pub const MAP: u8 = 5;
+    /// Tagged data item.
+    pub const TAG: u8 = 6;
+}
+
+/// Encode the initial byte and argument of a data item in the shortest form.
+fn head(major_type: u8, argument: u64) -> Vec<u8> {
+    let major_type = major_type << 5;
+    match argument {
+        0..24 => vec![major_type | argument as u8],
+        24..0x100 => vec![major_type | 24, argument as u8],
+        0x100..0x1_0000 => [&[major_type | 25], &(argument as u16).to_be_bytes()[..]].concat(),
+        0x1_0000..0x1_0000_0000 => {
+            [&[major_type | 26], &(argument as u32).to_be_bytes()[..]].concat()
+        }
+        _ => [&[major_type | 27], &argument.to_be_bytes()[..]].concat(),
+    }
+}
+
+/// Encode an unsigned integer.
+pub fn unsigned(value: u64) -> Vec<u8> {
+    head(major::UNSIGNED, value)
+}
+
+/// Encode a signed integer.
+pub fn integer(value: i64) -> Vec<u8> {
+    if value < 0 {
+        head(major::NEGATIVE, (-1 - value) as u64)
+    } else {
+        head(major::UNSIGNED, value as u64)
+    }
+}
+
+/// Encode a byte string.
+pub fn bytes(value: &[u8]) -> Vec<u8> {
+    [head(major::BYTES, value.len() as u64), value.to_vec()].concat()
+}
+
+/// Encode a UTF-8 text string.
+pub fn text(value: &str) -> Vec<u8> {
+    [
+        head(major::TEXT, value.len() as u64),
+        value.as_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// Encode an array of already encoded items.
+pub fn array(items: &[Vec<u8>]) -> Vec<u8> {
+    [head(major::ARRAY, items.len() as u64), items.concat()].concat()
+}
+
+/// Encode a map of already encoded keys and values.
+///
+/// The caller is responsible for providing the entries in deterministic
+/// (bytewise lexicographic key) order.
+pub fn map(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut ret = head(major::MAP, entries.len() as u64);
+    for (key, value) in entries {
+        ret.extend_from_slice(key);
+        ret.extend_from_slice(value);
+    }
+    ret
+}
+
+/// Encode a tagged item.
+pub fn tagged(tag: u64, encoded: &[u8]) -> Vec<u8> {
+    [head(major::TAG, tag), encoded.to_vec()].concat()
+}
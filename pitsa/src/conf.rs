@@ -17,21 +17,35 @@
 
 //! Parsing of application configuration.
 
+mod archive_config;
+mod audit_config;
+mod auth_config;
 mod context_config;
 mod limits_config;
+mod log_config;
 mod rest_api_config;
 mod signer_config;
 mod time_source_config;
+mod tracing_config;
+mod transparency_config;
+mod validation_config;
 
 use config::builder::BuilderState;
 use config::{Config, ConfigBuilder, Environment, File};
 use serde::{Deserialize, Serialize};
 
+use self::archive_config::ArchiveConfig;
+use self::audit_config::AuditConfig;
+use self::auth_config::AuthConfig;
 use self::context_config::ContextConfig;
 use self::limits_config::ResourceLimitsConfig;
+use self::log_config::LogConfig;
 use self::rest_api_config::RestApiConfig;
 use self::signer_config::SignerConfig;
 use self::time_source_config::TimeSourceConfig;
+use self::tracing_config::TracingConfig;
+use self::transparency_config::TransparencyConfig;
+use self::validation_config::ValidationConfig;
 
 /// Package version reported by Cargo at build time.
 const CARGO_PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -70,6 +84,20 @@ pub struct AppConfig {
     pub time: TimeSourceConfig,
     /// Configuration for the signer.
     pub sign: SignerConfig,
+    /// Configuration for validation of time-stamp tokens.
+    pub validation: ValidationConfig,
+    /// Configuration for the token transparency log.
+    pub transparency: TransparencyConfig,
+    /// Configuration for the archive of issued time-stamp tokens.
+    pub archive: ArchiveConfig,
+    /// Configuration for bearer token authentication.
+    pub auth: AuthConfig,
+    /// Configuration for distributed tracing.
+    pub tracing: TracingConfig,
+    /// Configuration for audit events.
+    pub audit: AuditConfig,
+    /// Configuration for logging.
+    pub log: LogConfig,
 
     /// Lower case application name. Ignored when loading configuration.
     #[serde(skip_deserializing)]
@@ -122,13 +150,14 @@ impl AppConfig {
         config_builder = ResourceLimitsConfig::set_defaults(config_builder, "limits");
         config_builder = SignerConfig::set_defaults(config_builder, "sign");
         config_builder = TimeSourceConfig::set_defaults(config_builder, "time");
+        config_builder = ValidationConfig::set_defaults(config_builder, "validation");
+        config_builder = TransparencyConfig::set_defaults(config_builder, "transparency");
+        config_builder = ArchiveConfig::set_defaults(config_builder, "archive");
+        config_builder = AuthConfig::set_defaults(config_builder, "auth");
+        config_builder = TracingConfig::set_defaults(config_builder, "tracing");
+        config_builder = AuditConfig::set_defaults(config_builder, "audit");
+        config_builder = LogConfig::set_defaults(config_builder, "log");
         let conf_file = std::env::current_dir().unwrap().join(config_filename);
-        if log::log_enabled!(log::Level::Debug) {
-            log::debug!(
-                "Will load '{}' configuration if present.",
-                conf_file.display()
-            );
-        }
         let config = config_builder
             .add_source(File::with_name(conf_file.as_os_str().to_str().unwrap()).required(false))
             .add_source(
@@ -141,13 +170,29 @@ impl AppConfig {
             .unwrap();
         let mut app_config: AppConfig = config.try_deserialize().unwrap();
         app_config.app_name = app_name;
-        log::info!("Running with configuration: {app_config:?}");
+        app_config
+    }
+
+    /// Log the configuration.
+    ///
+    /// The configuration is loaded before the logger is initialized, since it
+    /// includes the [log format](LogConfig::format()).
+    pub fn log_configuration(&self) {
+        if log::log_enabled!(log::Level::Debug) {
+            log::debug!(
+                "Loaded '{}.json' configuration if present in '{}'.",
+                self.app_name,
+                std::env::current_dir()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_default()
+            );
+        }
+        log::info!("Running with configuration: {self:?}");
         if log::log_enabled!(log::Level::Trace) {
             log::trace!(
                 "Running with configuration: {}",
-                serde_json::to_string(&app_config).unwrap()
+                serde_json::to_string(self).unwrap()
             );
         }
-        app_config
     }
 }
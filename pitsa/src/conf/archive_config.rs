@@ -0,0 +1,126 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for the archive of issued time-stamp tokens.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+
+/// Configuration for the archive of issued time-stamp tokens.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    /// See [directory()](Self::directory()).
+    directory: String,
+    /// See [retention_micros()](Self::retention_micros()).
+    retention: u64,
+    /// See [max_size_bytes()](Self::max_size_bytes()).
+    maxsize: u64,
+    /// See [segment_size_bytes()](Self::segment_size_bytes()).
+    segmentsize: u64,
+    /// See [admins()](Self::admins()).
+    admins: String,
+    /// See [reject_on_failure()](Self::reject_on_failure()).
+    onfailure: String,
+}
+
+impl AppConfigDefaults for ArchiveConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "directory", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "retention", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "maxsize", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "segmentsize", "67108864")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "admins", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "onfailure", "reject")
+            .unwrap()
+    }
+}
+
+impl ArchiveConfig {
+    /// Return the directory where issued tokens are archived or `None` if the
+    /// archive is disabled.
+    pub fn directory(&self) -> Option<std::path::PathBuf> {
+        Some(self.directory.trim())
+            .filter(|directory| !directory.is_empty())
+            .map(std::path::PathBuf::from)
+    }
+
+    /// Return how long archived tokens are kept in microseconds or `None` if
+    /// they are kept forever.
+    ///
+    /// The configured value is in seconds. Whole segments are removed when
+    /// their newest token is older.
+    pub fn retention_micros(&self) -> Option<u64> {
+        Some(self.retention)
+            .filter(|retention| *retention > 0)
+            .map(|retention| retention.saturating_mul(1_000_000))
+    }
+
+    /// Return the largest total size of the archive in bytes or `None` if
+    /// unlimited.
+    ///
+    /// The oldest segments are removed when the limit is exceeded.
+    pub fn max_size_bytes(&self) -> Option<u64> {
+        Some(self.maxsize).filter(|maxsize| *maxsize > 0)
+    }
+
+    /// Return the size in bytes after which a new archive segment file is
+    /// started.
+    pub fn segment_size_bytes(&self) -> u64 {
+        std::cmp::max(self.segmentsize, 64 * 1024)
+    }
+
+    /// Return the client identities (subject alternative name or subject
+    /// `commonName` of a TLS client certificate, or bearer token identity)
+    /// allowed to look up and export archived tokens.
+    ///
+    /// Configured as a comma separated list. Empty (the default) refuses all
+    /// clients.
+    pub fn admins(&self) -> Vec<String> {
+        self.admins
+            .split(',')
+            .map(str::trim)
+            .filter(|admin| !admin.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Return `true` if a time-stamp that could not be archived is refused
+    /// (`reject`, the default) instead of only logging the failure (`log`).
+    pub fn reject_on_failure(&self) -> bool {
+        match self.onfailure.trim() {
+            "log" => false,
+            "reject" => true,
+            other => {
+                log::warn!("Unknown archive failure policy '{other}'. Using 'reject'.");
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for audit events.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+use crate::logging::AuditSink;
+
+/// Configuration for audit events.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditConfig {
+    /// See [sink()](Self::sink()).
+    sink: String,
+    /// See [sink()](Self::sink()).
+    path: String,
+    /// See [sink()](Self::sink()).
+    syslog: String,
+}
+
+impl AppConfigDefaults for AuditConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "sink", "none")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "path", "audit.log")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "syslog", "/dev/log")
+            .unwrap()
+    }
+}
+
+impl AuditConfig {
+    /// Where to write audit events in addition to the log.
+    ///
+    /// * `none` (default) only writes audit events to the log.
+    /// * `file` appends one JSON object per line to `path`. Defaults to
+    ///   `audit.log` in the current working directory.
+    /// * `syslog` sends RFC 5424 messages to `syslog`, either a local datagram
+    ///   socket path or a UDP `host:port`. Defaults to `/dev/log`.
+    pub fn sink(&self) -> AuditSink {
+        match self.sink.trim().to_lowercase().as_str() {
+            "none" | "" => AuditSink::None,
+            "file" => AuditSink::File {
+                path: self.path.trim().to_string(),
+            },
+            "syslog" => AuditSink::Syslog {
+                address: self.syslog.trim().to_string(),
+            },
+            other => {
+                log::warn!("Unknown audit sink '{other}'. Audit events are only logged.");
+                AuditSink::None
+            }
+        }
+    }
+}
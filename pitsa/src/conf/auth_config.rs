@@ -0,0 +1,105 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for bearer token authentication.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+
+/// Configuration for bearer token authentication.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// See [api_keys_path()](Self::api_keys_path()).
+    apikeys: String,
+    /// See [jwks_location()](Self::jwks_location()).
+    jwks: String,
+    /// See [issuer()](Self::issuer()).
+    issuer: String,
+    /// See [audience()](Self::audience()).
+    audience: String,
+    /// See [identity_claim()](Self::identity_claim()).
+    claim: String,
+    /// See [refresh_interval_micros()](Self::refresh_interval_micros()).
+    refresh: u64,
+}
+
+impl AppConfigDefaults for AuthConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "apikeys", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "jwks", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "issuer", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "audience", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "claim", "sub")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "refresh", "300")
+            .unwrap()
+    }
+}
+
+impl AuthConfig {
+    /// Path of the JSON file with hashed API keys or `None` (the default) to
+    /// not accept API keys.
+    pub fn api_keys_path(&self) -> Option<String> {
+        Self::non_empty(&self.apikeys)
+    }
+
+    /// Path or HTTPS URL of the JSON Web Key Set of trusted JWT signers or
+    /// `None` (the default) to not accept JWTs.
+    pub fn jwks_location(&self) -> Option<String> {
+        Self::non_empty(&self.jwks)
+    }
+
+    /// Required JWT `iss` claim. Must be set when a JWKS is configured.
+    pub fn issuer(&self) -> Option<String> {
+        Self::non_empty(&self.issuer)
+    }
+
+    /// Required JWT `aud` claim value. Must be set when a JWKS is configured.
+    pub fn audience(&self) -> Option<String> {
+        Self::non_empty(&self.audience)
+    }
+
+    /// JWT claim holding the client identity. Defaults to `sub`.
+    pub fn identity_claim(&self) -> String {
+        self.claim.trim().to_string()
+    }
+
+    /// How often to reload API keys and the JWKS in microseconds. Configured
+    /// in seconds and defaults to `300`.
+    pub fn refresh_interval_micros(&self) -> u64 {
+        std::cmp::max(self.refresh, 1) * 1_000_000
+    }
+
+    /// Return the trimmed value or `None` if empty.
+    fn non_empty(value: &str) -> Option<String> {
+        Some(value.trim())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+}
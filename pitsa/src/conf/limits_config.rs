@@ -22,6 +22,9 @@ use config::builder::BuilderState;
 use serde::{Deserialize, Serialize};
 
 use super::AppConfigDefaults;
+use crate::RateLimit;
+use crate::RateLimitMode;
+use std::net::IpAddr;
 
 /// Resource limitations override configuration.
 #[derive(Debug, Deserialize, Serialize)]
@@ -30,6 +33,22 @@ pub struct ResourceLimitsConfig {
     cpus: f64,
     /// See [memory_bytes()](Self::memory_bytes()).
     memory: Option<u64>,
+    /// See [global_rate_limit()](Self::global_rate_limit()).
+    globalrate: f64,
+    /// See [global_rate_limit()](Self::global_rate_limit()).
+    globalburst: f64,
+    /// See [ip_address_rate_limit()](Self::ip_address_rate_limit()).
+    iprate: f64,
+    /// See [ip_address_rate_limit()](Self::ip_address_rate_limit()).
+    ipburst: f64,
+    /// See [client_rate_limit()](Self::client_rate_limit()).
+    clientrate: f64,
+    /// See [client_rate_limit()](Self::client_rate_limit()).
+    clientburst: f64,
+    /// See [rate_limit_mode()](Self::rate_limit_mode()).
+    ratemode: String,
+    /// See [trusted_proxies()](Self::trusted_proxies()).
+    trustedproxies: String,
 }
 
 impl AppConfigDefaults for ResourceLimitsConfig {
@@ -87,6 +106,22 @@ impl AppConfigDefaults for ResourceLimitsConfig {
         config_builder
             .set_default(prefix.to_string() + "." + "cpus", format!("{cpus}"))
             .unwrap()
+            .set_default(prefix.to_string() + "." + "globalrate", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "globalburst", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "iprate", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "ipburst", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "clientrate", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "clientburst", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "ratemode", "http")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "trustedproxies", "")
+            .unwrap()
     }
 }
 
@@ -111,4 +146,62 @@ impl ResourceLimitsConfig {
     pub fn memory_bytes(&self) -> Option<u64> {
         self.memory
     }
+
+    /// Requests per second and burst size over all clients. A rate of `0`
+    /// (the default) is unlimited and a burst of `0` defaults to the rate.
+    pub fn global_rate_limit(&self) -> Option<RateLimit> {
+        Self::rate_limit(self.globalrate, self.globalburst)
+    }
+
+    /// Requests per second and burst size per client IP address. A rate of
+    /// `0` (the default) is unlimited and a burst of `0` defaults to the rate.
+    pub fn ip_address_rate_limit(&self) -> Option<RateLimit> {
+        Self::rate_limit(self.iprate, self.ipburst)
+    }
+
+    /// Requests per second and burst size per authenticated client identity.
+    /// A rate of `0` (the default) is unlimited and a burst of `0` defaults to
+    /// the rate.
+    pub fn client_rate_limit(&self) -> Option<RateLimit> {
+        Self::rate_limit(self.clientrate, self.clientburst)
+    }
+
+    /// How requests over a rate limit are answered: `http` (the default) for
+    /// HTTP status 429 or `tsp` for a `TimeStampResp` with status `waiting`.
+    pub fn rate_limit_mode(&self) -> RateLimitMode {
+        match self.ratemode.trim().to_lowercase().as_str() {
+            "tsp" => RateLimitMode::Tsp,
+            "http" => RateLimitMode::Http,
+            other => {
+                log::warn!("Unknown rate limit mode '{other}'. Will use 'http'.");
+                RateLimitMode::Http
+            }
+        }
+    }
+
+    /// Comma separated IP addresses of reverse proxies that are trusted to
+    /// report the client IP address in the `X-Forwarded-For` header. Empty
+    /// (the default) limits per peer address, which only works when clients
+    /// connect directly.
+    pub fn trusted_proxies(&self) -> Vec<IpAddr> {
+        self.trustedproxies
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .filter_map(|value| {
+                value
+                    .parse::<IpAddr>()
+                    .map_err(|e| log::warn!("Ignoring trusted proxy '{value}': {e}"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Return the rate limit or `None` if unlimited.
+    fn rate_limit(rate: f64, burst: f64) -> Option<RateLimit> {
+        (rate > 0.0).then(|| RateLimit {
+            rate,
+            burst: if burst >= 1.0 { burst } else { rate.max(1.0) },
+        })
+    }
 }
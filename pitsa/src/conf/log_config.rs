@@ -0,0 +1,64 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for logging.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+use crate::logging::LogFormat;
+
+/// Configuration for logging.
+///
+/// The log level is configured with `LOG_LEVEL` as supported by `env_logger`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogConfig {
+    /// See [format()](Self::format()).
+    format: String,
+}
+
+impl AppConfigDefaults for LogConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "format", "text")
+            .unwrap()
+    }
+}
+
+impl LogConfig {
+    /// Format of log lines.
+    ///
+    /// * `text` (default) for plain text lines.
+    /// * `json` for one JSON object per line.
+    pub fn format(&self) -> LogFormat {
+        match self.format.trim().to_lowercase().as_str() {
+            "json" => LogFormat::Json,
+            "text" | "" => LogFormat::Text,
+            other => {
+                // The logger is not initialized yet.
+                eprintln!("Unknown log format '{other}'. Will use 'text'.");
+                LogFormat::Text
+            }
+        }
+    }
+}
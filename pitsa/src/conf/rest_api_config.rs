@@ -20,6 +20,7 @@
 use config::ConfigBuilder;
 use config::builder::BuilderState;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 use super::AppConfigDefaults;
 
@@ -30,6 +31,18 @@ pub struct RestApiConfig {
     address: String,
     /// See [bind_port()](Self::bind_port()).
     port: u16,
+    /// See [tcp_bind_port()](Self::tcp_bind_port()).
+    tcpport: u16,
+    /// See [tls_pem_paths()](Self::tls_pem_paths()).
+    tlscert: String,
+    /// See [tls_pem_paths()](Self::tls_pem_paths()).
+    tlskey: String,
+    /// See [tls_reload_interval_micros()](Self::tls_reload_interval_micros()).
+    tlsreload: u64,
+    /// See [tls_client_ca_path()](Self::tls_client_ca_path()).
+    tlsclientca: String,
+    /// See [client_rules_path()](Self::client_rules_path()).
+    clients: String,
 }
 
 impl AppConfigDefaults for RestApiConfig {
@@ -43,6 +56,18 @@ impl AppConfigDefaults for RestApiConfig {
             .unwrap()
             .set_default(prefix.to_string() + "." + "port", "8080")
             .unwrap()
+            .set_default(prefix.to_string() + "." + "tcpport", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "tlscert", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "tlskey", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "tlsreload", "60")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "tlsclientca", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "clients", "")
+            .unwrap()
     }
 }
 
@@ -56,4 +81,49 @@ impl RestApiConfig {
     pub fn bind_port(&self) -> u16 {
         self.port
     }
+
+    /// IP port to bind the RFC 3161 TCP-based transport to. Defaults to `0`
+    /// which disables this transport. (Port 318 is assigned to this protocol,
+    /// but requires privileges to bind to.)
+    pub fn tcp_bind_port(&self) -> Option<u16> {
+        Some(self.tcpport).filter(|port| *port != 0)
+    }
+
+    /// Paths of the PEM encoded TLS certificate chain (leaf first) and
+    /// private key files. Returns `None` unless both are configured, which
+    /// serves plaintext HTTP/1.1 and h2c.
+    pub fn tls_pem_paths(&self) -> Option<(PathBuf, PathBuf)> {
+        let certificate_path = self.tlscert.trim();
+        let private_key_path = self.tlskey.trim();
+        (!certificate_path.is_empty() && !private_key_path.is_empty()).then(|| {
+            (
+                PathBuf::from(certificate_path),
+                PathBuf::from(private_key_path),
+            )
+        })
+    }
+
+    /// How often to check the TLS PEM files for changes in microseconds.
+    /// Configured in seconds and defaults to `60`.
+    pub fn tls_reload_interval_micros(&self) -> u64 {
+        std::cmp::max(self.tlsreload, 1) * 1_000_000
+    }
+
+    /// Path of the PEM encoded CA certificates that TLS client certificates
+    /// are required to chain to. Returns `None` (the default) to not request
+    /// client certificates.
+    pub fn tls_client_ca_path(&self) -> Option<PathBuf> {
+        Some(self.tlsclientca.trim())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Path of the JSON file that maps TLS client identities to allowed
+    /// policies, digest algorithms and quotas. Returns `None` (the default)
+    /// to allow any client.
+    pub fn client_rules_path(&self) -> Option<PathBuf> {
+        Some(self.clients.trim())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
 }
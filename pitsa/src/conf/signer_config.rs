@@ -39,6 +39,16 @@ pub struct SignerConfig {
     digest: String,
     /// See [enrollment_provider_options()](Self::enrollment_provider_options()).
     enprov: Option<String>,
+    /// See [qualified_policy_oids()](Self::qualified_policy_oids()).
+    qualified: String,
+    /// See [correlation_id_extension_oid()](Self::correlation_id_extension_oid()).
+    correlationoid: String,
+    /// See [archive_hint_extension_oid()](Self::archive_hint_extension_oid()).
+    archivehintoid: String,
+    /// See [aggregation_window_micros()](Self::aggregation_window_micros()).
+    aggregation: u64,
+    /// See [aggregation_proof_attribute_oid()](Self::aggregation_proof_attribute_oid()).
+    aggregationoid: String,
 }
 
 impl std::fmt::Debug for SignerConfig {
@@ -49,6 +59,11 @@ impl std::fmt::Debug for SignerConfig {
             .field("digest", &self.digest)
             .field("enprov", &self.enprov)
             .field("enprov_options", &self.enrollment_provider_options())
+            .field("qualified", &self.qualified)
+            .field("correlationoid", &self.correlationoid)
+            .field("archivehintoid", &self.archivehintoid)
+            .field("aggregation", &self.aggregation)
+            .field("aggregationoid", &self.aggregationoid)
             .finish()
     }
 }
@@ -78,6 +93,16 @@ impl AppConfigDefaults for SignerConfig {
             .unwrap()
             .set_default(prefix.to_string() + "." + "enprov", "")
             .unwrap()
+            .set_default(prefix.to_string() + "." + "qualified", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "correlationoid", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "archivehintoid", "")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "aggregation", "0")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "aggregationoid", "")
+            .unwrap()
     }
 }
 
@@ -92,6 +117,93 @@ impl SignerConfig {
             .unwrap_or(vec![2, 5, 29, 32, 0])
     }
 
+    /// Return the comma separated TSA policy OIDs under which issued
+    /// time-stamps are marked as qualified electronic time-stamps (ETSI EN
+    /// 319 422).
+    ///
+    /// When any is configured, the TSU certificate must have the matching
+    /// qcStatements and key usage or the service will not start.
+    pub fn qualified_policy_oids(&self) -> Vec<String> {
+        self.qualified
+            .split(',')
+            .map(str::trim)
+            .filter(|oid| !oid.is_empty())
+            .filter_map(|oid| {
+                tyst::encdec::oid::from_string(oid)
+                    .map_err(|e| {
+                        log::warn!("Unable to parse configured qualified policy '{oid}' as OID. It is ignored. Error was: {e:?}");
+                    })
+                    .ok()
+                    .map(|oid| tyst::encdec::oid::as_string(&oid))
+            })
+            .collect()
+    }
+
+    /// Return the OID of the request extension that carries a client correlation
+    /// identifier that should be echoed in the response.
+    ///
+    /// An empty string disables this extension.
+    pub fn correlation_id_extension_oid(&self) -> Option<String> {
+        let oid = self.correlationoid.trim();
+        if oid.is_empty() {
+            return None;
+        }
+        tyst::encdec::oid::from_string(oid)
+            .map_err(|e| {
+                log::warn!("Unable to parse configured correlation identifier extension '{oid}' as OID. Extension is disabled. Error was: {e:?}");
+            })
+            .ok()
+            .map(|_| oid.to_string())
+    }
+
+    /// Return the OID of the request extension that carries an archive
+    /// revocation info hint.
+    ///
+    /// There is no standardized `TimeStampReq` extension for this hint
+    /// (`adbe-archiveRevInfo` is a certificate extension), so the OID that the
+    /// signing clients send must be configured. An empty string disables this
+    /// extension.
+    pub fn archive_hint_extension_oid(&self) -> Option<String> {
+        let oid = self.archivehintoid.trim();
+        if oid.is_empty() {
+            return None;
+        }
+        tyst::encdec::oid::from_string(oid)
+            .map_err(|e| {
+                log::warn!("Unable to parse configured archive hint extension '{oid}' as OID. Extension is disabled. Error was: {e:?}");
+            })
+            .ok()
+            .map(|_| oid.to_string())
+    }
+
+    /// Return the window in microseconds during which aggregated time-stamp
+    /// requests are collected into a single Merkle tree before the root is
+    /// signed.
+    ///
+    /// The tree uses the [digest algorithm](Self::digest_algorithm_oid()) of
+    /// the time-stamp signature. `0` disables aggregation.
+    pub fn aggregation_window_micros(&self) -> Option<u64> {
+        Some(self.aggregation).filter(|window_micros| *window_micros > 0)
+    }
+
+    /// Return the OID of the unsigned `SignerInfo` attribute that carries the
+    /// Merkle inclusion proof in aggregated time-stamp tokens.
+    ///
+    /// There is no standardized attribute for this, so an OID under the
+    /// operator's own arc must be configured. An empty string disables
+    /// aggregation.
+    pub fn aggregation_proof_attribute_oid(&self) -> Option<Vec<u32>> {
+        let oid = self.aggregationoid.trim();
+        if oid.is_empty() {
+            return None;
+        }
+        tyst::encdec::oid::from_string(oid)
+            .map_err(|e| {
+                log::warn!("Unable to parse configured aggregation proof attribute '{oid}' as OID. Aggregation is disabled. Error was: {e:?}");
+            })
+            .ok()
+    }
+
     /// Return a list of OID strings with allowed message imprint digest
     /// algorithms.
     ///
@@ -0,0 +1,110 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for distributed tracing.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+use crate::TraceExporter;
+
+/// Configuration for distributed tracing.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TracingConfig {
+    /// See [exporter()](Self::exporter()).
+    exporter: String,
+    /// See [exporter()](Self::exporter()).
+    endpoint: String,
+    /// See [service_name()](Self::service_name()).
+    servicename: String,
+    /// See [sample_ratio()](Self::sample_ratio()).
+    sampleratio: f64,
+    /// See [export_interval_micros()](Self::export_interval_micros()).
+    interval: u64,
+}
+
+impl AppConfigDefaults for TracingConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "exporter", "none")
+            .unwrap()
+            .set_default(
+                prefix.to_string() + "." + "endpoint",
+                "http://localhost:4318/v1/traces",
+            )
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "servicename", "pitsa")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "sampleratio", "1")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "interval", "5")
+            .unwrap()
+    }
+}
+
+impl TracingConfig {
+    /// Where to send finished spans.
+    ///
+    /// * `none` (default) disables tracing.
+    /// * `log` writes each finished span to the log.
+    /// * `otlp` sends spans as OTLP/HTTP JSON to the `endpoint` URL. Defaults
+    ///   to `http://localhost:4318/v1/traces`.
+    pub fn exporter(&self) -> TraceExporter {
+        match self.exporter.trim().to_lowercase().as_str() {
+            "none" | "" => TraceExporter::None,
+            "log" => TraceExporter::Log,
+            "otlp" => TraceExporter::Otlp {
+                endpoint: self.endpoint.trim().to_string(),
+            },
+            other => {
+                log::warn!("Unknown trace exporter '{other}'. Tracing is disabled.");
+                TraceExporter::None
+            }
+        }
+    }
+
+    /// Value of the `service.name` resource attribute of exported spans.
+    /// Defaults to `pitsa`.
+    pub fn service_name(&self) -> String {
+        self.servicename.trim().to_string()
+    }
+
+    /// Ratio of new traces that are sampled from `0` (none) to `1` (all, the
+    /// default). Traces with a remote parent follow the parent's decision.
+    pub fn sample_ratio(&self) -> f64 {
+        if !(0.0..=1.0).contains(&self.sampleratio) {
+            log::warn!(
+                "Trace sample ratio {} is not between 0 and 1. Will use 1.",
+                self.sampleratio
+            );
+            return 1.0;
+        }
+        self.sampleratio
+    }
+
+    /// How often to export finished spans in microseconds. Configured in
+    /// seconds and defaults to `5`.
+    pub fn export_interval_micros(&self) -> u64 {
+        std::cmp::max(self.interval, 1) * 1_000_000
+    }
+}
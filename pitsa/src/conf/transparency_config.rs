@@ -0,0 +1,66 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for the token transparency log.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+
+/// Configuration for the token transparency log.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransparencyConfig {
+    /// See [enabled()](Self::enabled()).
+    enabled: bool,
+    /// See [file()](Self::file()).
+    file: String,
+}
+
+impl AppConfigDefaults for TransparencyConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "enabled", "false")
+            .unwrap()
+            .set_default(prefix.to_string() + "." + "file", "")
+            .unwrap()
+    }
+}
+
+impl TransparencyConfig {
+    /// Return `true` if every issued time-stamp token should be appended to
+    /// the Merkle tree transparency log of this instance.
+    ///
+    /// The log is kept in memory and grows by roughly two hashes and the log
+    /// entry for each issued token.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Return the file where log entries are persisted or `None` if the log
+    /// starts over (with a new log identifier) on every start.
+    pub fn file(&self) -> Option<std::path::PathBuf> {
+        Some(self.file.trim())
+            .filter(|file| !file.is_empty())
+            .map(std::path::PathBuf::from)
+    }
+}
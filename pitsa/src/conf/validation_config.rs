@@ -0,0 +1,74 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Parsing of configuration for validation of time-stamp tokens.
+
+use config::ConfigBuilder;
+use config::builder::BuilderState;
+use serde::{Deserialize, Serialize};
+
+use super::AppConfigDefaults;
+
+/// Configuration for validation of time-stamp tokens.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ValidationConfig {
+    /// See [trust_anchors()](Self::trust_anchors()).
+    trustanchors: String,
+}
+
+impl AppConfigDefaults for ValidationConfig {
+    /// Provide defaults for this part of the configuration
+    fn set_defaults<T: BuilderState>(
+        config_builder: ConfigBuilder<T>,
+        prefix: &str,
+    ) -> ConfigBuilder<T> {
+        config_builder
+            .set_default(prefix.to_string() + "." + "trustanchors", "")
+            .unwrap()
+    }
+}
+
+impl ValidationConfig {
+    /// Return the DER encoded trust anchor certificates loaded from the
+    /// configured PEM file.
+    ///
+    /// When no file is configured, the root of the current TSU certificate
+    /// chain is used as the only trust anchor.
+    pub fn trust_anchors(&self) -> Option<Vec<Vec<u8>>> {
+        let filename = self.trustanchors.trim();
+        if filename.is_empty() {
+            return None;
+        }
+        let full_filename = std::path::PathBuf::from(filename);
+        if log::log_enabled!(log::Level::Debug) {
+            log::debug!("Loading '{}'.", full_filename.display());
+        }
+        match std::fs::read_to_string(&full_filename) {
+            Ok(content) => {
+                let trust_anchors = crate::der::from_pem("CERTIFICATE", &content);
+                if trust_anchors.is_empty() {
+                    log::warn!("No certificates found in '{}'.", full_filename.display());
+                }
+                Some(trust_anchors)
+            }
+            Err(e) => {
+                log::warn!("Failed to read '{}': {e}", full_filename.display());
+                Some(vec![])
+            }
+        }
+    }
+}
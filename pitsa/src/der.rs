@@ -0,0 +1,904 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Minimal DER encoding and decoding.
+//!
+//! Used for the few ASN.1 structures that are not (yet) provided by
+//! [`upkit-common`](https://github.com/mydriatech/upkit-common).
+
+pub mod x509;
+
+/// Universal and commonly used tags.
+pub mod tag {
+    /// `BOOLEAN`
+    pub const BOOLEAN: u8 = 0x01;
+    /// `INTEGER`
+    pub const INTEGER: u8 = 0x02;
+    /// `BIT STRING`
+    pub const BIT_STRING: u8 = 0x03;
+    /// `OCTET STRING`
+    pub const OCTET_STRING: u8 = 0x04;
+    /// `NULL`
+    pub const NULL: u8 = 0x05;
+    /// `OBJECT IDENTIFIER`
+    pub const OID: u8 = 0x06;
+    /// `ENUMERATED`
+    pub const ENUMERATED: u8 = 0x0a;
+    /// `UTF8String`
+    pub const UTF8_STRING: u8 = 0x0c;
+    /// `IA5String`
+    pub const IA5_STRING: u8 = 0x16;
+    /// `UTCTime`
+    pub const UTC_TIME: u8 = 0x17;
+    /// `GeneralizedTime`
+    pub const GENERALIZED_TIME: u8 = 0x18;
+    /// `SEQUENCE` and `SEQUENCE OF`
+    pub const SEQUENCE: u8 = 0x30;
+    /// `SET` and `SET OF`
+    pub const SET: u8 = 0x31;
+
+    /// Context specific constructed tag `[n]`.
+    pub const fn context_constructed(n: u8) -> u8 {
+        0xa0 | n
+    }
+
+    /// Context specific primitive tag `[n]`.
+    pub const fn context_primitive(n: u8) -> u8 {
+        0x80 | n
+    }
+}
+
+/// Failure to decode DER.
+#[derive(Debug)]
+pub struct DerError(String);
+
+impl DerError {
+    /// Return a new instance.
+    pub fn new(msg: &str) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl std::fmt::Display for DerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DER decoding failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for DerError {}
+
+/// Seconds since the Unix epoch of 2050-01-01T00:00:00Z, the first point in
+/// time that can't be encoded as `UTCTime`.
+const UTC_TIME_END_EPOCH_SECONDS: u64 = 2_524_608_000;
+
+/// Encode a tag-length-value.
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(content.len() + 6);
+    ret.push(tag);
+    let len = content.len();
+    if len < 0x80 {
+        ret.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        ret.push(0x80 | (len_bytes.len() - skip) as u8);
+        ret.extend_from_slice(&len_bytes[skip..]);
+    }
+    ret.extend_from_slice(content);
+    ret
+}
+
+/// Encode a `SEQUENCE` of already encoded items.
+pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(tag::SEQUENCE, &items.concat())
+}
+
+/// Encode a `SET OF` already encoded items in DER canonical order.
+pub fn set_of(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut items = items.to_vec();
+    items.sort();
+    tlv(tag::SET, &items.concat())
+}
+
+/// Encode an `OBJECT IDENTIFIER`.
+///
+/// Object identifiers from clients must be parsed with [oid_from_string()],
+/// which rejects arcs that can't be encoded (X.690 8.19.4).
+pub fn oid(oid: &[u32]) -> Vec<u8> {
+    let mut content = vec![];
+    let mut arcs = oid.iter().copied().map(u64::from);
+    let first = arcs.next().unwrap_or(0);
+    let second = arcs.next().unwrap_or(0);
+    // 32 bit arcs can't overflow in 64 bits
+    let first_subidentifier = first * 40 + second;
+    for arc in std::iter::once(first_subidentifier).chain(arcs) {
+        let mut base128 = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            base128.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        base128.reverse();
+        content.extend_from_slice(&base128);
+    }
+    tlv(tag::OID, &content)
+}
+
+/// Parse an object identifier in dot notation.
+///
+/// The first arc must be 0, 1 or 2 and the second arc must be below 40 under
+/// the first arcs 0 and 1. The first two arcs are encoded as one
+/// subidentifier (`first * 40 + second`), which must fit into 32 bits like
+/// every other arc.
+pub fn oid_from_string(value: &str) -> Result<Vec<u32>, String> {
+    let arcs = tyst::encdec::oid::from_string(value)
+        .map_err(|e| format!("'{value}' is not an object identifier: {e:?}"))?;
+    let [first, second, ..] = arcs[..] else {
+        return Err(format!("'{value}' has less than two arcs."));
+    };
+    if first > 2 {
+        return Err(format!("The first arc of '{value}' must be 0, 1 or 2."));
+    }
+    if first < 2 && second >= 40 {
+        return Err(format!("The second arc of '{value}' must be below 40."));
+    }
+    first
+        .checked_mul(40)
+        .and_then(|first| first.checked_add(second))
+        .ok_or(format!("The first two arcs of '{value}' are too large."))?;
+    Ok(arcs)
+}
+
+/// Encode an `INTEGER` from an unsigned value.
+pub fn integer(value: u64) -> Vec<u8> {
+    unsigned_integer(&value.to_be_bytes())
+}
+
+/// Encode an `INTEGER` from unsigned big-endian bytes.
+pub fn unsigned_integer(value: &[u8]) -> Vec<u8> {
+    let skip = value.iter().take_while(|b| **b == 0).count();
+    let mut content = value[skip..].to_vec();
+    if content.first().is_none_or(|b| b & 0x80 != 0) {
+        content.insert(0, 0);
+    }
+    tlv(tag::INTEGER, &content)
+}
+
+/// Encode a `BOOLEAN`.
+pub fn boolean(value: bool) -> Vec<u8> {
+    tlv(tag::BOOLEAN, &[if value { 0xff } else { 0x00 }])
+}
+
+/// Encode a `NULL`.
+pub fn null() -> Vec<u8> {
+    tlv(tag::NULL, &[])
+}
+
+/// Encode an `OCTET STRING`.
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    tlv(tag::OCTET_STRING, value)
+}
+
+/// Encode a `BIT STRING` without unused bits.
+pub fn bit_string(value: &[u8]) -> Vec<u8> {
+    tlv(tag::BIT_STRING, &[&[0u8][..], value].concat())
+}
+
+/// Encode a named `BIT STRING` with the bits at the provided positions set.
+pub fn named_bit_string(bits: &[usize]) -> Vec<u8> {
+    let Some(highest) = bits.iter().max() else {
+        return tlv(tag::BIT_STRING, &[0]);
+    };
+    let mut octets = vec![0u8; highest / 8 + 1];
+    for bit in bits {
+        octets[bit / 8] |= 0x80 >> (bit % 8);
+    }
+    let unused = 7 - (highest % 8) as u8;
+    tlv(tag::BIT_STRING, &[&[unused][..], &octets].concat())
+}
+
+/// Encode a `UTF8String`.
+pub fn utf8_string(value: &str) -> Vec<u8> {
+    tlv(tag::UTF8_STRING, value.as_bytes())
+}
+
+/// Encode an `IA5String`.
+pub fn ia5_string(value: &str) -> Vec<u8> {
+    tlv(tag::IA5_STRING, value.as_bytes())
+}
+
+/// Encode a `GeneralizedTime` with microsecond precision (trailing zeros
+/// removed as required by DER).
+pub fn generalized_time(epoch_micros: u64) -> Vec<u8> {
+    let (year, month, day, hour, minute, second) =
+        civil_from_epoch_seconds(epoch_micros / 1_000_000);
+    let mut value = format!("{year:04}{month:02}{day:02}{hour:02}{minute:02}{second:02}");
+    let fraction = format!("{:06}", epoch_micros % 1_000_000);
+    let fraction = fraction.trim_end_matches('0');
+    if !fraction.is_empty() {
+        value.push('.');
+        value.push_str(fraction);
+    }
+    value.push('Z');
+    tlv(tag::GENERALIZED_TIME, value.as_bytes())
+}
+
+/// Format microseconds since the Unix epoch as an RFC 3339 UTC timestamp.
+pub fn rfc3339(epoch_micros: u64) -> String {
+    let (year, month, day, hour, minute, second) =
+        civil_from_epoch_seconds(epoch_micros / 1_000_000);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{:06}Z",
+        epoch_micros % 1_000_000
+    )
+}
+
+/// Encode a `UTCTime` with second precision.
+///
+/// Only years 1950 to 2049 can be represented.
+fn utc_time(epoch_seconds: u64) -> Vec<u8> {
+    let (year, month, day, hour, minute, second) = civil_from_epoch_seconds(epoch_seconds);
+    let value = format!(
+        "{:02}{month:02}{day:02}{hour:02}{minute:02}{second:02}Z",
+        year % 100
+    );
+    tlv(tag::UTC_TIME, value.as_bytes())
+}
+
+/// Encode a `Time` with second precision: A `UTCTime` before the year 2050
+/// and a `GeneralizedTime` from then on
+/// ([RFC 5280 4.1.2.5](https://www.rfc-editor.org/rfc/rfc5280#section-4.1.2.5),
+/// [RFC 5652 11.3](https://www.rfc-editor.org/rfc/rfc5652#section-11.3)).
+pub fn time(epoch_seconds: u64) -> Vec<u8> {
+    if epoch_seconds < UTC_TIME_END_EPOCH_SECONDS {
+        utc_time(epoch_seconds)
+    } else {
+        generalized_time(epoch_seconds * 1_000_000)
+    }
+}
+
+/// Encode an EXPLICIT context specific tag `[n]` around an encoded value.
+pub fn explicit(n: u8, encoded: &[u8]) -> Vec<u8> {
+    tlv(tag::context_constructed(n), encoded)
+}
+
+/// Replace the tag of an encoded value with an IMPLICIT context specific tag
+/// `[n]`, keeping the constructed bit of the original.
+pub fn implicit(n: u8, encoded: &[u8]) -> Vec<u8> {
+    let mut ret = encoded.to_vec();
+    if let Some(first) = ret.first_mut() {
+        *first = if *first & 0x20 != 0 {
+            tag::context_constructed(n)
+        } else {
+            tag::context_primitive(n)
+        };
+    }
+    ret
+}
+
+/// Encode an `AlgorithmIdentifier` without parameters.
+pub fn algorithm_identifier(algorithm_oid: &[u32]) -> Vec<u8> {
+    sequence(&[oid(algorithm_oid)])
+}
+
+/// Convert a DER encoded `ECDSA-Sig-Value` into the fixed size `R || S`
+/// encoding where each component is `size` bytes.
+pub fn ecdsa_sig_value_to_fixed(signature: &[u8], size: usize) -> Result<Vec<u8>, DerError> {
+    let mut ecdsa_sig_value = DerReader::read_single(signature, tag::SEQUENCE)?.reader();
+    let mut ret = Vec::with_capacity(size * 2);
+    for _ in 0..2 {
+        let component = ecdsa_sig_value
+            .read_expected(tag::INTEGER)?
+            .as_unsigned_integer_bytes()?;
+        if component.len() > size {
+            return Err(DerError::new("ECDSA signature component too large"));
+        }
+        ret.resize(ret.len() + size - component.len(), 0);
+        ret.extend_from_slice(component);
+    }
+    Ok(ret)
+}
+
+/// Convert a fixed size `R || S` ECDSA signature into a DER encoded
+/// `ECDSA-Sig-Value`.
+pub fn ecdsa_sig_value_from_fixed(signature: &[u8], size: usize) -> Option<Vec<u8>> {
+    if signature.len() != size * 2 {
+        return None;
+    }
+    let (r, s) = signature.split_at(size);
+    Some(sequence(&[unsigned_integer(r), unsigned_integer(s)]))
+}
+
+/// Encode DER as PEM with the provided label (e.g. `CERTIFICATE`).
+pub fn to_pem(label: &str, encoded: &[u8]) -> String {
+    let base64 = tyst::encdec::base64::encode(encoded);
+    let mut ret = format!("-----BEGIN {label}-----\n");
+    base64.as_bytes().chunks(64).for_each(|line| {
+        ret.push_str(&String::from_utf8_lossy(line));
+        ret.push('\n');
+    });
+    ret.push_str(&format!("-----END {label}-----\n"));
+    ret
+}
+
+/// Decode all PEM blocks with the provided label (e.g. `CERTIFICATE`).
+pub fn from_pem(label: &str, pem: &str) -> Vec<Vec<u8>> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+    let mut ret = vec![];
+    let mut base64: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        if line.eq(&begin) {
+            base64 = Some(String::new());
+        } else if line.eq(&end) {
+            if let Some(encoded) = base64
+                .take()
+                .and_then(|base64| tyst::encdec::base64::decode(&base64).ok())
+            {
+                ret.push(encoded);
+            }
+        } else if let Some(base64) = base64.as_mut() {
+            base64.push_str(line);
+        }
+    }
+    ret
+}
+
+/// Convert seconds since the Unix epoch into `(year, month, day, hour, minute,
+/// second)` in UTC.
+pub fn civil_from_epoch_seconds(epoch_seconds: u64) -> (i64, u32, u32, u32, u32, u32) {
+    // See https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (epoch_seconds / 86_400) as i64;
+    let seconds_of_day = (epoch_seconds % 86_400) as u32;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60,
+    )
+}
+
+/// Convert a UTC calendar date and time into seconds since the Unix epoch.
+fn epoch_seconds_from_civil(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<u64> {
+    // See https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(
+        days * 86_400 + i64::from(hour) * 3_600 + i64::from(minute) * 60 + i64::from(second),
+    )
+    .ok()
+}
+
+/// A decoded tag-length-value.
+#[derive(Clone, Copy, Debug)]
+pub struct Tlv<'a> {
+    /// The (single octet) tag.
+    pub tag: u8,
+    /// The value octets.
+    pub content: &'a [u8],
+    /// The full encoding including tag and length.
+    pub encoded: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Return a reader of the contained elements of a constructed value.
+    pub fn reader(&self) -> DerReader<'a> {
+        DerReader::new(self.content)
+    }
+
+    /// Return the content of an `OBJECT IDENTIFIER` as arcs.
+    pub fn as_oid(&self) -> Result<Vec<u32>, DerError> {
+        self.expect_tag(tag::OID)?;
+        let mut arcs = vec![];
+        let mut value: u32 = 0;
+        for b in self.content {
+            value = value
+                .checked_mul(128)
+                .ok_or(DerError::new("OID arc overflow"))?
+                | u32::from(b & 0x7f);
+            if b & 0x80 == 0 {
+                if arcs.is_empty() {
+                    let first = std::cmp::min(value / 40, 2);
+                    arcs.push(first);
+                    arcs.push(value - first * 40);
+                } else {
+                    arcs.push(value);
+                }
+                value = 0;
+            }
+        }
+        if self.content.last().is_some_and(|b| b & 0x80 != 0) {
+            return Err(DerError::new("truncated OID arc"));
+        }
+        if arcs.is_empty() {
+            return Err(DerError::new("empty OID"));
+        }
+        Ok(arcs)
+    }
+
+    /// Return the content of an `OBJECT IDENTIFIER` in dot notation.
+    pub fn as_oid_string(&self) -> Result<String, DerError> {
+        self.as_oid()
+            .map(|arcs| tyst::encdec::oid::as_string(&arcs))
+    }
+
+    /// Return the content of a non-negative `INTEGER` or `ENUMERATED` that
+    /// fits into 64 bits.
+    pub fn as_u64(&self) -> Result<u64, DerError> {
+        if self.tag != tag::INTEGER && self.tag != tag::ENUMERATED {
+            return Err(DerError::new("expected INTEGER"));
+        }
+        let magnitude = self.as_unsigned_integer_bytes()?;
+        if magnitude.len() > 8 {
+            return Err(DerError::new("INTEGER too large"));
+        }
+        Ok(magnitude
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+    }
+
+    /// Return the big-endian magnitude of a non-negative `INTEGER` without
+    /// leading zeros.
+    pub fn as_unsigned_integer_bytes(&self) -> Result<&'a [u8], DerError> {
+        if self.content.first().is_some_and(|b| b & 0x80 != 0) {
+            return Err(DerError::new("negative INTEGER"));
+        }
+        let skip = self.content.iter().take_while(|b| **b == 0).count();
+        Ok(&self.content[skip..])
+    }
+
+    /// Return the content of a `BOOLEAN`.
+    pub fn as_bool(&self) -> Result<bool, DerError> {
+        self.expect_tag(tag::BOOLEAN)?;
+        Ok(self.content.first().is_some_and(|b| *b != 0))
+    }
+
+    /// Return the content of a `BIT STRING` without the unused bits octet.
+    pub fn as_bit_string(&self) -> Result<&'a [u8], DerError> {
+        self.expect_tag(tag::BIT_STRING)?;
+        self.content
+            .split_first()
+            .map(|(_unused, bits)| bits)
+            .ok_or(DerError::new("empty BIT STRING"))
+    }
+
+    /// Return `true` if the named bit at `position` is set in a `BIT STRING`.
+    pub fn is_bit_set(&self, position: usize) -> Result<bool, DerError> {
+        Ok(self
+            .as_bit_string()?
+            .get(position / 8)
+            .is_some_and(|b| b & (0x80 >> (position % 8)) != 0))
+    }
+
+    /// Return the content of a string type as UTF-8.
+    pub fn as_string(&self) -> Result<String, DerError> {
+        String::from_utf8(self.content.to_vec()).map_err(|_| DerError::new("invalid string"))
+    }
+
+    /// Return a `GeneralizedTime` or `UTCTime` as microseconds since the Unix
+    /// epoch.
+    pub fn as_epoch_micros(&self) -> Result<u64, DerError> {
+        let value = self.as_string()?;
+        let value = value
+            .strip_suffix('Z')
+            .ok_or(DerError::new("time is not in UTC"))?;
+        let (value, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let digits = |range: std::ops::Range<usize>| {
+            value
+                .get(range)
+                .and_then(|s| s.parse::<u32>().ok())
+                .ok_or(DerError::new("malformed time"))
+        };
+        let (year, rest) = match self.tag {
+            tag::GENERALIZED_TIME => (i64::from(digits(0..4)?), 4),
+            tag::UTC_TIME => {
+                let yy = i64::from(digits(0..2)?);
+                (if yy >= 50 { 1900 + yy } else { 2000 + yy }, 2)
+            }
+            _ => return Err(DerError::new("expected time")),
+        };
+        let seconds = epoch_seconds_from_civil(
+            year,
+            digits(rest..rest + 2)?,
+            digits(rest + 2..rest + 4)?,
+            digits(rest + 4..rest + 6)?,
+            digits(rest + 6..rest + 8)?,
+            digits(rest + 8..rest + 10)?,
+        )
+        .ok_or(DerError::new("time out of range"))?;
+        let fraction_micros = if fraction.is_empty() {
+            0
+        } else {
+            format!("{fraction:0<6}")
+                .get(0..6)
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or(DerError::new("malformed time fraction"))?
+        };
+        Ok(seconds * 1_000_000 + fraction_micros)
+    }
+
+    /// Fail unless the tag matches.
+    pub fn expect_tag(&self, expected: u8) -> Result<(), DerError> {
+        if self.tag != expected {
+            return Err(DerError::new(&format!(
+                "expected tag 0x{expected:02x}, found 0x{:02x}",
+                self.tag
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Sequential reader of DER encoded elements.
+#[derive(Clone, Copy, Debug)]
+pub struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    /// Return a new instance reading from `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Return `true` when there are no more elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Return the tag of the next element without consuming it.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element.
+    pub fn read(&mut self) -> Result<Tlv<'a>, DerError> {
+        let data = self.data;
+        let tag = *data
+            .first()
+            .ok_or(DerError::new("unexpected end of data"))?;
+        if tag & 0x1f == 0x1f {
+            return Err(DerError::new("high tag numbers are not supported"));
+        }
+        let first_len = *data.get(1).ok_or(DerError::new("missing length"))?;
+        let (len, header_len) = if first_len < 0x80 {
+            (usize::from(first_len), 2)
+        } else {
+            let len_octets = usize::from(first_len & 0x7f);
+            if len_octets == 0 || len_octets > std::mem::size_of::<usize>() {
+                return Err(DerError::new("unsupported length encoding"));
+            }
+            let len = data
+                .get(2..2 + len_octets)
+                .ok_or(DerError::new("truncated length"))?
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+            (len, 2 + len_octets)
+        };
+        let end = header_len
+            .checked_add(len)
+            .filter(|end| *end <= data.len())
+            .ok_or(DerError::new("truncated value"))?;
+        self.data = &data[end..];
+        Ok(Tlv {
+            tag,
+            content: &data[header_len..end],
+            encoded: &data[..end],
+        })
+    }
+
+    /// Read the next element and fail unless it has the expected tag.
+    pub fn read_expected(&mut self, expected: u8) -> Result<Tlv<'a>, DerError> {
+        let tlv = self.read()?;
+        tlv.expect_tag(expected)?;
+        Ok(tlv)
+    }
+
+    /// Read the next element only if it has the expected tag.
+    pub fn read_optional(&mut self, expected: u8) -> Result<Option<Tlv<'a>>, DerError> {
+        if self.peek_tag() == Some(expected) {
+            self.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read a single element that must span all of `data`.
+    pub fn read_single(data: &'a [u8], expected: u8) -> Result<Tlv<'a>, DerError> {
+        let mut reader = Self::new(data);
+        let tlv = reader.read_expected(expected)?;
+        if !reader.is_empty() {
+            return Err(DerError::new("trailing data"));
+        }
+        Ok(tlv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_length_forms() {
+        assert_eq!(
+            tlv(tag::OCTET_STRING, &[0xab; 3]),
+            [0x04, 0x03, 0xab, 0xab, 0xab]
+        );
+        let long = tlv(tag::OCTET_STRING, &[0; 0x80]);
+        assert_eq!(long[..3], [0x04, 0x81, 0x80]);
+        let longer = tlv(tag::OCTET_STRING, &[0; 0x1234]);
+        assert_eq!(longer[..4], [0x04, 0x82, 0x12, 0x34]);
+        let read = DerReader::read_single(&longer, tag::OCTET_STRING).unwrap();
+        assert_eq!(read.content.len(), 0x1234);
+    }
+
+    #[test]
+    fn set_of_is_sorted() {
+        assert_eq!(
+            set_of(&[integer(0x80), integer(2), integer(1)]),
+            tlv(tag::SET, &[integer(1), integer(2), integer(0x80)].concat())
+        );
+    }
+
+    #[test]
+    fn oid_round_trip() {
+        let encoded = oid(&[1, 2, 840, 113549, 1, 7, 2]);
+        assert_eq!(
+            encoded,
+            [
+                0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02
+            ]
+        );
+        for arcs in [
+            vec![1, 2, 840, 113549, 1, 7, 2],
+            vec![2, 999, 3],
+            vec![0, 39],
+            vec![2, 5, 29, 32, 0],
+            vec![1, 3, 6, 1, 4, 1, 311, 4294967295],
+        ] {
+            let decoded = DerReader::read_single(&oid(&arcs), tag::OID)
+                .unwrap()
+                .as_oid()
+                .unwrap();
+            assert_eq!(decoded, arcs);
+        }
+    }
+
+    #[test]
+    fn oid_from_string_rejects_invalid_arcs() {
+        assert_eq!(
+            oid_from_string("1.2.840.113549").unwrap(),
+            [1, 2, 840, 113549]
+        );
+        assert_eq!(oid_from_string("2.999.3").unwrap(), [2, 999, 3]);
+        assert_eq!(oid_from_string("2.4294967215").unwrap(), [2, 4294967215]);
+        for value in [
+            "",
+            "1",
+            "3.1",
+            "0.40",
+            "1.40.1",
+            "2.4294967216",
+            "2.4294967295",
+            "1.2.x",
+        ] {
+            assert!(oid_from_string(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn oid_encoding_does_not_overflow() {
+        assert_eq!(
+            oid(&[2, u32::MAX]),
+            [0x06, 0x05, 0x90, 0x80, 0x80, 0x80, 0x4f]
+        );
+    }
+
+    #[test]
+    fn oid_with_truncated_arc_is_rejected() {
+        let encoded = tlv(tag::OID, &[0x2a, 0x86]);
+        let parsed = DerReader::read_single(&encoded, tag::OID).unwrap();
+        assert!(parsed.as_oid().is_err());
+        let encoded = tlv(tag::OID, &[]);
+        assert!(
+            DerReader::read_single(&encoded, tag::OID)
+                .unwrap()
+                .as_oid()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn integer_encoding() {
+        assert_eq!(integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(integer(0x7f), [0x02, 0x01, 0x7f]);
+        assert_eq!(integer(0x80), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(unsigned_integer(&[0, 0, 1, 2]), [0x02, 0x02, 0x01, 0x02]);
+        for value in [0, 1, 0x80, 0xffff, u64::MAX] {
+            let encoded = integer(value);
+            let tlv = DerReader::read_single(&encoded, tag::INTEGER).unwrap();
+            assert_eq!(tlv.as_u64().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn negative_integer_is_rejected() {
+        let encoded = tlv(tag::INTEGER, &[0xff]);
+        let tlv = DerReader::read_single(&encoded, tag::INTEGER).unwrap();
+        assert!(tlv.as_unsigned_integer_bytes().is_err());
+        assert!(tlv.as_u64().is_err());
+    }
+
+    #[test]
+    fn named_bit_string_encoding() {
+        assert_eq!(named_bit_string(&[]), [0x03, 0x01, 0x00]);
+        assert_eq!(named_bit_string(&[0]), [0x03, 0x02, 0x07, 0x80]);
+        assert_eq!(named_bit_string(&[2, 9]), [0x03, 0x03, 0x06, 0x20, 0x40]);
+        let encoded = named_bit_string(&[2, 9]);
+        let tlv = DerReader::read_single(&encoded, tag::BIT_STRING).unwrap();
+        for position in 0..16 {
+            assert_eq!(
+                tlv.is_bit_set(position).unwrap(),
+                [2, 9].contains(&position)
+            );
+        }
+    }
+
+    #[test]
+    fn generalized_time_drops_trailing_zeros() {
+        assert_eq!(
+            generalized_time(1_750_000_000_000_000),
+            tlv(tag::GENERALIZED_TIME, b"20250615150640Z")
+        );
+        assert_eq!(
+            generalized_time(1_750_000_000_120_000),
+            tlv(tag::GENERALIZED_TIME, b"20250615150640.12Z")
+        );
+        for epoch_micros in [0, 1_709_164_800_000_000, 1_750_000_000_123_456] {
+            let encoded = generalized_time(epoch_micros);
+            let tlv = DerReader::read_single(&encoded, tag::GENERALIZED_TIME).unwrap();
+            assert_eq!(tlv.as_epoch_micros().unwrap(), epoch_micros);
+        }
+    }
+
+    #[test]
+    fn time_switches_to_generalized_time_in_2050() {
+        assert_eq!(
+            time(UTC_TIME_END_EPOCH_SECONDS - 1),
+            tlv(tag::UTC_TIME, b"491231235959Z")
+        );
+        assert_eq!(
+            time(UTC_TIME_END_EPOCH_SECONDS),
+            tlv(tag::GENERALIZED_TIME, b"20500101000000Z")
+        );
+        for epoch_seconds in [
+            0,
+            UTC_TIME_END_EPOCH_SECONDS - 1,
+            UTC_TIME_END_EPOCH_SECONDS,
+        ] {
+            let encoded = time(epoch_seconds);
+            let tlv = DerReader::new(&encoded).read().unwrap();
+            assert_eq!(tlv.as_epoch_micros().unwrap(), epoch_seconds * 1_000_000);
+        }
+    }
+
+    #[test]
+    fn civil_round_trip() {
+        assert_eq!(civil_from_epoch_seconds(0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(
+            civil_from_epoch_seconds(1_709_164_800),
+            (2024, 2, 29, 0, 0, 0)
+        );
+        assert_eq!(
+            civil_from_epoch_seconds(1_750_000_000),
+            (2025, 6, 15, 15, 6, 40)
+        );
+        for epoch_seconds in [0, 1_709_164_800, 1_750_000_000, 4_102_444_800] {
+            let (year, month, day, hour, minute, second) = civil_from_epoch_seconds(epoch_seconds);
+            assert_eq!(
+                epoch_seconds_from_civil(year, month, day, hour, minute, second),
+                Some(epoch_seconds)
+            );
+        }
+        assert_eq!(epoch_seconds_from_civil(2025, 13, 1, 0, 0, 0), None);
+        assert_eq!(epoch_seconds_from_civil(1969, 12, 31, 23, 59, 59), None);
+    }
+
+    #[test]
+    fn implicit_keeps_constructed_bit() {
+        assert_eq!(
+            implicit(1, &octet_string(&[1]))[0],
+            tag::context_primitive(1)
+        );
+        assert_eq!(implicit(2, &sequence(&[]))[0], tag::context_constructed(2));
+    }
+
+    #[test]
+    fn ecdsa_signature_round_trip() {
+        let fixed = [vec![0x00; 31], vec![0x01], vec![0x80; 32]].concat();
+        let encoded = ecdsa_sig_value_from_fixed(&fixed, 32).unwrap();
+        assert_eq!(
+            encoded,
+            sequence(&[unsigned_integer(&[0x01]), unsigned_integer(&[0x80; 32])])
+        );
+        assert_eq!(ecdsa_sig_value_to_fixed(&encoded, 32).unwrap(), fixed);
+    }
+
+    #[test]
+    fn pem_round_trip() {
+        let encoded = vec![sequence(&[integer(1)]), sequence(&[integer(2)])];
+        let pem = encoded
+            .iter()
+            .map(|encoded| to_pem("CERTIFICATE", encoded))
+            .collect::<String>();
+        assert_eq!(from_pem("CERTIFICATE", &pem), encoded);
+        assert!(from_pem("PRIVATE KEY", &pem).is_empty());
+    }
+
+    #[test]
+    fn reader_rejects_malformed_input() {
+        assert!(DerReader::read_single(&[0x04, 0x02, 0x00], tag::OCTET_STRING).is_err());
+        assert!(DerReader::read_single(&[0x04, 0x00, 0x00], tag::OCTET_STRING).is_err());
+        assert!(DerReader::read_single(&[0x04, 0x00], tag::INTEGER).is_err());
+        assert!(DerReader::read_single(&[0x1f, 0x01, 0x00], 0x1f).is_err());
+        assert!(DerReader::read_single(&[0x04, 0x80], tag::OCTET_STRING).is_err());
+        assert!(DerReader::read_single(&[0x04, 0x82, 0x01], tag::OCTET_STRING).is_err());
+    }
+
+    #[test]
+    fn read_optional_only_consumes_matching_tag() {
+        let encoded = [integer(1), octet_string(&[2])].concat();
+        let mut reader = DerReader::new(&encoded);
+        assert!(reader.read_optional(tag::BOOLEAN).unwrap().is_none());
+        assert_eq!(
+            reader
+                .read_optional(tag::INTEGER)
+                .unwrap()
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            1
+        );
+        assert_eq!(reader.peek_tag(), Some(tag::OCTET_STRING));
+    }
+}
@@ -0,0 +1,510 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Access to X.509 certificate fields not exposed by
+//! [`CertificateParser`](upkit_common::x509::cert::parse::CertificateParser).
+
+use super::DerError;
+use super::DerReader;
+use super::tag;
+
+/// `id-ce-keyUsage` from RFC 5280.
+pub const OID_CE_KEY_USAGE: &[u32] = &[2, 5, 29, 15];
+/// `id-ce-basicConstraints` from RFC 5280.
+pub const OID_CE_BASIC_CONSTRAINTS: &[u32] = &[2, 5, 29, 19];
+/// `id-ce-extKeyUsage` from RFC 5280.
+pub const OID_CE_EXT_KEY_USAGE: &[u32] = &[2, 5, 29, 37];
+/// `id-kp-timeStamping` from RFC 5280.
+pub const OID_KP_TIME_STAMPING: &[u32] = &[1, 3, 6, 1, 5, 5, 7, 3, 8];
+/// `id-kp-OCSPSigning` from RFC 5280.
+pub const OID_KP_OCSP_SIGNING: &[u32] = &[1, 3, 6, 1, 5, 5, 7, 3, 9];
+/// `id-pkix-ocsp-basic` from RFC 6960.
+const OID_PKIX_OCSP_BASIC: &[u32] = &[1, 3, 6, 1, 5, 5, 7, 48, 1, 1];
+
+/// A certificate extension.
+pub struct Extension {
+    /// Extension object identifier in dot notation.
+    pub oid: String,
+    /// Extension criticality.
+    pub critical: bool,
+    /// DER encoded extension value (content of the `extnValue` OCTET STRING).
+    pub value: Vec<u8>,
+}
+
+impl Extension {
+    /// Return `true` if this is the extension with the requested OID.
+    pub fn has_oid(&self, oid: &[u32]) -> bool {
+        self.oid.eq(&tyst::encdec::oid::as_string(oid))
+    }
+}
+
+/// Return the extension with the requested OID.
+pub fn extension_by_oid<'a>(extensions: &'a [Extension], oid: &[u32]) -> Option<&'a Extension> {
+    extensions.iter().find(|extension| extension.has_oid(oid))
+}
+
+/// Return the extensions of a DER encoded X.509 certificate.
+pub fn certificate_extensions(encoded_certificate: &[u8]) -> Result<Vec<Extension>, DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    // Skip to the optional extensions: version, serialNumber, signature,
+    // issuer, validity, subject, subjectPublicKeyInfo, issuerUniqueID and
+    // subjectUniqueID.
+    while let Some(next_tag) = tbs_certificate.peek_tag() {
+        if next_tag == tag::context_constructed(3) {
+            let explicit_extensions = tbs_certificate.read()?;
+            return parse_extensions(
+                explicit_extensions
+                    .reader()
+                    .read_expected(tag::SEQUENCE)?
+                    .content,
+            );
+        }
+        tbs_certificate.read()?;
+    }
+    Ok(vec![])
+}
+
+/// Parse the content of an `Extensions` SEQUENCE.
+pub fn parse_extensions(extensions_content: &[u8]) -> Result<Vec<Extension>, DerError> {
+    let mut ret = vec![];
+    let mut extensions = DerReader::new(extensions_content);
+    while !extensions.is_empty() {
+        let mut extension = extensions.read_expected(tag::SEQUENCE)?.reader();
+        let oid = extension.read_expected(tag::OID)?.as_oid_string()?;
+        let critical = extension
+            .read_optional(tag::BOOLEAN)?
+            .map(|tlv| tlv.as_bool())
+            .transpose()?
+            .unwrap_or(false);
+        let value = extension.read_expected(tag::OCTET_STRING)?.content.to_vec();
+        ret.push(Extension {
+            oid,
+            critical,
+            value,
+        });
+    }
+    Ok(ret)
+}
+
+/// Return the DER encoded `IssuerAndSerialNumber` of a DER encoded X.509
+/// certificate.
+///
+/// ```text
+/// IssuerAndSerialNumber ::= SEQUENCE {
+///   issuer Name,
+///   serialNumber CertificateSerialNumber }
+/// ```
+pub fn issuer_and_serial_number(encoded_certificate: &[u8]) -> Result<Vec<u8>, DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    let serial_number = tbs_certificate.read_expected(tag::INTEGER)?;
+    let _signature = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let issuer = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    Ok(super::sequence(&[
+        issuer.encoded.to_vec(),
+        serial_number.encoded.to_vec(),
+    ]))
+}
+
+/// Return the content octets of the `serialNumber` of a DER encoded X.509
+/// certificate.
+pub fn serial_number(encoded_certificate: &[u8]) -> Result<&[u8], DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    Ok(tbs_certificate.read_expected(tag::INTEGER)?.content)
+}
+
+/// Return the `subjectPublicKey` bits of a DER encoded X.509 certificate.
+pub fn subject_public_key(encoded_certificate: &[u8]) -> Result<Vec<u8>, DerError> {
+    let subject_public_key_info = subject_public_key_info(encoded_certificate)?;
+    let mut subject_public_key_info =
+        DerReader::read_single(&subject_public_key_info, tag::SEQUENCE)?.reader();
+    let _algorithm = subject_public_key_info.read_expected(tag::SEQUENCE)?;
+    Ok(subject_public_key_info
+        .read_expected(tag::BIT_STRING)?
+        .as_bit_string()?
+        .to_vec())
+}
+
+/// Return the key purpose object identifiers of the extended key usage
+/// extensions of a DER encoded X.509 certificate.
+pub fn ext_key_usages(encoded_certificate: &[u8]) -> Result<Vec<Vec<u32>>, DerError> {
+    let mut ret = vec![];
+    for extension in certificate_extensions(encoded_certificate)? {
+        if !extension.has_oid(OID_CE_EXT_KEY_USAGE) {
+            continue;
+        }
+        let mut key_purpose_ids = DerReader::read_single(&extension.value, tag::SEQUENCE)?.reader();
+        while !key_purpose_ids.is_empty() {
+            ret.push(key_purpose_ids.read_expected(tag::OID)?.as_oid()?);
+        }
+    }
+    Ok(ret)
+}
+
+/// Return the DER encoded `SubjectPublicKeyInfo` of a DER encoded X.509
+/// certificate.
+pub fn subject_public_key_info(encoded_certificate: &[u8]) -> Result<Vec<u8>, DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    let _serial_number = tbs_certificate.read_expected(tag::INTEGER)?;
+    let _signature = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _issuer = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _validity = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _subject = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    Ok(tbs_certificate
+        .read_expected(tag::SEQUENCE)?
+        .encoded
+        .to_vec())
+}
+
+/// Return the DER encoded issuer and subject `Name` of a DER encoded X.509
+/// certificate.
+pub fn issuer_and_subject(encoded_certificate: &[u8]) -> Result<(Vec<u8>, Vec<u8>), DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    let _serial_number = tbs_certificate.read_expected(tag::INTEGER)?;
+    let _signature = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let issuer = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _validity = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let subject = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    Ok((issuer.encoded.to_vec(), subject.encoded.to_vec()))
+}
+
+/// Return the `notBefore` and `notAfter` of a DER encoded X.509 certificate
+/// in microseconds since the Unix epoch.
+pub fn validity_epoch_micros(encoded_certificate: &[u8]) -> Result<(u64, u64), DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    let _serial_number = tbs_certificate.read_expected(tag::INTEGER)?;
+    let _signature = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _issuer = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let mut validity = tbs_certificate.read_expected(tag::SEQUENCE)?.reader();
+    let not_before = validity.read()?.as_epoch_micros()?;
+    let not_after = validity.read()?.as_epoch_micros()?;
+    Ok((not_before, not_after))
+}
+
+/// Return the `thisUpdate` of a DER encoded X.509 CRL in microseconds since
+/// the Unix epoch.
+pub fn crl_this_update_epoch_micros(encoded_crl: &[u8]) -> Result<u64, DerError> {
+    let mut certificate_list = DerReader::new(encoded_crl)
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    let mut tbs_cert_list = certificate_list.read_expected(tag::SEQUENCE)?.reader();
+    tbs_cert_list.read_optional(tag::INTEGER)?;
+    let _signature = tbs_cert_list.read_expected(tag::SEQUENCE)?;
+    let _issuer = tbs_cert_list.read_expected(tag::SEQUENCE)?;
+    tbs_cert_list.read()?.as_epoch_micros()
+}
+
+/** Return the DER encoded issuer `Name` of a DER encoded X.509 CRL and the
+serial number content octets and `revocationDate` in microseconds since the
+Unix epoch of every revoked certificate.
+
+[RFC 5280 5.1](https://www.rfc-editor.org/rfc/rfc5280#section-5.1):
+
+```text
+   TBSCertList  ::=  SEQUENCE  {
+        version                 Version OPTIONAL,
+                                     -- if present, MUST be v2
+        signature               AlgorithmIdentifier,
+        issuer                  Name,
+        thisUpdate              Time,
+        nextUpdate              Time OPTIONAL,
+        revokedCertificates     SEQUENCE OF SEQUENCE  {
+             userCertificate         CertificateSerialNumber,
+             revocationDate          Time,
+             crlEntryExtensions      Extensions OPTIONAL
+                                      -- if present, version MUST be v2
+                                  }  OPTIONAL,
+        crlExtensions           [0]  EXPLICIT Extensions OPTIONAL
+                                      -- if present, version MUST be v2
+                                  }
+```
+*/
+pub fn crl_issuer_and_revoked_certificates(
+    encoded_crl: &[u8],
+) -> Result<(Vec<u8>, Vec<(Vec<u8>, u64)>), DerError> {
+    let mut certificate_list = DerReader::new(encoded_crl)
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    let mut tbs_cert_list = certificate_list.read_expected(tag::SEQUENCE)?.reader();
+    tbs_cert_list.read_optional(tag::INTEGER)?;
+    let _signature = tbs_cert_list.read_expected(tag::SEQUENCE)?;
+    let issuer = tbs_cert_list.read_expected(tag::SEQUENCE)?;
+    let _this_update = tbs_cert_list.read()?.as_epoch_micros()?;
+    if matches!(
+        tbs_cert_list.peek_tag(),
+        Some(tag::UTC_TIME | tag::GENERALIZED_TIME)
+    ) {
+        let _next_update = tbs_cert_list.read()?;
+    }
+    let mut revoked_certificates = vec![];
+    if let Some(entries) = tbs_cert_list.read_optional(tag::SEQUENCE)? {
+        let mut entries = entries.reader();
+        while !entries.is_empty() {
+            let mut entry = entries.read_expected(tag::SEQUENCE)?.reader();
+            let user_certificate = entry.read_expected(tag::INTEGER)?.content.to_vec();
+            let revocation_date = entry.read()?.as_epoch_micros()?;
+            revoked_certificates.push((user_certificate, revocation_date));
+        }
+    }
+    Ok((issuer.encoded.to_vec(), revoked_certificates))
+}
+
+/// Return the `producedAt` of a DER encoded OCSP response in microseconds
+/// since the Unix epoch.
+///
+/// Only `id-pkix-ocsp-basic` responses (RFC 6960 4.2.1) are supported.
+pub fn ocsp_produced_at_epoch_micros(encoded_ocsp_response: &[u8]) -> Result<u64, DerError> {
+    let mut tbs_response_data = DerReader::new(basic_ocsp_response(encoded_ocsp_response)?)
+        .read_expected(tag::SEQUENCE)?
+        .reader()
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    tbs_response_data.read_optional(tag::context_constructed(0))?;
+    let _responder_id = tbs_response_data.read()?;
+    tbs_response_data
+        .read_expected(tag::GENERALIZED_TIME)?
+        .as_epoch_micros()
+}
+
+/// Return the DER encoded `BasicOCSPResponse` of a DER encoded successful
+/// OCSP response.
+pub fn basic_ocsp_response(encoded_ocsp_response: &[u8]) -> Result<&[u8], DerError> {
+    let mut ocsp_response = DerReader::new(encoded_ocsp_response)
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    let _response_status = ocsp_response.read_expected(tag::ENUMERATED)?;
+    let mut response_bytes = ocsp_response
+        .read_expected(tag::context_constructed(0))?
+        .reader()
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    if response_bytes.read_expected(tag::OID)?.as_oid()? != OID_PKIX_OCSP_BASIC {
+        return Err(DerError::new("unsupported OCSP response type"));
+    }
+    Ok(response_bytes.read_expected(tag::OCTET_STRING)?.content)
+}
+
+/// Status of a certificate in an OCSP `SingleResponse`.
+#[derive(Debug, PartialEq)]
+pub enum OcspCertStatus {
+    /// The certificate is not revoked.
+    Good,
+    /// The certificate was revoked at the point in time in microseconds
+    /// since the Unix epoch.
+    Revoked(u64),
+    /// The responder doesn't know about the certificate.
+    Unknown,
+}
+
+/// The certificate identifier and status of an OCSP `SingleResponse`.
+pub struct OcspSingleResponse {
+    /// Object identifier of the digest algorithm of the issuer hashes.
+    pub hash_algorithm_oid: Vec<u32>,
+    /// Digest of the DER encoded issuer `Name`.
+    pub issuer_name_hash: Vec<u8>,
+    /// Digest of the issuer's `subjectPublicKey` bits.
+    pub issuer_key_hash: Vec<u8>,
+    /// Content octets of the certificate serial number.
+    pub serial_number: Vec<u8>,
+    /// Status of the certificate.
+    pub cert_status: OcspCertStatus,
+}
+
+/** Return the single responses of a DER encoded `BasicOCSPResponse`.
+
+[RFC 6960 4.2.1](https://www.rfc-editor.org/rfc/rfc6960#section-4.2.1):
+
+```text
+   ResponseData ::= SEQUENCE {
+      version              [0] EXPLICIT Version DEFAULT v1,
+      responderID              ResponderID,
+      producedAt               GeneralizedTime,
+      responses                SEQUENCE OF SingleResponse,
+      responseExtensions   [1] EXPLICIT Extensions OPTIONAL }
+
+   SingleResponse ::= SEQUENCE {
+      certID                       CertID,
+      certStatus                   CertStatus,
+      thisUpdate                   GeneralizedTime,
+      nextUpdate         [0]       EXPLICIT GeneralizedTime OPTIONAL,
+      singleExtensions   [1]       EXPLICIT Extensions OPTIONAL }
+
+   CertStatus ::= CHOICE {
+       good        [0]     IMPLICIT NULL,
+       revoked     [1]     IMPLICIT RevokedInfo,
+       unknown     [2]     IMPLICIT UnknownInfo }
+
+   RevokedInfo ::= SEQUENCE {
+       revocationTime              GeneralizedTime,
+       revocationReason    [0]     EXPLICIT CRLReason OPTIONAL }
+```
+*/
+pub fn basic_ocsp_single_responses(
+    encoded_basic_ocsp_response: &[u8],
+) -> Result<Vec<OcspSingleResponse>, DerError> {
+    let mut tbs_response_data = DerReader::read_single(encoded_basic_ocsp_response, tag::SEQUENCE)?
+        .reader()
+        .read_expected(tag::SEQUENCE)?
+        .reader();
+    tbs_response_data.read_optional(tag::context_constructed(0))?;
+    let _responder_id = tbs_response_data.read()?;
+    let _produced_at = tbs_response_data.read_expected(tag::GENERALIZED_TIME)?;
+    let mut responses = tbs_response_data.read_expected(tag::SEQUENCE)?.reader();
+    let mut ret = vec![];
+    while !responses.is_empty() {
+        let mut single_response = responses.read_expected(tag::SEQUENCE)?.reader();
+        let mut cert_id = single_response.read_expected(tag::SEQUENCE)?.reader();
+        let hash_algorithm_oid = cert_id
+            .read_expected(tag::SEQUENCE)?
+            .reader()
+            .read_expected(tag::OID)?
+            .as_oid()?;
+        let issuer_name_hash = cert_id.read_expected(tag::OCTET_STRING)?.content.to_vec();
+        let issuer_key_hash = cert_id.read_expected(tag::OCTET_STRING)?.content.to_vec();
+        let serial_number = cert_id.read_expected(tag::INTEGER)?.content.to_vec();
+        let cert_status = single_response.read()?;
+        let cert_status = if cert_status.tag == tag::context_primitive(0) {
+            OcspCertStatus::Good
+        } else if cert_status.tag == tag::context_constructed(1) {
+            OcspCertStatus::Revoked(
+                cert_status
+                    .reader()
+                    .read_expected(tag::GENERALIZED_TIME)?
+                    .as_epoch_micros()?,
+            )
+        } else if cert_status.tag == tag::context_primitive(2) {
+            OcspCertStatus::Unknown
+        } else {
+            return Err(DerError::new("malformed OCSP certificate status"));
+        };
+        ret.push(OcspSingleResponse {
+            hash_algorithm_oid,
+            issuer_name_hash,
+            issuer_key_hash,
+            serial_number,
+            cert_status,
+        });
+    }
+    Ok(ret)
+}
+
+/// Return the DER encoded certificates of a DER encoded `BasicOCSPResponse`
+/// that may identify a delegated responder.
+pub fn basic_ocsp_certificates(
+    encoded_basic_ocsp_response: &[u8],
+) -> Result<Vec<Vec<u8>>, DerError> {
+    let mut basic_ocsp_response =
+        DerReader::read_single(encoded_basic_ocsp_response, tag::SEQUENCE)?.reader();
+    let _tbs_response_data = basic_ocsp_response.read_expected(tag::SEQUENCE)?;
+    let _signature_algorithm = basic_ocsp_response.read_expected(tag::SEQUENCE)?;
+    let _signature = basic_ocsp_response.read_expected(tag::BIT_STRING)?;
+    let mut ret = vec![];
+    if let Some(certs) = basic_ocsp_response.read_optional(tag::context_constructed(0))? {
+        let mut certs = certs.reader().read_expected(tag::SEQUENCE)?.reader();
+        while !certs.is_empty() {
+            ret.push(certs.read_expected(tag::SEQUENCE)?.encoded.to_vec());
+        }
+    }
+    Ok(ret)
+}
+
+/// Return the `commonName` attribute values of the subject of a DER encoded
+/// X.509 certificate.
+pub fn subject_common_names(encoded_certificate: &[u8]) -> Result<Vec<String>, DerError> {
+    let mut tbs_certificate = tbs_certificate_reader(encoded_certificate)?;
+    tbs_certificate.read_optional(tag::context_constructed(0))?;
+    let _serial_number = tbs_certificate.read_expected(tag::INTEGER)?;
+    let _signature = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _issuer = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let _validity = tbs_certificate.read_expected(tag::SEQUENCE)?;
+    let mut subject = tbs_certificate.read_expected(tag::SEQUENCE)?.reader();
+    let mut ret = vec![];
+    while !subject.is_empty() {
+        let mut relative_distinguished_name = subject.read_expected(tag::SET)?.reader();
+        while !relative_distinguished_name.is_empty() {
+            let mut attribute = relative_distinguished_name
+                .read_expected(tag::SEQUENCE)?
+                .reader();
+            if attribute.read_expected(tag::OID)?.as_oid()? == [2, 5, 4, 3] {
+                ret.push(attribute.read()?.as_string()?);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Return the `rfc822Name`, `dNSName` and `uniformResourceIdentifier` entries
+/// of the subject alternative name extension of a DER encoded X.509
+/// certificate.
+pub fn subject_alternative_names(encoded_certificate: &[u8]) -> Result<Vec<String>, DerError> {
+    let mut ret = vec![];
+    for extension in certificate_extensions(encoded_certificate)? {
+        if extension.oid != "2.5.29.17" {
+            continue;
+        }
+        let mut general_names = DerReader::read_single(&extension.value, tag::SEQUENCE)?.reader();
+        while !general_names.is_empty() {
+            let general_name = general_names.read()?;
+            if [1, 2, 6]
+                .iter()
+                .any(|n| general_name.tag == tag::context_primitive(*n))
+            {
+                ret.push(general_name.as_string()?);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/** Return the DER encoded `TBSCertificate`, the signature algorithm object
+identifier and the signature value of a DER encoded X.509 certificate.
+
+```text
+   Certificate  ::=  SEQUENCE  {
+        tbsCertificate       TBSCertificate,
+        signatureAlgorithm   AlgorithmIdentifier,
+        signatureValue       BIT STRING  }
+```
+
+A `CertificateList` (CRL) and a `BasicOCSPResponse` start with the same
+three fields.
+*/
+pub fn signed_parts(encoded_certificate: &[u8]) -> Result<(&[u8], Vec<u32>, &[u8]), DerError> {
+    let mut certificate = DerReader::read_single(encoded_certificate, tag::SEQUENCE)?.reader();
+    let tbs_certificate = certificate.read_expected(tag::SEQUENCE)?.encoded;
+    let signature_algorithm_oid = certificate
+        .read_expected(tag::SEQUENCE)?
+        .reader()
+        .read_expected(tag::OID)?
+        .as_oid()?;
+    let signature_value = certificate
+        .read_expected(tag::BIT_STRING)?
+        .as_bit_string()?;
+    Ok((tbs_certificate, signature_algorithm_oid, signature_value))
+}
+
+/// Return a reader positioned at the first element of the `TBSCertificate`.
+fn tbs_certificate_reader(encoded_certificate: &[u8]) -> Result<DerReader<'_>, DerError> {
+    DerReader::read_single(encoded_certificate, tag::SEQUENCE)?
+        .reader()
+        .read_expected(tag::SEQUENCE)
+        .map(|tbs_certificate| tbs_certificate.reader())
+}
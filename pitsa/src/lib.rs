@@ -21,14 +21,52 @@
 
 //! Library entry point
 
+mod cbor;
 pub mod conf;
+mod der;
+pub mod logging;
 pub mod rest_api;
+pub mod tcp_api;
+mod telemetry;
 mod time_stamper;
+mod util;
 
 use conf::AppConfig;
+use futures::TryFutureExt;
+use logging::AuditLog;
 use std::process::ExitCode;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use telemetry::Telemetry;
+pub use telemetry::TraceExporter;
+pub(crate) use time_stamper::ArchivedToken;
+pub(crate) use time_stamper::CONTENT_TYPE_COSE;
+pub(crate) use time_stamper::CONTENT_TYPE_JOSE;
+pub(crate) use time_stamper::ChainStatus;
+pub(crate) use time_stamper::ClientIdentity;
+pub(crate) use time_stamper::ContentDigest;
+pub(crate) use time_stamper::EncodedTimeStampResp;
+pub(crate) use time_stamper::ExpectedImprint;
+pub(crate) use time_stamper::ImprintRequest;
+pub(crate) use time_stamper::JwsTimeStampVerification;
+pub(crate) use time_stamper::Metrics;
+pub(crate) use time_stamper::ParsedTimeStampToken;
+pub use time_stamper::RateLimit;
+pub use time_stamper::RateLimitMode;
+pub(crate) use time_stamper::RateLimits;
+pub(crate) use time_stamper::ReadinessCondition;
+pub(crate) use time_stamper::RevocationStatus;
+pub(crate) use time_stamper::SelfTest;
+pub(crate) use time_stamper::SignerStatus;
+pub(crate) use time_stamper::TimeSourceStatus;
+pub(crate) use time_stamper::TimeStampFormatError;
+pub(crate) use time_stamper::TimeStampedDataError;
 pub use time_stamper::TimeStamper;
+pub(crate) use time_stamper::TokenArchive;
+pub(crate) use time_stamper::TokenVerification;
+pub(crate) use time_stamper::TransparencyLog;
+pub(crate) use time_stamper::Transport;
 use tokio::signal::unix::{SignalKind, signal};
 use tyst_api_rest_health::AppHealth;
 
@@ -37,8 +75,12 @@ pub async fn run_async(app_config: Arc<AppConfig>) -> ExitCode {
     let app_future = run_async_abortable_with_logging(&app_config);
     let signals_future = block_until_signaled();
     tokio::select! {
-        _ = app_future => {
+        result = app_future => {
             log::trace!("app_future finished");
+            if let Err(e) = result {
+                log::error!("{e}");
+                return ExitCode::FAILURE;
+            }
         },
         _ = signals_future => {
             log::trace!("signals_future finished");
@@ -62,38 +104,82 @@ async fn block_until_signaled() {
 }
 
 /// Simple health check that gets the provider instance.
+///
+/// The instance has started once it was ready the first time. Liveness is
+/// independent of readiness, so that a time quality problem doesn't get the
+/// instance restarted.
 pub struct SimpleHealth {
     time_stamper: Arc<TimeStamper>,
+    started: AtomicBool,
 }
 impl SimpleHealth {
     fn with_app(time_stamper: &Arc<TimeStamper>) -> Arc<dyn AppHealth> {
         Arc::new(Self {
             time_stamper: Arc::clone(time_stamper),
+            started: AtomicBool::new(false),
         })
     }
 }
 impl AppHealth for SimpleHealth {
     fn is_health_started(&self) -> bool {
-        self.is_health_ready()
+        if self.started.load(Ordering::Relaxed) {
+            return true;
+        }
+        let ready = self.is_health_ready();
+        if ready {
+            self.started.store(true, Ordering::Relaxed);
+        }
+        ready
     }
     fn is_health_ready(&self) -> bool {
         self.time_stamper.is_ready()
     }
     fn is_health_live(&self) -> bool {
-        self.is_health_ready()
+        self.time_stamper.is_live()
     }
 }
 
-async fn run_async_abortable_with_logging(app_config: &Arc<AppConfig>) {
-    let app = TimeStamper::new(&Arc::clone(app_config)).await;
+async fn run_async_abortable_with_logging(app_config: &Arc<AppConfig>) -> Result<(), String> {
+    AuditLog::init(app_config.audit.sink());
+    Telemetry::init(
+        app_config.tracing.exporter(),
+        &app_config.tracing.service_name(),
+        app_config.tracing.sample_ratio(),
+        app_config.tracing.export_interval_micros(),
+    );
+    let app = TimeStamper::new(&Arc::clone(app_config)).await?;
     let app_health: Arc<dyn AppHealth> = SimpleHealth::with_app(&app);
-    rest_api::run_http_server(
+    let http_server_future = rest_api::run_http_server(
         app_config.limits.available_parallelism(),
         &app_config.api.bind_address(),
         app_config.api.bind_port(),
+        app_config
+            .api
+            .tls_pem_paths()
+            .map(
+                |(certificate_path, private_key_path)| rest_api::TlsOptions {
+                    certificate_path,
+                    private_key_path,
+                    client_ca_path: app_config.api.tls_client_ca_path(),
+                    reload_interval_micros: app_config.api.tls_reload_interval_micros(),
+                },
+            ),
         &app_health,
         &app,
     )
-    .await
-    .unwrap();
+    .map_err(|e| format!("HTTP server failed: {e}"));
+    if let Some(tcp_port) = app_config.api.tcp_bind_port() {
+        let tcp_server_future = tcp_api::run_tcp_server(
+            app_config.limits.available_parallelism(),
+            &app_config.api.bind_address(),
+            tcp_port,
+            &app_health,
+            &app,
+        )
+        .map_err(|e| format!("TCP server failed: {e}"));
+        futures::future::try_join(http_server_future, tcp_server_future).await?;
+    } else {
+        http_server_future.await?;
+    }
+    Ok(())
 }
@@ -0,0 +1,342 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Structured JSON logging and audit events.
+//!
+//! Log lines written within the processing of a request carry the request
+//! identifier and the authenticated client. Audit events have a stable
+//! [event type](AuditEventType) and stable field names and can additionally be
+//! written to a separate [audit sink](AuditSink) independent of the log level.
+
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::io::Write;
+use std::net::UdpSocket;
+use std::os::unix::net::UnixDatagram;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use crate::ClientIdentity;
+use crate::der;
+
+/// Log target of audit events.
+pub const AUDIT_LOG_TARGET: &str = "audit";
+
+tokio::task_local! {
+    /// Identifier of the request processed by the current task.
+    static REQUEST_ID: String;
+}
+
+/// The process wide audit sink, if initialized.
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// Return the current time in microseconds since the Unix epoch.
+fn now_epoch_micros() -> u64 {
+    upkit_common::util::time::now_epoch_micros()
+}
+
+/** Format a log record as a single line JSON object.
+
+```json
+{"timestamp":"2025-01-01T00:00:00.000000Z","level":"INFO","target":"pitsa","message":"...","requestId":"...","client":"..."}
+```
+
+Audit events are written with their fields at the top level instead of a
+`message`.
+
+Use with `env_logger::Builder::format`.
+*/
+pub fn format_json(
+    formatter: &mut env_logger::fmt::Formatter,
+    record: &log::Record,
+) -> std::io::Result<()> {
+    let mut line = serde_json::Map::new();
+    line.insert(
+        "timestamp".to_string(),
+        der::rfc3339(now_epoch_micros()).into(),
+    );
+    line.insert("level".to_string(), record.level().as_str().into());
+    line.insert("target".to_string(), record.target().into());
+    let message = record.args().to_string();
+    let event = if record.target() == AUDIT_LOG_TARGET {
+        serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&message).ok()
+    } else {
+        None
+    };
+    if let Some(event) = event {
+        // Audit events already carry a timestamp, the request and the client.
+        line.extend(event);
+    } else {
+        line.insert("message".to_string(), message.into());
+        if let Some(request_id) = RequestId::current() {
+            line.insert("requestId".to_string(), request_id.into());
+        }
+        if let Some(Some(client_identity)) = ClientIdentity::current() {
+            line.insert("client".to_string(), client_identity.to_string().into());
+        }
+    }
+    writeln!(formatter, "{}", serde_json::Value::Object(line))
+}
+
+/// Identifier of a request for correlation of log lines and audit events.
+pub struct RequestId;
+
+impl RequestId {
+    /// HTTP header carrying the request identifier.
+    pub const HEADER_NAME: &'static str = "x-request-id";
+
+    /// Longest accepted request identifier from a client.
+    const MAX_LENGTH: usize = 128;
+
+    /// Return the client provided request identifier if acceptable or a new
+    /// random identifier.
+    pub fn from_header_or_new(header_value: Option<&str>) -> String {
+        header_value
+            .map(str::trim)
+            .filter(|value| {
+                !value.is_empty()
+                    && value.len() <= Self::MAX_LENGTH
+                    && value
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+            })
+            .map(str::to_string)
+            .unwrap_or_else(Self::new_random)
+    }
+
+    /// Return a new random request identifier.
+    ///
+    /// Request identifiers are not security sensitive, so seeding a keyed
+    /// hash with a counter and the current time is sufficient.
+    pub fn new_random() -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        static RANDOM_STATE: OnceLock<RandomState> = OnceLock::new();
+        let mut hasher = RANDOM_STATE.get_or_init(RandomState::new).build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u64(now_epoch_micros());
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Run `future` as the processing of the identified request.
+    pub async fn scope<F: Future>(request_id: String, future: F) -> F::Output {
+        REQUEST_ID.scope(request_id, future).await
+    }
+
+    /// Return the identifier of the request processed by the current task.
+    pub fn current() -> Option<String> {
+        REQUEST_ID.try_with(String::clone).ok()
+    }
+}
+
+/// Stable types of audit events.
+#[derive(Clone, Copy, Debug)]
+pub enum AuditEventType {
+    /// A time-stamp token was issued.
+    TimeStampIssued,
+    /// A time-stamp request was not granted.
+    TimeStampRejected,
+    /// A new TSU signing certificate and key is in use.
+    SignerRotated,
+    /// A TSU signing certificate was refused.
+    SignerRefused,
+    /// The time source went in or out of the tolerable accuracy.
+    TimeToleranceChanged,
+}
+
+impl AuditEventType {
+    /// Return the stable name of the event type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TimeStampIssued => "tsp.issued",
+            Self::TimeStampRejected => "tsp.rejected",
+            Self::SignerRotated => "signer.rotated",
+            Self::SignerRefused => "signer.refused",
+            Self::TimeToleranceChanged => "time.toleranceChanged",
+        }
+    }
+}
+
+/** An audit event with stable field names.
+
+Common fields are `eventType`, `timestamp` and, when emitted within the
+processing of a request, `requestId` and `client`. Event specific fields
+include `serialNumber`, `policy`, `genTime`, `accuracyMicros`, `failureInfo`,
+`statusStrings` and `tsuCertificateSerialNumber`.
+*/
+pub struct AuditEvent {
+    event_type: AuditEventType,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AuditEvent {
+    /// Return a new event of the type with the common fields set.
+    pub fn new(event_type: AuditEventType) -> Self {
+        let mut fields = serde_json::Map::new();
+        fields.insert("eventType".to_string(), event_type.as_str().into());
+        fields.insert(
+            "timestamp".to_string(),
+            der::rfc3339(now_epoch_micros()).into(),
+        );
+        if let Some(request_id) = RequestId::current() {
+            fields.insert("requestId".to_string(), request_id.into());
+        }
+        if let Some(Some(client_identity)) = ClientIdentity::current() {
+            fields.insert("client".to_string(), client_identity.to_string().into());
+        }
+        Self { event_type, fields }
+    }
+
+    /// Add a field to the event.
+    pub fn with(mut self, name: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.fields.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Write the event to the audit sink and log it at `DEBUG` or, without
+    /// an audit sink, write it to the log at `INFO`.
+    pub fn emit(self) {
+        let line = serde_json::Value::Object(self.fields).to_string();
+        if let Some(audit_log) = AUDIT_LOG.get() {
+            log::debug!(target: AUDIT_LOG_TARGET, "{line}");
+            audit_log.write(self.event_type, &line);
+        } else {
+            log::info!(target: AUDIT_LOG_TARGET, "{line}");
+        }
+    }
+}
+
+/// Format of log lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// Plain text lines.
+    Text,
+    /// One JSON object per line, see [format_json].
+    Json,
+}
+
+/// Where to write audit events in addition to the log.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditSink {
+    /// Audit events are only written to the log.
+    None,
+    /// Append one JSON object per line to a file.
+    File {
+        /// Path of the file.
+        path: String,
+    },
+    /// Send RFC 5424 syslog messages to a local socket or a remote UDP
+    /// `host:port`.
+    Syslog {
+        /// Path of a local datagram socket like `/dev/log` or `host:port`.
+        address: String,
+    },
+}
+
+/// Open destination of audit events.
+enum AuditWriter {
+    File(File),
+    UnixDatagram(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+/** Writes audit events to a separate sink.
+
+Events are written synchronously and events written to a file are synced to
+disk, so that they are persisted before the response is returned. Failures to
+write are logged, but do not fail the request.
+*/
+pub struct AuditLog {
+    writer: Mutex<AuditWriter>,
+    hostname: String,
+}
+
+impl AuditLog {
+    /// Syslog facility `log audit` (13) with severity `informational` (6).
+    const SYSLOG_PRIORITY: u8 = 13 * 8 + 6;
+
+    /// Start writing audit events to the sink unless it is
+    /// [AuditSink::None].
+    pub fn init(sink: AuditSink) {
+        let writer_res = match &sink {
+            AuditSink::None => return,
+            AuditSink::File { path } => File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(AuditWriter::File),
+            AuditSink::Syslog { address } if address.starts_with('/') => UnixDatagram::unbound()
+                .and_then(|socket| {
+                    socket.connect(address)?;
+                    Ok(AuditWriter::UnixDatagram(socket))
+                }),
+            AuditSink::Syslog { address } => UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+                socket.connect(address)?;
+                Ok(AuditWriter::Udp(socket))
+            }),
+        };
+        match writer_res {
+            Ok(writer) => {
+                log::info!("Audit events are written to {sink:?}.");
+                let audit_log = Self {
+                    writer: Mutex::new(writer),
+                    hostname: std::env::var("HOSTNAME")
+                        .ok()
+                        .filter(|hostname| !hostname.is_empty())
+                        .unwrap_or("-".to_string()),
+                };
+                if AUDIT_LOG.set(audit_log).is_err() {
+                    log::warn!("Audit sink was already initialized.");
+                }
+            }
+            Err(e) => log::error!("Unable to open audit sink {sink:?}: {e}"),
+        }
+    }
+
+    /// Write a single event.
+    fn write(&self, event_type: AuditEventType, line: &str) {
+        let mut writer = self.writer.lock().unwrap();
+        let write_res = match &mut *writer {
+            AuditWriter::File(file) => writeln!(file, "{line}").and_then(|()| file.sync_data()),
+            AuditWriter::UnixDatagram(socket) => socket
+                .send(self.syslog_message(event_type, line).as_bytes())
+                .map(|_len| ()),
+            AuditWriter::Udp(socket) => socket
+                .send(self.syslog_message(event_type, line).as_bytes())
+                .map(|_len| ()),
+        };
+        if let Err(e) = write_res {
+            log::warn!("Failed to write audit event: {e}");
+        }
+    }
+
+    /// Return an RFC 5424 syslog message with the event type as `MSGID`.
+    fn syslog_message(&self, event_type: AuditEventType, line: &str) -> String {
+        format!(
+            "<{}>1 {} {} pitsa {} {} - {line}",
+            Self::SYSLOG_PRIORITY,
+            der::rfc3339(now_epoch_micros()),
+            self.hostname,
+            std::process::id(),
+            event_type.as_str(),
+        )
+    }
+}
@@ -20,16 +20,18 @@
 #![doc = include_str!("../README.md")]
 
 use lib::conf::AppConfig;
+use lib::logging::LogFormat;
 use pitsa as lib;
 use std::process::ExitCode;
 use std::sync::Arc;
 
 fn main() -> ExitCode {
-    if let Err(e) = init_logger() {
+    let app_config = Arc::new(AppConfig::new(env!("CARGO_BIN_NAME")));
+    if let Err(e) = init_logger(app_config.log.format()) {
         println!("Failed to initialize logging: {e:?}");
         return ExitCode::FAILURE;
     }
-    let app_config = Arc::new(AppConfig::new(env!("CARGO_BIN_NAME")));
+    app_config.log_configuration();
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .worker_threads(app_config.limits.available_parallelism())
@@ -39,8 +41,12 @@ fn main() -> ExitCode {
 }
 
 /// Initialize the logging system and apply filters.
-fn init_logger() -> Result<(), log::SetLoggerError> {
-    env_logger::builder()
+fn init_logger(log_format: LogFormat) -> Result<(), log::SetLoggerError> {
+    let mut builder = env_logger::builder();
+    if log_format == LogFormat::Json {
+        builder.format(lib::logging::format_json);
+    }
+    builder
         // Set default log level
         .filter_level(log::LevelFilter::Debug)
         // Customize logging for dependencies
@@ -17,19 +17,48 @@
 
 //! REST API server and resources.
 
+mod archive_resources;
+mod authenticode_resources;
+mod content;
+mod cose_resources;
+mod dvcs_resources;
+mod ers_resources;
+mod jws_resources;
+mod metrics_resources;
+mod sigstore_resources;
+mod status_resources;
+mod tls;
+mod transparency_resources;
+mod tsa_resources;
+mod tsd_resources;
+mod tsp_hash_resources;
+mod tsp_json_resources;
 mod tsp_resources;
+mod verify_resources;
 
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
 use actix_web::http::header::ContentType;
-use actix_web::{App, HttpResponse, HttpServer, Responder, get, web};
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, Responder, error, get, web};
+use futures::StreamExt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tyst_api_rest_health::AppHealth;
 use tyst_api_rest_health::health_resources;
 use utoipa::OpenApi;
 
+use crate::ClientIdentity;
+use crate::RateLimitMode;
+use crate::RateLimits;
 use crate::TimeStamper;
+use crate::logging::RequestId;
+use crate::telemetry::Span;
+use crate::telemetry::SpanContext;
 
 /// Number of parallel requests the can be served for each assigned CPU core.
-const WORKERS_PER_CORE: usize = 1024;
+pub(crate) const WORKERS_PER_CORE: usize = 1024;
 
 /// Shared state between requests.
 #[derive(Clone)]
@@ -37,14 +66,34 @@ struct AppState {
     app: Arc<TimeStamper>,
 }
 
+/// Native TLS termination options.
+pub struct TlsOptions {
+    /// PEM encoded server certificate chain with the leaf first.
+    pub certificate_path: PathBuf,
+    /// PEM encoded server private key.
+    pub private_key_path: PathBuf,
+    /// PEM encoded CA certificates that client certificates are required to
+    /// chain to or `None` to not request client certificates.
+    pub client_ca_path: Option<PathBuf>,
+    /// How often to check the PEM files for changes.
+    pub reload_interval_micros: u64,
+}
+
 /// Run HTTP server.
 pub async fn run_http_server(
     available_parallelism: usize,
     bind_address: &str,
     bind_port: u16,
+    tls_options: Option<TlsOptions>,
     app_health: &Arc<dyn AppHealth>,
     app: &Arc<TimeStamper>,
 ) -> std::io::Result<()> {
+    let client_certificate_required = tls_options
+        .as_ref()
+        .is_some_and(|tls_options| tls_options.client_ca_path.is_some());
+    let tls_server_config = tls_options
+        .map(|tls_options| tls::server_config(tls_options).map_err(std::io::Error::other))
+        .transpose()?;
     let workers = available_parallelism;
     let max_connections = WORKERS_PER_CORE * workers;
     log::info!(
@@ -58,10 +107,86 @@ pub async fn run_http_server(
     let app_data = web::Data::<AppState>::new(app_state);
     let app_health = web::Data::<Arc<dyn AppHealth>>::new(Arc::clone(app_health));
 
-    HttpServer::new(move || {
+    let http_server = HttpServer::new(move || {
         let scope = web::scope("/api/v1")
+            .wrap_fn(move |service_request, service| {
+                match admitted_client(&app, &service_request, client_certificate_required) {
+                    Ok(client_identity) => {
+                        let mut span = Span::start_server(
+                            "HTTP request",
+                            service_request
+                                .headers()
+                                .get("traceparent")
+                                .and_then(|value| value.to_str().ok())
+                                .and_then(SpanContext::from_traceparent),
+                        );
+                        span.set_attribute("http.request.method", service_request.method());
+                        span.set_attribute("url.path", service_request.path());
+                        let request_id = RequestId::from_header_or_new(
+                            service_request
+                                .headers()
+                                .get(RequestId::HEADER_NAME)
+                                .and_then(|value| value.to_str().ok()),
+                        );
+                        let response_future = RequestId::scope(
+                            request_id.clone(),
+                            ClientIdentity::scope(client_identity, service.call(service_request)),
+                        );
+                        futures::future::Either::Left(async move {
+                            let mut response = span.in_scope(response_future).await;
+                            match &mut response {
+                                Ok(service_response) => {
+                                    if let Ok(value) = HeaderValue::from_str(&request_id) {
+                                        service_response.headers_mut().insert(
+                                            HeaderName::from_static(RequestId::HEADER_NAME),
+                                            value,
+                                        );
+                                    }
+                                    span.set_attribute(
+                                        "http.response.status_code",
+                                        service_response.status().as_u16(),
+                                    );
+                                    if service_response.status().is_server_error() {
+                                        span.set_error();
+                                    }
+                                }
+                                Err(_e) => span.set_error(),
+                            }
+                            response
+                        })
+                    }
+                    Err(e) => futures::future::Either::Right(futures::future::ready(Err(e))),
+                }
+            })
             .service(get_openapi)
-            .service(tsp_resources::tsp_raw_time_stamp_request);
+            .service(status_resources::status)
+            .service(tsp_resources::tsp_raw_time_stamp_request)
+            .service(tsp_resources::tsp_aggregated_time_stamp_request)
+            .service(tsp_json_resources::tsp_json_time_stamp_request)
+            .service(tsp_hash_resources::tsp_hash_time_stamp_request)
+            .service(verify_resources::tsp_verify)
+            .service(jws_resources::tsp_jws_time_stamp_request)
+            .service(jws_resources::tsp_jws_verify)
+            .service(cose_resources::tsp_cose_time_stamp_request)
+            .service(authenticode_resources::authenticode_time_stamp_request)
+            .service(dvcs_resources::dvcs_request)
+            .service(ers_resources::ers_create)
+            .service(ers_resources::ers_renew_time_stamp)
+            .service(ers_resources::ers_renew_hash_tree)
+            .service(sigstore_resources::sigstore_time_stamp_request)
+            .service(sigstore_resources::sigstore_certificate_chain)
+            .service(tsa_resources::tsa_certs)
+            .service(tsa_resources::tsa_info)
+            .service(tsd_resources::tsd_wrap)
+            .service(tsd_resources::tsd_unwrap)
+            .service(transparency_resources::transparency_signed_tree_head)
+            .service(transparency_resources::transparency_inclusion_proof)
+            .service(transparency_resources::transparency_consistency_proof)
+            .service(transparency_resources::transparency_entries)
+            .service(archive_resources::archive_by_serial_number)
+            .service(archive_resources::archive_by_imprint)
+            .service(archive_resources::archive_export)
+            .service(metrics_resources::metrics);
         App::new()
             .app_data(app_data.clone())
             .app_data(app_health.clone())
@@ -73,15 +198,202 @@ pub async fn run_http_server(
             .service(health_resources::health_ready)
             .service(health_resources::health_started)
     })
+    .on_connect(|connection, extensions| {
+        if let Some(client_identity) = tls::client_identity(connection) {
+            extensions.insert(client_identity);
+        }
+    })
     .workers(workers)
     .backlog(u32::try_from(max_connections / 2).unwrap()) // Default is 2048
     .worker_max_blocking_threads(max_connections)
-    .max_connections(max_connections)
-    .bind_auto_h2c((bind_address, bind_port))?
-    .disable_signals()
-    .shutdown_timeout(5) // Default 30
-    .run()
-    .await
+    .max_connections(max_connections);
+    let http_server = if let Some(tls_server_config) = tls_server_config {
+        log::info!("Serving HTTPS on {bind_address}:{bind_port}.");
+        http_server.bind_rustls_0_23((bind_address, bind_port), tls_server_config)?
+    } else {
+        http_server.bind_auto_h2c((bind_address, bind_port))?
+    };
+    http_server
+        .disable_signals()
+        .shutdown_timeout(5) // Default 30
+        .run()
+        .await
+}
+
+/// Return the identity of an authenticated client within the rate limits or
+/// the error response refusing the request.
+fn admitted_client(
+    app: &Arc<TimeStamper>,
+    service_request: &ServiceRequest,
+    client_certificate_required: bool,
+) -> Result<Option<ClientIdentity>, actix_web::Error> {
+    let client_identity = authenticated_client(app, service_request, client_certificate_required)?;
+    let ip_address = app.rate_limits().client_ip_address(
+        service_request.peer_addr().map(|peer_addr| peer_addr.ip()),
+        service_request
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok()),
+    );
+    let Err(retry_after_seconds) = app
+        .rate_limits()
+        .admit(ip_address, client_identity.as_ref())
+    else {
+        return Ok(client_identity);
+    };
+    if log::log_enabled!(log::Level::Debug) {
+        log::debug!("Rate limited request from {ip_address:?} ({client_identity:?}).");
+    }
+    let waiting_response = match service_request.path() {
+        _ if app.rate_limits().mode() == RateLimitMode::Http => None,
+        "/api/v1/tsp" | "/api/v1/tsp/aggregated" => Some((
+            content::CONTENT_TYPE_TS_REPLY,
+            RateLimits::waiting_time_stamp_resp(retry_after_seconds),
+        )),
+        _ => None,
+    };
+    let http_response = match waiting_response {
+        Some((content_type, body)) => HttpResponse::Ok()
+            .insert_header(("content-type", content_type))
+            .insert_header(("retry-after", retry_after_seconds.to_string()))
+            .body(body),
+        None => HttpResponse::TooManyRequests()
+            .insert_header(("retry-after", retry_after_seconds.to_string()))
+            .body("Rate limit exceeded."),
+    };
+    Err(error::InternalError::from_response("Rate limit exceeded.", http_response).into())
+}
+
+/// Resources under `/api/v1` that don't require authentication. A trailing
+/// `/` matches every resource below.
+const ANONYMOUS_RESOURCES: [&str; 5] = [
+    "openapi.json",
+    "status",
+    "tsa/",
+    "transparency/",
+    "tsp/verify",
+];
+
+/// Return `true` if the resource at `path` may be used without
+/// authentication.
+fn is_anonymous_resource(path: &str) -> bool {
+    path.strip_prefix("/api/v1/").is_some_and(|resource| {
+        ANONYMOUS_RESOURCES.iter().any(|anonymous| {
+            if anonymous.ends_with('/') {
+                resource.starts_with(anonymous)
+            } else {
+                resource.eq(*anonymous)
+            }
+        })
+    })
+}
+
+/// Return the identity of the client from its TLS client certificate or
+/// bearer token.
+///
+/// When TLS client certificates are verified, every request requires one.
+/// When bearer authentication is configured, every request except to the
+/// [ANONYMOUS_RESOURCES] requires a valid identity.
+fn authenticated_client(
+    app: &Arc<TimeStamper>,
+    service_request: &ServiceRequest,
+    client_certificate_required: bool,
+) -> Result<Option<ClientIdentity>, actix_web::Error> {
+    if let Some(client_identity) = service_request.conn_data::<ClientIdentity>() {
+        return Ok(Some(client_identity.clone()));
+    }
+    if client_certificate_required {
+        return Err(error::ErrorForbidden(
+            "A TLS client certificate is required.",
+        ));
+    }
+    let Some(bearer_authentication) = app.bearer_authentication() else {
+        return Ok(None);
+    };
+    let unauthorized = |msg: &'static str| {
+        error::InternalError::from_response(
+            msg,
+            HttpResponse::Unauthorized()
+                .insert_header(("www-authenticate", "Bearer"))
+                .body(msg),
+        )
+        .into()
+    };
+    let bearer_token = service_request
+        .headers()
+        .get("authorization")
+        .and_then(|header_value| header_value.to_str().ok())
+        .and_then(|header_value| header_value.strip_prefix("Bearer "));
+    match bearer_token {
+        Some(bearer_token) => bearer_authentication
+            .authenticate(bearer_token.trim())
+            .map(Some)
+            .map_err(|e| {
+                log::info!("Bearer token authentication failed: {e}");
+                unauthorized("Invalid bearer token.")
+            }),
+        None if is_anonymous_resource(service_request.path()) => Ok(None),
+        None => Err(unauthorized("A bearer token is required.")),
+    }
+}
+
+/// Upper bound of the request body buffer allocated before any data arrived.
+const MAX_PREALLOCATED_SIZE: usize = 64 * 1024;
+
+/// Read the full request body while enforcing a maximum size.
+async fn read_payload(
+    http_request: &HttpRequest,
+    payload: &mut web::Payload,
+    max_size: usize,
+) -> Result<web::Bytes, actix_web::Error> {
+    let content_length_estimate = http_request
+        .headers()
+        .get("content-length")
+        .and_then(|header_value| header_value.to_str().ok())
+        .and_then(|header_value_str| header_value_str.parse::<usize>().ok())
+        .unwrap_or(1024);
+    if content_length_estimate > max_size {
+        return Err(error::ErrorBadRequest(
+            "Content-length indicates that this request is too large.",
+        ));
+    }
+    // TODO: Since max_size is fairly low for TSRs, we could avoid heap alloc.
+    //       This however only makes sense when redoing the rest of the call stack.
+    // Don't trust the announced length for more than a modest allocation up front.
+    let mut request_body =
+        web::BytesMut::with_capacity(content_length_estimate.min(MAX_PREALLOCATED_SIZE));
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if (request_body.len() + chunk.len()) > max_size {
+            return Err(error::ErrorBadRequest("Request is too large."));
+        }
+        request_body.extend_from_slice(&chunk);
+    }
+    Ok(request_body.freeze())
+}
+
+/// Convert an arbitrary large non-negative decimal integer into unsigned
+/// big-endian bytes.
+fn decimal_to_unsigned_bytes(decimal: &str) -> Result<Vec<u8>, String> {
+    let decimal = decimal.trim();
+    if decimal.is_empty() || !decimal.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("Nonce must be a non-negative integer.".to_string());
+    }
+    // Little-endian accumulator: value = value * 10 + digit
+    let mut value: Vec<u8> = vec![];
+    for digit in decimal.bytes().map(|b| b - b'0') {
+        let mut carry = u16::from(digit);
+        for byte in value.iter_mut() {
+            let product = u16::from(*byte) * 10 + carry;
+            *byte = (product & 0xff) as u8;
+            carry = product >> 8;
+        }
+        if carry > 0 {
+            value.push(carry as u8);
+        }
+    }
+    value.reverse();
+    Ok(value)
 }
 
 /// Serve Open API documentation.
@@ -98,7 +410,34 @@ pub fn openapi_as_string() -> String {
     #[openapi(
         // Use Cargo.toml as source for the "info" section
         paths(
+            status_resources::status,
             tsp_resources::tsp_raw_time_stamp_request,
+            tsp_resources::tsp_aggregated_time_stamp_request,
+            tsp_json_resources::tsp_json_time_stamp_request,
+            tsp_hash_resources::tsp_hash_time_stamp_request,
+            verify_resources::tsp_verify,
+            jws_resources::tsp_jws_time_stamp_request,
+            jws_resources::tsp_jws_verify,
+            cose_resources::tsp_cose_time_stamp_request,
+            authenticode_resources::authenticode_time_stamp_request,
+            dvcs_resources::dvcs_request,
+            ers_resources::ers_create,
+            ers_resources::ers_renew_time_stamp,
+            ers_resources::ers_renew_hash_tree,
+            sigstore_resources::sigstore_time_stamp_request,
+            sigstore_resources::sigstore_certificate_chain,
+            tsa_resources::tsa_certs,
+            tsa_resources::tsa_info,
+            tsd_resources::tsd_wrap,
+            tsd_resources::tsd_unwrap,
+            transparency_resources::transparency_signed_tree_head,
+            transparency_resources::transparency_inclusion_proof,
+            transparency_resources::transparency_consistency_proof,
+            transparency_resources::transparency_entries,
+            archive_resources::archive_by_serial_number,
+            archive_resources::archive_by_imprint,
+            archive_resources::archive_export,
+            metrics_resources::metrics,
             health_resources::health,
             health_resources::health_live,
             health_resources::health_ready,
@@ -108,3 +447,33 @@ pub fn openapi_as_string() -> String {
     struct ApiDoc;
     ApiDoc::openapi().to_pretty_json().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_resources_are_anonymous() {
+        for path in [
+            "/api/v1/openapi.json",
+            "/api/v1/status",
+            "/api/v1/tsa/certs",
+            "/api/v1/transparency/sth",
+            "/api/v1/tsp/verify",
+        ] {
+            assert!(is_anonymous_resource(path), "{path}");
+        }
+        for path in [
+            "/api/v1/tsp",
+            "/api/v1/tspfoo",
+            "/api/v1/tsp/verify/x",
+            "/api/v1/tsa",
+            "/api/v1/statusx",
+            "/api/v1/admin/archive/export",
+            "/api/v1/metrics",
+            "/api/v2/status",
+        ] {
+            assert!(!is_anonymous_resource(path), "{path}");
+        }
+    }
+}
@@ -0,0 +1,233 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Administrative lookup and export of archived time-stamp tokens.
+
+use super::AppState;
+use crate::ArchivedToken;
+use crate::ClientIdentity;
+use crate::TokenArchive;
+use crate::util;
+use actix_web::Error;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::get;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use tyst::encdec::hex::ToHex;
+
+/// Archived time-stamp token.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedTokenResponse {
+    /// Hex encoded time-stamp serial number.
+    serial_number: String,
+    /// RFC 3339 point in time of the time-stamp.
+    gen_time: String,
+    /// Message imprint digest algorithm object identifier.
+    hash_algorithm: String,
+    /// Base64 encoded message imprint digest.
+    hash_value: String,
+    /// TSA policy object identifier.
+    policy: String,
+    /// Hex encoded SHA-256 fingerprint of the TSU certificate.
+    tsu_certificate_fingerprint: String,
+    /// Format of the time-stamp: `cms`, `jws`, `cose`, `authenticode` or
+    /// `dvcs`.
+    format: String,
+    /// Base64 encoded time-stamp as returned to the client: a DER
+    /// `TimeStampToken`, a JWS compact serialization, a `COSE_Sign1` or the
+    /// DER `ContentInfo` of an Authenticode or DVCS response.
+    time_stamp_token: String,
+}
+
+impl From<&ArchivedToken> for ArchivedTokenResponse {
+    fn from(archived_token: &ArchivedToken) -> Self {
+        Self {
+            serial_number: archived_token.serial_number.to_hex(),
+            gen_time: crate::der::rfc3339(archived_token.gen_time_epoch_micros),
+            hash_algorithm: tyst::encdec::oid::as_string(&archived_token.digest_algorithm_oid),
+            hash_value: tyst::encdec::base64::encode(&archived_token.digest),
+            policy: tyst::encdec::oid::as_string(&archived_token.policy_oid),
+            tsu_certificate_fingerprint: archived_token.tsu_certificate_fingerprint.to_hex(),
+            format: archived_token.format.as_str().to_string(),
+            time_stamp_token: tyst::encdec::base64::encode(&archived_token.time_stamp_token),
+        }
+    }
+}
+
+/// Archived time-stamp tokens over a message imprint.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedTokensResponse {
+    /// The matching tokens.
+    tokens: Vec<ArchivedTokenResponse>,
+}
+
+/// Query for tokens by message imprint.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct ImprintQuery {
+    /// Base64 encoded message imprint digest.
+    hash: String,
+}
+
+/// Query for tokens issued within a time range.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct ExportQuery {
+    /// Microseconds since the Unix epoch of the first token to export.
+    from: u64,
+    /// Microseconds since the Unix epoch of the last token to export
+    /// (inclusive).
+    to: u64,
+}
+
+/// Return the token archive or fail when disabled or the client is not an
+/// archive administrator.
+fn token_archive(app_state: &AppState) -> Result<&TokenArchive, Error> {
+    let Some(client_identity) = ClientIdentity::current().flatten() else {
+        return Err(error::ErrorUnauthorized(
+            "The token archive requires an authenticated client.",
+        ));
+    };
+    if !app_state.app.is_archive_admin(&client_identity) {
+        log::info!("Refused token archive access by '{client_identity}'.");
+        return Err(error::ErrorForbidden(
+            "The client is not a token archive administrator.",
+        ));
+    }
+    app_state
+        .app
+        .token_archive()
+        .ok_or(error::ErrorNotFound("The token archive is not enabled."))
+}
+
+/// Run a lookup in the token archive on a blocking thread.
+async fn look_up<T: Send + 'static>(
+    app_state: &AppState,
+    lookup: impl FnOnce(&TokenArchive) -> T + Send + 'static,
+) -> Result<T, Error> {
+    token_archive(app_state)?;
+    let app = Arc::clone(&app_state.app);
+    tokio::task::spawn_blocking(move || app.token_archive().map(lookup))
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or(error::ErrorNotFound("The token archive is not enabled."))
+}
+
+/// Return the archived token with the hex encoded serial number.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("serial" = String, Path, description = "Hex encoded serial number.")),
+    responses(
+        (status = 200, description = "Ok.", body = ArchivedTokenResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "The client is not authenticated."),
+        (status = 403, description = "The client is not a token archive administrator."),
+        (status = 404, description = "The token is not archived or the archive is not enabled."),
+    ),
+)]
+#[get("/admin/archive/serial/{serial}")]
+pub async fn archive_by_serial_number(
+    app_state: web::Data<AppState>,
+    serial: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let serial_number = util::decode_hex(serial.trim_start_matches("0x"))
+        .ok_or(error::ErrorBadRequest("Invalid serial number."))?;
+    // Lookup is by the minimal unsigned big-endian encoding
+    let first_non_zero = serial_number
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(serial_number.len().saturating_sub(1));
+    let archived_token = look_up(&app_state, move |token_archive| {
+        token_archive.by_serial_number(&serial_number[first_non_zero..])
+    })
+    .await?
+    .ok_or(error::ErrorNotFound("The token is not archived."))?;
+    Ok(HttpResponse::Ok().json(ArchivedTokenResponse::from(&archived_token)))
+}
+
+/// Return all archived tokens over a message imprint digest.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(ImprintQuery),
+    responses(
+        (status = 200, description = "Ok.", body = ArchivedTokensResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "The client is not authenticated."),
+        (status = 403, description = "The client is not a token archive administrator."),
+        (status = 404, description = "The archive is not enabled."),
+    ),
+)]
+#[get("/admin/archive/imprint")]
+pub async fn archive_by_imprint(
+    app_state: web::Data<AppState>,
+    query: web::Query<ImprintQuery>,
+) -> Result<HttpResponse, Error> {
+    let digest = tyst::encdec::base64::decode(&query.hash)
+        .map_err(|e| error::ErrorBadRequest(format!("Invalid hash: {e:?}")))?;
+    let archived_tokens = look_up(&app_state, move |token_archive| {
+        token_archive.by_digest(&digest)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ArchivedTokensResponse {
+        tokens: archived_tokens
+            .iter()
+            .map(ArchivedTokenResponse::from)
+            .collect(),
+    }))
+}
+
+/// Export the tokens issued within a time range as concatenated DER encoded
+/// `ArchivedToken`s.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "Ok.", content_type = "application/octet-stream"),
+        (status = 400, description = "Bad Request"),
+        (status = 401, description = "The client is not authenticated."),
+        (status = 403, description = "The client is not a token archive administrator."),
+        (status = 404, description = "The archive is not enabled."),
+    ),
+)]
+#[get("/admin/archive/export")]
+pub async fn archive_export(
+    app_state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, Error> {
+    if query.from > query.to {
+        return Err(error::ErrorBadRequest("from must not be larger than to."));
+    }
+    let export = token_archive(&app_state)?.export(query.from, query.to);
+    // Segments are read in bounded chunks on a blocking thread as the response
+    // is consumed
+    let chunks = futures::stream::unfold(export, |mut export| async move {
+        let (chunk, export) = tokio::task::spawn_blocking(move || (export.next_chunk(), export))
+            .await
+            .map_err(|e| log::error!("Archive export failed: {e}"))
+            .ok()?;
+        chunk.map(|chunk| (Ok::<_, Error>(web::Bytes::from(chunk)), export))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .streaming(chunks))
+}
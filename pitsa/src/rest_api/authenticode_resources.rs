@@ -0,0 +1,84 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Microsoft Authenticode legacy time-stamp protocol via HTTP.
+
+use super::AppState;
+use super::read_payload;
+use super::tsp_resources::BinaryType;
+use crate::TimeStampFormatError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+
+const CONTENT_TYPE_AUTHENTICODE: &str = "application/octet-stream";
+// The request contains the (base64 encoded) signature value to counter-sign.
+const MAX_SIZE: usize = 16 * 1024;
+
+/// Microsoft Authenticode legacy time-stamp protocol as used by for example
+/// `signtool sign /t`.
+///
+/// The digest algorithm of the counter-signature is the configured TSU digest
+/// algorithm, so this needs to be an algorithm that the verifying Windows
+/// version understands.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "Base64 encoded DER TimeStampRequest.",
+        content_type = CONTENT_TYPE_AUTHENTICODE,
+        content = inline(BinaryType)
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Ok. Base64 encoded DER PKCS #7 SignedData with the counter-signature.",
+            content_type = CONTENT_TYPE_AUTHENTICODE,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/authenticode")]
+pub async fn authenticode_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request = read_payload(&http_request, &mut payload, MAX_SIZE).await?;
+    match app_state
+        .app
+        .authenticode_time_stamp_request(&request)
+        .await
+    {
+        Ok(response) => Ok(HttpResponse::Ok()
+            .insert_header(("content-type", CONTENT_TYPE_AUTHENTICODE))
+            .body(response)),
+        Err(TimeStampFormatError::BadRequest(msg)) => {
+            log::debug!("Bad Authenticode time-stamp request: {msg}");
+            Err(error::ErrorBadRequest(msg))
+        }
+        Err(TimeStampFormatError::Unavailable(msg)) => {
+            log::warn!("Unable to process Authenticode time-stamp request: {msg}");
+            Err(error::ErrorServiceUnavailable(msg))
+        }
+    }
+}
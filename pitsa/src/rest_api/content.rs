@@ -0,0 +1,41 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Content types and request size limits shared by the resources.
+
+use crate::TimeStamper;
+use actix_web::HttpRequest;
+
+pub(super) const CONTENT_TYPE_JSON: &str = "application/json";
+pub(super) const CONTENT_TYPE_TS_REPLY: &str = "application/timestamp-reply";
+pub(super) const CONTENT_TYPE_PEM_CHAIN: &str = "application/pem-certificate-chain";
+
+/// Size limit of a DER encoded `TimeStampReq`.
+pub(super) const MAX_TSP_SIZE: usize = TimeStamper::MAX_REQUEST_SIZE;
+/// Size limit of a JSON request with a message imprint.
+pub(super) const MAX_JSON_SIZE: usize = 8 * 1024;
+/// Size limit of a request that embeds the data, which is kept in memory.
+pub(super) const MAX_EMBEDDED_DATA_SIZE: usize = 16 * 1024 * 1024;
+
+/// Return `true` if the request body is declared as JSON.
+pub(super) fn is_json(http_request: &HttpRequest) -> bool {
+    http_request
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(CONTENT_TYPE_JSON))
+}
@@ -0,0 +1,83 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamps in CBOR Object Signing and Encryption (COSE) format.
+
+use super::AppState;
+use super::content::CONTENT_TYPE_JSON;
+use super::content::MAX_JSON_SIZE;
+use super::read_payload;
+use super::tsp_json_resources::JsonTspRequest;
+use super::tsp_resources::BinaryType;
+use crate::CONTENT_TYPE_COSE;
+use crate::TimeStampFormatError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+
+/// Request a time-stamp over a message imprint as a COSE_Sign1.
+///
+/// The COSE_Sign1 is signed with the same key as the RFC 3161 time-stamp
+/// tokens and references the TSU certificate (or the full chain when
+/// `certReq` is set) in `x5chain`.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = JsonTspRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (
+            status = 200,
+            description = "Ok. Tagged COSE_Sign1.",
+            content_type = CONTENT_TYPE_COSE,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/tsp/cose")]
+pub async fn tsp_cose_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_JSON_SIZE).await?;
+    let imprint_request = serde_json::from_slice::<JsonTspRequest>(&request_body)
+        .map_err(|e| e.to_string())
+        .and_then(JsonTspRequest::into_imprint_request)
+        .map_err(error::ErrorBadRequest)?;
+    let cose_sign1 = app_state
+        .app
+        .cose_time_stamp_request(&imprint_request)
+        .await
+        .map_err(|e| match e {
+            TimeStampFormatError::BadRequest(msg) => {
+                log::debug!("Bad COSE time-stamp request: {msg}");
+                error::ErrorBadRequest(msg)
+            }
+            TimeStampFormatError::Unavailable(msg) => {
+                log::info!("Unable to provide COSE time-stamp: {msg}");
+                error::ErrorServiceUnavailable(msg)
+            }
+        })?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("content-type", CONTENT_TYPE_COSE))
+        .body(cose_sign1))
+}
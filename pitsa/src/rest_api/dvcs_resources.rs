@@ -0,0 +1,82 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Data Validation and Certification Server (DVCS) protocol as defined in
+//! [RFC 3029](https://www.rfc-editor.org/rfc/rfc3029) via HTTP.
+
+use super::AppState;
+use super::content::MAX_EMBEDDED_DATA_SIZE;
+use super::read_payload;
+use super::tsp_resources::BinaryType;
+use crate::TimeStampFormatError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+
+/// MIME type from RFC 3029 10.
+const CONTENT_TYPE_DVCS: &str = "application/dvcs";
+
+/// Data Validation and Certification Server request.
+///
+/// Supports the `cpd` (Certification of Possession of Data) and `ccpd`
+/// (Certification of Claim of Possession of Data) services. Requests for other
+/// services are answered with a signed error notice. The message imprint is
+/// subject to the same digest algorithm, policy and client rules as an
+/// RFC 3161 request, and the response is archived and logged like a token.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "DER encoded DVCSRequest, optionally encapsulated in CMS SignedData.",
+        content_type = CONTENT_TYPE_DVCS,
+        content = inline(BinaryType)
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Ok. DER encoded CMS SignedData with the DVCSResponse.",
+            content_type = CONTENT_TYPE_DVCS,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/dvcs")]
+pub async fn dvcs_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request = read_payload(&http_request, &mut payload, MAX_EMBEDDED_DATA_SIZE).await?;
+    match app_state.app.dvcs_request(&request).await {
+        Ok(response) => Ok(HttpResponse::Ok()
+            .insert_header(("content-type", CONTENT_TYPE_DVCS))
+            .body(response)),
+        Err(TimeStampFormatError::BadRequest(msg)) => {
+            log::debug!("Bad DVCS request: {msg}");
+            Err(error::ErrorBadRequest(msg))
+        }
+        Err(TimeStampFormatError::Unavailable(msg)) => {
+            log::warn!("Unable to process DVCS request: {msg}");
+            Err(error::ErrorServiceUnavailable(msg))
+        }
+    }
+}
@@ -0,0 +1,210 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Evidence Record Syntax (ERS) as defined in
+//! [RFC 4998](https://www.rfc-editor.org/rfc/rfc4998).
+
+use super::AppState;
+use super::content::CONTENT_TYPE_JSON;
+use super::read_payload;
+use crate::TimeStampFormatError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+
+// Evidence records grow with every renewal.
+const MAX_SIZE: usize = 256 * 1024;
+
+/// Request for evidence records over a set of data objects.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EvidenceRecordRequest {
+    /// Object identifier of the digest algorithm used for the data object
+    /// hashes and the hash tree.
+    hash_algorithm: String,
+    /// Base64 encoded data object hashes.
+    hashes: Vec<String>,
+}
+
+/// Evidence records in the same order as the data object hashes.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EvidenceRecordsResponse {
+    /// Base64 encoded DER `EvidenceRecord`s.
+    evidence_records: Vec<String>,
+}
+
+/// Request for a time-stamp renewal of an evidence record.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TimeStampRenewalRequest {
+    /// Base64 encoded DER `EvidenceRecord`.
+    evidence_record: String,
+}
+
+/// Request for a hash-tree renewal of an evidence record.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HashTreeRenewalRequest {
+    /// Base64 encoded DER `EvidenceRecord`.
+    evidence_record: String,
+    /// Object identifier of the new digest algorithm.
+    hash_algorithm: String,
+    /// Base64 encoded data object hash using the new digest algorithm.
+    hash: String,
+}
+
+/// Renewed evidence record.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EvidenceRecordResponse {
+    /// Base64 encoded DER `EvidenceRecord`.
+    evidence_record: String,
+}
+
+/// Create an evidence record for each data object hash, sealed by a single
+/// time-stamp over the archive hash tree.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = EvidenceRecordRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (status = 200, description = "Ok.", body = EvidenceRecordsResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/ers")]
+pub async fn ers_create(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_SIZE).await?;
+    let request = parse_json::<EvidenceRecordRequest>(&request_body)?;
+    let digest_algorithm_oid = parse_oid(&request.hash_algorithm)?;
+    let data_object_hashes = request
+        .hashes
+        .iter()
+        .map(|hash| decode_base64(hash))
+        .collect::<Result<Vec<_>, _>>()?;
+    let evidence_records = app_state
+        .app
+        .create_evidence_records(&digest_algorithm_oid, &data_object_hashes)
+        .await
+        .map_err(as_http_error)?;
+    Ok(HttpResponse::Ok().json(EvidenceRecordsResponse {
+        evidence_records: evidence_records
+            .iter()
+            .map(|encoded| tyst::encdec::base64::encode(encoded))
+            .collect(),
+    }))
+}
+
+/// Renew the time-stamp of an evidence record before the time-stamp's
+/// signature algorithm or certificate becomes weak.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = TimeStampRenewalRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (status = 200, description = "Ok.", body = EvidenceRecordResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/ers/renew/timestamp")]
+pub async fn ers_renew_time_stamp(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_SIZE).await?;
+    let request = parse_json::<TimeStampRenewalRequest>(&request_body)?;
+    let evidence_record = app_state
+        .app
+        .renew_evidence_record_time_stamp(&decode_base64(&request.evidence_record)?)
+        .await
+        .map_err(as_http_error)?;
+    Ok(HttpResponse::Ok().json(EvidenceRecordResponse {
+        evidence_record: tyst::encdec::base64::encode(&evidence_record),
+    }))
+}
+
+/// Renew the hash tree of an evidence record before the digest algorithm
+/// becomes weak.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = HashTreeRenewalRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (status = 200, description = "Ok.", body = EvidenceRecordResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/ers/renew/hashtree")]
+pub async fn ers_renew_hash_tree(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_SIZE).await?;
+    let request = parse_json::<HashTreeRenewalRequest>(&request_body)?;
+    let evidence_record = app_state
+        .app
+        .renew_evidence_record_hash_tree(
+            &decode_base64(&request.evidence_record)?,
+            &parse_oid(&request.hash_algorithm)?,
+            &decode_base64(&request.hash)?,
+        )
+        .await
+        .map_err(as_http_error)?;
+    Ok(HttpResponse::Ok().json(EvidenceRecordResponse {
+        evidence_record: tyst::encdec::base64::encode(&evidence_record),
+    }))
+}
+
+fn parse_json<'a, T: Deserialize<'a>>(request_body: &'a [u8]) -> Result<T, Error> {
+    serde_json::from_slice::<T>(request_body).map_err(error::ErrorBadRequest)
+}
+
+fn parse_oid(oid: &str) -> Result<Vec<u32>, Error> {
+    crate::der::oid_from_string(oid)
+        .map_err(|e| error::ErrorBadRequest(format!("Invalid hashAlgorithm: {e}")))
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, Error> {
+    tyst::encdec::base64::decode(value)
+        .map_err(|e| error::ErrorBadRequest(format!("Invalid base64 value: {e:?}")))
+}
+
+fn as_http_error(e: TimeStampFormatError) -> Error {
+    match e {
+        TimeStampFormatError::BadRequest(msg) => {
+            log::debug!("Bad evidence record request: {msg}");
+            error::ErrorBadRequest(msg)
+        }
+        TimeStampFormatError::Unavailable(msg) => {
+            log::warn!("Unable to process evidence record request: {msg}");
+            error::ErrorServiceUnavailable(msg)
+        }
+    }
+}
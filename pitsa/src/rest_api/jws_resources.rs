@@ -0,0 +1,245 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamps in JSON Web Signature (JWS) format.
+
+use super::AppState;
+use super::content::CONTENT_TYPE_JSON;
+use super::content::MAX_EMBEDDED_DATA_SIZE;
+use super::content::MAX_JSON_SIZE;
+use super::content::is_json;
+use super::read_payload;
+use super::tsp_json_resources::JsonTspRequest;
+use crate::CONTENT_TYPE_JOSE;
+use crate::ChainStatus;
+use crate::ExpectedImprint;
+use crate::JwsTimeStampVerification;
+use crate::TimeStampFormatError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use upkit_common::x509::cert::parse::CertificateParser;
+
+/// Request to verify a JWS time-stamp.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct JwsVerifyRequest {
+    /// JWS time-stamp in compact serialization.
+    jws: String,
+    /// Optional base64 encoded original data.
+    data: Option<String>,
+    /// Optional object identifier of the digest algorithm used for `hash`.
+    hash_algorithm: Option<String>,
+    /// Optional base64 encoded message imprint digest of the original data.
+    hash: Option<String>,
+}
+
+impl JwsVerifyRequest {
+    /// Return the JWS and the expected message imprint.
+    fn decode(self) -> Result<(String, Option<ExpectedImprint>), String> {
+        let decode_base64 = |name: &str, value: &str| {
+            tyst::encdec::base64::decode(value).map_err(|e| format!("Invalid {name}: {e:?}"))
+        };
+        let expected_imprint = match (self.data, self.hash_algorithm, self.hash) {
+            (Some(data), None, None) => Some(ExpectedImprint::Data(decode_base64("data", &data)?)),
+            (None, Some(hash_algorithm), Some(hash)) => Some(ExpectedImprint::Digest(
+                crate::der::oid_from_string(&hash_algorithm)
+                    .map_err(|e| format!("Invalid hashAlgorithm: {e}"))?,
+                decode_base64("hash", &hash)?,
+            )),
+            (None, None, None) => None,
+            _ => {
+                return Err(
+                    "Provide either data or both hashAlgorithm and hash, not both.".to_string(),
+                );
+            }
+        };
+        Ok((self.jws, expected_imprint))
+    }
+}
+
+/// Verification verdict of a JWS time-stamp.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct JwsVerifyResponse {
+    /// `true` when the signature and chain are valid and the imprint (if
+    /// provided) matches.
+    valid: bool,
+    /// Reason why the time-stamp is not valid.
+    failure: Option<String>,
+    /// Subject distinguished name of the signing certificate.
+    #[schema(value_type = Object)]
+    signer: serde_json::Value,
+    /// RFC 3339 point in time of the time-stamp.
+    gen_time: String,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    gen_time_epoch_micros: u64,
+    /// Accuracy of the point in time in microseconds.
+    accuracy_micros: Option<u64>,
+    /// TSA policy object identifier.
+    policy: String,
+    /// Hex encoded time-stamp serial number.
+    serial_number: String,
+    /// Hex encoded nonce.
+    nonce: Option<String>,
+    /// Message imprint digest algorithm object identifier.
+    hash_algorithm: String,
+    /// Base64url encoded message imprint digest.
+    hash_value: String,
+    /// Whether the message imprint matches the provided data or hash.
+    imprint_matches: Option<bool>,
+    /// `valid`, `invalid` or `noTrustAnchors`.
+    chain_status: String,
+    /// `good`, `revoked`, `unknown` or `notEmbedded` (no revocation
+    /// information is fetched for the signer's chain).
+    revocation_status: String,
+    /// Why the revocation status is `revoked` or `unknown`.
+    revocation_reason: Option<String>,
+}
+
+impl From<JwsTimeStampVerification> for JwsVerifyResponse {
+    fn from(verification: JwsTimeStampVerification) -> Self {
+        let failure = verification.failure();
+        let signer = verification
+            .time_stamp
+            .certificate_chain
+            .first()
+            .and_then(|encoded| CertificateParser::from_bytes(encoded).ok())
+            .and_then(|certificate| certificate.get_subject().ok())
+            .and_then(|value| serde_json::to_value(&value).ok())
+            .unwrap_or_default();
+        let claims = verification.time_stamp.claims;
+        Self {
+            valid: failure.is_none(),
+            failure,
+            signer,
+            gen_time: claims.gen_time,
+            gen_time_epoch_micros: claims.gen_time_epoch_micros,
+            accuracy_micros: claims.accuracy_micros,
+            policy: claims.policy,
+            serial_number: claims.serial_number,
+            nonce: claims.nonce,
+            hash_algorithm: claims.hash_algorithm,
+            hash_value: claims.hash_value,
+            imprint_matches: verification.imprint_matches,
+            chain_status: match verification.chain_status {
+                ChainStatus::Valid => "valid",
+                ChainStatus::Invalid(_) => "invalid",
+                ChainStatus::NoTrustAnchors => "noTrustAnchors",
+            }
+            .to_string(),
+            revocation_status: verification.revocation_status.as_str().to_string(),
+            revocation_reason: verification.revocation_status.reason(),
+        }
+    }
+}
+
+/// Request a time-stamp over a message imprint in JWS compact serialization.
+///
+/// The JWS is signed with the same key and certificate chain (`x5c`) as the
+/// RFC 3161 time-stamp tokens.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = JsonTspRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (status = 200, description = "Ok. JWS in compact serialization.", content_type = CONTENT_TYPE_JOSE, body = String),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/tsp/jws")]
+pub async fn tsp_jws_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_JSON_SIZE).await?;
+    let imprint_request = serde_json::from_slice::<JsonTspRequest>(&request_body)
+        .map_err(|e| e.to_string())
+        .and_then(JsonTspRequest::into_imprint_request)
+        .map_err(error::ErrorBadRequest)?;
+    let jws = app_state
+        .app
+        .jws_time_stamp_request(&imprint_request)
+        .await
+        .map_err(|e| match e {
+            TimeStampFormatError::BadRequest(msg) => {
+                log::debug!("Bad JWS time-stamp request: {msg}");
+                error::ErrorBadRequest(msg)
+            }
+            TimeStampFormatError::Unavailable(msg) => {
+                log::info!("Unable to provide JWS time-stamp: {msg}");
+                error::ErrorServiceUnavailable(msg)
+            }
+        })?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("content-type", CONTENT_TYPE_JOSE))
+        .body(jws))
+}
+
+/// Verify a JWS time-stamp against the configured trust anchors and
+/// optionally against the original data or message imprint.
+///
+/// The JWS can also be posted directly when there is no data to compare with.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "JSON request or JWS in compact serialization.",
+        content(
+            (JwsVerifyRequest = "application/json"),
+            (String = "application/jose"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "Ok. See the verdict for the outcome.", body = JwsVerifyResponse),
+        (status = 400, description = "Bad Request"),
+    ),
+)]
+#[post("/tsp/jws/verify")]
+pub async fn tsp_jws_verify(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_EMBEDDED_DATA_SIZE).await?;
+    let (jws, expected_imprint) = if is_json(&http_request) {
+        serde_json::from_slice::<JwsVerifyRequest>(&request_body)
+            .map_err(|e| e.to_string())
+            .and_then(JwsVerifyRequest::decode)
+            .map_err(error::ErrorBadRequest)?
+    } else {
+        (
+            String::from_utf8(request_body.to_vec()).map_err(error::ErrorBadRequest)?,
+            None,
+        )
+    };
+    // Chain, signature and revocation checks are CPU bound
+    let app = Arc::clone(&app_state.app);
+    let verification =
+        tokio::task::spawn_blocking(move || app.verify_jws_time_stamp(&jws, expected_imprint))
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .map_err(error::ErrorBadRequest)?;
+    Ok(HttpResponse::Ok().json(JwsVerifyResponse::from(verification)))
+}
@@ -0,0 +1,49 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Prometheus metrics.
+
+use super::AppState;
+use crate::Metrics;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::get;
+use actix_web::web;
+
+/// Return metrics in the Prometheus text-based exposition format.
+///
+/// Counters of responses by status and failure information, requests by
+/// digest algorithm, policy and client, latency histograms of the request
+/// processing phases and gauges of the time source and signer state.
+///
+/// The metrics reveal the configured clients and their request volume, so
+/// they require the same TLS client certificate or bearer token as the time
+/// stamping resources.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (status = 200, description = "Ok.", content_type = "text/plain"),
+        (status = 401, description = "A bearer token is required."),
+        (status = 403, description = "A TLS client certificate is required."),
+    ),
+)]
+#[get("/metrics")]
+pub async fn metrics(app_state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(Metrics::CONTENT_TYPE)
+        .body(app_state.app.metrics_exposition())
+}
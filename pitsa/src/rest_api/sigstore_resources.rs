@@ -0,0 +1,151 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Sigstore [timestamp-authority](https://github.com/sigstore/timestamp-authority)
+//! compatible API.
+
+use super::AppState;
+use super::content::CONTENT_TYPE_PEM_CHAIN;
+use super::content::CONTENT_TYPE_TS_REPLY;
+use super::content::MAX_TSP_SIZE;
+use super::content::is_json;
+use super::decimal_to_unsigned_bytes;
+use super::read_payload;
+use super::tsa_resources;
+use super::tsp_resources::BinaryType;
+use crate::ImprintRequest;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::get;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+/// JSON time-stamp request.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct JsonTimeStampRequest {
+    /// Base64 encoded message imprint digest.
+    artifact_hash: String,
+    /// Message imprint digest algorithm: `sha256`, `sha384` or `sha512`.
+    hash_algorithm: String,
+    /// Optional nonce as an arbitrary large positive JSON integer.
+    #[schema(value_type = Option<u64>)]
+    nonce: Option<Box<RawValue>>,
+    /// Optional requested TSA policy object identifier.
+    #[serde(rename = "tsaPolicyOID")]
+    tsa_policy_oid: Option<String>,
+    /// Request that the TSU certificate chain is included in the response.
+    certificates: Option<bool>,
+}
+
+impl JsonTimeStampRequest {
+    /// Convert into a request over the message imprint.
+    fn into_imprint_request(self) -> Result<ImprintRequest, String> {
+        let digest_algorithm_oid = match self.hash_algorithm.to_lowercase().as_str() {
+            "sha256" => vec![2, 16, 840, 1, 101, 3, 4, 2, 1],
+            "sha384" => vec![2, 16, 840, 1, 101, 3, 4, 2, 2],
+            "sha512" => vec![2, 16, 840, 1, 101, 3, 4, 2, 3],
+            other => return Err(format!("Unsupported hash algorithm '{other}'.")),
+        };
+        let digest = tyst::encdec::base64::decode(&self.artifact_hash)
+            .map_err(|e| format!("Invalid artifactHash: {e:?}"))?;
+        let mut imprint_request = ImprintRequest::new(
+            &digest_algorithm_oid,
+            &digest,
+            self.certificates.unwrap_or(false),
+        );
+        if let Some(policy_oid) = self.tsa_policy_oid.filter(|oid| !oid.is_empty()) {
+            imprint_request.policy_oid = Some(
+                crate::der::oid_from_string(&policy_oid)
+                    .map_err(|e| format!("Invalid tsaPolicyOID: {e}"))?,
+            );
+        }
+        if let Some(nonce) = self.nonce {
+            imprint_request.nonce = Some(decimal_to_unsigned_bytes(nonce.get())?);
+        }
+        Ok(imprint_request)
+    }
+}
+
+/// Request a time-stamp using a DER encoded `TimeStampReq` or a JSON request.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "DER encoded TimeStampReq or JSON request.",
+        content(
+            (BinaryType = "application/timestamp-query"),
+            (JsonTimeStampRequest = "application/json"),
+        ),
+    ),
+    responses(
+        (
+            status = 201,
+            description = "Created. Parse the DER encoded TimeStampResponse for actual status defined in the time-stamping protocol.",
+            content_type = CONTENT_TYPE_TS_REPLY,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+    ),
+)]
+#[post("/timestamp")]
+pub async fn sigstore_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_TSP_SIZE).await?;
+    let time_stamp_response = if is_json(&http_request) {
+        let imprint_request = serde_json::from_slice::<JsonTimeStampRequest>(&request_body)
+            .map_err(|e| e.to_string())
+            .and_then(JsonTimeStampRequest::into_imprint_request)
+            .map_err(error::ErrorBadRequest)?;
+        app_state
+            .app
+            .imprint_time_stamp_request(&imprint_request)
+            .await
+    } else {
+        app_state.app.raw_time_stamp_request(&request_body).await
+    };
+    Ok(HttpResponse::Created()
+        .insert_header(("content-type", CONTENT_TYPE_TS_REPLY))
+        .body(time_stamp_response))
+}
+
+/// Return the current TSU certificate chain as PEM with the leaf first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200,
+            description = "Ok. PEM encoded certificate chain.",
+            content_type = CONTENT_TYPE_PEM_CHAIN,
+            body = String,
+        ),
+        (status = 503, description = "No certificate chain is available (yet)."),
+    ),
+)]
+#[get("/timestamp/certchain")]
+pub async fn sigstore_certificate_chain(
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    tsa_resources::pem_certificate_chain(&app_state)
+}
@@ -0,0 +1,228 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Detailed status explaining the readiness of this instance.
+
+use super::AppState;
+use crate::ReadinessCondition;
+use crate::SelfTest;
+use crate::SignerStatus;
+use crate::TimeSourceStatus;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::get;
+use actix_web::web;
+use serde::Serialize;
+
+/// A condition that must be fulfilled for the instance to be ready.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ConditionResponse {
+    /// Name of the condition.
+    name: String,
+    /// `true` if the condition is fulfilled.
+    ready: bool,
+    /// Human readable explanation.
+    message: String,
+}
+
+impl From<&ReadinessCondition> for ConditionResponse {
+    fn from(readiness_condition: &ReadinessCondition) -> Self {
+        Self {
+            name: readiness_condition.name.to_string(),
+            ready: readiness_condition.ready,
+            message: readiness_condition.message.to_owned(),
+        }
+    }
+}
+
+/// State of the time source.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TimeSourceResponse {
+    /// `ntp` or `system` when only the local system time is used.
+    source: String,
+    /// NTP host in use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ntp_host: Option<String>,
+    /// Accuracy of the most recent time measurement in microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accuracy_micros: Option<u64>,
+    /// Worst accuracy accepted when issuing time-stamps in microseconds.
+    tolerable_accuracy_micros: u64,
+    /// `true` if the most recent time measurement was within tolerance.
+    within_tolerance: bool,
+    /// Offset to the NTP host in microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ntp_offset_micros: Option<i64>,
+    /// Roundtrip time to the NTP host in microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ntp_roundtrip_micros: Option<u64>,
+    /// Seconds since the most recent successful NTP response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_sync_age_seconds: Option<u64>,
+}
+
+impl TimeSourceResponse {
+    fn new(time_source_status: TimeSourceStatus, now_epoch_micros: u64) -> Self {
+        Self {
+            source: if time_source_status.ntp_host.is_some() {
+                "ntp".to_string()
+            } else {
+                "system".to_string()
+            },
+            ntp_host: time_source_status.ntp_host,
+            accuracy_micros: time_source_status.declared_accuracy_micros,
+            tolerable_accuracy_micros: time_source_status.tolerable_accuracy_micros,
+            within_tolerance: time_source_status.within_tolerance,
+            ntp_offset_micros: time_source_status.ntp_offset_micros,
+            ntp_roundtrip_micros: time_source_status.ntp_roundtrip_micros,
+            last_sync_age_seconds: time_source_status
+                .last_ntp_sync_epoch_micros
+                .map(|epoch_micros| now_epoch_micros.saturating_sub(epoch_micros) / 1_000_000),
+        }
+    }
+}
+
+/// State of the signing key and certificate.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SignerResponse {
+    /// RFC 3339 start of the signing certificate validity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_before: Option<String>,
+    /// RFC 3339 end of the signing certificate validity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_after: Option<String>,
+    /// Seconds since the oldest revocation information of the chain was
+    /// produced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revocation_info_age_seconds: Option<u64>,
+    /// `true` if revocation information is missing for any certificate in
+    /// the chain.
+    revocation_info_missing: bool,
+    /// Number of signatures made with the current key.
+    signature_count: u64,
+    /// Why the most recent enrollment failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enrollment_error: Option<String>,
+}
+
+impl SignerResponse {
+    fn new(
+        signer_status: Option<SignerStatus>,
+        enrollment_error: Option<String>,
+        now_epoch_micros: u64,
+    ) -> Self {
+        Self {
+            not_before: signer_status
+                .as_ref()
+                .map(|signer_status| crate::der::rfc3339(signer_status.not_before_epoch_micros)),
+            not_after: signer_status
+                .as_ref()
+                .map(|signer_status| crate::der::rfc3339(signer_status.not_after_epoch_micros)),
+            revocation_info_age_seconds: signer_status
+                .as_ref()
+                .and_then(|signer_status| signer_status.oldest_revocation_info_epoch_micros)
+                .map(|epoch_micros| now_epoch_micros.saturating_sub(epoch_micros) / 1_000_000),
+            revocation_info_missing: signer_status
+                .as_ref()
+                .is_some_and(|signer_status| signer_status.revocation_info_missing),
+            signature_count: signer_status
+                .as_ref()
+                .map(|signer_status| signer_status.signature_count)
+                .unwrap_or_default(),
+            enrollment_error,
+        }
+    }
+}
+
+/// Outcome of a built-in self-test.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SelfTestResponse {
+    /// Name of the test.
+    name: String,
+    /// `true` if the test passed.
+    passed: bool,
+    /// Why the test failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl From<&SelfTest> for SelfTestResponse {
+    fn from(self_test: &SelfTest) -> Self {
+        Self {
+            name: self_test.name.to_string(),
+            passed: self_test.result.is_ok(),
+            message: self_test.result.as_ref().err().cloned(),
+        }
+    }
+}
+
+/// Detailed status of this instance.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct StatusResponse {
+    /// `true` if all readiness conditions are fulfilled.
+    ready: bool,
+    /// `false` if a restart of this instance might help.
+    live: bool,
+    /// Each condition that must be fulfilled to be ready.
+    conditions: Vec<ConditionResponse>,
+    /// State of the time source.
+    time_source: TimeSourceResponse,
+    /// State of the signing key and certificate.
+    signer: SignerResponse,
+    /// Outcome of the built-in self-tests.
+    self_tests: Vec<SelfTestResponse>,
+    /// Configuration that works, but is probably not intended for production.
+    configuration_warnings: Vec<String>,
+}
+
+/// Return the detailed status of this instance with each readiness condition.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (status = 200, description = "Ok.", body = StatusResponse),
+    ),
+)]
+#[get("/status")]
+pub async fn status(app_state: web::Data<AppState>) -> impl Responder {
+    let app = &app_state.app;
+    let now_epoch_micros = upkit_common::util::time::now_epoch_micros();
+    let conditions = app.readiness_conditions();
+    HttpResponse::Ok().json(StatusResponse {
+        ready: conditions
+            .iter()
+            .all(|readiness_condition| readiness_condition.ready),
+        live: app.is_live(),
+        conditions: conditions.iter().map(ConditionResponse::from).collect(),
+        time_source: TimeSourceResponse::new(app.time_source_status(), now_epoch_micros),
+        signer: SignerResponse::new(
+            app.signer_status(),
+            app.signer_enrollment_error(),
+            now_epoch_micros,
+        ),
+        self_tests: app
+            .self_tests()
+            .iter()
+            .map(SelfTestResponse::from)
+            .collect(),
+        configuration_warnings: app.configuration_warnings().to_vec(),
+    })
+}
@@ -0,0 +1,225 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Native TLS termination with certificates reloaded from disk on change.
+
+use super::TlsOptions;
+use crate::ClientIdentity;
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::rt::net::TcpStream;
+use rustls::RootCertStore;
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::PrivateKeyDer;
+use rustls::pki_types::pem::PemObject;
+use rustls::server::ClientHello;
+use rustls::server::ResolvesServerCert;
+use rustls::server::WebPkiClientVerifier;
+use rustls::sign::CertifiedKey;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/** Server certificate resolver that serves the latest PEM files.
+
+The files are polled for modification and a successfully loaded new
+certificate chain and private key are used for all following handshakes.
+A failed reload (e.g. while the files are partially written) keeps the
+previous certificate.
+*/
+#[derive(Debug)]
+pub struct ReloadingCertResolver {
+    certificate_path: PathBuf,
+    private_key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+    modified: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl ReloadingCertResolver {
+    /// Return a new instance that checks the files for changes every
+    /// `reload_interval_micros`.
+    pub fn new(
+        certificate_path: PathBuf,
+        private_key_path: PathBuf,
+        provider: &Arc<CryptoProvider>,
+        reload_interval_micros: u64,
+    ) -> Result<Arc<Self>, String> {
+        let modified = Self::modified(&certificate_path, &private_key_path);
+        let certified_key =
+            Self::load_certified_key(&certificate_path, &private_key_path, provider)?;
+        Ok(Arc::new(Self {
+            certificate_path,
+            private_key_path,
+            provider: Arc::clone(provider),
+            certified_key: RwLock::new(Arc::new(certified_key)),
+            modified: Mutex::new(modified),
+        })
+        .init(reload_interval_micros))
+    }
+
+    /// Start background task that reloads the files when modified.
+    fn init(self: Arc<Self>, reload_interval_micros: u64) -> Arc<Self> {
+        let self_clone = Arc::clone(&self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_micros(reload_interval_micros))
+                    .await;
+                self_clone.reload_if_modified();
+            }
+        });
+        self
+    }
+
+    /// Reload the certificate chain and private key if any file changed.
+    fn reload_if_modified(&self) {
+        let modified = Self::modified(&self.certificate_path, &self.private_key_path);
+        if *self.modified.lock().unwrap() == modified {
+            return;
+        }
+        match Self::load_certified_key(
+            &self.certificate_path,
+            &self.private_key_path,
+            &self.provider,
+        ) {
+            Ok(certified_key) => {
+                *self.certified_key.write().unwrap() = Arc::new(certified_key);
+                *self.modified.lock().unwrap() = modified;
+                log::info!(
+                    "Reloaded TLS certificate from '{}'.",
+                    self.certificate_path.display()
+                );
+            }
+            Err(e) => log::warn!("Keeping current TLS certificate: {e}"),
+        }
+    }
+
+    /// Return the last modification times of the certificate and key files.
+    fn modified(
+        certificate_path: &Path,
+        private_key_path: &Path,
+    ) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path: &Path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        (modified(certificate_path), modified(private_key_path))
+    }
+
+    /// Parse the PEM encoded certificate chain and private key.
+    fn load_certified_key(
+        certificate_path: &Path,
+        private_key_path: &Path,
+        provider: &Arc<CryptoProvider>,
+    ) -> Result<CertifiedKey, String> {
+        let certificate_chain = CertificateDer::pem_file_iter(certificate_path)
+            .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| {
+                format!(
+                    "Unable to read TLS certificates from '{}': {e:?}",
+                    certificate_path.display()
+                )
+            })?;
+        if certificate_chain.is_empty() {
+            return Err(format!(
+                "No TLS certificate found in '{}'.",
+                certificate_path.display()
+            ));
+        }
+        let private_key = PrivateKeyDer::from_pem_file(private_key_path).map_err(|e| {
+            format!(
+                "Unable to read TLS private key from '{}': {e:?}",
+                private_key_path.display()
+            )
+        })?;
+        let signing_key = provider
+            .key_provider
+            .load_private_key(private_key)
+            .map_err(|e| format!("Unsupported TLS private key: {e}"))?;
+        Ok(CertifiedKey::new(certificate_chain, signing_key))
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(Arc::clone(&self.certified_key.read().unwrap()))
+    }
+}
+
+/// Return a TLS server configuration that serves the PEM files, reloads them
+/// when changed and optionally verifies client certificates.
+///
+/// Connections without a client certificate are accepted, so that health
+/// probes and metrics scrapers don't need one. The `/api/v1` resources
+/// require a verified client certificate instead.
+///
+/// actix-web offers `h2` and `http/1.1` via ALPN for rustls listeners.
+pub fn server_config(tls_options: TlsOptions) -> Result<ServerConfig, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let cert_resolver = ReloadingCertResolver::new(
+        tls_options.certificate_path,
+        tls_options.private_key_path,
+        &provider,
+        tls_options.reload_interval_micros,
+    )?;
+    let server_config_builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Unable to configure TLS: {e}"))?;
+    let Some(client_ca_path) = tls_options.client_ca_path else {
+        return Ok(server_config_builder
+            .with_no_client_auth()
+            .with_cert_resolver(cert_resolver));
+    };
+    let mut client_trust_anchors = RootCertStore::empty();
+    for certificate in CertificateDer::pem_file_iter(&client_ca_path).map_err(|e| {
+        format!(
+            "Unable to read TLS client CA certificates from '{}': {e:?}",
+            client_ca_path.display()
+        )
+    })? {
+        client_trust_anchors
+            .add(certificate.map_err(|e| format!("Invalid TLS client CA certificate: {e:?}"))?)
+            .map_err(|e| format!("Invalid TLS client CA certificate: {e}"))?;
+    }
+    let client_cert_verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(client_trust_anchors), provider)
+            .allow_unauthenticated()
+            .build()
+            .map_err(|e| format!("Unable to configure TLS client authentication: {e}"))?;
+    log::info!(
+        "Requiring TLS client certificates issued by the CAs in '{}' for /api/v1.",
+        client_ca_path.display()
+    );
+    Ok(server_config_builder
+        .with_client_cert_verifier(client_cert_verifier)
+        .with_cert_resolver(cert_resolver))
+}
+
+/// Return the identity of the client certificate of a TLS connection.
+pub fn client_identity(connection: &dyn std::any::Any) -> Option<ClientIdentity> {
+    let tls_stream = connection.downcast_ref::<TlsStream<TcpStream>>()?;
+    let (_, server_connection) = tls_stream.get_ref();
+    server_connection
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .map(|certificate| ClientIdentity::from_certificate(certificate))
+}
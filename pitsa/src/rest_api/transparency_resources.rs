@@ -0,0 +1,267 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Token transparency log of all issued time-stamp tokens.
+
+use super::AppState;
+use crate::TransparencyLog;
+use actix_web::Error;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::get;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use tyst::encdec::hex::ToHex;
+
+/// Largest number of entries returned by a single request.
+const MAX_ENTRIES: u64 = 1000;
+
+/// Signed tree head of the transparency log.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SignedTreeHeadResponse {
+    /// Hex encoded identifier of the log. Tree heads of different logs can't
+    /// be proven consistent.
+    log_id: String,
+    /// Number of entries in the tree.
+    tree_size: u64,
+    /// Milliseconds since the Unix epoch when the tree head was signed.
+    timestamp: u64,
+    /// Base64 encoded Merkle Tree Hash.
+    root_hash: String,
+    /// Digest algorithm object identifier of the tree.
+    hash_algorithm: String,
+    /// Signature algorithm object identifier.
+    signature_algorithm: String,
+    /// Base64 encoded signature over the RFC 6962 `TreeHeadSignature`
+    /// followed by the length prefixed log identifier.
+    tree_head_signature: String,
+    /// Base64 encoded DER signing certificate chain with the leaf first.
+    certificate_chain: Vec<String>,
+}
+
+/// Query for an inclusion proof.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProofQuery {
+    /// Base64 encoded hash of the DER encoded `TimeStampToken`.
+    hash: String,
+    /// Size of the tree to prove inclusion in.
+    tree_size: u64,
+}
+
+/// Inclusion proof of a token.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProofResponse {
+    /// Index of the token's leaf.
+    leaf_index: u64,
+    /// Base64 encoded audit path from the leaf to the root.
+    audit_path: Vec<String>,
+}
+
+/// Query for a consistency proof.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct ConsistencyProofQuery {
+    /// Size of the earlier tree.
+    first: u64,
+    /// Size of the later tree.
+    second: u64,
+}
+
+/// Consistency proof between two tree sizes.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ConsistencyProofResponse {
+    /// Base64 encoded nodes of the proof.
+    consistency: Vec<String>,
+}
+
+/// Query for a range of entries.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct EntriesQuery {
+    /// Index of the first entry.
+    start: u64,
+    /// Index of the last entry (inclusive).
+    end: u64,
+}
+
+/// Entry of the transparency log.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EntryResponse {
+    /// Base64 encoded DER `TransparencyLogEntry` that is the leaf input.
+    leaf_input: String,
+    /// Base64 encoded hash of the DER encoded `TimeStampToken`.
+    token_hash: String,
+    /// Hex encoded time-stamp serial number.
+    serial_number: String,
+    /// RFC 3339 point in time of the time-stamp.
+    gen_time: String,
+}
+
+/// Entries of the transparency log.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EntriesResponse {
+    /// The requested entries.
+    entries: Vec<EntryResponse>,
+}
+
+/// Return the transparency log or fail when disabled.
+fn transparency_log(app_state: &AppState) -> Result<&TransparencyLog, Error> {
+    app_state
+        .app
+        .transparency_log()
+        .ok_or(error::ErrorNotFound("The transparency log is not enabled."))
+}
+
+/// Return the signed head of the token transparency log.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (status = 200, description = "Ok.", body = SignedTreeHeadResponse),
+        (status = 404, description = "The transparency log is not enabled."),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[get("/transparency/sth")]
+pub async fn transparency_signed_tree_head(
+    app_state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    transparency_log(&app_state)?;
+    let signed_tree_head = app_state
+        .app
+        .transparency_log_signed_tree_head()
+        .await
+        .ok_or(error::ErrorServiceUnavailable(
+            "Unable to sign the tree head.",
+        ))?;
+    Ok(HttpResponse::Ok().json(SignedTreeHeadResponse {
+        log_id: signed_tree_head.log_id.to_hex(),
+        tree_size: signed_tree_head.tree_size,
+        timestamp: signed_tree_head.timestamp_millis,
+        root_hash: tyst::encdec::base64::encode(&signed_tree_head.root_hash),
+        hash_algorithm: tyst::encdec::oid::as_string(&signed_tree_head.digest_algorithm_oid),
+        signature_algorithm: tyst::encdec::oid::as_string(&signed_tree_head.signing_algorithm_oid),
+        tree_head_signature: tyst::encdec::base64::encode(&signed_tree_head.signature),
+        certificate_chain: signed_tree_head
+            .certificate_chain
+            .iter()
+            .map(|encoded| tyst::encdec::base64::encode(encoded))
+            .collect(),
+    }))
+}
+
+/// Return the proof that a token is included in the tree of the given size.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(InclusionProofQuery),
+    responses(
+        (status = 200, description = "Ok.", body = InclusionProofResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 404, description = "The token is not in the tree or the log is not enabled."),
+    ),
+)]
+#[get("/transparency/proof")]
+pub async fn transparency_inclusion_proof(
+    app_state: web::Data<AppState>,
+    query: web::Query<InclusionProofQuery>,
+) -> Result<HttpResponse, Error> {
+    let transparency_log = transparency_log(&app_state)?;
+    let token_hash = tyst::encdec::base64::decode(&query.hash)
+        .map_err(|e| error::ErrorBadRequest(format!("Invalid hash: {e:?}")))?;
+    let leaf_index = transparency_log
+        .index_of(&token_hash)
+        .filter(|leaf_index| *leaf_index < query.tree_size)
+        .ok_or(error::ErrorNotFound("The token is not in the tree."))?;
+    let audit_path = transparency_log
+        .inclusion_proof(leaf_index, query.tree_size)
+        .ok_or(error::ErrorBadRequest("The tree size is out of range."))?;
+    Ok(HttpResponse::Ok().json(InclusionProofResponse {
+        leaf_index,
+        audit_path: audit_path
+            .iter()
+            .map(|node| tyst::encdec::base64::encode(node))
+            .collect(),
+    }))
+}
+
+/// Return the proof that the earlier tree is a prefix of the later tree.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(ConsistencyProofQuery),
+    responses(
+        (status = 200, description = "Ok.", body = ConsistencyProofResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 404, description = "The transparency log is not enabled."),
+    ),
+)]
+#[get("/transparency/consistency")]
+pub async fn transparency_consistency_proof(
+    app_state: web::Data<AppState>,
+    query: web::Query<ConsistencyProofQuery>,
+) -> Result<HttpResponse, Error> {
+    let consistency = transparency_log(&app_state)?
+        .consistency_proof(query.first, query.second)
+        .ok_or(error::ErrorBadRequest("The tree sizes are out of range."))?;
+    Ok(HttpResponse::Ok().json(ConsistencyProofResponse {
+        consistency: consistency
+            .iter()
+            .map(|node| tyst::encdec::base64::encode(node))
+            .collect(),
+    }))
+}
+
+/// Return a range of entries of the transparency log.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(EntriesQuery),
+    responses(
+        (status = 200, description = "Ok.", body = EntriesResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 404, description = "The transparency log is not enabled."),
+    ),
+)]
+#[get("/transparency/entries")]
+pub async fn transparency_entries(
+    app_state: web::Data<AppState>,
+    query: web::Query<EntriesQuery>,
+) -> Result<HttpResponse, Error> {
+    let transparency_log = transparency_log(&app_state)?;
+    if query.start > query.end {
+        return Err(error::ErrorBadRequest("start must not be larger than end."));
+    }
+    let end = query
+        .end
+        .min(query.start.saturating_add(MAX_ENTRIES - 1))
+        .min(transparency_log.tree_size().saturating_sub(1));
+    let entries = (query.start..=end)
+        .filter_map(|index| transparency_log.entry(index))
+        .map(|entry| EntryResponse {
+            leaf_input: tyst::encdec::base64::encode(&entry.as_bytes()),
+            token_hash: tyst::encdec::base64::encode(&entry.token_hash),
+            serial_number: entry.serial_number.to_hex(),
+            gen_time: crate::der::rfc3339(entry.gen_time_epoch_micros),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(EntriesResponse { entries }))
+}
@@ -0,0 +1,239 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Discovery of the TSU certificate chain and the capabilities of this TSA.
+
+use super::AppState;
+use super::content::CONTENT_TYPE_PEM_CHAIN;
+use super::tsp_resources::BinaryType;
+use crate::Transport;
+use actix_web::Error;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::get;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+
+const CONTENT_TYPE_PKCS7_CERTS_ONLY: &str = "application/pkcs7-mime; smime-type=certs-only";
+
+/// Query for the TSU certificate chain.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct CertsQuery {
+    /// `pem` (default), `pkcs7` or `json`.
+    format: Option<String>,
+    /// Include recently retired certificate chains. Only for the `pkcs7` and
+    /// `json` formats.
+    retired: Option<bool>,
+}
+
+/// A TSU certificate chain that is no longer used for new time-stamps.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RetiredCertificateChainResponse {
+    /// RFC 3339 time when the chain was replaced.
+    retired_at: String,
+    /// Base64 encoded DER certificates with the leaf first.
+    certificate_chain: Vec<String>,
+}
+
+/// TSU certificate chains.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CertsResponse {
+    /// Base64 encoded DER certificates of the current chain with the leaf
+    /// first.
+    certificate_chain: Vec<String>,
+    /// Recently retired chains with the newest first when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retired: Option<Vec<RetiredCertificateChainResponse>>,
+}
+
+/// A way to submit requests to this TSA.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TransportResponse {
+    /// Request and response format.
+    format: String,
+    /// `http`, `https` or `tcp`.
+    protocol: String,
+    /// Path of the HTTP resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// Listening port.
+    port: u16,
+}
+
+impl From<&Transport> for TransportResponse {
+    fn from(transport: &Transport) -> Self {
+        Self {
+            format: transport.format.to_string(),
+            protocol: transport.protocol.to_string(),
+            path: transport.path.map(str::to_string),
+            port: transport.port,
+        }
+    }
+}
+
+/// Capabilities of this TSA.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct InfoResponse {
+    /// Object identifiers of the TSA policies that requests may ask for.
+    policies: Vec<String>,
+    /// Object identifiers of the allowed message imprint digest algorithms.
+    /// Empty when any supported algorithm is allowed.
+    allowed_digest_algorithms: Vec<String>,
+    /// Object identifier of the time-stamp token signature algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_algorithm: Option<String>,
+    /// Object identifier of the digest algorithm used in time-stamp token
+    /// signatures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest_algorithm: Option<String>,
+    /// Accuracy of the most recent time measurement in microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accuracy_micros: Option<u64>,
+    /// Worst accuracy declared in issued time-stamps in microseconds.
+    tolerable_accuracy_micros: u64,
+    /// Policy object identifiers under which qualified electronic
+    /// time-stamps are issued.
+    qualified_policies: Vec<String>,
+    /// Ways to submit requests.
+    transports: Vec<TransportResponse>,
+}
+
+/// Return the current TSU certificate chain and optionally the recently
+/// retired ones.
+///
+/// The `pem` format lists the current chain with the leaf first and can't
+/// include retired chains. The `pkcs7` format is a DER encoded PKCS #7
+/// "certs-only" bundle without duplicates. The `json` format lists each
+/// retired chain separately.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(CertsQuery),
+    responses(
+        (
+            status = 200,
+            description = "Ok.",
+            content(
+                (String = "application/pem-certificate-chain"),
+                (BinaryType = "application/pkcs7-mime; smime-type=certs-only"),
+                (CertsResponse = "application/json"),
+            ),
+        ),
+        (status = 400, description = "Unknown format or retired chains requested as PEM."),
+        (status = 503, description = "No certificate chain is available (yet)."),
+    ),
+)]
+#[get("/tsa/certs")]
+pub async fn tsa_certs(
+    app_state: web::Data<AppState>,
+    query: web::Query<CertsQuery>,
+) -> Result<HttpResponse, Error> {
+    let app = &app_state.app;
+    let include_retired = query.retired.unwrap_or(false);
+    let unavailable = || error::ErrorServiceUnavailable("No certificate chain is available.");
+    match query.format.as_deref().unwrap_or("pem") {
+        "pem" if include_retired => Err(error::ErrorBadRequest(
+            "Retired chains are only available in the 'pkcs7' and 'json' formats.",
+        )),
+        "pem" => pem_certificate_chain(&app_state),
+        "pkcs7" => Ok(HttpResponse::Ok()
+            .insert_header(("content-type", CONTENT_TYPE_PKCS7_CERTS_ONLY))
+            .body(
+                app.certificate_bundle(include_retired)
+                    .ok_or_else(unavailable)?,
+            )),
+        "json" => {
+            let encode_chain = |certificate_chain: &[Vec<u8>]| {
+                certificate_chain
+                    .iter()
+                    .map(|encoded| tyst::encdec::base64::encode(encoded))
+                    .collect::<Vec<_>>()
+            };
+            let certificate_chain = app
+                .get_current_certificate_chain()
+                .ok_or_else(unavailable)?;
+            Ok(HttpResponse::Ok().json(CertsResponse {
+                certificate_chain: encode_chain(&certificate_chain),
+                retired: include_retired.then(|| {
+                    app.retired_certificate_chains()
+                        .iter()
+                        .map(|retired| RetiredCertificateChainResponse {
+                            retired_at: crate::der::rfc3339(retired.retired_epoch_micros),
+                            certificate_chain: encode_chain(&retired.certificate_chain),
+                        })
+                        .collect()
+                }),
+            }))
+        }
+        _ => Err(error::ErrorBadRequest(
+            "Format must be one of 'pem', 'pkcs7' or 'json'.",
+        )),
+    }
+}
+
+/// Return the current TSU certificate chain as PEM with the leaf first.
+pub(super) fn pem_certificate_chain(app_state: &AppState) -> Result<HttpResponse, Error> {
+    let certificate_chain =
+        app_state
+            .app
+            .get_current_certificate_chain()
+            .ok_or(error::ErrorServiceUnavailable(
+                "No certificate chain is available.",
+            ))?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("content-type", CONTENT_TYPE_PEM_CHAIN))
+        .body(
+            certificate_chain
+                .iter()
+                .map(|encoded| crate::der::to_pem("CERTIFICATE", encoded))
+                .collect::<String>(),
+        ))
+}
+
+/// Return the policies, algorithms, accuracy and transports supported by
+/// this TSA.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (status = 200, description = "Ok.", body = InfoResponse),
+    ),
+)]
+#[get("/tsa/info")]
+pub async fn tsa_info(app_state: web::Data<AppState>) -> impl Responder {
+    let tsa_info = app_state.app.tsa_info();
+    HttpResponse::Ok().json(InfoResponse {
+        policies: tsa_info.policy_oids,
+        allowed_digest_algorithms: tsa_info.allowed_digest_oids,
+        signature_algorithm: tsa_info.signature_algorithm_oid,
+        digest_algorithm: tsa_info.digest_algorithm_oid,
+        accuracy_micros: tsa_info.declared_accuracy_micros,
+        tolerable_accuracy_micros: tsa_info.tolerable_accuracy_micros,
+        qualified_policies: tsa_info.qualified_policy_oids,
+        transports: tsa_info
+            .transports
+            .iter()
+            .map(TransportResponse::from)
+            .collect(),
+    })
+}
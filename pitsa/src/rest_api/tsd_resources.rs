@@ -0,0 +1,209 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! CMS TimeStampedData envelopes as defined in
+//! [RFC 5544](https://www.rfc-editor.org/rfc/rfc5544).
+
+use super::AppState;
+use super::content::MAX_EMBEDDED_DATA_SIZE;
+use super::content::is_json;
+use super::read_payload;
+use super::tsp_resources::BinaryType;
+use crate::TimeStampedDataError;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+
+const CONTENT_TYPE_TIMESTAMPED_DATA: &str = "application/timestamped-data";
+
+/// Optional metadata when the data is sent as the request body.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct TimeStampedDataQuery {
+    /// Original file name of the data.
+    file_name: Option<String>,
+    /// Media type of the data.
+    media_type: Option<String>,
+}
+
+/// Request for a time-stamp over data that is not embedded.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct DataUriRequest {
+    /// URI where the data can be retrieved.
+    data_uri: String,
+    /// Object identifier of the digest algorithm used for the hash.
+    hash_algorithm: String,
+    /// Base64 encoded digest of the data.
+    hash: String,
+    /// Original file name of the data.
+    file_name: Option<String>,
+    /// Media type of the data.
+    media_type: Option<String>,
+}
+
+/// Content and metadata of a validated TimeStampedData.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct UnwrappedTimeStampedData {
+    /// URI where the data can be retrieved when not embedded.
+    data_uri: Option<String>,
+    /// Original file name of the data.
+    file_name: Option<String>,
+    /// Media type of the data.
+    media_type: Option<String>,
+    /// Base64 encoded embedded data.
+    content: Option<String>,
+    /// Point in time of the first time-stamp in microseconds since the Unix
+    /// epoch.
+    time_stamp_epoch_micros: u64,
+    /// Base64 encoded DER `TimeStampToken`s with the one over the data first.
+    time_stamp_tokens: Vec<String>,
+}
+
+/// Wrap data, or a data URI and the digest of the data, with a time-stamp in
+/// a `TimeStampedData` envelope.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(TimeStampedDataQuery),
+    request_body(
+        description = "The data to embed or a JSON request with a data URI.",
+        content(
+            (BinaryType = "application/octet-stream"),
+            (DataUriRequest = "application/json"),
+        ),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Ok. DER encoded ContentInfo with TimeStampedData.",
+            content_type = CONTENT_TYPE_TIMESTAMPED_DATA,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/tsd")]
+pub async fn tsd_wrap(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    query: web::Query<TimeStampedDataQuery>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_EMBEDDED_DATA_SIZE).await?;
+    let query = query.into_inner();
+    let time_stamped_data = if is_json(&http_request) {
+        let request = serde_json::from_slice::<DataUriRequest>(&request_body)
+            .map_err(error::ErrorBadRequest)?;
+        let digest_algorithm_oid = crate::der::oid_from_string(&request.hash_algorithm)
+            .map_err(|e| error::ErrorBadRequest(format!("Invalid hashAlgorithm: {e}")))?;
+        let digest = tyst::encdec::base64::decode(&request.hash)
+            .map_err(|e| error::ErrorBadRequest(format!("Invalid hash: {e:?}")))?;
+        app_state
+            .app
+            .wrap_time_stamped_data(
+                Some(request.data_uri),
+                request.file_name,
+                request.media_type,
+                Err((digest_algorithm_oid, digest)),
+            )
+            .await
+    } else {
+        app_state
+            .app
+            .wrap_time_stamped_data(
+                None,
+                query.file_name,
+                query.media_type,
+                Ok(request_body.to_vec()),
+            )
+            .await
+    }
+    .map_err(as_http_error)?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("content-type", CONTENT_TYPE_TIMESTAMPED_DATA))
+        .body(time_stamped_data))
+}
+
+/// Unwrap and validate a `TimeStampedData` envelope.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "DER encoded ContentInfo with TimeStampedData.",
+        content_type = CONTENT_TYPE_TIMESTAMPED_DATA,
+        content = inline(BinaryType)
+    ),
+    responses(
+        (status = 200, description = "Ok. The envelope is valid.", body = UnwrappedTimeStampedData),
+        (status = 400, description = "Bad Request"),
+        (status = 422, description = "The envelope failed validation."),
+    ),
+)]
+#[post("/tsd/unwrap")]
+pub async fn tsd_unwrap(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_EMBEDDED_DATA_SIZE).await?;
+    // Chain, signature and revocation checks are CPU bound
+    let app = Arc::clone(&app_state.app);
+    let (time_stamped_data, time_stamp_epoch_micros) =
+        tokio::task::spawn_blocking(move || app.unwrap_time_stamped_data(&request_body))
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .map_err(as_http_error)?;
+    Ok(HttpResponse::Ok().json(UnwrappedTimeStampedData {
+        data_uri: time_stamped_data.data_uri,
+        file_name: time_stamped_data.file_name,
+        media_type: time_stamped_data.media_type,
+        content: time_stamped_data
+            .content
+            .map(|content| tyst::encdec::base64::encode(&content)),
+        time_stamp_epoch_micros,
+        time_stamp_tokens: time_stamped_data
+            .time_stamp_tokens
+            .iter()
+            .map(|encoded| tyst::encdec::base64::encode(encoded))
+            .collect(),
+    }))
+}
+
+fn as_http_error(e: TimeStampedDataError) -> Error {
+    match e {
+        TimeStampedDataError::BadRequest(msg) => {
+            log::debug!("Bad TimeStampedData request: {msg}");
+            error::ErrorBadRequest(msg)
+        }
+        TimeStampedDataError::Invalid(msg) => {
+            log::debug!("Invalid TimeStampedData: {msg}");
+            error::ErrorUnprocessableEntity(msg)
+        }
+        TimeStampedDataError::Unavailable(msg) => {
+            log::warn!("Unable to process TimeStampedData request: {msg}");
+            error::ErrorServiceUnavailable(msg)
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamping of content that is hashed by the server while it is
+//! streamed.
+
+use super::AppState;
+use super::tsp_json_resources::JsonTspResponse;
+use super::tsp_json_resources::digest_algorithm_oid;
+use super::tsp_resources::BinaryType;
+use crate::ContentDigest;
+use crate::ImprintRequest;
+use actix_multipart::Multipart;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tyst::encdec::hex::ToHex;
+
+const CONTENT_TYPE_MULTIPART: &str = "multipart/form-data";
+
+/// Options for hashing and time-stamping the uploaded content.
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct HashQuery {
+    /// Digest algorithm as object identifier or name (e.g. `SHA256`).
+    /// Defaults to the digest algorithm of the TSU signing key.
+    hash_algorithm: Option<String>,
+    /// Optional requested TSA policy object identifier.
+    policy: Option<String>,
+    /// Request that the TSU certificate chain is included in the token.
+    cert_req: Option<bool>,
+}
+
+/// Computed digest of the uploaded content and the time-stamp over it.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HashResponse {
+    /// Digest algorithm object identifier.
+    hash_algorithm: String,
+    /// Hex encoded digest of the content.
+    hash_value: String,
+    /// Size of the hashed content in bytes.
+    content_size: u64,
+    /// Time-stamp over the digest.
+    #[serde(flatten)]
+    time_stamp: JsonTspResponse,
+}
+
+/// Hash the uploaded content while it is streamed and request a time-stamp
+/// over the digest.
+///
+/// The content is either the raw request body or the first file part of a
+/// `multipart/form-data` request. The content is never buffered, so there is
+/// no size limit. A digest algorithm, policy or client that would be refused
+/// is rejected before the content is consumed. Otherwise the time-stamp
+/// protocol status is part of the response body.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(HashQuery),
+    request_body(
+        description = "The content to hash.",
+        content(
+            (BinaryType = "application/octet-stream"),
+            (BinaryType = "multipart/form-data"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "Ok. See status for the outcome.", body = HashResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 503, description = "Service Unavailable"),
+    ),
+)]
+#[post("/tsp/hash")]
+pub async fn tsp_hash_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    query: web::Query<HashQuery>,
+    payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let digest_algorithm_oid = match query.hash_algorithm {
+        Some(hash_algorithm) => {
+            digest_algorithm_oid(&hash_algorithm).map_err(error::ErrorBadRequest)?
+        }
+        None => app_state
+            .app
+            .default_digest_algorithm_oid()
+            .ok_or(error::ErrorServiceUnavailable("No signing key available."))?,
+    };
+    let policy_oid = query
+        .policy
+        .filter(|oid| !oid.is_empty())
+        .map(|policy_oid| crate::der::oid_from_string(&policy_oid))
+        .transpose()
+        .map_err(|e| error::ErrorBadRequest(format!("Invalid policy: {e}")))?;
+    // Refuse before the upload is consumed
+    app_state
+        .app
+        .assert_acceptable_digest_algorithm(&digest_algorithm_oid, policy_oid.as_deref())
+        .map_err(error::ErrorBadRequest)?;
+    let mut content_digest = ContentDigest::new(&digest_algorithm_oid)
+        .ok_or(error::ErrorBadRequest("Unsupported hashAlgorithm."))?;
+    let is_multipart = http_request
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(CONTENT_TYPE_MULTIPART));
+    if is_multipart {
+        hash_multipart(&http_request, payload, &mut content_digest).await?;
+    } else {
+        hash_payload(payload, &mut content_digest).await?;
+    }
+    let content_size = content_digest.content_size();
+    let digest = content_digest.finalize();
+    if log::log_enabled!(log::Level::Debug) {
+        log::debug!(
+            "Hashed {content_size} bytes of uploaded content using {}.",
+            tyst::encdec::oid::as_string(&digest_algorithm_oid)
+        );
+    }
+    let mut imprint_request = ImprintRequest::new(
+        &digest_algorithm_oid,
+        &digest,
+        query.cert_req.unwrap_or(false),
+    );
+    imprint_request.policy_oid = policy_oid;
+    let time_stamp_response = app_state
+        .app
+        .imprint_time_stamp_request(&imprint_request)
+        .await;
+    let time_stamp = JsonTspResponse::from_time_stamp_response(&time_stamp_response)
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(HashResponse {
+        hash_algorithm: tyst::encdec::oid::as_string(&digest_algorithm_oid),
+        hash_value: digest.to_hex(),
+        content_size,
+        time_stamp,
+    }))
+}
+
+/// Feed the raw request body to the digest.
+async fn hash_payload(
+    mut payload: web::Payload,
+    content_digest: &mut ContentDigest,
+) -> Result<(), Error> {
+    while let Some(chunk) = payload.next().await {
+        content_digest.update(&chunk?);
+    }
+    Ok(())
+}
+
+/// Feed the first file part of a multipart request to the digest.
+///
+/// All other parts are drained and ignored.
+async fn hash_multipart(
+    http_request: &HttpRequest,
+    payload: web::Payload,
+    content_digest: &mut ContentDigest,
+) -> Result<(), Error> {
+    let mut multipart = Multipart::new(http_request.headers(), payload);
+    let mut hashed = false;
+    while let Some(field) = multipart.next().await {
+        let mut field = field?;
+        let is_file = field
+            .content_disposition()
+            .is_some_and(|content_disposition| content_disposition.get_filename().is_some());
+        let hash_field = is_file && !hashed;
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if hash_field {
+                content_digest.update(&chunk);
+            }
+        }
+        hashed |= hash_field;
+    }
+    if !hashed {
+        return Err(error::ErrorBadRequest(
+            "No file part found in multipart request.",
+        ));
+    }
+    Ok(())
+}
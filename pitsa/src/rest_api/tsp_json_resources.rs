@@ -0,0 +1,226 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! JSON convenience API for requesting time-stamps over a message imprint.
+
+use super::AppState;
+use super::content::CONTENT_TYPE_JSON;
+use super::content::MAX_JSON_SIZE;
+use super::decimal_to_unsigned_bytes;
+use super::read_payload;
+use crate::EncodedTimeStampResp;
+use crate::ImprintRequest;
+use crate::ParsedTimeStampToken;
+use crate::util;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use tyst::Tyst;
+use tyst::encdec::hex::ToHex;
+
+/// JSON time-stamp request.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct JsonTspRequest {
+    /// Message imprint digest algorithm as object identifier or name (e.g.
+    /// `2.16.840.1.101.3.4.2.1` or `SHA256`).
+    hash_algorithm: String,
+    /// Base64 or hex encoded message imprint digest.
+    hash_value: String,
+    /// Optional nonce as an arbitrary large positive JSON integer or decimal
+    /// string.
+    #[schema(value_type = Option<u64>)]
+    nonce: Option<Box<RawValue>>,
+    /// Optional requested TSA policy object identifier.
+    policy: Option<String>,
+    /// Request that the TSU certificate chain is included in the token.
+    cert_req: Option<bool>,
+}
+
+impl JsonTspRequest {
+    /// Convert into a request over the message imprint.
+    pub(super) fn into_imprint_request(self) -> Result<ImprintRequest, String> {
+        let digest_algorithm_oid = digest_algorithm_oid(&self.hash_algorithm)?;
+        let digest_size = util::digest_size(&digest_algorithm_oid)
+            .ok_or(format!("Unknown hashAlgorithm '{}'.", self.hash_algorithm))?;
+        let hash_value = self.hash_value.trim();
+        // Hex and base64 encodings of the same digest never have the same length
+        let digest = if hash_value.len() == digest_size * 2
+            && hash_value.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            (0..hash_value.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hash_value[i..i + 2], 16))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Invalid hashValue: {e:?}"))?
+        } else {
+            tyst::encdec::base64::decode(hash_value)
+                .map_err(|e| format!("Invalid hashValue: {e:?}"))?
+        };
+        let mut imprint_request = ImprintRequest::new(
+            &digest_algorithm_oid,
+            &digest,
+            self.cert_req.unwrap_or(false),
+        );
+        if let Some(policy_oid) = self.policy.filter(|oid| !oid.is_empty()) {
+            imprint_request.policy_oid = Some(
+                crate::der::oid_from_string(&policy_oid)
+                    .map_err(|e| format!("Invalid policy: {e}"))?,
+            );
+        }
+        if let Some(nonce) = self.nonce {
+            imprint_request.nonce = Some(decimal_to_unsigned_bytes(nonce.get().trim_matches('"'))?);
+        }
+        Ok(imprint_request)
+    }
+}
+
+/// Return the object identifier of a digest algorithm given by object
+/// identifier or (case-insensitive) name.
+pub(super) fn digest_algorithm_oid(hash_algorithm: &str) -> Result<Vec<u32>, String> {
+    if hash_algorithm.starts_with(|c: char| c.is_ascii_digit()) {
+        return crate::der::oid_from_string(hash_algorithm)
+            .map_err(|e| format!("Invalid hashAlgorithm: {e}"));
+    }
+    let normalized = |name: &str| name.replace(['-', '_'], "").to_lowercase();
+    Tyst::instance()
+        .digests()
+        .get_algorithm_meta_datas()
+        .iter()
+        .find(|amd| normalized(&amd.name().to_string()).eq(&normalized(hash_algorithm)))
+        .and_then(|amd| amd.oid())
+        .and_then(|oid| tyst::encdec::oid::from_string(&oid.to_string()).ok())
+        .ok_or(format!("Unknown hashAlgorithm '{hash_algorithm}'."))
+}
+
+/// Decoded `TSTInfo` fields.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct JsonTstInfo {
+    /// TSA policy object identifier.
+    policy: String,
+    /// Message imprint digest algorithm object identifier.
+    hash_algorithm: String,
+    /// Base64 encoded message imprint digest.
+    hash_value: String,
+    /// Hex encoded time-stamp serial number.
+    serial_number: String,
+    /// RFC 3339 point in time of the time-stamp.
+    gen_time: String,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    gen_time_epoch_micros: u64,
+    /// Accuracy of the point in time in microseconds.
+    accuracy_micros: Option<u64>,
+    /// Ordering of time-stamps from this TSA is guaranteed by `genTime`.
+    ordering: bool,
+    /// Hex encoded nonce.
+    nonce: Option<String>,
+}
+
+impl From<ParsedTimeStampToken> for JsonTstInfo {
+    fn from(token: ParsedTimeStampToken) -> Self {
+        Self {
+            policy: tyst::encdec::oid::as_string(&token.policy_oid),
+            hash_algorithm: tyst::encdec::oid::as_string(&token.digest_algorithm_oid),
+            hash_value: tyst::encdec::base64::encode(&token.digest),
+            serial_number: token.serial_number.to_hex(),
+            gen_time: crate::der::rfc3339(token.gen_time_epoch_micros),
+            gen_time_epoch_micros: token.gen_time_epoch_micros,
+            accuracy_micros: token.accuracy_micros,
+            ordering: token.ordering,
+            nonce: token.nonce.map(|nonce| nonce.to_hex()),
+        }
+    }
+}
+
+/// JSON time-stamp response.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct JsonTspResponse {
+    /// `PKIStatus` name, e.g. `granted` or `rejection`.
+    status: String,
+    /// `PKIFreeText` status strings.
+    status_strings: Vec<String>,
+    /// Names of the `PKIFailureInfo` bits, e.g. `badAlg`.
+    failure_info: Vec<String>,
+    /// Base64 encoded DER `TimeStampToken`.
+    time_stamp_token: Option<String>,
+    /// Decoded `TSTInfo` of the token.
+    tst_info: Option<JsonTstInfo>,
+}
+
+impl JsonTspResponse {
+    /// Return a new instance from a DER encoded `TimeStampResp`.
+    pub(super) fn from_time_stamp_response(time_stamp_response: &[u8]) -> Result<Self, String> {
+        let (status, time_stamp_token) =
+            EncodedTimeStampResp::status_and_token(time_stamp_response)?;
+        let tst_info = time_stamp_token
+            .as_ref()
+            .and_then(|encoded| ParsedTimeStampToken::from_bytes(encoded).ok())
+            .map(JsonTstInfo::from);
+        Ok(Self {
+            status: status.status_name().to_string(),
+            status_strings: status.status_strings,
+            failure_info: status
+                .failure_info
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            time_stamp_token: time_stamp_token
+                .map(|encoded| tyst::encdec::base64::encode(&encoded)),
+            tst_info,
+        })
+    }
+}
+
+/// Request a time-stamp over a message imprint using JSON.
+///
+/// The time-stamp protocol status is part of the response body.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content = JsonTspRequest, content_type = CONTENT_TYPE_JSON),
+    responses(
+        (status = 200, description = "Ok. See status for the outcome.", body = JsonTspResponse),
+        (status = 400, description = "Bad Request"),
+    ),
+)]
+#[post("/tsp/json")]
+pub async fn tsp_json_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_JSON_SIZE).await?;
+    let imprint_request = serde_json::from_slice::<JsonTspRequest>(&request_body)
+        .map_err(|e| e.to_string())
+        .and_then(JsonTspRequest::into_imprint_request)
+        .map_err(error::ErrorBadRequest)?;
+    let time_stamp_response = app_state
+        .app
+        .imprint_time_stamp_request(&imprint_request)
+        .await;
+    let json_response = JsonTspResponse::from_time_stamp_response(&time_stamp_response)
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(json_response))
+}
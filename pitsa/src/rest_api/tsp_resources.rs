@@ -19,24 +19,22 @@
 //! [RFC3161 3.4](https://www.rfc-editor.org/rfc/rfc3161#section-3.4).
 
 use super::AppState;
+use super::content::CONTENT_TYPE_TS_REPLY;
+use super::content::MAX_TSP_SIZE;
+use super::read_payload;
 use actix_web::Error;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Result;
-use actix_web::error;
 use actix_web::post;
 use actix_web::web;
-use futures::StreamExt;
 
 const CONTENT_TYPE_TS_QUERY: &str = "application/timestamp-query";
-const CONTENT_TYPE_TS_REPLY: &str = "application/timestamp-reply";
-// A TimeStamp request is usually around 5 KiB.
-const MAX_SIZE: usize = 8 * 1024;
 
 #[allow(dead_code)]
 #[derive(utoipa::ToSchema)]
 #[schema(format = Binary, value_type = Object)]
-struct BinaryType(Vec<u8>);
+pub(super) struct BinaryType(Vec<u8>);
 
 /// Time-Stamp Protocol via HTTP as defined in
 /// [RFC3161 3.4](https://www.rfc-editor.org/rfc/rfc3161#section-3.4).
@@ -54,7 +52,7 @@ struct BinaryType(Vec<u8>);
             content_type = CONTENT_TYPE_TS_REPLY,
             body = inline(BinaryType),
         ),
-        (status = 404, description = "Bad Reqest"),
+        (status = 400, description = "Bad Request"),
     ),
 )]
 #[post("/tsp")]
@@ -76,28 +74,7 @@ pub async fn tsp_raw_time_stamp_request(
     {
         log::debug!("Wrong content-type in request. (Allowing this to proceed anyway.)");
     }
-    let content_length_estimate = http_request
-        .headers()
-        .get("content-length")
-        .and_then(|header_value| header_value.to_str().ok())
-        .and_then(|header_value_str| header_value_str.parse::<usize>().ok())
-        .unwrap_or(1024);
-    if content_length_estimate > MAX_SIZE {
-        return Err(error::ErrorBadRequest(
-            "Content-length indicates that this request is too large.",
-        ));
-    }
-    // TODO: Since MAX_SIZE is fairly low for TSRs, we could avoid heap alloc.
-    //       This however only makes sense when redoing the rest of the call stack.
-    let mut request_body = web::BytesMut::with_capacity(content_length_estimate);
-    while let Some(chunk) = payload.next().await {
-        let chunk = chunk?;
-        if (request_body.len() + chunk.len()) > MAX_SIZE {
-            return Err(error::ErrorBadRequest("Request is too large."));
-        }
-        request_body.extend_from_slice(&chunk);
-    }
-    let time_stamp_request = request_body.freeze();
+    let time_stamp_request = read_payload(&http_request, &mut payload, MAX_TSP_SIZE).await?;
     let time_stamp_response = app_state
         .app
         .raw_time_stamp_request(&time_stamp_request)
@@ -106,3 +83,42 @@ pub async fn tsp_raw_time_stamp_request(
         .insert_header(("content-type", CONTENT_TYPE_TS_REPLY))
         .body(time_stamp_response))
 }
+
+/// Time-Stamp Protocol via HTTP with requests aggregated into a Merkle tree.
+///
+/// The response is a DER encoded `TimeStampResp` with a token over the tree
+/// root. The token carries the inclusion proof of the request as an unsigned
+/// attribute. Requests with a nonce or extensions are rejected, since these
+/// can't be reflected in the shared token.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "DER encoded TimeStampReq.",
+        content_type = CONTENT_TYPE_TS_QUERY,
+        content = inline(BinaryType)
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Ok. Parse the DER encoded TimeStampResponse for actual status defined in the time-stamping protocol.",
+            content_type = CONTENT_TYPE_TS_REPLY,
+            body = inline(BinaryType),
+        ),
+        (status = 400, description = "Bad Request"),
+    ),
+)]
+#[post("/tsp/aggregated")]
+pub async fn tsp_aggregated_time_stamp_request(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let time_stamp_request = read_payload(&http_request, &mut payload, MAX_TSP_SIZE).await?;
+    let aggregated_response = app_state
+        .app
+        .aggregated_time_stamp_request(&time_stamp_request)
+        .await;
+    Ok(HttpResponse::Ok()
+        .insert_header(("content-type", CONTENT_TYPE_TS_REPLY))
+        .body(aggregated_response))
+}
@@ -0,0 +1,221 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Verification of time-stamp tokens.
+
+use super::AppState;
+use super::content::is_json;
+use super::read_payload;
+use crate::ChainStatus;
+use crate::ExpectedImprint;
+use crate::TokenVerification;
+use actix_web::Error;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Result;
+use actix_web::error;
+use actix_web::post;
+use actix_web::web;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use tyst::encdec::hex::ToHex;
+use upkit_common::x509::cert::parse::CertificateParser;
+
+// The original data is kept in memory. Larger data should be hashed by the client.
+const MAX_SIZE: usize = 1024 * 1024;
+
+/// Request to verify a time-stamp token.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct VerifyRequest {
+    /// Base64 encoded DER `TimeStampToken` or granted `TimeStampResp`.
+    time_stamp: String,
+    /// Optional base64 encoded original data of at most 1 MiB. Provide the
+    /// hash for larger data.
+    data: Option<String>,
+    /// Optional object identifier of the digest algorithm used for `hash`.
+    hash_algorithm: Option<String>,
+    /// Optional base64 encoded message imprint digest of the original data.
+    hash: Option<String>,
+}
+
+/// Identification of the time-stamp signer.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SignerVerdict {
+    /// Subject distinguished name of the signing certificate.
+    #[schema(value_type = Object)]
+    subject: serde_json::Value,
+    /// Issuer distinguished name of the signing certificate.
+    #[schema(value_type = Object)]
+    issuer: serde_json::Value,
+    /// Hex encoded serial number of the signing certificate.
+    serial_number: String,
+}
+
+/// Verification verdict of a time-stamp token.
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct VerifyResponse {
+    /// `true` when the chain is valid and the imprint (if provided) matches.
+    valid: bool,
+    /// Reason why the token is not valid.
+    failure: Option<String>,
+    /// Signer if the signing certificate is embedded in the token.
+    signer: Option<SignerVerdict>,
+    /// RFC 3339 point in time of the time-stamp.
+    gen_time: String,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    gen_time_epoch_micros: u64,
+    /// Accuracy of the point in time in microseconds.
+    accuracy_micros: Option<u64>,
+    /// TSA policy object identifier.
+    policy: String,
+    /// Hex encoded time-stamp serial number.
+    serial_number: String,
+    /// Hex encoded nonce.
+    nonce: Option<String>,
+    /// Message imprint digest algorithm object identifier.
+    hash_algorithm: String,
+    /// Base64 encoded message imprint digest.
+    hash: String,
+    /// Whether the message imprint matches the provided data or hash.
+    imprint_matches: Option<bool>,
+    /// `valid`, `invalid` or `noTrustAnchors`.
+    chain_status: String,
+    /// `good`, `revoked`, `unknown` or `notEmbedded` (no revocation
+    /// information is embedded or fetched for the signer's chain).
+    revocation_status: String,
+    /// Why the revocation status is `revoked` or `unknown`.
+    revocation_reason: Option<String>,
+}
+
+impl From<TokenVerification> for VerifyResponse {
+    fn from(verification: TokenVerification) -> Self {
+        let failure = verification.failure();
+        let token = verification.token;
+        let signer = token
+            .signer_certificate
+            .as_ref()
+            .and_then(|encoded| CertificateParser::from_bytes(encoded).ok())
+            .map(|certificate| SignerVerdict {
+                subject: certificate
+                    .get_subject()
+                    .ok()
+                    .and_then(|value| serde_json::to_value(&value).ok())
+                    .unwrap_or_default(),
+                issuer: certificate
+                    .get_issuer()
+                    .ok()
+                    .and_then(|value| serde_json::to_value(&value).ok())
+                    .unwrap_or_default(),
+                serial_number: certificate.get_serial_number().to_hex(),
+            });
+        Self {
+            valid: failure.is_none(),
+            failure,
+            signer,
+            gen_time: crate::der::rfc3339(token.gen_time_epoch_micros),
+            gen_time_epoch_micros: token.gen_time_epoch_micros,
+            accuracy_micros: token.accuracy_micros,
+            policy: tyst::encdec::oid::as_string(&token.policy_oid),
+            serial_number: token.serial_number.to_hex(),
+            nonce: token.nonce.map(|nonce| nonce.to_hex()),
+            hash_algorithm: tyst::encdec::oid::as_string(&token.digest_algorithm_oid),
+            hash: tyst::encdec::base64::encode(&token.digest),
+            imprint_matches: verification.imprint_matches,
+            chain_status: match verification.chain_status {
+                ChainStatus::Valid => "valid",
+                ChainStatus::Invalid(_) => "invalid",
+                ChainStatus::NoTrustAnchors => "noTrustAnchors",
+            }
+            .to_string(),
+            revocation_status: verification.revocation_status.as_str().to_string(),
+            revocation_reason: verification.revocation_status.reason(),
+        }
+    }
+}
+
+impl VerifyRequest {
+    /// Return the decoded time-stamp and the expected message imprint.
+    fn decode(self) -> Result<(Vec<u8>, Option<ExpectedImprint>), String> {
+        let decode_base64 = |name: &str, value: &str| {
+            tyst::encdec::base64::decode(value).map_err(|e| format!("Invalid {name}: {e:?}"))
+        };
+        let time_stamp = decode_base64("timeStamp", &self.time_stamp)?;
+        let expected_imprint = match (self.data, self.hash_algorithm, self.hash) {
+            (Some(data), None, None) => Some(ExpectedImprint::Data(decode_base64("data", &data)?)),
+            (None, Some(hash_algorithm), Some(hash)) => Some(ExpectedImprint::Digest(
+                crate::der::oid_from_string(&hash_algorithm)
+                    .map_err(|e| format!("Invalid hashAlgorithm: {e}"))?,
+                decode_base64("hash", &hash)?,
+            )),
+            (None, None, None) => None,
+            _ => {
+                return Err(
+                    "Provide either data or both hashAlgorithm and hash, not both.".to_string(),
+                );
+            }
+        };
+        Ok((time_stamp, expected_imprint))
+    }
+}
+
+/// Verify a time-stamp token, or a response, against the configured trust
+/// anchors and optionally against the original data or message imprint.
+///
+/// A DER encoded `TimeStampToken` or `TimeStampResp` can also be posted
+/// directly when there is no data to compare with.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(
+        description = "JSON request or DER encoded TimeStampToken or TimeStampResp.",
+        content(
+            (VerifyRequest = "application/json"),
+            (super::tsp_resources::BinaryType = "application/timestamp-reply"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "Ok. See the verdict for the outcome.", body = VerifyResponse),
+        (status = 400, description = "Bad Request"),
+    ),
+)]
+#[post("/tsp/verify")]
+pub async fn tsp_verify(
+    http_request: HttpRequest,
+    app_state: web::Data<AppState>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let request_body = read_payload(&http_request, &mut payload, MAX_SIZE).await?;
+    let (time_stamp, expected_imprint) = if is_json(&http_request) {
+        serde_json::from_slice::<VerifyRequest>(&request_body)
+            .map_err(|e| e.to_string())
+            .and_then(VerifyRequest::decode)
+            .map_err(error::ErrorBadRequest)?
+    } else {
+        (request_body.to_vec(), None)
+    };
+    // Chain, signature and revocation checks are CPU bound
+    let app = Arc::clone(&app_state.app);
+    let verification =
+        tokio::task::spawn_blocking(move || app.verify_time_stamp(&time_stamp, expected_imprint))
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .map_err(error::ErrorBadRequest)?;
+    Ok(HttpResponse::Ok().json(VerifyResponse::from(verification)))
+}
@@ -0,0 +1,181 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-Stamp Protocol via Sockets as defined in
+//! [RFC3161 3.3](https://www.rfc-editor.org/rfc/rfc3161#section-3.3).
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tyst_api_rest_health::AppHealth;
+use upkit_common::x509::tsp::build::TimeStampResp;
+use upkit_common::x509::tsp::types::PkiFailureInfo;
+
+use crate::RateLimits;
+use crate::TimeStamper;
+use crate::logging::RequestId;
+use crate::rest_api::WORKERS_PER_CORE;
+
+/// Message containing a time-stamp request.
+const FLAG_TSA_MSG: u8 = 0x00;
+/// Poll request.
+const FLAG_POLL_REQ: u8 = 0x02;
+/// Negative poll response.
+const FLAG_NEG_POLL_REP: u8 = 0x03;
+/// Final message containing the time-stamp response.
+const FLAG_FINAL_MSG_REP: u8 = 0x05;
+/// Error message.
+const FLAG_ERROR_MSG_REP: u8 = 0x06;
+/// Close idle connections after this many seconds.
+const IDLE_TIMEOUT_SECONDS: u64 = 30;
+
+/** Run the TCP-based Time-Stamp Protocol server.
+
+Each message is framed as
+
+```text
+   length (32-bit integer) | flag (8-bit) | value (defined below)
+```
+
+where `length` covers the flag and the value. Requests are answered
+immediately, so polling is never required.
+*/
+pub async fn run_tcp_server(
+    available_parallelism: usize,
+    bind_address: &str,
+    bind_port: u16,
+    app_health: &Arc<dyn AppHealth>,
+    app: &Arc<TimeStamper>,
+) -> std::io::Result<()> {
+    let max_connections = WORKERS_PER_CORE * available_parallelism;
+    let listener = TcpListener::bind((bind_address, bind_port)).await?;
+    log::info!(
+        "Time-Stamp Protocol via sockets listens on {bind_address}:{bind_port} and allows {max_connections} concurrent connections."
+    );
+    let connection_permits = Arc::new(Semaphore::new(max_connections));
+    loop {
+        let permit = Arc::clone(&connection_permits)
+            .acquire_owned()
+            .await
+            .unwrap();
+        match listener.accept().await {
+            Ok((stream, peer_addr)) => {
+                let app_health = Arc::clone(app_health);
+                let app = Arc::clone(app);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, peer_addr, &app_health, &app).await
+                        && log::log_enabled!(log::Level::Debug)
+                    {
+                        log::debug!("Connection from {peer_addr} failed: {e:?}");
+                    }
+                    drop(permit);
+                });
+            }
+            Err(e) => {
+                log::warn!("Failed to accept TCP connection: {e:?}");
+            }
+        }
+    }
+}
+
+/// Serve framed messages on a connection until the client closes it.
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer_addr: SocketAddr,
+    app_health: &Arc<dyn AppHealth>,
+    app: &Arc<TimeStamper>,
+) -> std::io::Result<()> {
+    while let Some((flag, value)) = read_message(&mut stream).await? {
+        let (flag, value) = match flag {
+            FLAG_TSA_MSG if !app_health.is_health_ready() => (
+                FLAG_FINAL_MSG_REP,
+                TimeStampResp::with_rejection(
+                    &["Service is not ready.".to_string()],
+                    &Some(PkiFailureInfo::SystemFailure),
+                )
+                .as_bytes()
+                .to_vec(),
+            ),
+            FLAG_TSA_MSG => match app.rate_limits().admit(Some(peer_addr.ip()), None) {
+                Ok(()) => (
+                    FLAG_FINAL_MSG_REP,
+                    RequestId::scope(RequestId::new_random(), app.raw_time_stamp_request(&value))
+                        .await,
+                ),
+                Err(retry_after_seconds) => (
+                    FLAG_FINAL_MSG_REP,
+                    RateLimits::waiting_time_stamp_resp(retry_after_seconds),
+                ),
+            },
+            // Requests are never deferred, so there is never anything to poll for.
+            FLAG_POLL_REQ => (FLAG_NEG_POLL_REP, vec![]),
+            unexpected => (
+                FLAG_ERROR_MSG_REP,
+                format!("Unsupported message type 0x{unexpected:02x}.").into_bytes(),
+            ),
+        };
+        write_message(&mut stream, flag, &value).await?;
+    }
+    Ok(())
+}
+
+/// Read the next message or `None` if the client closed the connection.
+async fn read_message(stream: &mut TcpStream) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+    let mut length = [0u8; 4];
+    let read_res = tokio::time::timeout(
+        tokio::time::Duration::from_secs(IDLE_TIMEOUT_SECONDS),
+        stream.read_exact(&mut length),
+    )
+    .await
+    .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))?;
+    match read_res {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = usize::try_from(u32::from_be_bytes(length)).unwrap();
+    if length == 0 || length > TimeStamper::MAX_REQUEST_SIZE + 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Message length {length} is not acceptable."),
+        ));
+    }
+    let mut message = vec![0u8; length];
+    tokio::time::timeout(
+        tokio::time::Duration::from_secs(IDLE_TIMEOUT_SECONDS),
+        stream.read_exact(&mut message),
+    )
+    .await
+    .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    let value = message.split_off(1);
+    Ok(Some((message[0], value)))
+}
+
+/// Write a framed message.
+async fn write_message(stream: &mut TcpStream, flag: u8, value: &[u8]) -> std::io::Result<()> {
+    let length = u32::try_from(value.len() + 1).unwrap();
+    let mut message = Vec::with_capacity(value.len() + 5);
+    message.extend_from_slice(&length.to_be_bytes());
+    message.push(flag);
+    message.extend_from_slice(value);
+    stream.write_all(&message).await?;
+    stream.flush().await
+}
@@ -0,0 +1,634 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Distributed tracing of request processing.
+//!
+//! Spans are exported as [OTLP/HTTP](https://opentelemetry.io/docs/specs/otlp/#otlphttp)
+//! JSON and the context is propagated with the
+//! [W3C Trace Context](https://www.w3.org/TR/trace-context/) `traceparent`
+//! header.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+use tyst::encdec::hex::ToHex;
+
+use crate::util;
+
+tokio::task_local! {
+    /// Context of the innermost span of the current task.
+    static CURRENT_SPAN_CONTEXT: SpanContext;
+}
+
+/// The process wide telemetry, if initialized with an exporter.
+static TELEMETRY: OnceLock<Arc<Telemetry>> = OnceLock::new();
+
+/// Where to send finished spans.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceExporter {
+    /// Tracing is disabled.
+    None,
+    /// Write finished spans to the log.
+    Log,
+    /// Send finished spans as OTLP/HTTP JSON to the endpoint URL.
+    Otlp {
+        /// Full URL of the OTLP/HTTP traces endpoint.
+        endpoint: String,
+    },
+}
+
+/// Identifies a span and the trace it belongs to.
+#[derive(Clone, Copy, Debug)]
+pub struct SpanContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    sampled: bool,
+}
+
+impl SpanContext {
+    /// Parse a W3C Trace Context `traceparent` header value.
+    ///
+    /// ```text
+    /// traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01
+    /// ```
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next().filter(|version| version.len() == 2)?;
+        if version == "ff" {
+            return None;
+        }
+        let trace_id = decode_hex::<16>(parts.next()?)?;
+        let span_id = decode_hex::<8>(parts.next()?)?;
+        let flags = decode_hex::<1>(parts.next()?)?;
+        // Later versions may append fields, version 00 may not.
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        if trace_id == [0u8; 16] || span_id == [0u8; 8] {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags[0] & 0x01 == 0x01,
+        })
+    }
+
+    /// Return the W3C Trace Context `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id.to_vec().to_hex(),
+            self.span_id.to_vec().to_hex(),
+            u8::from(self.sampled)
+        )
+    }
+}
+
+/// Decode exactly `N` bytes of lower or upper case hex.
+fn decode_hex<const N: usize>(value: &str) -> Option<[u8; N]> {
+    util::decode_hex(value).and_then(|decoded| decoded.try_into().ok())
+}
+
+/// Return the context of the current span of this task if any.
+fn current_span_context() -> Option<SpanContext> {
+    CURRENT_SPAN_CONTEXT.try_with(|context| *context).ok()
+}
+
+/// Return the current time in nanoseconds since the Unix epoch.
+fn now_epoch_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+/// OTLP `SpanKind`.
+#[derive(Clone, Copy)]
+enum SpanKind {
+    Internal = 1,
+    Server = 2,
+}
+
+/// A span that has ended and awaits export.
+struct FinishedSpan {
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    kind: SpanKind,
+    start_epoch_nanos: u64,
+    end_epoch_nanos: u64,
+    attributes: Vec<(&'static str, String)>,
+    error: bool,
+}
+
+impl FinishedSpan {
+    /// Return the OTLP JSON representation of the span.
+    fn as_otlp_json(&self) -> serde_json::Value {
+        let mut span = serde_json::json!({
+            "traceId": self.context.trace_id.to_vec().to_hex(),
+            "spanId": self.context.span_id.to_vec().to_hex(),
+            "name": self.name,
+            "kind": self.kind as u8,
+            "startTimeUnixNano": self.start_epoch_nanos.to_string(),
+            "endTimeUnixNano": self.end_epoch_nanos.to_string(),
+            "attributes": self.attributes.iter().map(|(key, value)| {
+                serde_json::json!({ "key": key, "value": { "stringValue": value } })
+            }).collect::<Vec<_>>(),
+            // STATUS_CODE_UNSET = 0, STATUS_CODE_ERROR = 2
+            "status": { "code": if self.error { 2 } else { 0 } },
+        });
+        if let Some(parent_span_id) = self.parent_span_id {
+            span["parentSpanId"] = serde_json::Value::String(parent_span_id.to_vec().to_hex());
+        }
+        span
+    }
+}
+
+/** A unit of work within a trace.
+
+The span ends when dropped. Spans are no-ops unless tracing was enabled with
+[Telemetry::init].
+*/
+pub struct Span {
+    inner: Option<ActiveSpan>,
+}
+
+struct ActiveSpan {
+    telemetry: Arc<Telemetry>,
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    kind: SpanKind,
+    start_epoch_nanos: u64,
+    attributes: Vec<(&'static str, String)>,
+    error: bool,
+}
+
+impl Span {
+    /// Start a span that is a child of the current span of this task if any.
+    pub fn start(name: &'static str) -> Self {
+        Self::start_with_parent(name, SpanKind::Internal, current_span_context())
+    }
+
+    /// Start a span for serving a request on behalf of a remote parent,
+    /// usually from a `traceparent` header.
+    pub fn start_server(name: &'static str, remote_parent: Option<SpanContext>) -> Self {
+        Self::start_with_parent(name, SpanKind::Server, remote_parent)
+    }
+
+    fn start_with_parent(name: &'static str, kind: SpanKind, parent: Option<SpanContext>) -> Self {
+        match TELEMETRY.get() {
+            Some(telemetry) => telemetry.start_span(name, kind, parent),
+            None => Self { inner: None },
+        }
+    }
+
+    /// Return the context of this span if tracing is enabled.
+    pub fn context(&self) -> Option<SpanContext> {
+        self.inner.as_ref().map(|inner| inner.context)
+    }
+
+    /// Add an attribute to the span.
+    pub fn set_attribute(&mut self, key: &'static str, value: impl std::fmt::Display) {
+        if let Some(inner) = self.inner.as_mut()
+            && inner.context.sampled
+        {
+            inner.attributes.push((key, value.to_string()));
+        }
+    }
+
+    /// Mark the span as failed.
+    pub fn set_error(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.error = true;
+        }
+    }
+
+    /// Run `future` with this span as the parent of spans started within.
+    pub async fn in_scope<F: Future>(&self, future: F) -> F::Output {
+        if let Some(context) = self.context() {
+            CURRENT_SPAN_CONTEXT.scope(context, future).await
+        } else {
+            future.await
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take()
+            && inner.context.sampled
+        {
+            inner.telemetry.finish(FinishedSpan {
+                context: inner.context,
+                parent_span_id: inner.parent_span_id,
+                name: inner.name,
+                kind: inner.kind,
+                start_epoch_nanos: inner.start_epoch_nanos,
+                end_epoch_nanos: now_epoch_nanos(),
+                attributes: inner.attributes,
+                error: inner.error,
+            });
+        }
+    }
+}
+
+/** Collects finished spans and exports them in the background.
+
+Finished spans are buffered and periodically sent to the configured exporter.
+When the exporter can't keep up, spans beyond
+[MAX_BUFFERED_SPANS](Self::MAX_BUFFERED_SPANS) are dropped rather than slowing
+down request processing.
+
+A new trace is sampled with the configured ratio derived from its trace
+identifier, like the OpenTelemetry `TraceIdRatioBased` sampler. A trace
+continued from a remote parent follows the sampling decision of the parent.
+*/
+pub struct Telemetry {
+    exporter: TraceExporter,
+    service_name: String,
+    sample_ratio: f64,
+    id_random_state: RandomState,
+    id_counter: AtomicU64,
+    finished_spans: Mutex<Vec<FinishedSpan>>,
+}
+
+impl Telemetry {
+    /// Largest number of finished spans waiting for export.
+    pub const MAX_BUFFERED_SPANS: usize = 8192;
+
+    /// Enable tracing for this process unless the exporter is
+    /// [TraceExporter::None].
+    pub fn init(
+        exporter: TraceExporter,
+        service_name: &str,
+        sample_ratio: f64,
+        export_interval_micros: u64,
+    ) {
+        if exporter == TraceExporter::None {
+            return;
+        }
+        log::info!(
+            "Tracing is enabled with exporter {exporter:?} and sample ratio {sample_ratio}."
+        );
+        let telemetry = Self::new(exporter, service_name, sample_ratio);
+        if TELEMETRY.set(Arc::clone(&telemetry)).is_err() {
+            log::warn!("Tracing was already initialized.");
+            return;
+        }
+        if matches!(telemetry.exporter, TraceExporter::Otlp { .. }) {
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(tokio::time::Duration::from_micros(export_interval_micros))
+                        .await;
+                    telemetry.export().await;
+                }
+            });
+        }
+    }
+
+    fn new(exporter: TraceExporter, service_name: &str, sample_ratio: f64) -> Arc<Self> {
+        Arc::new(Self {
+            exporter,
+            service_name: service_name.to_string(),
+            sample_ratio,
+            id_random_state: RandomState::new(),
+            id_counter: AtomicU64::new(0),
+            finished_spans: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Start a span that is a child of `parent` or the root of a new trace.
+    fn start_span(
+        self: &Arc<Self>,
+        name: &'static str,
+        kind: SpanKind,
+        parent: Option<SpanContext>,
+    ) -> Span {
+        let trace_id = parent
+            .map(|parent| parent.trace_id)
+            .unwrap_or_else(|| self.next_trace_id());
+        let context = SpanContext {
+            trace_id,
+            span_id: self.next_span_id(),
+            sampled: parent.map_or_else(|| self.is_sampled(&trace_id), |parent| parent.sampled),
+        };
+        Span {
+            inner: Some(ActiveSpan {
+                telemetry: Arc::clone(self),
+                context,
+                parent_span_id: parent.map(|parent| parent.span_id),
+                name,
+                kind,
+                start_epoch_nanos: now_epoch_nanos(),
+                attributes: vec![],
+                error: false,
+            }),
+        }
+    }
+
+    /// Return `true` if a new trace should be sampled.
+    ///
+    /// The lower 8 bytes of the trace identifier are compared with the ratio
+    /// of the `u64` range, so all spans of the trace get the same decision.
+    fn is_sampled(&self, trace_id: &[u8; 16]) -> bool {
+        if self.sample_ratio >= 1.0 {
+            return true;
+        }
+        let mut lower = [0u8; 8];
+        lower.copy_from_slice(&trace_id[8..]);
+        (u64::from_be_bytes(lower) as f64) < self.sample_ratio * u64::MAX as f64
+    }
+
+    /// Return a unique and unpredictable enough identifier.
+    ///
+    /// Trace identifiers are not security sensitive, so seeding a keyed hash
+    /// with a counter and the current time is sufficient.
+    fn next_id(&self) -> u64 {
+        let mut hasher = self.id_random_state.build_hasher();
+        hasher.write_u64(self.id_counter.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u64(now_epoch_nanos());
+        hasher.finish()
+    }
+
+    fn next_trace_id(&self) -> [u8; 16] {
+        let mut trace_id = [0u8; 16];
+        trace_id[..8].copy_from_slice(&self.next_id().to_be_bytes());
+        trace_id[8..].copy_from_slice(&self.next_id().to_be_bytes());
+        trace_id
+    }
+
+    fn next_span_id(&self) -> [u8; 8] {
+        self.next_id().to_be_bytes()
+    }
+
+    /// Hand over a finished span to the exporter.
+    fn finish(&self, finished_span: FinishedSpan) {
+        match &self.exporter {
+            TraceExporter::None => {}
+            TraceExporter::Log => {
+                log::info!(
+                    "Span '{}' trace: {} span: {} parent: {} duration: {} µs{}{}",
+                    finished_span.name,
+                    finished_span.context.trace_id.to_vec().to_hex(),
+                    finished_span.context.span_id.to_vec().to_hex(),
+                    finished_span
+                        .parent_span_id
+                        .map(|parent_span_id| parent_span_id.to_vec().to_hex())
+                        .unwrap_or("-".to_string()),
+                    finished_span
+                        .end_epoch_nanos
+                        .saturating_sub(finished_span.start_epoch_nanos)
+                        / 1_000,
+                    finished_span
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| format!(" {key}: {value}"))
+                        .collect::<String>(),
+                    if finished_span.error { " (error)" } else { "" },
+                );
+            }
+            TraceExporter::Otlp { .. } => {
+                let mut finished_spans = self.finished_spans.lock().unwrap();
+                if finished_spans.len() < Self::MAX_BUFFERED_SPANS {
+                    finished_spans.push(finished_span);
+                }
+            }
+        }
+    }
+
+    /// Send all buffered spans to the OTLP endpoint.
+    async fn export(&self) {
+        let TraceExporter::Otlp { endpoint } = &self.exporter else {
+            return;
+        };
+        let finished_spans = std::mem::take(&mut *self.finished_spans.lock().unwrap());
+        if finished_spans.is_empty() {
+            return;
+        }
+        let span_count = finished_spans.len();
+        let body = serde_json::json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{
+                        "key": "service.name",
+                        "value": { "stringValue": self.service_name },
+                    }],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "pitsa", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": finished_spans
+                        .iter()
+                        .map(FinishedSpan::as_otlp_json)
+                        .collect::<Vec<_>>(),
+                }],
+            }],
+        })
+        .to_string();
+        let endpoint = endpoint.to_owned();
+        let export_res = tokio::task::spawn_blocking(move || {
+            util::http_agent()
+                .post(&endpoint)
+                .header("Content-Type", "application/json")
+                .send(body.as_bytes())
+                .map(|_response| ())
+                .map_err(|e| format!("Failed to export spans to '{endpoint}': {e}"))
+        })
+        .await;
+        match export_res {
+            Ok(Ok(())) => {
+                if log::log_enabled!(log::Level::Trace) {
+                    log::trace!("Exported {span_count} spans.");
+                }
+            }
+            Ok(Err(msg)) => log::warn!("{msg} {span_count} spans were dropped."),
+            Err(e) => log::warn!("Span export failed: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Read;
+    use std::io::Write;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    /// Accept one HTTP request, answer `200 OK` and return the body.
+    fn collector_stand_in() -> (String, std::thread::JoinHandle<Vec<u8>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _peer_addr) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            body
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn traceparent_is_parsed() {
+        let context = SpanContext::from_traceparent(TRACEPARENT).unwrap();
+        assert!(context.sampled);
+        assert_eq!(context.traceparent(), TRACEPARENT);
+        let upper_case = TRACEPARENT.to_uppercase();
+        assert_eq!(
+            SpanContext::from_traceparent(&upper_case)
+                .unwrap()
+                .traceparent(),
+            TRACEPARENT
+        );
+        let not_sampled = SpanContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00",
+        );
+        assert!(!not_sampled.unwrap().sampled);
+        // Later versions may append fields
+        assert!(
+            SpanContext::from_traceparent(
+                "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra"
+            )
+            .is_some()
+        );
+    }
+
+    #[test]
+    fn invalid_traceparent_is_ignored() {
+        for value in [
+            "",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e47-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902zz-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert!(SpanContext::from_traceparent(value).is_none(), "{value}");
+        }
+    }
+
+    #[tokio::test]
+    async fn spans_are_parented() {
+        let telemetry = Telemetry::new(TraceExporter::Log, "pitsa", 1.0);
+        let remote_parent = SpanContext::from_traceparent(TRACEPARENT);
+        let server_span = telemetry.start_span("server", SpanKind::Server, remote_parent);
+        let server_context = server_span.context().unwrap();
+        assert_eq!(server_context.trace_id, remote_parent.unwrap().trace_id);
+        assert_ne!(server_context.span_id, remote_parent.unwrap().span_id);
+        let child_span = server_span
+            .in_scope(async {
+                telemetry.start_span("child", SpanKind::Internal, current_span_context())
+            })
+            .await;
+        let child = child_span.inner.as_ref().unwrap();
+        assert_eq!(child.context.trace_id, server_context.trace_id);
+        assert_eq!(child.parent_span_id, Some(server_context.span_id));
+        assert!(current_span_context().is_none());
+        let root_span = telemetry.start_span("root", SpanKind::Internal, None);
+        let root = root_span.inner.as_ref().unwrap();
+        assert_ne!(root.context.trace_id, server_context.trace_id);
+        assert_eq!(root.parent_span_id, None);
+    }
+
+    #[test]
+    fn new_traces_are_sampled_by_ratio() {
+        let never = Telemetry::new(TraceExporter::Log, "pitsa", 0.0);
+        assert!((0..100).all(|_| !never.is_sampled(&never.next_trace_id())));
+        let always = Telemetry::new(TraceExporter::Log, "pitsa", 1.0);
+        assert!((0..100).all(|_| always.is_sampled(&always.next_trace_id())));
+        let half = Telemetry::new(TraceExporter::Log, "pitsa", 0.5);
+        assert!(half.is_sampled(&[0u8; 16]));
+        assert!(!half.is_sampled(&[0xffu8; 16]));
+        // A remote parent's decision is kept regardless of the ratio
+        let remote_parent = SpanContext::from_traceparent(TRACEPARENT);
+        let span = never.start_span("server", SpanKind::Server, remote_parent);
+        assert!(span.context().unwrap().sampled);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn spans_are_exported_as_otlp_json() {
+        let (endpoint, collector) = collector_stand_in();
+        let telemetry = Telemetry::new(TraceExporter::Otlp { endpoint }, "pitsa-test", 1.0);
+        let remote_parent = SpanContext::from_traceparent(TRACEPARENT);
+        let mut server_span = telemetry.start_span("server", SpanKind::Server, remote_parent);
+        server_span.set_attribute("http.route", "/api/v1/tsp");
+        let server_context = server_span.context().unwrap();
+        let mut child_span =
+            telemetry.start_span("child", SpanKind::Internal, server_span.context());
+        child_span.set_error();
+        drop(child_span);
+        drop(server_span);
+        telemetry.export().await;
+        assert!(telemetry.finished_spans.lock().unwrap().is_empty());
+        let body = serde_json::from_slice::<serde_json::Value>(&collector.join().unwrap()).unwrap();
+        let resource_spans = &body["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "pitsa-test"
+        );
+        let spans = resource_spans["scopeSpans"][0]["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+        let (child, server) = (&spans[0], &spans[1]);
+        assert_eq!(child["name"], "child");
+        assert_eq!(child["kind"], SpanKind::Internal as u8);
+        assert_eq!(child["status"]["code"], 2);
+        assert_eq!(
+            child["parentSpanId"],
+            server_context.span_id.to_vec().to_hex()
+        );
+        assert_eq!(server["name"], "server");
+        assert_eq!(server["kind"], SpanKind::Server as u8);
+        assert_eq!(server["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(server["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(server["attributes"][0]["key"], "http.route");
+        assert_eq!(
+            server["attributes"][0]["value"]["stringValue"],
+            "/api/v1/tsp"
+        );
+    }
+}
@@ -17,19 +17,113 @@
 
 //! Time-Stamp app
 
+mod aggregator;
+mod authenticode;
+mod bearer_authentication;
+mod client_authorization;
+mod cms_signed_data;
+mod content_digest;
+mod cose_time_stamp;
+mod dvcs;
+mod encoded_time_stamp_resp;
+mod evidence_record;
+mod extension_handlers;
+mod imprint_request;
+mod jws_time_stamp;
+mod merkle_tree;
+mod metrics;
+mod parsed_time_stamp_token;
+mod qualified;
+mod rate_limits;
+mod readiness;
+mod revocation_evaluation;
 mod time_keeper;
+mod time_stamp_claims;
+mod time_stamped_data;
+mod token_archive;
+mod token_verification;
+mod transparency_log;
+mod tsa_info;
 mod tst_signing_info;
 
+use self::aggregator::Aggregator;
+use self::authenticode::AuthenticodeTimeStampRequest;
+pub use self::bearer_authentication::BearerAuthentication;
+use self::client_authorization::ClientAuthorization;
+pub use self::client_authorization::ClientIdentity;
+use self::cms_signed_data::CmsSignedData;
+pub use self::content_digest::ContentDigest;
+pub use self::cose_time_stamp::CONTENT_TYPE_COSE;
+use self::cose_time_stamp::CoseTimeStamp;
+use self::dvcs::DvcsData;
+use self::dvcs::DvcsRequest;
+use self::dvcs::DvcsResponse;
+pub use self::encoded_time_stamp_resp::EncodedTimeStampResp;
+pub use self::encoded_time_stamp_resp::TimeStampRespStatus;
+use self::evidence_record::ArchiveHashTree;
+use self::evidence_record::ArchiveTimeStamp;
+use self::evidence_record::EvidenceRecord;
+use self::extension_handlers::ExtensionHandlers;
+pub use self::imprint_request::ImprintRequest;
+pub use self::jws_time_stamp::CONTENT_TYPE_JOSE;
+pub use self::jws_time_stamp::JwsTimeStamp;
+pub use self::jws_time_stamp::JwsTimeStampClaims;
+pub use self::jws_time_stamp::JwsTimeStampVerification;
+use self::metrics::Gauge;
+pub use self::metrics::Metrics;
+use self::metrics::Phase;
+pub use self::parsed_time_stamp_token::ParsedTimeStampToken;
+use self::qualified::QualifiedTimeStamp;
+pub use self::rate_limits::RateLimit;
+pub use self::rate_limits::RateLimitMode;
+pub use self::rate_limits::RateLimits;
+pub use self::readiness::ReadinessCondition;
+pub use self::readiness::SelfTest;
+pub use self::revocation_evaluation::RevocationInfo;
 use self::time_keeper::TimeKeeper;
+pub use self::time_keeper::TimeSourceStatus;
+use self::time_stamp_claims::TimeStampClaims;
+use self::time_stamp_claims::TimeStampFormat;
+pub use self::time_stamp_claims::TimeStampFormatError;
+pub use self::time_stamped_data::TimeStampedData;
+pub use self::time_stamped_data::TimeStampedDataError;
+pub use self::token_archive::ArchivedToken;
+pub use self::token_archive::TokenArchive;
+pub use self::token_verification::ChainStatus;
+pub use self::token_verification::ExpectedImprint;
+pub use self::token_verification::RevocationStatus;
+pub use self::token_verification::TokenVerification;
+pub use self::transparency_log::SignedTreeHead;
+pub use self::transparency_log::TransparencyLog;
+pub use self::transparency_log::TransparencyLogEntry;
+pub use self::tsa_info::Transport;
+pub use self::tsa_info::TsaInfo;
+pub use self::tst_signing_info::RetiredCertificateChain;
+pub use self::tst_signing_info::SignerStatus;
+use self::tst_signing_info::SigningKeySnapshot;
 use self::tst_signing_info::TimeStampTokenSigningInfo;
 use crate::conf::AppConfig;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::Extension;
+use crate::logging::AuditEvent;
+use crate::logging::AuditEventType;
+use crate::telemetry::Span;
+use crate::util;
 use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 use tyst::Tyst;
+use tyst::encdec::hex::ToHex;
+use upkit_common::x509::cert::parse::CertificateParser;
 use upkit_common::x509::tsp::build::TimeStampResp;
 use upkit_common::x509::tsp::parse::TimeStampReqParser;
 use upkit_common::x509::tsp::types::PkiFailureInfo;
 use upkit_common::x509::tsp::types::TimeStampToken;
 use upkit_common::x509::tsp::types::TstInfo;
+use upkit_common::x509::tsp::validate::TimeStampResponseValidator;
 
 /** Time-Stamp app.
 
@@ -39,15 +133,45 @@ This object is responsible for maintaining (by delegation):
 * A time source
 */
 pub struct TimeStamper {
+    aggregator: Option<Aggregator>,
     allowed_digest_oids: Vec<String>,
     allowed_policy_oids: Vec<String>,
+    archive_admins: Vec<String>,
+    archive_rejects_on_failure: bool,
+    bearer_authentication: Option<Arc<BearerAuthentication>>,
+    client_authorization: Option<ClientAuthorization>,
+    configuration_warnings: Vec<String>,
+    digest_self_test: SelfTest,
+    extension_handlers: ExtensionHandlers,
+    metrics: Metrics,
+    qualified_policy_oids: Vec<String>,
+    rate_limits: RateLimits,
+    runtime_heartbeat_epoch_micros: Arc<AtomicU64>,
     tst_signing_info: Arc<TimeStampTokenSigningInfo>,
     time_keeper: Arc<TimeKeeper>,
+    token_archive: Option<TokenArchive>,
+    transparency_log: Option<TransparencyLog>,
+    transports: Vec<Transport>,
+    trust_anchors: Option<Vec<Vec<u8>>>,
 }
 
 impl TimeStamper {
-    /// Return a new instance of the app.
-    pub async fn new(app_config: &Arc<AppConfig>) -> Arc<Self> {
+    /// How often the async runtime proves that it still runs tasks.
+    const RUNTIME_HEARTBEAT_INTERVAL_MICROS: u64 = 1_000_000;
+
+    /// How long without a runtime heartbeat before the instance is no longer
+    /// considered alive.
+    const LIVENESS_HEARTBEAT_TIMEOUT_MICROS: u64 = 30 * 1_000_000;
+
+    /// Largest number of data objects in a single evidence record request.
+    pub const MAX_EVIDENCE_RECORD_OBJECTS: usize = 1024;
+
+    /// Largest accepted encoded request. A TimeStamp request is usually
+    /// around 5 KiB.
+    pub const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+    /// Return a new instance of the app or why the configuration is refused.
+    pub async fn new(app_config: &Arc<AppConfig>) -> Result<Arc<Self>, String> {
         let time_keeper = TimeKeeper::new(
             app_config.time.ntp_host(),
             app_config.time.ntp_timeout_micros(),
@@ -57,114 +181,1457 @@ impl TimeStamper {
             app_config.time.ntp_query_for_every_request(),
         )
         .await;
-        Arc::new(Self {
+        let tst_signing_info = TimeStampTokenSigningInfo::new(app_config).await?;
+        let aggregation_window_micros = app_config.sign.aggregation_window_micros();
+        let bearer_authentication = BearerAuthentication::new(
+            app_config.auth.api_keys_path(),
+            app_config.auth.jwks_location(),
+            app_config.auth.issuer(),
+            app_config.auth.audience(),
+            app_config.auth.identity_claim(),
+            app_config.auth.refresh_interval_micros(),
+        )
+        .await?;
+        let mut configuration_warnings = readiness::configuration_warnings(app_config);
+        let client_authorization = app_config.api.client_rules_path().map(|path| {
+            ClientAuthorization::from_file(&path).unwrap_or_else(|e| {
+                log::error!("{e} All clients will be refused.");
+                configuration_warnings.push(format!("{e} All clients will be refused."));
+                ClientAuthorization::default()
+            })
+        });
+        for configuration_warning in &configuration_warnings {
+            log::warn!("{configuration_warning}");
+        }
+        let token_archive = app_config
+            .archive
+            .directory()
+            .map(|directory| {
+                TokenArchive::new(
+                    directory,
+                    app_config.archive.retention_micros(),
+                    app_config.archive.max_size_bytes(),
+                    app_config.archive.segment_size_bytes(),
+                )
+                .map_err(|e| format!("Unable to open token archive: {e}"))
+            })
+            .transpose()?;
+        let transparency_log = app_config
+            .transparency
+            .enabled()
+            .then(|| {
+                TransparencyLog::new(
+                    &app_config.sign.digest_algorithm_oid(),
+                    app_config.transparency.file(),
+                )
+                .map_err(|e| format!("Unable to open transparency log: {e}"))
+            })
+            .transpose()?;
+        let runtime_heartbeat_epoch_micros =
+            Arc::new(AtomicU64::new(upkit_common::util::time::now_epoch_micros()));
+        let heartbeat = Arc::clone(&runtime_heartbeat_epoch_micros);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_micros(
+                    Self::RUNTIME_HEARTBEAT_INTERVAL_MICROS,
+                ))
+                .await;
+                heartbeat.store(
+                    upkit_common::util::time::now_epoch_micros(),
+                    Ordering::Relaxed,
+                );
+            }
+        });
+        Ok(Arc::new_cyclic(|time_stamper| {
+            Self {
+            aggregator: aggregation_window_micros.and_then(|window_micros| {
+                let Some(inclusion_proof_attribute_oid) =
+                    app_config.sign.aggregation_proof_attribute_oid()
+                else {
+                    log::error!(
+                        "Aggregation requires an inclusion proof attribute OID. Aggregation is disabled."
+                    );
+                    return None;
+                };
+                Aggregator::new(
+                    time_stamper.clone(),
+                    &app_config.sign.digest_algorithm_oid(),
+                    inclusion_proof_attribute_oid,
+                    window_micros,
+                )
+            }),
             allowed_digest_oids: app_config.sign.allowed_digest_oids(),
             allowed_policy_oids: vec![tyst::encdec::oid::as_string(&app_config.sign.policy_oid())],
-            tst_signing_info: TimeStampTokenSigningInfo::new(app_config).await,
+            archive_admins: app_config.archive.admins(),
+            archive_rejects_on_failure: app_config.archive.reject_on_failure(),
+            bearer_authentication,
+            client_authorization,
+            configuration_warnings,
+            digest_self_test: SelfTest::digest_known_answer(),
+            extension_handlers: ExtensionHandlers::new(app_config),
+            metrics: Metrics::default(),
+            qualified_policy_oids: app_config.sign.qualified_policy_oids(),
+            rate_limits: RateLimits::new(
+                app_config.limits.rate_limit_mode(),
+                app_config.limits.trusted_proxies(),
+                app_config.limits.global_rate_limit(),
+                app_config.limits.ip_address_rate_limit(),
+                app_config.limits.client_rate_limit(),
+            ),
+            runtime_heartbeat_epoch_micros,
+            tst_signing_info,
             time_keeper,
-        })
+            token_archive,
+            transparency_log,
+            transports: Transport::configured(app_config),
+            trust_anchors: app_config.validation.trust_anchors(),
+        }
+        }))
     }
 
     /// Return `true` when a usable TS signing certificate and private key is
     /// avaialable and the configured time source is has an acceptable acurracy.
     pub fn is_ready(self: &Arc<Self>) -> bool {
-        self.tst_signing_info.valid_signing_info_available()
-            && self.time_keeper.is_within_tolerance()
+        self.readiness_conditions()
+            .iter()
+            .all(|readiness_condition| readiness_condition.ready)
+    }
+
+    /// Return `false` only when the instance is wedged and a restart is the
+    /// way out: The async runtime stopped running tasks or a thread panicked
+    /// while holding a lock that every request needs.
+    ///
+    /// Time quality and signing certificate problems only affect readiness,
+    /// since a restart won't fix the time source or the enrollment.
+    pub fn is_live(self: &Arc<Self>) -> bool {
+        let heartbeat_epoch_micros = self.runtime_heartbeat_epoch_micros.load(Ordering::Relaxed);
+        let runtime_alive = upkit_common::util::time::now_epoch_micros()
+            .saturating_sub(heartbeat_epoch_micros)
+            < Self::LIVENESS_HEARTBEAT_TIMEOUT_MICROS;
+        if !runtime_alive {
+            log::error!("The async runtime has not run the heartbeat task recently.");
+        }
+        let poisoned = self.tst_signing_info.is_poisoned()
+            || self.metrics.is_poisoned()
+            || self
+                .transparency_log
+                .as_ref()
+                .is_some_and(TransparencyLog::is_poisoned)
+            || self
+                .token_archive
+                .as_ref()
+                .is_some_and(TokenArchive::is_poisoned);
+        if poisoned {
+            log::error!("A lock was poisoned by a panic.");
+        }
+        runtime_alive && !poisoned
+    }
+
+    /// Return each condition that must be fulfilled for this instance to be
+    /// ready.
+    pub fn readiness_conditions(self: &Arc<Self>) -> Vec<ReadinessCondition> {
+        let signer_status = self.tst_signing_info.signer_status();
+        ReadinessCondition::evaluate(
+            &self.time_keeper.status(),
+            signer_status.as_ref(),
+            self.tst_signing_info.enrollment_error(),
+            &self.self_tests_with(signer_status.as_ref()),
+            self.transparency_log
+                .as_ref()
+                .map(TransparencyLog::write_error),
+            upkit_common::util::time::now_epoch_micros(),
+        )
+    }
+
+    /// Return a snapshot of the state of the time source.
+    pub fn time_source_status(self: &Arc<Self>) -> TimeSourceStatus {
+        self.time_keeper.status()
+    }
+
+    /// Return a snapshot of the state of the current signing key and
+    /// certificate if any.
+    pub fn signer_status(self: &Arc<Self>) -> Option<SignerStatus> {
+        self.tst_signing_info.signer_status()
+    }
+
+    /// Return why the most recent signing certificate enrollment failed, if
+    /// it did.
+    pub fn signer_enrollment_error(self: &Arc<Self>) -> Option<String> {
+        self.tst_signing_info.enrollment_error()
+    }
+
+    /// Return the results of the built-in self-tests.
+    pub fn self_tests(self: &Arc<Self>) -> Vec<SelfTest> {
+        self.self_tests_with(self.tst_signing_info.signer_status().as_ref())
+    }
+
+    fn self_tests_with(&self, signer_status: Option<&SignerStatus>) -> Vec<SelfTest> {
+        let mut ret = vec![self.digest_self_test.clone()];
+        if let Some(signer_status) = signer_status {
+            ret.push(SelfTest {
+                name: "signature",
+                result: signer_status.signature_self_test.clone(),
+            });
+        }
+        ret
+    }
+
+    /// Return warnings about the configuration of this instance.
+    pub fn configuration_warnings(self: &Arc<Self>) -> &[String] {
+        &self.configuration_warnings
     }
 
     /// Process encoded request and respond with an encoded signed time-stamp.
     pub async fn raw_time_stamp_request(self: &Arc<Self>, time_stamp_request: &[u8]) -> Vec<u8> {
-        match TimeStampReqParser::from_bytes(time_stamp_request) {
-            Ok(time_stamp_req) => self
-                .time_stamp_request(&time_stamp_req)
-                .await
-                .as_bytes()
-                .to_vec(),
-            Err(e) => TimeStampResp::with_rejection(
+        let mut span = Span::start("TimeStamper::raw_time_stamp_request");
+        let time_stamp_resp = span
+            .in_scope(async {
+                let parse_start = Instant::now();
+                let time_stamp_resp = match TimeStampReqParser::from_bytes(time_stamp_request) {
+                    Ok(time_stamp_req) => {
+                        match ExtensionHandlers::request_extensions(time_stamp_request) {
+                            Ok(request_extensions) => {
+                                self.metrics.observe(Phase::Parse, parse_start.elapsed());
+                                self.count_request(&time_stamp_req);
+                                self.time_stamp_request(&time_stamp_req, &request_extensions)
+                                    .await
+                            }
+                            Err(e) => TimeStampResp::with_rejection(
+                                &[format!("Unable to parse request extensions: {e}")],
+                                &Some(PkiFailureInfo::BadDataFormat),
+                            ),
+                        }
+                    }
+                    Err(e) => TimeStampResp::with_rejection(
+                        &[format!("Unable to parse request: {e:?}")],
+                        &Some(PkiFailureInfo::SystemFailure),
+                    ),
+                };
+                let encode_start = Instant::now();
+                let encoded_time_stamp_resp = {
+                    let _encode_span = Span::start("TimeStampResp::as_bytes");
+                    time_stamp_resp.as_bytes().to_vec()
+                };
+                self.metrics.observe(Phase::Encode, encode_start.elapsed());
+                match self.record_issued_token(&encoded_time_stamp_resp).await {
+                    Ok(()) => encoded_time_stamp_resp,
+                    Err(e) => {
+                        TimeStampResp::with_rejection(&[e], &Some(PkiFailureInfo::SystemFailure))
+                            .as_bytes()
+                            .to_vec()
+                    }
+                }
+            })
+            .await;
+        let status = EncodedTimeStampResp::status_and_token(&time_stamp_resp)
+            .ok()
+            .map(|(status, _time_stamp_token)| status);
+        if let Some(status) = &status {
+            span.set_attribute("tsp.status", status.status_name());
+            if !status.is_granted() {
+                span.set_attribute("tsp.failure_info", status.failure_info.join(","));
+                span.set_error();
+            }
+        }
+        self.record_response_status(status.as_ref());
+        time_stamp_resp
+    }
+
+    /// Record the status of a response in the metrics and audit rejections.
+    fn record_response_status(self: &Arc<Self>, status: Option<&TimeStampRespStatus>) {
+        self.metrics.record_response(status);
+        if let Some(status) = status
+            && !status.is_granted()
+        {
+            AuditEvent::new(AuditEventType::TimeStampRejected)
+                .with("status", status.status_name())
+                .with("failureInfo", status.failure_info.clone())
+                .with("statusStrings", status.status_strings.clone())
+                .emit();
+        }
+    }
+
+    /// Count a parsed request by message imprint digest algorithm, policy and
+    /// client.
+    ///
+    /// Labels are bounded by the configuration: a digest algorithm that isn't
+    /// allowed (or unknown), a policy that isn't allowed and a client without
+    /// a client rule or API key are counted as `other`.
+    fn count_request(self: &Arc<Self>, time_stamp_req: &TimeStampReqParser) {
+        let digest_algorithm_oid = time_stamp_req.get_message_imprint_digest_oid();
+        let digest_algorithm_label = if self.allowed_digest_oids.is_empty() {
+            Tyst::instance()
+                .digests()
+                .by_oid(&digest_algorithm_oid)
+                .map(|_digest| digest_algorithm_oid.as_str())
+        } else {
+            self.allowed_digest_oids
+                .contains(&digest_algorithm_oid)
+                .then_some(digest_algorithm_oid.as_str())
+        };
+        let policy_oid = time_stamp_req.get_req_policy_oid();
+        let policy_label = match &policy_oid {
+            None => Some("none"),
+            Some(policy_oid) => self
+                .allowed_policy_oids
+                .contains(policy_oid)
+                .then_some(policy_oid.as_str()),
+        };
+        let client = ClientIdentity::current().flatten();
+        let client_label = match &client {
+            None => Some("anonymous".to_string()),
+            Some(client_identity) => self
+                .client_authorization
+                .as_ref()
+                .and_then(|client_authorization| {
+                    client_authorization.rule_identity(client_identity)
+                })
+                .or_else(|| {
+                    self.bearer_authentication
+                        .as_ref()
+                        .and_then(|bearer_authentication| {
+                            bearer_authentication.api_key_identity(client_identity)
+                        })
+                }),
+        };
+        self.metrics.record_request(
+            digest_algorithm_label.unwrap_or("other"),
+            policy_label.unwrap_or("other"),
+            client_label.as_deref().unwrap_or("other"),
+        );
+    }
+
+    /// Record a granted time-stamp token in the transparency log, the token
+    /// archive and the audit log.
+    ///
+    /// Fails if the token could not be appended to the transparency log or
+    /// could not be archived and the archive failure policy is to reject.
+    async fn record_issued_token(
+        self: &Arc<Self>,
+        encoded_time_stamp_resp: &[u8],
+    ) -> Result<(), String> {
+        let Ok(time_stamp_token) = EncodedTimeStampResp::granted_token(encoded_time_stamp_resp)
+        else {
+            return Ok(());
+        };
+        let token = match ParsedTimeStampToken::from_bytes(&time_stamp_token) {
+            Ok(token) => token,
+            Err(e) => {
+                log::warn!("Unable to parse issued token for recording: {e}");
+                return Ok(());
+            }
+        };
+        let tsu_certificate = token.signer_certificate.to_owned().or_else(|| {
+            self.tst_signing_info
+                .get_signing_key_snapshot()
+                .and_then(|signing_key| signing_key.certificate_chain.first().cloned())
+        });
+        self.record_issued_time_stamp(
+            TimeStampFormat::Cms,
+            time_stamp_token,
+            TimeStampClaims::from(&token),
+            tsu_certificate,
+        )
+        .await
+    }
+
+    /// Record an issued time-stamp, encoded as returned to the client, in the
+    /// token archive, the transparency log and the audit log.
+    ///
+    /// The archive and the transparency log are synced to disk on a blocking
+    /// thread before this returns. Fails if the time-stamp could not be
+    /// appended to the transparency log or could not be archived and the
+    /// archive failure policy is to reject.
+    async fn record_issued_time_stamp(
+        self: &Arc<Self>,
+        format: TimeStampFormat,
+        encoded_time_stamp: Vec<u8>,
+        claims: TimeStampClaims,
+        tsu_certificate: Option<Vec<u8>>,
+    ) -> Result<(), String> {
+        let mut audit_event = AuditEvent::new(AuditEventType::TimeStampIssued)
+            .with("format", format.as_str())
+            .with("serialNumber", claims.serial_number.to_hex())
+            .with("policy", tyst::encdec::oid::as_string(&claims.policy_oid))
+            .with(
+                "digestAlgorithm",
+                tyst::encdec::oid::as_string(&claims.digest_algorithm_oid),
+            )
+            .with("genTime", der::rfc3339(claims.gen_time_epoch_micros));
+        if let Some(accuracy_micros) = claims.accuracy_micros {
+            audit_event = audit_event.with("accuracyMicros", accuracy_micros);
+        }
+        if let Some(tsu_certificate) = tsu_certificate
+            .as_ref()
+            .and_then(|encoded| CertificateParser::from_bytes(encoded).ok())
+        {
+            audit_event = audit_event.with(
+                "tsuCertificateSerialNumber",
+                tsu_certificate.get_serial_number().to_hex(),
+            );
+        }
+        if self.token_archive.is_some() || self.transparency_log.is_some() {
+            let time_stamper = Arc::clone(self);
+            tokio::task::spawn_blocking(move || {
+                time_stamper.persist_issued_time_stamp(
+                    format,
+                    &encoded_time_stamp,
+                    &claims,
+                    tsu_certificate,
+                )
+            })
+            .await
+            .map_err(|e| format!("Recording the issued time-stamp failed: {e}"))??;
+        }
+        audit_event.emit();
+        Ok(())
+    }
+
+    /// Append an issued time-stamp to the token archive and the transparency
+    /// log.
+    ///
+    /// A failure to archive is only an error if the archive failure policy is
+    /// to reject. A failure to append to the transparency log is always an
+    /// error, since the time-stamp could never be proven to be logged. Blocks
+    /// until the records are synced.
+    fn persist_issued_time_stamp(
+        &self,
+        format: TimeStampFormat,
+        encoded_time_stamp: &[u8],
+        claims: &TimeStampClaims,
+        tsu_certificate: Option<Vec<u8>>,
+    ) -> Result<(), String> {
+        if let Some(token_archive) = &self.token_archive {
+            let tsu_certificate_fingerprint = tsu_certificate
+                .and_then(|tsu_certificate| util::digest(util::OID_SHA256, &tsu_certificate))
+                .unwrap_or_default();
+            let archived_token = ArchivedToken::new(
+                format,
+                encoded_time_stamp,
+                claims,
+                &tsu_certificate_fingerprint,
+            );
+            if let Err(e) = token_archive.append(&archived_token) {
+                log::error!("Failed to archive issued time-stamp: {e}");
+                if self.archive_rejects_on_failure {
+                    return Err("The time-stamp could not be archived.".to_string());
+                }
+            }
+        }
+        if let Some(transparency_log) = &self.transparency_log {
+            match transparency_log.append(encoded_time_stamp, claims) {
+                Ok(index) => {
+                    if log::log_enabled!(log::Level::Trace) {
+                        log::trace!(
+                            "Appended issued time-stamp to transparency log at index {index}."
+                        );
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to append issued time-stamp to transparency log: {e}");
+                    return Err(
+                        "The time-stamp could not be appended to the transparency log.".to_string(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return all metrics in the Prometheus text-based exposition format.
+    pub fn metrics_exposition(self: &Arc<Self>) -> String {
+        let time_source_status = self.time_keeper.status();
+        let signer_status = self.tst_signing_info.signer_status();
+        let now_epoch_micros = upkit_common::util::time::now_epoch_micros();
+        let gauges = [
+            Gauge::new(
+                "pitsa_time_accuracy_seconds",
+                "Declared accuracy of the most recent time measurement.",
+                time_source_status
+                    .declared_accuracy_micros
+                    .map(|micros| micros as f64 / 1_000_000f64),
+            ),
+            Gauge::new(
+                "pitsa_time_ntp_offset_seconds",
+                "Offset to the NTP host in the most recent NTP response.",
+                time_source_status
+                    .ntp_offset_micros
+                    .map(|micros| micros as f64 / 1_000_000f64),
+            ),
+            Gauge::new(
+                "pitsa_time_ntp_roundtrip_seconds",
+                "Roundtrip time of the most recent NTP response.",
+                time_source_status
+                    .ntp_roundtrip_micros
+                    .map(|micros| micros as f64 / 1_000_000f64),
+            ),
+            Gauge::new(
+                "pitsa_time_within_tolerance",
+                "1 if the time source accuracy is within the tolerable limit.",
+                Some(f64::from(u8::from(time_source_status.within_tolerance))),
+            ),
+            Gauge::new(
+                "pitsa_signer_certificate_not_after_seconds",
+                "Signing certificate notAfter in seconds since the Unix epoch.",
+                signer_status
+                    .as_ref()
+                    .map(|signer_status| (signer_status.not_after_epoch_micros / 1_000_000) as f64),
+            ),
+            Gauge::new(
+                "pitsa_signer_revocation_info_age_seconds",
+                "Age of the oldest revocation information of the signing certificate chain.",
+                signer_status
+                    .as_ref()
+                    .and_then(|signer_status| signer_status.oldest_revocation_info_epoch_micros)
+                    .map(|epoch_micros| {
+                        now_epoch_micros.saturating_sub(epoch_micros) as f64 / 1_000_000f64
+                    }),
+            ),
+            Gauge::new(
+                "pitsa_signer_key_signatures",
+                "Number of signatures made with the current signing key.",
+                signer_status
+                    .as_ref()
+                    .map(|signer_status| signer_status.signature_count as f64),
+            ),
+        ];
+        self.metrics.render(&gauges)
+    }
+
+    /// Return the bearer token authentication if configured.
+    pub fn bearer_authentication(self: &Arc<Self>) -> Option<&Arc<BearerAuthentication>> {
+        self.bearer_authentication.as_ref()
+    }
+
+    /// Return the request rate limits.
+    pub fn rate_limits(self: &Arc<Self>) -> &RateLimits {
+        &self.rate_limits
+    }
+
+    /// Return the archive of issued tokens if enabled.
+    pub fn token_archive(self: &Arc<Self>) -> Option<&TokenArchive> {
+        self.token_archive.as_ref()
+    }
+
+    /// Return `true` if the client may look up and export archived tokens.
+    pub fn is_archive_admin(self: &Arc<Self>, client_identity: &ClientIdentity) -> bool {
+        self.archive_admins
+            .iter()
+            .any(|admin| client_identity.has_name(admin))
+    }
+
+    /// Return the token transparency log if enabled.
+    pub fn transparency_log(self: &Arc<Self>) -> Option<&TransparencyLog> {
+        self.transparency_log.as_ref()
+    }
+
+    /// Return a head of the token transparency log signed by the TSU key or
+    /// `None` if the log is disabled or no signature can be produced right
+    /// now.
+    pub async fn transparency_log_signed_tree_head(self: &Arc<Self>) -> Option<SignedTreeHead> {
+        let transparency_log = self.transparency_log.as_ref()?;
+        let (point_in_time_epoch_micros, _accuracy_micros) = self
+            .time_keeper
+            .get_epoch_time_with_accuracy_micros()
+            .await?;
+        let signing_key = self.tst_signing_info.get_signing_key_snapshot()?;
+        transparency_log.signed_tree_head(&signing_key, point_in_time_epoch_micros / 1_000)
+    }
+
+    /// Process a request for a time-stamp over a message imprint and respond
+    /// with an encoded signed time-stamp.
+    pub async fn imprint_time_stamp_request(
+        self: &Arc<Self>,
+        imprint_request: &ImprintRequest,
+    ) -> Vec<u8> {
+        self.raw_time_stamp_request(&imprint_request.as_bytes())
+            .await
+    }
+
+    /** Process encoded request and respond with an encoded signed time-stamp
+    over the Merkle tree root of all requests in the same aggregation window.
+
+    The proof of the request's inclusion in the tree is added to the token as
+    an unsigned attribute (see [Aggregator]).
+
+    Request extensions and the nonce can't be reflected in the shared
+    time-stamp token, so requests with extensions or a nonce are rejected.
+    */
+    pub async fn aggregated_time_stamp_request(
+        self: &Arc<Self>,
+        time_stamp_request: &[u8],
+    ) -> Vec<u8> {
+        let time_stamp_resp = match self.aggregated_inclusion(time_stamp_request).await {
+            Ok(time_stamp_resp) => time_stamp_resp,
+            Err(rejection) => rejection.as_bytes().to_vec(),
+        };
+        // The shared token over the root is counted once as an internal
+        // request, so each leaf response is counted here as well
+        self.record_response_status(
+            EncodedTimeStampResp::status_and_token(&time_stamp_resp)
+                .ok()
+                .map(|(status, _time_stamp_token)| status)
+                .as_ref(),
+        );
+        time_stamp_resp
+    }
+
+    /// Return the encoded response with the inclusion proof.
+    async fn aggregated_inclusion(
+        self: &Arc<Self>,
+        time_stamp_request: &[u8],
+    ) -> Result<Vec<u8>, TimeStampResp> {
+        let aggregator = self.aggregator.as_ref().ok_or_else(|| {
+            TimeStampResp::with_rejection(
+                &["Aggregated time-stamps are not enabled for this service.".to_string()],
+                &Some(PkiFailureInfo::SystemFailure),
+            )
+        })?;
+        let parse_start = Instant::now();
+        let time_stamp_req = TimeStampReqParser::from_bytes(time_stamp_request).map_err(|e| {
+            TimeStampResp::with_rejection(
                 &[format!("Unable to parse request: {e:?}")],
                 &Some(PkiFailureInfo::SystemFailure),
             )
-            .as_bytes()
-            .to_vec(),
+        })?;
+        let (encoded_message_imprint, has_nonce) =
+            Self::encoded_message_imprint_and_nonce_presence(time_stamp_request).map_err(|e| {
+                TimeStampResp::with_rejection(
+                    &[format!("Unable to parse request message imprint: {e}")],
+                    &Some(PkiFailureInfo::BadDataFormat),
+                )
+            })?;
+        self.metrics.observe(Phase::Parse, parse_start.elapsed());
+        self.count_request(&time_stamp_req);
+        if !ExtensionHandlers::request_extensions(time_stamp_request)
+            .is_ok_and(|request_extensions| request_extensions.is_empty())
+        {
+            return Err(TimeStampResp::with_rejection(
+                &["Request extensions are not supported for aggregated time-stamps.".to_string()],
+                &Some(PkiFailureInfo::UnacceptedExtension),
+            ));
         }
+        if has_nonce {
+            return Err(TimeStampResp::with_rejection(
+                &["A nonce can't be included in aggregated time-stamps. Send the request without nonce.".to_string()],
+                &Some(PkiFailureInfo::BadRequest),
+            ));
+        }
+        self.assert_acceptable_request(&time_stamp_req, &[])?;
+        let aggregate_start = Instant::now();
+        let time_stamp_resp = aggregator
+            .aggregate(&encoded_message_imprint)
+            .await
+            .ok_or_else(|| {
+                TimeStampResp::with_rejection(
+                    &["Aggregation of the request failed.".to_string()],
+                    &Some(PkiFailureInfo::SystemFailure),
+                )
+            })?;
+        self.metrics
+            .observe(Phase::Aggregate, aggregate_start.elapsed());
+        Ok(time_stamp_resp)
     }
 
-    /// Process request and respond with a signed time-stamp.
-    async fn time_stamp_request(
+    /// Return the DER encoded `MessageImprint` of a `TimeStampReq` and
+    /// whether the request has a nonce.
+    ///
+    /// ```text
+    /// TimeStampReq ::= SEQUENCE  {
+    ///    version                      INTEGER  { v1(1) },
+    ///    messageImprint               MessageImprint,
+    ///    reqPolicy             TSAPolicyId              OPTIONAL,
+    ///    nonce                 INTEGER                  OPTIONAL,
+    ///    ...
+    /// ```
+    fn encoded_message_imprint_and_nonce_presence(
+        time_stamp_request: &[u8],
+    ) -> Result<(Vec<u8>, bool), der::DerError> {
+        let mut time_stamp_req =
+            DerReader::read_single(time_stamp_request, tag::SEQUENCE)?.reader();
+        time_stamp_req.read_expected(tag::INTEGER)?;
+        let encoded_message_imprint = time_stamp_req
+            .read_expected(tag::SEQUENCE)?
+            .encoded
+            .to_vec();
+        time_stamp_req.read_optional(tag::OID)?;
+        let has_nonce = time_stamp_req.read_optional(tag::INTEGER)?.is_some();
+        Ok((encoded_message_imprint, has_nonce))
+    }
+
+    /// Return the digest algorithm used by the current TSU signing key.
+    pub fn default_digest_algorithm_oid(self: &Arc<Self>) -> Option<Vec<u32>> {
+        self.tst_signing_info
+            .get_signing_key_snapshot()
+            .map(|signing_key| signing_key.digest_algorithm_oid)
+    }
+
+    /// Return the current DER encoded TSU certificate chain with the leaf
+    /// first.
+    pub fn get_current_certificate_chain(self: &Arc<Self>) -> Option<Vec<Vec<u8>>> {
+        self.tst_signing_info
+            .get_signing_key_snapshot()
+            .map(|signing_key| signing_key.certificate_chain)
+    }
+
+    /// Return the most recently retired DER encoded TSU certificate chains
+    /// with the newest first.
+    pub fn retired_certificate_chains(self: &Arc<Self>) -> Vec<RetiredCertificateChain> {
+        self.tst_signing_info.retired_certificate_chains()
+    }
+
+    /// Return a DER encoded PKCS #7 "certs-only" bundle of the current TSU
+    /// certificate chain and optionally the recently retired ones.
+    pub fn certificate_bundle(self: &Arc<Self>, include_retired: bool) -> Option<Vec<u8>> {
+        let mut certificates = self.get_current_certificate_chain()?;
+        if include_retired {
+            for retired in self.retired_certificate_chains() {
+                for certificate in retired.certificate_chain {
+                    if !certificates.contains(&certificate) {
+                        certificates.push(certificate);
+                    }
+                }
+            }
+        }
+        Some(CmsSignedData::certs_only(&certificates))
+    }
+
+    /// Return the capabilities of this TSA.
+    pub fn tsa_info(self: &Arc<Self>) -> TsaInfo {
+        let signing_key = self.tst_signing_info.get_signing_key_snapshot();
+        let time_source_status = self.time_keeper.status();
+        TsaInfo {
+            policy_oids: self.allowed_policy_oids.to_vec(),
+            allowed_digest_oids: self.allowed_digest_oids.to_vec(),
+            signature_algorithm_oid: signing_key.as_ref().map(|signing_key| {
+                tyst::encdec::oid::as_string(&signing_key.signing_algorithm_oid)
+            }),
+            digest_algorithm_oid: signing_key
+                .as_ref()
+                .map(|signing_key| tyst::encdec::oid::as_string(&signing_key.digest_algorithm_oid)),
+            declared_accuracy_micros: time_source_status.declared_accuracy_micros,
+            tolerable_accuracy_micros: time_source_status.tolerable_accuracy_micros,
+            qualified_policy_oids: self.qualified_policy_oids.to_vec(),
+            transports: self.transports.to_vec(),
+        }
+    }
+
+    /// Process a base64 encoded Microsoft Authenticode time-stamp request and
+    /// respond with a base64 encoded PKCS #7 counter-signature.
+    ///
+    /// The request is subject to the same checks as an RFC 3161 request over
+    /// the digest of the counter-signed content.
+    pub async fn authenticode_time_stamp_request(
         self: &Arc<Self>,
-        time_stamp_req: &TimeStampReqParser,
-    ) -> TimeStampResp {
-        let imprint_digest_oid = time_stamp_req.get_message_imprint_digest_oid();
-        if let Some(known_digest) = Tyst::instance().digests().by_oid(&imprint_digest_oid) {
-            // Assert correct message imprint digest size
-            let expected = known_digest.get_digest_size_bits() / 8;
-            let actual = time_stamp_req.get_message_imprint_digest_len_octets();
-            if expected != actual {
-                return TimeStampResp::with_rejection(
-                    &[format!(
-                        "Message imprint digest length ({actual} bytes) does not match the claimed algorithm's ({expected} bytes)."
-                    )],
-                    &Some(PkiFailureInfo::BadDataFormat),
-                );
+        encoded_request: &[u8],
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let request = AuthenticodeTimeStampRequest::from_base64(encoded_request)?;
+        let digest_algorithm_oid = self.content_digest_algorithm_oid();
+        let digest = util::digest(&digest_algorithm_oid, request.content()).ok_or(
+            TimeStampFormatError::Unavailable("Unable to hash content.".to_string()),
+        )?;
+        let imprint_request = ImprintRequest::new(&digest_algorithm_oid, &digest, false);
+        let (claims, signing_key) = self.time_stamp_claims(&imprint_request).await?;
+        let signed_data = CmsSignedData::build(
+            &signing_key,
+            cms_signed_data::OID_DATA,
+            request.content(),
+            claims.gen_time_epoch_micros / 1_000_000,
+        )
+        .ok_or_else(|| {
+            let msg = "Failed to sign response.";
+            self.record_response_status(Some(&TimeStampRespStatus::rejection(
+                msg,
+                "systemFailure",
+            )));
+            TimeStampFormatError::Unavailable(msg.to_string())
+        })?;
+        let encoded_response = tyst::encdec::base64::encode(&signed_data).into_bytes();
+        self.record_issued_time_stamp(
+            TimeStampFormat::Authenticode,
+            signed_data,
+            claims,
+            signing_key.certificate_chain.first().cloned(),
+        )
+        .await
+        .map_err(|e| {
+            self.record_response_status(Some(&TimeStampRespStatus::rejection(&e, "systemFailure")));
+            TimeStampFormatError::Unavailable(e)
+        })?;
+        self.record_response_status(Some(&TimeStampRespStatus::granted()));
+        Ok(encoded_response)
+    }
+
+    /// Process a DER encoded RFC 3029 DVCS request and respond with a signed
+    /// DVCS response.
+    ///
+    /// The `cpd` service certifies the possession of the data in the request
+    /// and `ccpd` the claim of possession of the data given as a message
+    /// imprint. Other services result in a DVCS error notice. The message
+    /// imprint is subject to the same checks as an RFC 3161 request.
+    pub async fn dvcs_request(
+        self: &Arc<Self>,
+        encoded_request: &[u8],
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let request = DvcsRequest::from_bytes(encoded_request).map_err(|e| {
+            TimeStampFormatError::BadRequest(format!("Malformed DVCS request: {e}"))
+        })?;
+        let imprint = match (request.service, &request.data) {
+            (dvcs::SERVICE_CPD, DvcsData::Message(data)) => {
+                let digest_algorithm_oid = self.content_digest_algorithm_oid();
+                util::digest(&digest_algorithm_oid, data)
+                    .map(|digest| (digest_algorithm_oid, digest))
+                    .ok_or(("Unable to hash data.", dvcs::FAILURE_BAD_ALG))
             }
-            // Assert that message digest algo is allowed by configuration
-            // Empty = allow any known
-            if !self.allowed_digest_oids.is_empty()
-                && !self.allowed_digest_oids.contains(&imprint_digest_oid)
-            {
-                return TimeStampResp::with_rejection(
-                    &[format!(
-                        "Message digest algorithm '{imprint_digest_oid}' in message imprint is not allowed."
-                    )],
-                    &Some(PkiFailureInfo::BadAlgo),
-                );
+            (dvcs::SERVICE_CCPD, DvcsData::MessageImprint(digest_algorithm_oid, digest)) => {
+                let digest_algorithm = tyst::encdec::oid::as_string(digest_algorithm_oid);
+                let digest_size = util::digest_size(digest_algorithm_oid);
+                if digest_size.is_none()
+                    || (!self.allowed_digest_oids.is_empty()
+                        && !self.allowed_digest_oids.contains(&digest_algorithm))
+                {
+                    Err((
+                        "Message imprint digest algorithm is not allowed.",
+                        dvcs::FAILURE_BAD_ALG,
+                    ))
+                } else if digest_size != Some(digest.len()) {
+                    Err((
+                        "Message imprint digest length does not match the algorithm.",
+                        dvcs::FAILURE_BAD_DATA_FORMAT,
+                    ))
+                } else {
+                    Ok((digest_algorithm_oid.clone(), digest.clone()))
+                }
             }
-        } else {
-            return TimeStampResp::with_rejection(
-                &[format!(
-                    "Unknown message digest algorithm '{imprint_digest_oid}' in message imprint."
-                )],
-                &Some(PkiFailureInfo::BadAlgo),
+            (dvcs::SERVICE_CPD, _) => Err((
+                "The cpd service requires the data as message.",
+                dvcs::FAILURE_BAD_DATA_FORMAT,
+            )),
+            (dvcs::SERVICE_CCPD, _) => Err((
+                "The ccpd service requires a message imprint.",
+                dvcs::FAILURE_BAD_DATA_FORMAT,
+            )),
+            _ => Err((
+                "The requested service is not supported.",
+                dvcs::FAILURE_BAD_REQUEST,
+            )),
+        };
+        let (msg, failure_info_bit) = match imprint {
+            Ok((digest_algorithm_oid, digest)) => {
+                let imprint_request = ImprintRequest::new(&digest_algorithm_oid, &digest, false);
+                let (claims, signing_key) = self.time_stamp_claims(&imprint_request).await?;
+                let dvcs_response = DvcsResponse::cert_info(&signing_key, &request, &claims)
+                    .ok_or_else(|| {
+                        let msg = "Failed to sign response.";
+                        self.record_response_status(Some(&TimeStampRespStatus::rejection(
+                            msg,
+                            "systemFailure",
+                        )));
+                        TimeStampFormatError::Unavailable(msg.to_string())
+                    })?;
+                self.record_issued_time_stamp(
+                    TimeStampFormat::Dvcs,
+                    dvcs_response.clone(),
+                    claims,
+                    signing_key.certificate_chain.first().cloned(),
+                )
+                .await
+                .map_err(|e| {
+                    self.record_response_status(Some(&TimeStampRespStatus::rejection(
+                        &e,
+                        "systemFailure",
+                    )));
+                    TimeStampFormatError::Unavailable(e)
+                })?;
+                self.record_response_status(Some(&TimeStampRespStatus::granted()));
+                return Ok(dvcs_response);
+            }
+            Err(rejection) => rejection,
+        };
+        if log::log_enabled!(log::Level::Debug) {
+            log::debug!("Rejected DVCS request: {msg}");
+        }
+        let (point_in_time_epoch_micros, _accuracy_micros) = self
+            .time_keeper
+            .get_epoch_time_with_accuracy_micros()
+            .await
+            .ok_or(TimeStampFormatError::Unavailable(
+                "Failed to recieve current time with tolerable acurracy.".to_string(),
+            ))?;
+        let signing_key = self.tst_signing_info.get_signing_key_snapshot().ok_or(
+            TimeStampFormatError::Unavailable("No signing key available.".to_string()),
+        )?;
+        DvcsResponse::error_notice(
+            &signing_key,
+            &request,
+            msg,
+            failure_info_bit,
+            point_in_time_epoch_micros,
+        )
+        .ok_or(TimeStampFormatError::Unavailable(
+            "Failed to sign response.".to_string(),
+        ))
+    }
+
+    /// Return the digest algorithm to hash content that is time-stamped by
+    /// value with: The one of the current signing key or else SHA-256.
+    fn content_digest_algorithm_oid(self: &Arc<Self>) -> Vec<u32> {
+        self.default_digest_algorithm_oid()
+            .unwrap_or_else(|| util::OID_SHA256.to_vec())
+    }
+
+    /// Return a DER encoded RFC 4998 `EvidenceRecord` for each data object
+    /// hash (in the same order) with a single time-stamped hash tree.
+    pub async fn create_evidence_records(
+        self: &Arc<Self>,
+        digest_algorithm_oid: &[u32],
+        data_object_hashes: &[Vec<u8>],
+    ) -> Result<Vec<Vec<u8>>, TimeStampFormatError> {
+        if data_object_hashes.is_empty()
+            || data_object_hashes.len() > Self::MAX_EVIDENCE_RECORD_OBJECTS
+        {
+            return Err(TimeStampFormatError::BadRequest(format!(
+                "Between 1 and {} data object hashes are required.",
+                Self::MAX_EVIDENCE_RECORD_OBJECTS
+            )));
+        }
+        let hash_tree = Self::archive_hash_tree(digest_algorithm_oid)?;
+        if data_object_hashes
+            .iter()
+            .any(|hash| hash.len() != hash_tree.hash_size())
+        {
+            return Err(TimeStampFormatError::BadRequest(
+                "Data object hash length does not match the digest algorithm.".to_string(),
+            ));
+        }
+        let (root, reduced_hash_trees) = hash_tree.build(data_object_hashes);
+        let time_stamp = self
+            .archive_time_stamp_token(digest_algorithm_oid, &root)
+            .await?;
+        Ok(reduced_hash_trees
+            .into_iter()
+            .map(|reduced_hash_tree| {
+                EvidenceRecord::new(&ArchiveTimeStamp::new(
+                    digest_algorithm_oid,
+                    reduced_hash_tree,
+                    time_stamp.to_vec(),
+                ))
+                .as_bytes()
+            })
+            .collect())
+    }
+
+    /// Return the DER encoded `EvidenceRecord` with a time-stamp renewal
+    /// ([RFC 4998 5.2](https://www.rfc-editor.org/rfc/rfc4998#section-5.2))
+    /// appended to the last archive time-stamp chain.
+    pub async fn renew_evidence_record_time_stamp(
+        self: &Arc<Self>,
+        encoded_evidence_record: &[u8],
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let mut evidence_record = Self::parse_evidence_record(encoded_evidence_record)?;
+        let last = evidence_record.last_archive_time_stamp().map_err(|e| {
+            TimeStampFormatError::BadRequest(format!("Malformed EvidenceRecord: {e}"))
+        })?;
+        let hash_tree = Self::archive_hash_tree(&last.digest_algorithm_oid)?;
+        let time_stamp = self
+            .archive_time_stamp_token(
+                &last.digest_algorithm_oid,
+                &hash_tree.hash(&last.time_stamp),
+            )
+            .await?;
+        evidence_record.append_to_last_chain(&ArchiveTimeStamp::new(
+            &last.digest_algorithm_oid,
+            vec![],
+            time_stamp,
+        ));
+        Ok(evidence_record.as_bytes())
+    }
+
+    /// Return the DER encoded `EvidenceRecord` with a hash-tree renewal
+    /// ([RFC 4998 5.2](https://www.rfc-editor.org/rfc/rfc4998#section-5.2))
+    /// in a new archive time-stamp chain.
+    ///
+    /// The data object hash must be computed with the new digest algorithm.
+    pub async fn renew_evidence_record_hash_tree(
+        self: &Arc<Self>,
+        encoded_evidence_record: &[u8],
+        digest_algorithm_oid: &[u32],
+        data_object_hash: &[u8],
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let mut evidence_record = Self::parse_evidence_record(encoded_evidence_record)?;
+        let hash_tree = Self::archive_hash_tree(digest_algorithm_oid)?;
+        if data_object_hash.len() != hash_tree.hash_size() {
+            return Err(TimeStampFormatError::BadRequest(
+                "Data object hash length does not match the digest algorithm.".to_string(),
+            ));
+        }
+        // h(d)' = H(h(d) + ha) where ha is the hash of the ArchiveTimeStampSequence
+        let sequence_hash = hash_tree.hash(&evidence_record.encoded_archive_time_stamp_sequence());
+        let renewed_hash = hash_tree.hash(&[data_object_hash, &sequence_hash].concat());
+        let time_stamp = self
+            .archive_time_stamp_token(digest_algorithm_oid, &renewed_hash)
+            .await?;
+        evidence_record.append_chain(&ArchiveTimeStamp::new(
+            digest_algorithm_oid,
+            vec![],
+            time_stamp,
+        ));
+        Ok(evidence_record.as_bytes())
+    }
+
+    /// Parse a DER encoded `EvidenceRecord` from a request.
+    fn parse_evidence_record(
+        encoded_evidence_record: &[u8],
+    ) -> Result<EvidenceRecord, TimeStampFormatError> {
+        EvidenceRecord::from_bytes(encoded_evidence_record)
+            .map_err(|e| TimeStampFormatError::BadRequest(format!("Malformed EvidenceRecord: {e}")))
+    }
+
+    /// Return an archive hash tree for a known digest algorithm.
+    fn archive_hash_tree(
+        digest_algorithm_oid: &[u32],
+    ) -> Result<ArchiveHashTree, TimeStampFormatError> {
+        ArchiveHashTree::new(digest_algorithm_oid).ok_or_else(|| {
+            TimeStampFormatError::BadRequest(format!(
+                "Unknown digest algorithm '{}'.",
+                tyst::encdec::oid::as_string(digest_algorithm_oid)
+            ))
+        })
+    }
+
+    /// Return the DER encoded `TimeStampToken` over a hash tree root.
+    async fn archive_time_stamp_token(
+        self: &Arc<Self>,
+        digest_algorithm_oid: &[u32],
+        root: &[u8],
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let time_stamp_resp = self
+            .imprint_time_stamp_request(&ImprintRequest::new(digest_algorithm_oid, root, true))
+            .await;
+        let (status, time_stamp_token) = EncodedTimeStampResp::status_and_token(&time_stamp_resp)
+            .map_err(TimeStampFormatError::Unavailable)?;
+        if !status.is_granted() {
+            let msg = format!(
+                "Time-stamp was not granted (status {}): {}",
+                status.status_name(),
+                status.status_strings.join(" ")
             );
+            // A rejected digest algorithm or policy is the client's to fix
+            if status.is_request_failure() {
+                return Err(TimeStampFormatError::BadRequest(msg));
+            }
+            return Err(TimeStampFormatError::Unavailable(msg));
         }
-        // Verify policy oid against allow list. (Allow any policy if list is empty.)
-        let mut response_policy_oid = [2, 5, 29, 32, 0].to_vec();
-        if let Some(policy_oid) = time_stamp_req.get_req_policy_oid() {
-            if self.allowed_policy_oids.is_empty() || self.allowed_policy_oids.contains(&policy_oid)
-            {
-                response_policy_oid = tyst::encdec::oid::from_string(&policy_oid).unwrap();
-            } else {
-                return TimeStampResp::with_rejection(
-                    &[format!(
-                        "Requested policy '{policy_oid}' is not allowed by this service."
-                    )],
-                    &Some(PkiFailureInfo::UnacceptedPolicy),
-                );
+        time_stamp_token.ok_or(TimeStampFormatError::Unavailable(
+            "Granted time-stamp response has no token.".to_string(),
+        ))
+    }
+
+    /// Return a DER encoded RFC 5544 `TimeStampedData` with a time-stamp over
+    /// the embedded content or over the provided digest of the data at the
+    /// data URI.
+    pub async fn wrap_time_stamped_data(
+        self: &Arc<Self>,
+        data_uri: Option<String>,
+        file_name: Option<String>,
+        media_type: Option<String>,
+        content_or_digest: Result<Vec<u8>, (Vec<u32>, Vec<u8>)>,
+    ) -> Result<Vec<u8>, TimeStampedDataError> {
+        let (content, (digest_algorithm_oid, digest)) = match content_or_digest {
+            Ok(content) => {
+                let signing_key = self.tst_signing_info.get_signing_key_snapshot().ok_or(
+                    TimeStampedDataError::Unavailable("No signing key available.".to_string()),
+                )?;
+                let digest =
+                    signing_key
+                        .digest(&content)
+                        .ok_or(TimeStampedDataError::Unavailable(
+                            "Unable to hash content.".to_string(),
+                        ))?;
+                (Some(content), (signing_key.digest_algorithm_oid, digest))
             }
-        } else if let Some(first_allowed) = self.allowed_policy_oids.first() {
-            response_policy_oid = tyst::encdec::oid::from_string(first_allowed).unwrap();
+            Err(digest_algorithm_and_digest) => {
+                if data_uri.is_none() {
+                    return Err(TimeStampedDataError::BadRequest(
+                        "A data URI is required when the content is not embedded.".to_string(),
+                    ));
+                }
+                (None, digest_algorithm_and_digest)
+            }
+        };
+        let mut time_stamped_data = TimeStampedData::new(data_uri, file_name, media_type, content);
+        let time_stamp_resp = self
+            .imprint_time_stamp_request(&ImprintRequest::new(&digest_algorithm_oid, &digest, true))
+            .await;
+        match EncodedTimeStampResp::granted_token(&time_stamp_resp) {
+            Ok(time_stamp_token) => time_stamped_data.time_stamp_tokens.push(time_stamp_token),
+            // Requests over a client supplied digest can be rejected for the digest
+            Err(msg) if time_stamped_data.content.is_none() => {
+                return Err(TimeStampedDataError::BadRequest(msg));
+            }
+            Err(msg) => return Err(TimeStampedDataError::Unavailable(msg)),
         }
-        // Assert that there are no critical extensions (since we don't understand any atm).
-        if !time_stamp_req.get_critical_extension_oids().is_empty() {
-            return TimeStampResp::with_rejection(
-                &["Requested extension(s) are not supported by this service.".to_string()],
-                &Some(PkiFailureInfo::UnacceptedExtension),
-            );
+        Ok(time_stamped_data.as_bytes())
+    }
+
+    /// Validate a DER encoded RFC 5544 `TimeStampedData` and return its parts
+    /// and the point in time of the first time-stamp in epoch microseconds.
+    ///
+    /// When the content is embedded, the first time-stamp must cover it. Every
+    /// following time-stamp must cover the previous `TimeStampAndCRL`.
+    pub fn unwrap_time_stamped_data(
+        self: &Arc<Self>,
+        encoded_time_stamped_data: &[u8],
+    ) -> Result<(TimeStampedData, u64), TimeStampedDataError> {
+        let time_stamped_data =
+            TimeStampedData::from_bytes(encoded_time_stamped_data).map_err(|e| {
+                TimeStampedDataError::BadRequest(format!("Malformed TimeStampedData: {e}"))
+            })?;
+        let mut point_in_time_epoch_micros = None;
+        for (index, time_stamp_token) in time_stamped_data.time_stamp_tokens.iter().enumerate() {
+            // The first time-stamp covers the content and each renewal the previous one
+            let expected_imprint = if index == 0 {
+                time_stamped_data
+                    .time_stamped_content()
+                    .map(ExpectedImprint::Data)
+            } else {
+                time_stamped_data
+                    .renewed_time_stamp_and_crl(index)
+                    .map(ExpectedImprint::Data)
+            };
+            let verification = self
+                .verify_time_stamp(time_stamp_token, expected_imprint)
+                .map_err(TimeStampedDataError::Invalid)?;
+            if let Some(msg) = verification.failure() {
+                return Err(TimeStampedDataError::Invalid(msg));
+            }
+            point_in_time_epoch_micros.get_or_insert(verification.token.gen_time_epoch_micros);
         }
-        if let Some((point_in_time_epoch_micros, accuracy_micros)) =
-            self.time_keeper.get_epoch_time_with_accuracy_micros().await
-        {
+        Ok((
+            time_stamped_data,
+            point_in_time_epoch_micros.unwrap_or_default(),
+        ))
+    }
+
+    /// Verify a DER encoded `TimeStampToken` or granted `TimeStampResp`
+    /// against the configured trust anchors (or the root of the current TSU
+    /// certificate chain).
+    ///
+    /// An error is returned when the input can't be parsed.
+    pub fn verify_time_stamp(
+        self: &Arc<Self>,
+        encoded_time_stamp: &[u8],
+        expected_imprint: Option<ExpectedImprint>,
+    ) -> Result<TokenVerification, String> {
+        // A TimeStampResp starts with the PKIStatusInfo and a token with the content type
+        let is_response = DerReader::read_single(encoded_time_stamp, tag::SEQUENCE)
+            .map_err(|e| format!("Malformed time-stamp: {e}"))?
+            .reader()
+            .peek_tag()
+            == Some(tag::SEQUENCE);
+        let (time_stamp_token, encoded_time_stamp_resp) = if is_response {
+            (
+                EncodedTimeStampResp::granted_token(encoded_time_stamp)?,
+                encoded_time_stamp.to_vec(),
+            )
+        } else {
+            (
+                encoded_time_stamp.to_vec(),
+                EncodedTimeStampResp::from_token(encoded_time_stamp),
+            )
+        };
+        let token = ParsedTimeStampToken::from_bytes(&time_stamp_token)
+            .map_err(|e| format!("Malformed time-stamp token: {e}"))?;
+        let trust_anchors = self.validation_trust_anchors();
+        let chain_status = if let Some(trust_anchors) = trust_anchors.clone() {
+            match TimeStampResponseValidator::validate_at_point_of_timestamp(
+                trust_anchors,
+                &encoded_time_stamp_resp,
+            ) {
+                Ok(_) => ChainStatus::Valid,
+                Err(e) => {
+                    ChainStatus::Invalid(format!("Time-stamp token validation failed: {e:?}"))
+                }
+            }
+        } else {
+            ChainStatus::NoTrustAnchors
+        };
+        let revocation_status = self.revocation_status(
+            token.signer_certificate.as_deref(),
+            &token.certificates,
+            &token.revocation_infos,
+            &trust_anchors.unwrap_or_default(),
+            token.gen_time_epoch_micros,
+        );
+        let imprint_matches = expected_imprint.map(|expected_imprint| {
+            expected_imprint.matches(&token.digest_algorithm_oid, &token.digest)
+        });
+        Ok(TokenVerification {
+            token,
+            chain_status,
+            revocation_status,
+            imprint_matches,
+        })
+    }
+
+    /// Process a request over a message imprint and respond with a time-stamp
+    /// in JWS compact serialization.
+    ///
+    /// The JWS carries the same claims as the `TSTInfo` of an RFC 3161 token
+    /// and is signed with the TSU key, but no RFC 3161 token is issued.
+    pub async fn jws_time_stamp_request(
+        self: &Arc<Self>,
+        imprint_request: &ImprintRequest,
+    ) -> Result<String, TimeStampFormatError> {
+        let (claims, signing_key) = self.time_stamp_claims(imprint_request).await?;
+        let jws = JwsTimeStamp::sign(&JwsTimeStampClaims::from(&claims), &signing_key)
+            .inspect_err(|e| {
+                self.record_response_status(Some(&TimeStampRespStatus::rejection(
+                    &e.to_string(),
+                    "systemFailure",
+                )));
+            })?;
+        self.record_issued_time_stamp(
+            TimeStampFormat::Jws,
+            jws.as_bytes().to_vec(),
+            claims,
+            signing_key.certificate_chain.first().cloned(),
+        )
+        .await
+        .map_err(|e| {
+            self.record_response_status(Some(&TimeStampRespStatus::rejection(&e, "systemFailure")));
+            TimeStampFormatError::Unavailable(e)
+        })?;
+        self.record_response_status(Some(&TimeStampRespStatus::granted()));
+        Ok(jws)
+    }
+
+    /// Process a request over a message imprint and respond with an encoded
+    /// COSE_Sign1 time-stamp.
+    ///
+    /// The COSE_Sign1 carries the same claims as the `TSTInfo` of an RFC 3161
+    /// token and is signed with the TSU key, but no RFC 3161 token is issued.
+    pub async fn cose_time_stamp_request(
+        self: &Arc<Self>,
+        imprint_request: &ImprintRequest,
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let (claims, signing_key) = self.time_stamp_claims(imprint_request).await?;
+        let cose = CoseTimeStamp::sign(&claims, &signing_key, imprint_request.cert_req)
+            .inspect_err(|e| {
+                self.record_response_status(Some(&TimeStampRespStatus::rejection(
+                    &e.to_string(),
+                    "systemFailure",
+                )));
+            })?;
+        self.record_issued_time_stamp(
+            TimeStampFormat::Cose,
+            cose.clone(),
+            claims,
+            signing_key.certificate_chain.first().cloned(),
+        )
+        .await
+        .map_err(|e| {
+            self.record_response_status(Some(&TimeStampRespStatus::rejection(&e, "systemFailure")));
+            TimeStampFormatError::Unavailable(e)
+        })?;
+        self.record_response_status(Some(&TimeStampRespStatus::granted()));
+        Ok(cose)
+    }
+
+    /// Return the claims of a new time-stamp over a message imprint and the
+    /// snapshot of the signing key to sign them with.
+    ///
+    /// The request is subject to the same checks as an RFC 3161 request, and
+    /// the point in time and key are taken once, so the claims describe the
+    /// only time-stamp issued for the request.
+    async fn time_stamp_claims(
+        self: &Arc<Self>,
+        imprint_request: &ImprintRequest,
+    ) -> Result<(TimeStampClaims, SigningKeySnapshot), TimeStampFormatError> {
+        let encoded_request = imprint_request.as_bytes();
+        let time_stamp_req = TimeStampReqParser::from_bytes(&encoded_request).map_err(|e| {
+            TimeStampFormatError::BadRequest(format!("Unable to parse request: {e:?}"))
+        })?;
+        self.count_request(&time_stamp_req);
+        let policy_oid = match self.assert_acceptable_request(&time_stamp_req, &[]) {
+            Ok((policy_oid, _tst_extensions)) => policy_oid,
+            Err(rejection) => {
+                let status = EncodedTimeStampResp::status_and_token(&rejection.as_bytes())
+                    .ok()
+                    .map(|(status, _time_stamp_token)| status);
+                self.record_response_status(status.as_ref());
+                return Err(TimeStampFormatError::BadRequest(
+                    status
+                        .map(|status| status.status_strings.join(" "))
+                        .unwrap_or("Request was rejected.".to_string()),
+                ));
+            }
+        };
+        let unavailable = |msg: &str, failure_info: &'static str| {
+            self.record_response_status(Some(&TimeStampRespStatus::rejection(msg, failure_info)));
+            TimeStampFormatError::Unavailable(msg.to_string())
+        };
+        let (point_in_time_epoch_micros, accuracy_micros) = self
+            .time_keeper
+            .get_epoch_time_with_accuracy_micros()
+            .await
+            .ok_or_else(|| {
+                unavailable(
+                    "Failed to recieve current time with tolerable acurracy.",
+                    "timeNotAvailable",
+                )
+            })?;
+        let signing_key = self
+            .tst_signing_info
+            .get_signing_key_snapshot()
+            .ok_or_else(|| unavailable("No signing key available.", "systemFailure"))?;
+        let serial_number = TimeStampClaims::serial_number(
+            &signing_key,
+            point_in_time_epoch_micros,
+            &encoded_request,
+        )
+        .ok_or_else(|| unavailable("Unable to derive serial number.", "systemFailure"))?;
+        Ok((
+            TimeStampClaims {
+                policy_oid,
+                digest_algorithm_oid: imprint_request.digest_algorithm_oid.to_owned(),
+                digest: imprint_request.digest.to_owned(),
+                serial_number,
+                gen_time_epoch_micros: point_in_time_epoch_micros,
+                accuracy_micros: Some(accuracy_micros),
+                nonce: imprint_request.nonce.to_owned(),
+            },
+            signing_key,
+        ))
+    }
+
+    /// Verify a JWS time-stamp in compact serialization against the
+    /// configured trust anchors (or the root of the current TSU certificate
+    /// chain).
+    ///
+    /// An error is returned when the input can't be parsed.
+    pub fn verify_jws_time_stamp(
+        self: &Arc<Self>,
+        compact: &str,
+        expected_imprint: Option<ExpectedImprint>,
+    ) -> Result<JwsTimeStampVerification, String> {
+        let time_stamp = JwsTimeStamp::from_compact(compact)?;
+        let trust_anchors = self.validation_trust_anchors();
+        let chain_status = if let Some(trust_anchors) = &trust_anchors {
+            match time_stamp.verify_chain(trust_anchors) {
+                Ok(()) => ChainStatus::Valid,
+                Err(msg) => ChainStatus::Invalid(msg),
+            }
+        } else {
+            ChainStatus::NoTrustAnchors
+        };
+        let revocation_status = self.revocation_status(
+            time_stamp.certificate_chain.first().map(Vec::as_slice),
+            time_stamp.certificate_chain.get(1..).unwrap_or_default(),
+            &[],
+            &trust_anchors.unwrap_or_default(),
+            time_stamp.claims.gen_time_epoch_micros,
+        );
+        let imprint_matches = expected_imprint
+            .map(|expected_imprint| {
+                Ok::<_, String>(expected_imprint.matches(
+                    &time_stamp.claims.digest_algorithm_oid()?,
+                    &time_stamp.claims.digest()?,
+                ))
+            })
+            .transpose()?;
+        Ok(JwsTimeStampVerification {
+            time_stamp,
+            chain_status,
+            revocation_status,
+            imprint_matches,
+        })
+    }
+
+    /// Evaluate the embedded and the most recently fetched revocation
+    /// information for the chain of a signer certificate at the point in time
+    /// of a time-stamp.
+    fn revocation_status(
+        self: &Arc<Self>,
+        signer_certificate: Option<&[u8]>,
+        certificates: &[Vec<u8>],
+        embedded_revocation_infos: &[RevocationInfo],
+        trust_anchors: &[Vec<u8>],
+        point_in_time_epoch_micros: u64,
+    ) -> RevocationStatus {
+        let Some(signer_certificate) = signer_certificate else {
+            return if embedded_revocation_infos.is_empty() {
+                RevocationStatus::NotEmbedded
+            } else {
+                RevocationStatus::Unknown("The signer certificate is not embedded.".to_string())
+            };
+        };
+        let certificate_chain = revocation_evaluation::certificate_chain(
+            signer_certificate,
+            certificates,
+            trust_anchors,
+        );
+        let revocation_infos = embedded_revocation_infos
+            .iter()
+            .cloned()
+            .chain(self.tst_signing_info.current_revocation_infos())
+            .collect::<Vec<_>>();
+        revocation_evaluation::evaluate(
+            &certificate_chain,
+            &revocation_infos,
+            point_in_time_epoch_micros,
+            jws_time_stamp::verify_signature,
+        )
+    }
+
+    /// Return the configured trust anchors or else the root of the current
+    /// TSU certificate chain.
+    fn validation_trust_anchors(self: &Arc<Self>) -> Option<Vec<Vec<u8>>> {
+        self.trust_anchors
+            .clone()
+            .or_else(|| {
+                self.get_current_certificate_chain()
+                    .and_then(|certificate_chain| certificate_chain.last().cloned())
+                    .map(|trust_anchor| vec![trust_anchor])
+            })
+            .filter(|trust_anchors| !trust_anchors.is_empty())
+    }
+
+    /// Process request and respond with a signed time-stamp.
+    async fn time_stamp_request(
+        self: &Arc<Self>,
+        time_stamp_req: &TimeStampReqParser,
+        request_extensions: &[Extension],
+    ) -> TimeStampResp {
+        let (response_policy_oid, tst_extensions) =
+            match self.assert_acceptable_request(time_stamp_req, request_extensions) {
+                Ok(acceptable) => acceptable,
+                Err(rejection) => return rejection,
+            };
+        let time_start = Instant::now();
+        let point_in_time = self.time_keeper.get_epoch_time_with_accuracy_micros().await;
+        self.metrics.observe(Phase::Time, time_start.elapsed());
+        if let Some((point_in_time_epoch_micros, accuracy_micros)) = point_in_time {
             // Build time stamp token info
-            let tst_info = TstInfo::new(
+            let mut tst_info = TstInfo::new(
                 time_stamp_req,
                 &response_policy_oid,
                 point_in_time_epoch_micros,
                 accuracy_micros,
             );
+            for extension in &tst_extensions {
+                if let Ok(oid) = tyst::encdec::oid::from_string(&extension.oid) {
+                    tst_info = tst_info.with_extension(&oid, extension.critical, &extension.value);
+                }
+            }
             // Sign and insert certs, ocsp responses etc
+            let sign_start = Instant::now();
             if let Some(tst_signer) = self.tst_signing_info.get_dynamic_singing_info() {
+                let sign_span = Span::start("TimeStampToken::new");
                 let time_stamp_token =
                     TimeStampToken::new(tst_info, &tst_signer, time_stamp_req.get_cert_req());
+                drop(sign_span);
+                self.metrics.observe(Phase::Sign, sign_start.elapsed());
                 TimeStampResp::with_success(false, time_stamp_token)
             } else {
                 TimeStampResp::with_rejection(
@@ -179,4 +1646,148 @@ impl TimeStamper {
             )
         }
     }
+
+    /// Assert that a time-stamp over a message imprint of the digest
+    /// algorithm under the requested (or default) policy is acceptable for
+    /// the client without counting towards its quota.
+    ///
+    /// Allows refusing a request before uploaded content is hashed. The
+    /// checks are repeated when the time-stamp is requested.
+    pub fn assert_acceptable_digest_algorithm(
+        self: &Arc<Self>,
+        digest_algorithm_oid: &[u32],
+        policy_oid: Option<&[u32]>,
+    ) -> Result<(), String> {
+        self.acceptable_digest_and_policy(
+            &tyst::encdec::oid::as_string(digest_algorithm_oid),
+            policy_oid.map(tyst::encdec::oid::as_string),
+            false,
+        )
+        .map(|_response_policy_oid| ())
+        .map_err(|(_failure_info, msg)| msg)
+    }
+
+    /// Return the policy to use in the response or the failure if the digest
+    /// algorithm, the requested policy or the client isn't acceptable.
+    ///
+    /// The request counts towards the client's quota when `count_request` is
+    /// set.
+    fn acceptable_digest_and_policy(
+        self: &Arc<Self>,
+        imprint_digest_oid: &str,
+        requested_policy_oid: Option<String>,
+        count_request: bool,
+    ) -> Result<Vec<u32>, (PkiFailureInfo, String)> {
+        if Tyst::instance()
+            .digests()
+            .by_oid(imprint_digest_oid)
+            .is_none()
+        {
+            return Err((
+                PkiFailureInfo::BadAlgo,
+                format!(
+                    "Unknown message digest algorithm '{imprint_digest_oid}' in message imprint."
+                ),
+            ));
+        }
+        // Assert that message digest algo is allowed by configuration
+        // Empty = allow any known
+        if !self.allowed_digest_oids.is_empty()
+            && !self
+                .allowed_digest_oids
+                .iter()
+                .any(|oid| oid == imprint_digest_oid)
+        {
+            return Err((
+                PkiFailureInfo::BadAlgo,
+                format!(
+                    "Message digest algorithm '{imprint_digest_oid}' in message imprint is not allowed."
+                ),
+            ));
+        }
+        // Verify policy oid against allow list. (Allow any policy if list is empty.)
+        let mut response_policy_oid = [2, 5, 29, 32, 0].to_vec();
+        if let Some(policy_oid) = requested_policy_oid {
+            if self.allowed_policy_oids.is_empty() || self.allowed_policy_oids.contains(&policy_oid)
+            {
+                response_policy_oid = tyst::encdec::oid::from_string(&policy_oid).unwrap();
+            } else {
+                return Err((
+                    PkiFailureInfo::UnacceptedPolicy,
+                    format!("Requested policy '{policy_oid}' is not allowed by this service."),
+                ));
+            }
+        } else if let Some(first_allowed) = self.allowed_policy_oids.first() {
+            response_policy_oid = tyst::encdec::oid::from_string(first_allowed).unwrap();
+        }
+        // Assert that the client may use the policy and digest. Requests
+        // outside of an authenticated HTTP request (e.g. over the TCP
+        // transport) are unauthenticated, only the service's own are exempt.
+        if let Some(client_authorization) = &self.client_authorization
+            && !ClientIdentity::is_internal()
+            && let Err((failure_info, msg)) = client_authorization.authorize(
+                ClientIdentity::current().flatten().as_ref(),
+                &tyst::encdec::oid::as_string(&response_policy_oid),
+                imprint_digest_oid,
+                count_request,
+            )
+        {
+            log::info!("Refused time-stamp request: {msg}");
+            return Err((failure_info, msg));
+        }
+        Ok(response_policy_oid)
+    }
+
+    /// Return the policy and `TSTInfo` extensions to use in the response or
+    /// a rejection if the request is not acceptable.
+    fn assert_acceptable_request(
+        self: &Arc<Self>,
+        time_stamp_req: &TimeStampReqParser,
+        request_extensions: &[Extension],
+    ) -> Result<(Vec<u32>, Vec<Extension>), TimeStampResp> {
+        let imprint_digest_oid = time_stamp_req.get_message_imprint_digest_oid();
+        if let Some(known_digest) = Tyst::instance().digests().by_oid(&imprint_digest_oid) {
+            // Assert correct message imprint digest size
+            let expected = known_digest.get_digest_size_bits() / 8;
+            let actual = time_stamp_req.get_message_imprint_digest_len_octets();
+            if expected != actual {
+                return Err(TimeStampResp::with_rejection(
+                    &[format!(
+                        "Message imprint digest length ({actual} bytes) does not match the claimed algorithm's ({expected} bytes)."
+                    )],
+                    &Some(PkiFailureInfo::BadDataFormat),
+                ));
+            }
+        }
+        let response_policy_oid = self
+            .acceptable_digest_and_policy(
+                &imprint_digest_oid,
+                time_stamp_req.get_req_policy_oid(),
+                true,
+            )
+            .map_err(|(failure_info, msg)| {
+                TimeStampResp::with_rejection(&[msg], &Some(failure_info))
+            })?;
+        // Assert that all extensions are understood and acceptable.
+        let mut tst_extensions = match self.extension_handlers.process(request_extensions) {
+            Ok(tst_extensions) => tst_extensions,
+            Err(msg) => {
+                return Err(TimeStampResp::with_rejection(
+                    &[msg],
+                    &Some(PkiFailureInfo::UnacceptedExtension),
+                ));
+            }
+        };
+        if self
+            .qualified_policy_oids
+            .contains(&tyst::encdec::oid::as_string(&response_policy_oid))
+        {
+            tst_extensions.push(Extension {
+                oid: tyst::encdec::oid::as_string(qualified::OID_PE_QC_STATEMENTS),
+                critical: false,
+                value: QualifiedTimeStamp::tst_info_extension_value(),
+            });
+        }
+        Ok((response_policy_oid, tst_extensions))
+    }
 }
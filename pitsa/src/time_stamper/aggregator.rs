@@ -0,0 +1,437 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Aggregation of time-stamp requests into a single signed Merkle tree root.
+
+use super::ClientIdentity;
+use super::ImprintRequest;
+use super::TimeStamper;
+use super::merkle_tree::MerkleTree;
+use crate::der;
+use crate::der::DerError;
+use crate::der::DerReader;
+use crate::der::tag;
+use std::sync::Arc;
+use std::sync::Weak;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+/// Largest number of requests covered by a single signature.
+const MAX_BATCH_SIZE: usize = 4096;
+
+/// A request waiting for the current aggregation window to close.
+struct PendingLeaf {
+    leaf_hash: Vec<u8>,
+    reply: oneshot::Sender<AggregatedLeaf>,
+}
+
+/// Proof that a leaf is part of the time-stamped Merkle tree.
+pub struct InclusionProof {
+    /// Merkle tree digest algorithm object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Zero based position of the leaf in the tree.
+    pub leaf_index: usize,
+    /// Number of leaves in the tree.
+    pub tree_size: usize,
+    /// Audit path from the leaf to the root.
+    pub audit_path: Vec<Vec<u8>>,
+}
+
+impl InclusionProof {
+    /// Return the DER encoded `MerkleInclusionProof`.
+    ///
+    /// ```text
+    /// MerkleInclusionProof ::= SEQUENCE {
+    ///     hashAlgorithm   AlgorithmIdentifier,
+    ///     leafIndex       INTEGER,
+    ///     treeSize        INTEGER,
+    ///     auditPath       SEQUENCE OF OCTET STRING }
+    /// ```
+    pub fn as_bytes(&self) -> Vec<u8> {
+        der::sequence(&[
+            der::algorithm_identifier(&self.digest_algorithm_oid),
+            der::integer(self.leaf_index as u64),
+            der::integer(self.tree_size as u64),
+            der::sequence(
+                &self
+                    .audit_path
+                    .iter()
+                    .map(|node| der::octet_string(node))
+                    .collect::<Vec<_>>(),
+            ),
+        ])
+    }
+}
+
+/// The result of an aggregated request.
+struct AggregatedLeaf {
+    /// DER encoded `TimeStampResp` over the tree root shared by the batch.
+    time_stamp_resp: Arc<Vec<u8>>,
+    /// Proof of the request's inclusion in the time-stamped tree.
+    inclusion_proof: InclusionProof,
+}
+
+/** Collects requests arriving within a short window, builds a Merkle tree of
+their message imprints and issues a single standard RFC 3161 time-stamp over
+the tree root.
+
+The leaf of a request is the DER encoded `MessageImprint` of the request, so a
+client can recompute it from its own request. The time-stamp token over the
+root is an ordinary token with the root as message imprint.
+
+Each client gets the shared token with its own `MerkleInclusionProof` added as
+an unsigned attribute of the `SignerInfo`:
+
+```text
+   Attribute ::= SEQUENCE {
+       attrType    OBJECT IDENTIFIER,  -- the configured attribute OID
+       attrValues  SET OF MerkleInclusionProof }
+```
+
+Unsigned attributes are not covered by the signature, which is fine since the
+proof is verified by recomputing the signed root.
+*/
+pub struct Aggregator {
+    merkle_tree: Arc<MerkleTree>,
+    inclusion_proof_attribute_oid: Vec<u32>,
+    sender: mpsc::Sender<PendingLeaf>,
+}
+
+impl Aggregator {
+    /// Return a new instance with a background task that signs the root of
+    /// every window.
+    pub fn new(
+        time_stamper: Weak<TimeStamper>,
+        digest_algorithm_oid: &[u32],
+        inclusion_proof_attribute_oid: Vec<u32>,
+        window_micros: u64,
+    ) -> Option<Self> {
+        let merkle_tree = MerkleTree::new(digest_algorithm_oid)
+            .map(Arc::new)
+            .or_else(|| {
+                log::error!(
+                    "Unknown digest algorithm '{}'. Aggregation is disabled.",
+                    tyst::encdec::oid::as_string(digest_algorithm_oid)
+                );
+                None
+            })?;
+        let (sender, receiver) = mpsc::channel(MAX_BATCH_SIZE);
+        tokio::spawn(Self::run(
+            receiver,
+            time_stamper,
+            Arc::clone(&merkle_tree),
+            window_micros,
+        ));
+        Some(Self {
+            merkle_tree,
+            inclusion_proof_attribute_oid,
+            sender,
+        })
+    }
+
+    /// Wait for the current window to close and return the DER encoded
+    /// `TimeStampResp` with the inclusion proof for the encoded
+    /// `MessageImprint`.
+    pub async fn aggregate(&self, encoded_message_imprint: &[u8]) -> Option<Vec<u8>> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(PendingLeaf {
+                leaf_hash: self.merkle_tree.leaf_hash(encoded_message_imprint),
+                reply,
+            })
+            .await
+            .ok()?;
+        let aggregated_leaf = response.await.ok()?;
+        let attribute = der::sequence(&[
+            der::oid(&self.inclusion_proof_attribute_oid),
+            der::set_of(&[aggregated_leaf.inclusion_proof.as_bytes()]),
+        ]);
+        Self::with_unsigned_attribute(&aggregated_leaf.time_stamp_resp, &attribute)
+            .map_err(|e| log::error!("Unable to add inclusion proof to response: {e}"))
+            .ok()
+    }
+
+    /// Return the DER encoded `TimeStampResp` with `attribute` added to the
+    /// unsigned attributes of the token's `SignerInfo`.
+    ///
+    /// ```text
+    /// TimeStampResp ::= SEQUENCE  {
+    ///    status                  PKIStatusInfo,
+    ///    timeStampToken          TimeStampToken     OPTIONAL  }
+    ///
+    /// SignedData ::= SEQUENCE {
+    ///    version CMSVersion,
+    ///    digestAlgorithms DigestAlgorithmIdentifiers,
+    ///    encapContentInfo EncapsulatedContentInfo,
+    ///    certificates [0] IMPLICIT CertificateSet OPTIONAL,
+    ///    crls [1] IMPLICIT RevocationInfoChoices OPTIONAL,
+    ///    signerInfos SignerInfos }
+    ///
+    /// SignerInfo ::= SEQUENCE {
+    ///    ...
+    ///    signature SignatureValue,
+    ///    unsignedAttrs [1] IMPLICIT UnsignedAttributes OPTIONAL }
+    /// ```
+    fn with_unsigned_attribute(
+        time_stamp_resp: &[u8],
+        attribute: &[u8],
+    ) -> Result<Vec<u8>, DerError> {
+        let mut time_stamp_resp_reader =
+            DerReader::read_single(time_stamp_resp, tag::SEQUENCE)?.reader();
+        let status = time_stamp_resp_reader.read_expected(tag::SEQUENCE)?;
+        let Some(time_stamp_token) = time_stamp_resp_reader.read_optional(tag::SEQUENCE)? else {
+            // Rejections carry no token
+            return Ok(time_stamp_resp.to_vec());
+        };
+        let mut content_info = time_stamp_token.reader();
+        let content_type = content_info.read_expected(tag::OID)?;
+        let signed_data = DerReader::read_single(
+            content_info
+                .read_expected(tag::context_constructed(0))?
+                .content,
+            tag::SEQUENCE,
+        )?;
+        let mut signed_data_items = Self::read_all(signed_data.reader())?;
+        // signerInfos is the last item of the SignedData
+        let signer_infos = signed_data_items
+            .pop()
+            .ok_or(DerError::new("SignedData without signerInfos."))?;
+        signer_infos.expect_tag(tag::SET)?;
+        let mut signer_infos = signer_infos.reader();
+        let mut encoded_signer_infos = vec![];
+        while !signer_infos.is_empty() {
+            let mut signer_info_items =
+                Self::read_all(signer_infos.read_expected(tag::SEQUENCE)?.reader())?;
+            let mut unsigned_attrs = vec![];
+            if signer_info_items
+                .last()
+                .is_some_and(|tlv| tlv.tag == tag::context_constructed(1))
+                && let Some(existing) = signer_info_items.pop()
+            {
+                unsigned_attrs.extend_from_slice(existing.content);
+            }
+            unsigned_attrs.extend_from_slice(attribute);
+            let mut encoded_signer_info = signer_info_items
+                .iter()
+                .map(|tlv| tlv.encoded.to_vec())
+                .collect::<Vec<_>>();
+            encoded_signer_info.push(der::tlv(tag::context_constructed(1), &unsigned_attrs));
+            encoded_signer_infos.push(der::sequence(&encoded_signer_info));
+        }
+        let mut encoded_signed_data = signed_data_items
+            .iter()
+            .map(|tlv| tlv.encoded.to_vec())
+            .collect::<Vec<_>>();
+        encoded_signed_data.push(der::tlv(tag::SET, &encoded_signer_infos.concat()));
+        Ok(der::sequence(&[
+            status.encoded.to_vec(),
+            der::sequence(&[
+                content_type.encoded.to_vec(),
+                der::explicit(0, &der::sequence(&encoded_signed_data)),
+            ]),
+        ]))
+    }
+
+    /// Return all remaining elements of `reader`.
+    fn read_all(mut reader: DerReader<'_>) -> Result<Vec<der::Tlv<'_>>, DerError> {
+        let mut ret = vec![];
+        while !reader.is_empty() {
+            ret.push(reader.read()?);
+        }
+        Ok(ret)
+    }
+
+    /// Sign the tree root of each window.
+    async fn run(
+        mut receiver: mpsc::Receiver<PendingLeaf>,
+        time_stamper: Weak<TimeStamper>,
+        merkle_tree: Arc<MerkleTree>,
+        window_micros: u64,
+    ) {
+        while let Some(first) = receiver.recv().await {
+            let deadline =
+                tokio::time::Instant::now() + tokio::time::Duration::from_micros(window_micros);
+            let mut batch = vec![first];
+            while batch.len() < MAX_BATCH_SIZE {
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(pending)) => batch.push(pending),
+                    _ => break,
+                }
+            }
+            let Some(time_stamper) = time_stamper.upgrade() else {
+                break;
+            };
+            let (leaf_hashes, replies): (Vec<_>, Vec<_>) = batch
+                .into_iter()
+                .map(|pending| (pending.leaf_hash, pending.reply))
+                .unzip();
+            let tree_size = leaf_hashes.len();
+            // Hashing the tree and the proofs of a large batch is CPU bound
+            let merkle_tree_clone = Arc::clone(&merkle_tree);
+            let Some((root, audit_paths)) = tokio::task::spawn_blocking(move || {
+                let levels = merkle_tree_clone.levels(leaf_hashes);
+                let root = merkle_tree_clone.root(&levels, tree_size)?;
+                let audit_paths = (0..tree_size)
+                    .map(|leaf_index| {
+                        merkle_tree_clone.inclusion_proof(&levels, leaf_index, tree_size)
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some((root, audit_paths))
+            })
+            .await
+            .ok()
+            .flatten() else {
+                // Dropping the replies rejects the waiting requests
+                log::error!("Unable to build Merkle tree of {tree_size} leaves.");
+                continue;
+            };
+            if log::log_enabled!(log::Level::Debug) {
+                log::debug!("Time-stamping Merkle tree root of {tree_size} leaves.");
+            }
+            let time_stamp_resp = Arc::new(
+                ClientIdentity::internal(time_stamper.imprint_time_stamp_request(
+                    &ImprintRequest::new(merkle_tree.digest_algorithm_oid(), &root, true),
+                ))
+                .await,
+            );
+            for (leaf_index, (reply, audit_path)) in
+                replies.into_iter().zip(audit_paths).enumerate()
+            {
+                let inclusion_proof = InclusionProof {
+                    digest_algorithm_oid: merkle_tree.digest_algorithm_oid().to_vec(),
+                    leaf_index,
+                    tree_size,
+                    audit_path,
+                };
+                // The client might have given up waiting
+                let _ = reply.send(AggregatedLeaf {
+                    time_stamp_resp: Arc::clone(&time_stamp_resp),
+                    inclusion_proof,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTE_OID: &[u32] = &[1, 3, 6, 1, 4, 1, 99999, 2];
+
+    fn attribute(value: u64) -> Vec<u8> {
+        der::sequence(&[der::oid(ATTRIBUTE_OID), der::set_of(&[der::integer(value)])])
+    }
+
+    /// Return a structurally valid `TimeStampResp` with a single `SignerInfo`.
+    fn time_stamp_resp() -> Vec<u8> {
+        let signer_info = der::sequence(&[
+            der::integer(1),
+            der::sequence(&[der::sequence(&[]), der::integer(4711)]),
+            der::algorithm_identifier(&[2, 16, 840, 1, 101, 3, 4, 2, 1]),
+            der::algorithm_identifier(&[1, 3, 101, 112]),
+            der::octet_string(&[0xaa; 64]),
+        ]);
+        let signed_data = der::sequence(&[
+            der::integer(3),
+            der::set_of(&[der::algorithm_identifier(&[2, 16, 840, 1, 101, 3, 4, 2, 1])]),
+            der::sequence(&[
+                der::oid(&[1, 2, 840, 113549, 1, 9, 16, 1, 4]),
+                der::explicit(0, &der::octet_string(b"TSTInfo")),
+            ]),
+            der::tlv(tag::context_constructed(0), b""),
+            der::set_of(&[signer_info]),
+        ]);
+        der::sequence(&[
+            der::sequence(&[der::integer(0)]),
+            der::sequence(&[
+                der::oid(&[1, 2, 840, 113549, 1, 7, 2]),
+                der::explicit(0, &signed_data),
+            ]),
+        ])
+    }
+
+    /// Return the encoded elements of the `SignerInfo`.
+    fn signer_info_items(time_stamp_resp: &[u8]) -> Vec<Vec<u8>> {
+        let mut resp = DerReader::read_single(time_stamp_resp, tag::SEQUENCE)
+            .unwrap()
+            .reader();
+        resp.read_expected(tag::SEQUENCE).unwrap();
+        let mut content_info = resp.read_expected(tag::SEQUENCE).unwrap().reader();
+        content_info.read_expected(tag::OID).unwrap();
+        let signed_data = DerReader::read_single(
+            content_info
+                .read_expected(tag::context_constructed(0))
+                .unwrap()
+                .content,
+            tag::SEQUENCE,
+        )
+        .unwrap();
+        let signer_infos = Aggregator::read_all(signed_data.reader())
+            .unwrap()
+            .pop()
+            .unwrap();
+        let signer_info = DerReader::read_single(signer_infos.content, tag::SEQUENCE).unwrap();
+        Aggregator::read_all(signer_info.reader())
+            .unwrap()
+            .iter()
+            .map(|tlv| tlv.encoded.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn unsigned_attribute_is_added() {
+        let original = time_stamp_resp();
+        let with_proof = Aggregator::with_unsigned_attribute(&original, &attribute(1)).unwrap();
+        let original_items = signer_info_items(&original);
+        let items = signer_info_items(&with_proof);
+        assert_eq!(items.len(), original_items.len() + 1);
+        // Signed parts are left untouched
+        assert_eq!(items[..original_items.len()], original_items[..]);
+        assert_eq!(
+            items.last().unwrap(),
+            &der::tlv(tag::context_constructed(1), &attribute(1))
+        );
+    }
+
+    #[test]
+    fn unsigned_attribute_is_appended_to_existing() {
+        let once = Aggregator::with_unsigned_attribute(&time_stamp_resp(), &attribute(1)).unwrap();
+        let twice = Aggregator::with_unsigned_attribute(&once, &attribute(2)).unwrap();
+        assert_eq!(
+            signer_info_items(&twice).last().unwrap(),
+            &der::tlv(
+                tag::context_constructed(1),
+                &[attribute(1), attribute(2)].concat()
+            )
+        );
+    }
+
+    #[test]
+    fn rejection_is_left_untouched() {
+        let rejection = der::sequence(&[der::sequence(&[der::integer(2)])]);
+        assert_eq!(
+            Aggregator::with_unsigned_attribute(&rejection, &attribute(1)).unwrap(),
+            rejection
+        );
+    }
+
+    #[test]
+    fn malformed_response_is_refused() {
+        assert!(Aggregator::with_unsigned_attribute(&[0x30, 0x03, 0x02], &attribute(1)).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Microsoft Authenticode legacy time-stamp protocol.
+
+use super::cms_signed_data;
+use super::time_stamp_claims::TimeStampFormatError;
+use crate::der::DerError;
+use crate::der::DerReader;
+use crate::der::tag;
+
+/// `SPC_TIME_STAMP_REQUEST_OBJID`
+const OID_SPC_TIME_STAMP_REQUEST: &[u32] = &[1, 3, 6, 1, 4, 1, 311, 3, 2, 1];
+
+/** Microsoft Authenticode legacy time-stamp request.
+
+The request is sent as base64 encoded DER of
+
+```text
+TimeStampRequest ::= SEQUENCE {
+   countersignatureType OBJECT IDENTIFIER,
+   attributes Attributes OPTIONAL,
+   content  ContentInfo
+}
+```
+
+where `content` holds the signature value to counter-sign. The response is
+a base64 encoded PKCS #7 `SignedData` that encapsulates the same content and is
+signed with the authenticated attributes `contentType`, `signingTime` and
+`messageDigest`.
+
+See [Time Stamping Authenticode Signatures](https://learn.microsoft.com/en-us/windows/win32/seccrypto/time-stamping-authenticode-signatures).
+*/
+pub struct AuthenticodeTimeStampRequest {
+    content: Vec<u8>,
+}
+
+impl AuthenticodeTimeStampRequest {
+    /// Parse a base64 encoded request.
+    pub fn from_base64(encoded: &[u8]) -> Result<Self, TimeStampFormatError> {
+        // Clients wrap the base64 in lines and might add trailing NUL bytes.
+        let encoded = encoded
+            .iter()
+            .filter(|b| !b.is_ascii_whitespace() && **b != 0)
+            .map(|b| char::from(*b))
+            .collect::<String>();
+        let decoded = tyst::encdec::base64::decode(&encoded)
+            .map_err(|e| TimeStampFormatError::BadRequest(format!("Invalid base64: {e:?}")))?;
+        Self::from_der(&decoded).map_err(|e| TimeStampFormatError::BadRequest(e.to_string()))
+    }
+
+    /// Parse a DER encoded request.
+    fn from_der(encoded: &[u8]) -> Result<Self, DerError> {
+        let mut time_stamp_request = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        let countersignature_type = time_stamp_request.read_expected(tag::OID)?.as_oid()?;
+        if !countersignature_type.eq(OID_SPC_TIME_STAMP_REQUEST) {
+            return Err(DerError::new("unexpected countersignatureType"));
+        }
+        // Skip the optional attributes
+        let mut content_info = time_stamp_request.read()?;
+        if !time_stamp_request.is_empty() {
+            content_info = time_stamp_request.read_expected(tag::SEQUENCE)?;
+        }
+        content_info.expect_tag(tag::SEQUENCE)?;
+        let mut content_info = content_info.reader();
+        let content_type = content_info.read_expected(tag::OID)?.as_oid()?;
+        if !content_type.eq(cms_signed_data::OID_DATA) {
+            return Err(DerError::new("content is not of type data"));
+        }
+        let content = content_info
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::OCTET_STRING)?
+            .content
+            .to_vec();
+        Ok(Self { content })
+    }
+
+    /// The signature value that should be counter-signed.
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
@@ -0,0 +1,529 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Authentication of HTTP clients by `Authorization: Bearer` API keys and
+//! JSON Web Tokens (JWTs).
+
+use super::client_authorization::ClientIdentity;
+use super::jws_time_stamp::JWS_ALGORITHMS;
+use super::jws_time_stamp::verify_signature_with_public_key;
+use crate::der;
+use crate::util;
+use crate::util::base64url_decode;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::SystemTime;
+use tyst::encdec::hex::ToHex;
+
+/// Tolerated clock skew when checking the JWT validity period.
+const LEEWAY_SECONDS: u64 = 60;
+
+/// Hashed API key.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiKey {
+    /// Identity of the client holding the key.
+    identity: String,
+    /// Hex encoded SHA-256 hash of the key.
+    sha256: String,
+}
+
+/// The file format of API keys.
+#[derive(Default, Deserialize)]
+struct ApiKeys {
+    keys: Vec<ApiKey>,
+}
+
+/// JSON Web Key (RFC 7517) of a JWT signer.
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+impl Jwk {
+    /// Return `true` if the key may verify signatures of the JWS algorithm.
+    ///
+    /// An `alg` of the key must match exactly. Otherwise the algorithm must
+    /// fit the key type and curve.
+    fn allows(&self, alg: &str) -> bool {
+        if let Some(own_alg) = &self.alg {
+            return own_alg.eq(alg);
+        }
+        match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => ["RS256", "RS384", "RS512"].contains(&alg),
+            ("EC", Some("P-256")) => alg == "ES256",
+            ("EC", Some("P-384")) => alg == "ES384",
+            ("EC", Some("P-521")) => alg == "ES512",
+            ("OKP", Some("Ed25519")) => alg == "EdDSA",
+            _ => false,
+        }
+    }
+
+    /// Return the DER encoded `SubjectPublicKeyInfo` of the key.
+    fn subject_public_key_info(&self) -> Result<Vec<u8>, String> {
+        let component = |value: &Option<String>| {
+            value
+                .as_deref()
+                .ok_or(format!("Incomplete {} JWK.", self.kty))
+                .and_then(base64url_decode)
+        };
+        match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => Ok(der::sequence(&[
+                der::sequence(&[der::oid(&[1, 2, 840, 113549, 1, 1, 1]), der::null()]),
+                der::bit_string(&der::sequence(&[
+                    der::unsigned_integer(&component(&self.n)?),
+                    der::unsigned_integer(&component(&self.e)?),
+                ])),
+            ])),
+            ("EC", Some(crv)) => {
+                let curve_oid: &[u32] = match crv {
+                    "P-256" => &[1, 2, 840, 10045, 3, 1, 7],
+                    "P-384" => &[1, 3, 132, 0, 34],
+                    "P-521" => &[1, 3, 132, 0, 35],
+                    _ => return Err(format!("Unsupported JWK curve '{crv}'.")),
+                };
+                let point = [&[0x04u8][..], &component(&self.x)?, &component(&self.y)?].concat();
+                Ok(der::sequence(&[
+                    der::sequence(&[der::oid(&[1, 2, 840, 10045, 2, 1]), der::oid(curve_oid)]),
+                    der::bit_string(&point),
+                ]))
+            }
+            ("OKP", Some("Ed25519")) => Ok(der::sequence(&[
+                der::algorithm_identifier(&[1, 3, 101, 112]),
+                der::bit_string(&component(&self.x)?),
+            ])),
+            (kty, crv) => Err(format!("Unsupported JWK type '{kty}' ({crv:?}).")),
+        }
+    }
+}
+
+/// JSON Web Key Set.
+#[derive(Default, Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+/// Protected JWT header.
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+/** Authentication of bearer tokens.
+
+A bearer token is either
+
+* a static API key where only the hex encoded SHA-256 hash is configured:
+
+  ```text
+  { "keys": [ { "identity": "billing", "sha256": "5e88...42d8" } ] }
+  ```
+
+* or a JWT (RFC 7519) signed by a key in a JWKS file or URL. The issuer,
+  audience and validity period are checked and the identity is taken from a
+  configurable claim.
+
+The authenticated identity is authorized like the identity of a TLS client
+certificate.
+*/
+pub struct BearerAuthentication {
+    api_keys_path: Option<String>,
+    jwks_location: Option<String>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    identity_claim: String,
+    api_keys: RwLock<Arc<ApiKeys>>,
+    jwks: RwLock<Arc<Jwks>>,
+}
+
+impl BearerAuthentication {
+    /// Return a new instance that reloads the API keys and JWKS every
+    /// `refresh_interval_micros` or `None` if neither is configured.
+    ///
+    /// A JWKS requires an issuer and an audience, and must not be fetched
+    /// over plaintext HTTP.
+    pub async fn new(
+        api_keys_path: Option<String>,
+        jwks_location: Option<String>,
+        issuer: Option<String>,
+        audience: Option<String>,
+        identity_claim: String,
+        refresh_interval_micros: u64,
+    ) -> Result<Option<Arc<Self>>, String> {
+        if api_keys_path.is_none() && jwks_location.is_none() {
+            return Ok(None);
+        }
+        if let Some(jwks_location) = &jwks_location {
+            if jwks_location.starts_with("http://") {
+                return Err(format!("The JWKS URL '{jwks_location}' must use https."));
+            }
+            if issuer.is_none() || audience.is_none() {
+                return Err("JWT authentication requires an issuer and an audience.".to_string());
+            }
+        }
+        let bearer_authentication = Arc::new(Self {
+            api_keys_path,
+            jwks_location,
+            issuer,
+            audience,
+            identity_claim,
+            api_keys: RwLock::default(),
+            jwks: RwLock::default(),
+        });
+        bearer_authentication.refresh().await;
+        Ok(Some(bearer_authentication.init(refresh_interval_micros)))
+    }
+
+    /// Start background task that periodically reloads keys.
+    fn init(self: Arc<Self>, refresh_interval_micros: u64) -> Arc<Self> {
+        let self_clone = Arc::clone(&self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_micros(refresh_interval_micros))
+                    .await;
+                self_clone.refresh().await;
+            }
+        });
+        self
+    }
+
+    /// Reload the API keys and JWKS. Keys that fail to load are kept.
+    async fn refresh(self: &Arc<Self>) {
+        if let Some(api_keys_path) = &self.api_keys_path {
+            match std::fs::read_to_string(api_keys_path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_json::from_str::<ApiKeys>(&content).map_err(|e| e.to_string())
+                }) {
+                Ok(api_keys) => *self.api_keys.write().unwrap() = Arc::new(api_keys),
+                Err(e) => log::warn!("Failed to load API keys from '{api_keys_path}': {e}"),
+            }
+        }
+        if let Some(jwks_location) = &self.jwks_location {
+            let jwks_location = jwks_location.to_owned();
+            let content =
+                match tokio::task::spawn_blocking(move || Self::read_jwks(&jwks_location)).await {
+                    Ok(content) => content,
+                    Err(e) => Err(e.to_string()),
+                };
+            match content.and_then(|content| {
+                serde_json::from_str::<Jwks>(&content).map_err(|e| e.to_string())
+            }) {
+                Ok(jwks) => *self.jwks.write().unwrap() = Arc::new(jwks),
+                Err(e) => log::warn!("Failed to load JWKS: {e}"),
+            }
+        }
+    }
+
+    /// Read the JWKS from an HTTPS URL or a file.
+    fn read_jwks(jwks_location: &str) -> Result<String, String> {
+        if jwks_location.starts_with("https://") {
+            util::http_agent()
+                .get(jwks_location)
+                .call()
+                .map_err(|e| format!("Failed to fetch '{jwks_location}': {e}"))?
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| format!("Failed to read '{jwks_location}': {e}"))
+        } else {
+            std::fs::read_to_string(jwks_location)
+                .map_err(|e| format!("Failed to read '{jwks_location}': {e}"))
+        }
+    }
+
+    /// Return the identity of the holder of a bearer token.
+    pub fn authenticate(&self, bearer_token: &str) -> Result<ClientIdentity, String> {
+        if bearer_token.split('.').count() == 3 && self.jwks_location.is_some() {
+            self.authenticate_jwt(bearer_token)
+        } else {
+            self.authenticate_api_key(bearer_token)
+        }
+    }
+
+    /// Return the configured API key identity of the client, if any.
+    pub fn api_key_identity(&self, client_identity: &ClientIdentity) -> Option<String> {
+        let api_keys = Arc::clone(&self.api_keys.read().unwrap());
+        api_keys
+            .keys
+            .iter()
+            .find(|api_key| client_identity.has_name(&api_key.identity))
+            .map(|api_key| api_key.identity.to_owned())
+    }
+
+    /// Return the identity of the holder of an API key.
+    fn authenticate_api_key(&self, api_key: &str) -> Result<ClientIdentity, String> {
+        let sha256 = util::digest(util::OID_SHA256, api_key.as_bytes())
+            .map(|digest| digest.to_hex())
+            .ok_or("SHA-256 is not available.".to_string())?;
+        let api_keys = Arc::clone(&self.api_keys.read().unwrap());
+        api_keys
+            .keys
+            .iter()
+            .find(|api_key| constant_time_eq(&api_key.sha256.to_lowercase(), &sha256))
+            .map(|api_key| ClientIdentity::from_name(&api_key.identity))
+            .ok_or("Unknown API key.".to_string())
+    }
+
+    /// Return the identity claim of a valid JWT.
+    fn authenticate_jwt(&self, jwt: &str) -> Result<ClientIdentity, String> {
+        let mut parts = jwt.split('.');
+        let (Some(header), Some(payload), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("Malformed JWT.".to_string());
+        };
+        let jwt_header = serde_json::from_slice::<JwtHeader>(&base64url_decode(header)?)
+            .map_err(|e| format!("Malformed JWT header: {e}"))?;
+        let (signing_algorithm_oid, _alg, ecdsa_size) = JWS_ALGORITHMS
+            .iter()
+            .find(|(_oid, alg, _size)| jwt_header.alg.eq(*alg))
+            .copied()
+            .ok_or(format!("Unsupported JWT alg '{}'.", jwt_header.alg))?;
+        let signature = base64url_decode(signature)?;
+        let signature = if ecdsa_size > 0 {
+            der::ecdsa_sig_value_from_fixed(&signature, ecdsa_size)
+                .ok_or("Malformed ECDSA signature.".to_string())?
+        } else {
+            signature
+        };
+        let signing_input = &jwt[..header.len() + 1 + payload.len()];
+        let jwks = Arc::clone(&self.jwks.read().unwrap());
+        let verified = jwks
+            .keys
+            .iter()
+            .filter(|jwk| jwt_header.kid.is_none() || jwk.kid.eq(&jwt_header.kid))
+            .filter(|jwk| jwk.allows(&jwt_header.alg))
+            .filter_map(|jwk| jwk.subject_public_key_info().ok())
+            .any(|subject_public_key_info| {
+                verify_signature_with_public_key(
+                    signing_algorithm_oid,
+                    &subject_public_key_info,
+                    &signature,
+                    signing_input.as_bytes(),
+                )
+                .unwrap_or(false)
+            });
+        if !verified {
+            return Err("JWT signature verification failed.".to_string());
+        }
+        let claims = serde_json::from_slice::<serde_json::Value>(&base64url_decode(payload)?)
+            .map_err(|e| format!("Malformed JWT claims: {e}"))?;
+        let now_epoch_seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        self.identity_from_claims(&claims, now_epoch_seconds)
+    }
+
+    /// Return the identity claim of JWT claims that are valid at
+    /// `now_epoch_seconds` and match the issuer and audience.
+    fn identity_from_claims(
+        &self,
+        claims: &serde_json::Value,
+        now_epoch_seconds: u64,
+    ) -> Result<ClientIdentity, String> {
+        let expiration = claims["exp"]
+            .as_u64()
+            .ok_or("JWT without expiration.".to_string())?;
+        if expiration.saturating_add(LEEWAY_SECONDS) < now_epoch_seconds {
+            return Err("JWT has expired.".to_string());
+        }
+        if claims["nbf"]
+            .as_u64()
+            .is_some_and(|not_before| not_before > now_epoch_seconds.saturating_add(LEEWAY_SECONDS))
+        {
+            return Err("JWT is not yet valid.".to_string());
+        }
+        if let Some(issuer) = &self.issuer
+            && claims["iss"].as_str() != Some(issuer.as_str())
+        {
+            return Err("Unexpected JWT issuer.".to_string());
+        }
+        if let Some(audience) = &self.audience {
+            let audience_matches = match &claims["aud"] {
+                serde_json::Value::String(aud) => aud.eq(audience),
+                serde_json::Value::Array(auds) => auds
+                    .iter()
+                    .any(|aud| aud.as_str() == Some(audience.as_str())),
+                _ => false,
+            };
+            if !audience_matches {
+                return Err("Unexpected JWT audience.".to_string());
+            }
+        }
+        claims[self.identity_claim.as_str()]
+            .as_str()
+            .map(ClientIdentity::from_name)
+            .ok_or(format!("JWT without '{}' claim.", self.identity_claim))
+    }
+}
+
+/// Compare strings without revealing the position of the first difference.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_750_000_000;
+
+    fn jwt_authentication() -> BearerAuthentication {
+        BearerAuthentication {
+            api_keys_path: None,
+            jwks_location: Some("jwks.json".to_string()),
+            issuer: Some("https://issuer.example".to_string()),
+            audience: Some("pitsa".to_string()),
+            identity_claim: "sub".to_string(),
+            api_keys: RwLock::default(),
+            jwks: RwLock::default(),
+        }
+    }
+
+    fn claims(exp: u64, iss: &str, aud: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "exp": exp, "iss": iss, "aud": aud, "sub": "billing" })
+    }
+
+    fn jwk(kty: &str, crv: Option<&str>, alg: Option<&str>) -> Jwk {
+        Jwk {
+            kty: kty.to_string(),
+            kid: None,
+            alg: alg.map(str::to_string),
+            crv: crv.map(str::to_string),
+            n: None,
+            e: None,
+            x: None,
+            y: None,
+        }
+    }
+
+    #[test]
+    fn valid_claims_yield_identity() {
+        let auth = jwt_authentication();
+        let identity = auth
+            .identity_from_claims(
+                &claims(NOW + 10, "https://issuer.example", "pitsa".into()),
+                NOW,
+            )
+            .unwrap();
+        assert!(identity.has_name("billing"));
+        let audiences = serde_json::json!(["other", "pitsa"]);
+        assert!(
+            auth.identity_from_claims(&claims(NOW + 10, "https://issuer.example", audiences), NOW)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn expired_jwt_is_refused() {
+        let auth = jwt_authentication();
+        let expired = claims(
+            NOW - LEEWAY_SECONDS - 1,
+            "https://issuer.example",
+            "pitsa".into(),
+        );
+        assert!(auth.identity_from_claims(&expired, NOW).is_err());
+        let within_leeway = claims(
+            NOW - LEEWAY_SECONDS,
+            "https://issuer.example",
+            "pitsa".into(),
+        );
+        assert!(auth.identity_from_claims(&within_leeway, NOW).is_ok());
+        let mut without_expiry = within_leeway;
+        without_expiry.as_object_mut().unwrap().remove("exp");
+        assert!(auth.identity_from_claims(&without_expiry, NOW).is_err());
+        let mut not_yet_valid = claims(NOW + 3600, "https://issuer.example", "pitsa".into());
+        not_yet_valid["nbf"] = (NOW + LEEWAY_SECONDS + 1).into();
+        assert!(auth.identity_from_claims(&not_yet_valid, NOW).is_err());
+    }
+
+    #[test]
+    fn wrong_issuer_or_audience_is_refused() {
+        let auth = jwt_authentication();
+        let wrong_issuer = claims(NOW + 10, "https://evil.example", "pitsa".into());
+        assert!(auth.identity_from_claims(&wrong_issuer, NOW).is_err());
+        let wrong_audience = claims(NOW + 10, "https://issuer.example", "other".into());
+        assert!(auth.identity_from_claims(&wrong_audience, NOW).is_err());
+        let no_audience = claims(NOW + 10, "https://issuer.example", serde_json::Value::Null);
+        assert!(auth.identity_from_claims(&no_audience, NOW).is_err());
+    }
+
+    #[test]
+    fn alg_is_bound_to_key_type() {
+        let rsa = jwk("RSA", None, None);
+        assert!(rsa.allows("RS256"));
+        assert!(!rsa.allows("ES256"));
+        assert!(!rsa.allows("HS256"));
+        let p256 = jwk("EC", Some("P-256"), None);
+        assert!(p256.allows("ES256"));
+        assert!(!p256.allows("ES384"));
+        assert!(!p256.allows("RS256"));
+        assert!(jwk("OKP", Some("Ed25519"), None).allows("EdDSA"));
+        let pinned = jwk("RSA", None, Some("RS512"));
+        assert!(pinned.allows("RS512"));
+        assert!(!pinned.allows("RS256"));
+    }
+
+    #[test]
+    fn unsupported_alg_is_refused() {
+        let auth = jwt_authentication();
+        let header = util::base64url_encode(br#"{"alg":"none"}"#);
+        let payload = util::base64url_encode(
+            claims(NOW + 10, "https://issuer.example", "pitsa".into())
+                .to_string()
+                .as_bytes(),
+        );
+        let jwt = format!("{header}.{payload}.");
+        assert!(auth.authenticate(&jwt).is_err());
+    }
+
+    #[tokio::test]
+    async fn insecure_jwks_configuration_is_refused() {
+        let new = |jwks: &str, issuer: Option<&str>, audience: Option<&str>| {
+            BearerAuthentication::new(
+                None,
+                Some(jwks.to_string()),
+                issuer.map(str::to_string),
+                audience.map(str::to_string),
+                "sub".to_string(),
+                60_000_000,
+            )
+        };
+        let issuer = Some("https://issuer.example");
+        assert!(
+            new("http://issuer.example/jwks", issuer, Some("pitsa"))
+                .await
+                .is_err()
+        );
+        assert!(new("jwks.json", None, Some("pitsa")).await.is_err());
+        assert!(new("jwks.json", issuer, None).await.is_err());
+    }
+}
@@ -0,0 +1,338 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Authorization of clients authenticated by TLS client certificates or
+//! bearer tokens.
+
+use crate::der::x509;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use upkit_common::x509::tsp::types::PkiFailureInfo;
+
+tokio::task_local! {
+    /// Identity of the client on whose behalf the current HTTP request is
+    /// processed or `None` if the client did not present a certificate.
+    ///
+    /// Unset outside of HTTP requests (e.g. for the TCP transport).
+    static CLIENT_IDENTITY: Option<ClientIdentity>;
+
+    /// Set while processing a request the service issues itself, e.g. the
+    /// time-stamp over an aggregated Merkle tree root.
+    static INTERNAL_REQUEST: ();
+}
+
+/// Identity of a client from its TLS client certificate.
+#[derive(Clone, Debug)]
+pub struct ClientIdentity {
+    /// Subject `commonName` values.
+    pub common_names: Vec<String>,
+    /// Subject alternative names (e-mail, DNS name and URI).
+    pub alternative_names: Vec<String>,
+}
+
+impl ClientIdentity {
+    /// Return the identity of a DER encoded client certificate.
+    pub fn from_certificate(encoded_certificate: &[u8]) -> Self {
+        Self {
+            common_names: x509::subject_common_names(encoded_certificate).unwrap_or_default(),
+            alternative_names: x509::subject_alternative_names(encoded_certificate)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Return the identity of a client authenticated by other means than a
+    /// certificate.
+    pub fn from_name(name: &str) -> Self {
+        Self {
+            common_names: vec![],
+            alternative_names: vec![name.to_string()],
+        }
+    }
+
+    /// Return `true` if any subject alternative name or `commonName` equals
+    /// `name`.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.alternative_names
+            .iter()
+            .chain(self.common_names.iter())
+            .any(|own_name| own_name.eq(name))
+    }
+
+    /// Process `future` on behalf of the client.
+    pub async fn scope<F: Future>(client_identity: Option<ClientIdentity>, future: F) -> F::Output {
+        CLIENT_IDENTITY.scope(client_identity, future).await
+    }
+
+    /// Return the identity of the client of the current HTTP request.
+    ///
+    /// The outer `None` means that the current task is not an HTTP request.
+    pub fn current() -> Option<Option<ClientIdentity>> {
+        CLIENT_IDENTITY.try_with(Clone::clone).ok()
+    }
+
+    /// Process `future` as a request of the service itself, which is not
+    /// subject to the client rules.
+    pub async fn internal<F: Future>(future: F) -> F::Output {
+        INTERNAL_REQUEST.scope((), future).await
+    }
+
+    /// Return `true` if the current task processes a request of the service
+    /// itself.
+    pub fn is_internal() -> bool {
+        INTERNAL_REQUEST.try_with(|_| ()).is_ok()
+    }
+}
+
+impl std::fmt::Display for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.alternative_names.first().or(self.common_names.first()) {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "<unnamed>"),
+        }
+    }
+}
+
+/// What a client is allowed to request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientRule {
+    /// Subject alternative name or subject `commonName` of the client.
+    pub identity: String,
+    /// Allowed TSA policy object identifiers. Empty allows all policies of
+    /// the service.
+    #[serde(default)]
+    pub policies: Vec<String>,
+    /// Allowed message imprint digest algorithm object identifiers. Empty
+    /// allows all digest algorithms of the service.
+    #[serde(default)]
+    pub digests: Vec<String>,
+    /// Largest number of time-stamps per minute. `0` is unlimited.
+    #[serde(default)]
+    pub quota_per_minute: u64,
+}
+
+/// The file format of [ClientAuthorization] rules.
+#[derive(Deserialize)]
+struct ClientRules {
+    clients: Vec<ClientRule>,
+}
+
+/** Mapping of authenticated client identities to allowed policies, digest
+algorithms and quotas.
+
+Rules are loaded from a JSON file:
+
+```text
+{
+  "clients": [
+    {
+      "identity": "billing.internal.example.com",
+      "policies": ["1.2.3.4.1"],
+      "digests": ["2.16.840.1.101.3.4.2.1"],
+      "quotaPerMinute": 600
+    }
+  ]
+}
+```
+
+Clients without a matching rule are refused.
+*/
+#[derive(Default)]
+pub struct ClientAuthorization {
+    rules: Vec<ClientRule>,
+    /// Per rule index: start of the current minute and the number of
+    /// time-stamps within it.
+    usage: Mutex<HashMap<usize, (u64, u64)>>,
+}
+
+impl ClientAuthorization {
+    /// Return a new instance from JSON encoded rules.
+    pub fn from_json(rules: &str) -> Result<Self, String> {
+        let client_rules = serde_json::from_str::<ClientRules>(rules)
+            .map_err(|e| format!("Invalid client rules: {e}"))?;
+        Ok(Self {
+            rules: client_rules.clients,
+            usage: Mutex::default(),
+        })
+    }
+
+    /// Return a new instance from a JSON file.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {e}", path.display()))
+            .and_then(|rules| Self::from_json(&rules))
+    }
+
+    /// Return the identity of the rule that applies to the client, if any.
+    pub fn rule_identity(&self, client_identity: &ClientIdentity) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|rule| client_identity.has_name(&rule.identity))
+            .map(|rule| rule.identity.to_owned())
+    }
+
+    /// Assert that the client may request a time-stamp under the policy and
+    /// over a message imprint of the digest algorithm. A granted request
+    /// counts towards the client's quota when `count_request` is set.
+    pub fn authorize(
+        &self,
+        client_identity: Option<&ClientIdentity>,
+        policy_oid: &str,
+        digest_oid: &str,
+        count_request: bool,
+    ) -> Result<(), (PkiFailureInfo, String)> {
+        let client_identity = client_identity.ok_or((
+            PkiFailureInfo::BadRequest,
+            "Client is not authenticated.".to_string(),
+        ))?;
+        let (rule_index, rule) = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| client_identity.has_name(&rule.identity))
+            .ok_or_else(|| {
+                (
+                    PkiFailureInfo::BadRequest,
+                    format!("Client '{client_identity}' is not authorized."),
+                )
+            })?;
+        if !rule.policies.is_empty() && !rule.policies.iter().any(|oid| oid.eq(policy_oid)) {
+            return Err((
+                PkiFailureInfo::UnacceptedPolicy,
+                format!("Policy '{policy_oid}' is not allowed for client '{client_identity}'."),
+            ));
+        }
+        if !rule.digests.is_empty() && !rule.digests.iter().any(|oid| oid.eq(digest_oid)) {
+            return Err((
+                PkiFailureInfo::BadAlgo,
+                format!(
+                    "Message digest algorithm '{digest_oid}' is not allowed for client '{client_identity}'."
+                ),
+            ));
+        }
+        if rule.quota_per_minute > 0 {
+            let minute = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_secs() / 60)
+                .unwrap_or_default();
+            let mut usage = self.usage.lock().unwrap();
+            let (window, count) = usage.entry(rule_index).or_insert((minute, 0));
+            if *window != minute {
+                *window = minute;
+                *count = 0;
+            }
+            if *count >= rule.quota_per_minute {
+                return Err((
+                    PkiFailureInfo::BadRequest,
+                    format!("Quota of client '{client_identity}' is exhausted."),
+                ));
+            }
+            if count_request {
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_authorization() -> ClientAuthorization {
+        ClientAuthorization::from_json(
+            r#"{"clients": [{"identity": "billing", "policies": ["1.2.3.4.1"]}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn unauthenticated_client_is_refused() {
+        let result =
+            client_authorization().authorize(None, "1.2.3.4.1", "2.16.840.1.101.3.4.2.1", true);
+        assert!(result.unwrap_err().1.contains("not authenticated"));
+    }
+
+    #[test]
+    fn rules_apply_by_name() {
+        let client_authorization = client_authorization();
+        let billing = ClientIdentity::from_name("billing");
+        assert!(
+            client_authorization
+                .authorize(Some(&billing), "1.2.3.4.1", "2.16.840.1.101.3.4.2.1", true)
+                .is_ok()
+        );
+        assert!(
+            client_authorization
+                .authorize(Some(&billing), "1.2.3.4.2", "2.16.840.1.101.3.4.2.1", true)
+                .is_err()
+        );
+        let other = ClientIdentity::from_name("other");
+        assert!(
+            client_authorization
+                .authorize(Some(&other), "1.2.3.4.1", "2.16.840.1.101.3.4.2.1", true)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn only_counted_requests_use_the_quota() {
+        let client_authorization = ClientAuthorization::from_json(
+            r#"{"clients": [{"identity": "billing", "quotaPerMinute": 1}]}"#,
+        )
+        .unwrap();
+        let billing = ClientIdentity::from_name("billing");
+        let authorize = |count_request| {
+            client_authorization.authorize(
+                Some(&billing),
+                "1.2.3.4.1",
+                "2.16.840.1.101.3.4.2.1",
+                count_request,
+            )
+        };
+        assert!(authorize(false).is_ok());
+        assert!(authorize(true).is_ok());
+        let exhausted = authorize(false).unwrap_err();
+        assert!(exhausted.1.contains("exhausted"));
+    }
+
+    #[test]
+    fn rule_identity_is_known_identity() {
+        let client_authorization = client_authorization();
+        let billing = ClientIdentity::from_name("billing");
+        assert_eq!(
+            client_authorization.rule_identity(&billing).as_deref(),
+            Some("billing")
+        );
+        let other = ClientIdentity::from_name("other");
+        assert_eq!(client_authorization.rule_identity(&other), None);
+    }
+
+    #[tokio::test]
+    async fn only_internal_scope_is_internal() {
+        assert!(!ClientIdentity::is_internal());
+        assert!(ClientIdentity::current().is_none());
+        assert!(ClientIdentity::internal(async { ClientIdentity::is_internal() }).await);
+        let in_http_request = ClientIdentity::scope(None, async {
+            (ClientIdentity::is_internal(), ClientIdentity::current())
+        })
+        .await;
+        assert!(matches!(in_http_request, (false, Some(None))));
+    }
+}
@@ -0,0 +1,203 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! CMS SignedData for other content than time-stamp tokens.
+
+use super::tst_signing_info::SigningKeySnapshot;
+use crate::der;
+
+/// `id-data` from RFC 5652.
+pub const OID_DATA: &[u32] = &[1, 2, 840, 113549, 1, 7, 1];
+/// `id-signedData` from RFC 5652.
+pub const OID_SIGNED_DATA: &[u32] = &[1, 2, 840, 113549, 1, 7, 2];
+/// `id-contentType` from RFC 5652.
+const OID_CONTENT_TYPE: &[u32] = &[1, 2, 840, 113549, 1, 9, 3];
+/// `id-messageDigest` from RFC 5652.
+const OID_MESSAGE_DIGEST: &[u32] = &[1, 2, 840, 113549, 1, 9, 4];
+/// `id-signingTime` from RFC 5652.
+const OID_SIGNING_TIME: &[u32] = &[1, 2, 840, 113549, 1, 9, 5];
+
+/** CMS SignedData ([RFC 5652 5](https://www.rfc-editor.org/rfc/rfc5652#section-5))
+with encapsulated content and a single signer identified by issuer and serial
+number.
+
+The signed attributes are `contentType`, `messageDigest` and `signingTime`.
+The full signing certificate chain is included.
+*/
+pub struct CmsSignedData;
+
+impl CmsSignedData {
+    /// Return the DER encoded `ContentInfo` with `SignedData` content or `None`
+    /// if the signing key or algorithms are unusable.
+    pub fn build(
+        signing_key: &SigningKeySnapshot,
+        e_content_type: &[u32],
+        e_content: &[u8],
+        signing_time_epoch_seconds: u64,
+    ) -> Option<Vec<u8>> {
+        let signing_certificate = signing_key.certificate_chain.first()?;
+        let sid = der::x509::issuer_and_serial_number(signing_certificate)
+            .map_err(|e| log::warn!("Unable to identify signer: {e}"))
+            .ok()?;
+        let message_digest = signing_key.digest(e_content)?;
+        let signed_attrs =
+            Self::signed_attributes(e_content_type, &message_digest, signing_time_epoch_seconds);
+        // The signature covers the DER encoding of the SET OF attributes
+        let signature = signing_key.sign(&signed_attrs)?;
+        // CMSVersion is 1 for issuerAndSerialNumber and id-data, otherwise 3.
+        let version = if e_content_type.eq(OID_DATA) { 1 } else { 3 };
+        let signer_info = der::sequence(&[
+            der::integer(1),
+            sid,
+            der::algorithm_identifier(&signing_key.digest_algorithm_oid),
+            der::implicit(0, &signed_attrs),
+            der::algorithm_identifier(&signing_key.signing_algorithm_oid),
+            der::octet_string(&signature),
+        ]);
+        let signed_data = der::sequence(&[
+            der::integer(version),
+            der::set_of(&[der::algorithm_identifier(&signing_key.digest_algorithm_oid)]),
+            der::sequence(&[
+                der::oid(e_content_type),
+                der::explicit(0, &der::octet_string(e_content)),
+            ]),
+            der::tlv(
+                der::tag::context_constructed(0),
+                &signing_key.certificate_chain.concat(),
+            ),
+            der::set_of(&[signer_info]),
+        ]);
+        Some(der::sequence(&[
+            der::oid(OID_SIGNED_DATA),
+            der::explicit(0, &signed_data),
+        ]))
+    }
+
+    /// Return the DER encoded `ContentInfo` with degenerate "certs-only"
+    /// `SignedData` content ([RFC 8551 3.2.2](https://www.rfc-editor.org/rfc/rfc8551#section-3.2.2))
+    /// that carries the `certificates` without any signers.
+    pub fn certs_only(certificates: &[Vec<u8>]) -> Vec<u8> {
+        let signed_data = der::sequence(&[
+            der::integer(1),
+            der::set_of(&[]),
+            der::sequence(&[der::oid(OID_DATA)]),
+            der::tlv(der::tag::context_constructed(0), &certificates.concat()),
+            der::set_of(&[]),
+        ]);
+        der::sequence(&[der::oid(OID_SIGNED_DATA), der::explicit(0, &signed_data)])
+    }
+
+    /// Encode the `SignedAttributes`, a SET OF `Attribute` in DER order.
+    ///
+    /// The signing time is a `UTCTime` before 2050 and a `GeneralizedTime`
+    /// from then on as required by RFC 5652 11.3.
+    fn signed_attributes(
+        e_content_type: &[u32],
+        message_digest: &[u8],
+        signing_time_epoch_seconds: u64,
+    ) -> Vec<u8> {
+        der::set_of(&[
+            Self::attribute(OID_CONTENT_TYPE, der::oid(e_content_type)),
+            Self::attribute(OID_MESSAGE_DIGEST, der::octet_string(message_digest)),
+            Self::attribute(OID_SIGNING_TIME, der::time(signing_time_epoch_seconds)),
+        ])
+    }
+
+    /// Encode an `Attribute` with a single value.
+    fn attribute(attr_type: &[u32], attr_value: Vec<u8>) -> Vec<u8> {
+        der::sequence(&[der::oid(attr_type), der::set_of(&[attr_value])])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::der::DerReader;
+    use crate::der::tag;
+
+    /// Return the type and encoded value of each signed attribute in order.
+    fn attributes(signing_time_epoch_seconds: u64) -> Vec<(Vec<u32>, Vec<u8>)> {
+        let signed_attrs =
+            CmsSignedData::signed_attributes(OID_DATA, &[0xab; 32], signing_time_epoch_seconds);
+        let mut reader = DerReader::read_single(&signed_attrs, tag::SET)
+            .unwrap()
+            .reader();
+        let mut ret = vec![];
+        let mut previous: Option<Vec<u8>> = None;
+        while !reader.is_empty() {
+            let attribute = reader.read_expected(tag::SEQUENCE).unwrap();
+            if let Some(previous) = previous {
+                assert!(previous.as_slice() <= attribute.encoded);
+            }
+            previous = Some(attribute.encoded.to_vec());
+            let mut attribute = attribute.reader();
+            let attr_type = attribute.read_expected(tag::OID).unwrap().as_oid().unwrap();
+            let attr_value = attribute
+                .read_expected(tag::SET)
+                .unwrap()
+                .reader()
+                .read()
+                .unwrap()
+                .encoded
+                .to_vec();
+            ret.push((attr_type, attr_value));
+        }
+        ret
+    }
+
+    /// Return the encoded signing time attribute value.
+    fn signing_time(signing_time_epoch_seconds: u64) -> Vec<u8> {
+        attributes(signing_time_epoch_seconds)
+            .into_iter()
+            .find(|(attr_type, _)| attr_type.eq(OID_SIGNING_TIME))
+            .map(|(_, attr_value)| attr_value)
+            .unwrap()
+    }
+
+    #[test]
+    fn signed_attributes_are_in_der_order() {
+        let attr_types = attributes(1_750_000_000)
+            .into_iter()
+            .map(|(attr_type, _)| attr_type)
+            .collect::<Vec<_>>();
+        // Sorted by encoding: the attribute lengths differ before the types.
+        assert_eq!(
+            attr_types,
+            vec![
+                OID_CONTENT_TYPE.to_vec(),
+                OID_SIGNING_TIME.to_vec(),
+                OID_MESSAGE_DIGEST.to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn signing_time_is_utc_time_before_2050() {
+        assert_eq!(
+            signing_time(2_524_607_999),
+            der::tlv(tag::UTC_TIME, b"491231235959Z")
+        );
+    }
+
+    #[test]
+    fn signing_time_is_generalized_time_from_2050() {
+        assert_eq!(
+            signing_time(2_524_608_000),
+            der::tlv(tag::GENERALIZED_TIME, b"20500101000000Z")
+        );
+    }
+}
@@ -0,0 +1,63 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Incremental hashing of content that is not kept in memory.
+
+use tyst::Tyst;
+use tyst::traits::digest::Digest;
+
+/// Incremental digest over content that arrives in chunks.
+pub struct ContentDigest {
+    digest_algorithm_oid: Vec<u32>,
+    digest: Box<dyn Digest>,
+    content_size: u64,
+}
+
+impl ContentDigest {
+    /// Return a new instance or `None` if the digest algorithm is unknown.
+    pub fn new(digest_algorithm_oid: &[u32]) -> Option<Self> {
+        Tyst::instance()
+            .digests()
+            .by_oid(&tyst::encdec::oid::as_string(digest_algorithm_oid))
+            .map(|digest| Self {
+                digest_algorithm_oid: digest_algorithm_oid.to_vec(),
+                digest,
+                content_size: 0,
+            })
+    }
+
+    /// Return the object identifier of the digest algorithm.
+    pub fn digest_algorithm_oid(&self) -> &[u32] {
+        &self.digest_algorithm_oid
+    }
+
+    /// Return the number of bytes hashed so far.
+    pub fn content_size(&self) -> u64 {
+        self.content_size
+    }
+
+    /// Feed the next chunk of content to the digest.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.content_size += chunk.len() as u64;
+        self.digest.update(chunk);
+    }
+
+    /// Return the digest of all content fed so far.
+    pub fn finalize(mut self) -> Vec<u8> {
+        self.digest.finalize()
+    }
+}
@@ -0,0 +1,239 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamps in CBOR Object Signing and Encryption (COSE) format.
+
+use super::time_stamp_claims::TimeStampClaims;
+use super::time_stamp_claims::TimeStampFormatError;
+use super::tst_signing_info::SigningKeySnapshot;
+use crate::cbor;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+
+/// Media type of a COSE_Sign1 time-stamp.
+pub const CONTENT_TYPE_COSE: &str = "application/cose; cose-type=\"cose-sign1\"";
+/// Content type of the COSE payload.
+const PAYLOAD_CONTENT_TYPE: &str = "application/tst+cbor";
+/// CBOR tag of a `COSE_Sign1` structure.
+const TAG_COSE_SIGN1: u64 = 18;
+/// CBOR tag of a BER encoded object identifier (without tag and length) from
+/// [RFC 9090](https://www.rfc-editor.org/rfc/rfc9090).
+const TAG_OID: u64 = 111;
+/// COSE header parameter `alg`.
+const HEADER_ALG: u64 = 1;
+/// COSE header parameter `content type`.
+const HEADER_CONTENT_TYPE: u64 = 3;
+/// COSE header parameter `x5chain` from
+/// [RFC 9360](https://www.rfc-editor.org/rfc/rfc9360).
+const HEADER_X5CHAIN: u64 = 33;
+
+/// COSE `alg` of a signature algorithm object identifier and the size of an
+/// ECDSA signature component in bytes (zero for other algorithms).
+const COSE_ALGORITHMS: &[(&[u32], i64, usize)] = &[
+    (&[1, 3, 101, 112], -8, 0),
+    (&[1, 2, 840, 10045, 4, 3, 2], -7, 32),
+    (&[1, 2, 840, 10045, 4, 3, 3], -35, 48),
+    (&[1, 2, 840, 10045, 4, 3, 4], -36, 66),
+    (&[1, 2, 840, 113549, 1, 1, 11], -257, 0),
+    (&[1, 2, 840, 113549, 1, 1, 12], -258, 0),
+    (&[1, 2, 840, 113549, 1, 1, 13], -259, 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 17], -48, 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 18], -49, 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 19], -50, 0),
+];
+
+/** Time-stamp as a tagged
+[RFC 9052 4.2](https://www.rfc-editor.org/rfc/rfc9052#section-4.2)
+`COSE_Sign1` with the same semantics as the `TSTInfo` of an RFC 3161 token.
+
+```text
+   COSE_Sign1 = [
+       Headers,
+       payload : bstr / nil,
+       signature : bstr
+   ]
+
+   TstClaims = {
+       1 => oid,                   ; policy
+       2 => [ oid, bstr ],         ; messageImprint
+       3 => bstr,                  ; serialNumber
+       4 => uint,                  ; genTime (microseconds since the epoch)
+       ? 5 => uint,                ; accuracy (microseconds)
+       ? 7 => bstr,                ; nonce
+   }
+
+   oid = #6.111(bstr)
+```
+
+The protected header holds `alg`, the payload content type and `x5chain`
+with only the TSU certificate, or the full chain when requested.
+*/
+pub struct CoseTimeStamp;
+
+impl CoseTimeStamp {
+    /// Sign the claims with the TSU key and return the encoded `COSE_Sign1`.
+    pub fn sign(
+        claims: &TimeStampClaims,
+        signing_key: &SigningKeySnapshot,
+        include_chain: bool,
+    ) -> Result<Vec<u8>, TimeStampFormatError> {
+        let (_oid, alg, ecdsa_size) = COSE_ALGORITHMS
+            .iter()
+            .find(|(oid, _alg, _size)| signing_key.signing_algorithm_oid.eq(oid))
+            .copied()
+            .ok_or(TimeStampFormatError::Unavailable(format!(
+                "Signature algorithm {} has no COSE equivalent.",
+                tyst::encdec::oid::as_string(&signing_key.signing_algorithm_oid)
+            )))?;
+        let x5chain = match signing_key.certificate_chain.as_slice() {
+            [] => {
+                return Err(TimeStampFormatError::Unavailable(
+                    "No signing certificate available.".to_string(),
+                ));
+            }
+            [leaf, ..] if !include_chain => cbor::bytes(leaf),
+            chain => cbor::array(
+                &chain
+                    .iter()
+                    .map(|encoded| cbor::bytes(encoded))
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        let protected = cbor::map(&[
+            (cbor::unsigned(HEADER_ALG), cbor::integer(alg)),
+            (
+                cbor::unsigned(HEADER_CONTENT_TYPE),
+                cbor::text(PAYLOAD_CONTENT_TYPE),
+            ),
+            (cbor::unsigned(HEADER_X5CHAIN), x5chain),
+        ]);
+        let payload =
+            Self::claims(claims).map_err(|e| TimeStampFormatError::Unavailable(e.to_string()))?;
+        // Sig_structure from RFC 9052 4.4
+        let to_be_signed = cbor::array(&[
+            cbor::text("Signature1"),
+            cbor::bytes(&protected),
+            cbor::bytes(&[]),
+            cbor::bytes(&payload),
+        ]);
+        let signature =
+            signing_key
+                .sign(&to_be_signed)
+                .ok_or(TimeStampFormatError::Unavailable(
+                    "Failed to sign time-stamp.".to_string(),
+                ))?;
+        let signature = if ecdsa_size > 0 {
+            der::ecdsa_sig_value_to_fixed(&signature, ecdsa_size)
+                .map_err(|e| TimeStampFormatError::Unavailable(e.to_string()))?
+        } else {
+            signature
+        };
+        Ok(cbor::tagged(
+            TAG_COSE_SIGN1,
+            &cbor::array(&[
+                cbor::bytes(&protected),
+                cbor::map(&[]),
+                cbor::bytes(&payload),
+                cbor::bytes(&signature),
+            ]),
+        ))
+    }
+
+    /// Return the CBOR encoded `TstClaims`.
+    fn claims(claims: &TimeStampClaims) -> Result<Vec<u8>, der::DerError> {
+        let mut tst_claims = vec![
+            (cbor::unsigned(1), Self::oid(&claims.policy_oid)?),
+            (
+                cbor::unsigned(2),
+                cbor::array(&[
+                    Self::oid(&claims.digest_algorithm_oid)?,
+                    cbor::bytes(&claims.digest),
+                ]),
+            ),
+            (cbor::unsigned(3), cbor::bytes(&claims.serial_number)),
+            (
+                cbor::unsigned(4),
+                cbor::unsigned(claims.gen_time_epoch_micros),
+            ),
+        ];
+        if let Some(accuracy_micros) = claims.accuracy_micros {
+            tst_claims.push((cbor::unsigned(5), cbor::unsigned(accuracy_micros)));
+        }
+        if let Some(nonce) = &claims.nonce {
+            tst_claims.push((cbor::unsigned(7), cbor::bytes(nonce)));
+        }
+        Ok(cbor::map(&tst_claims))
+    }
+
+    /// Return a CBOR tagged object identifier.
+    fn oid(oid: &[u32]) -> Result<Vec<u8>, der::DerError> {
+        let encoded = der::oid(oid);
+        let content = DerReader::read_single(&encoded, tag::OID)?.content;
+        Ok(cbor::tagged(TAG_OID, &cbor::bytes(content)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> TimeStampClaims {
+        TimeStampClaims {
+            policy_oid: vec![1, 2, 3],
+            digest_algorithm_oid: vec![2, 16, 840, 1, 101, 3, 4, 2, 1],
+            digest: vec![0xab, 0xcd],
+            serial_number: vec![0x01],
+            gen_time_epoch_micros: 1_000_000,
+            accuracy_micros: None,
+            nonce: None,
+        }
+    }
+
+    #[test]
+    fn oid_is_tagged_content() {
+        assert_eq!(
+            CoseTimeStamp::oid(&[1, 2, 3]).unwrap(),
+            vec![0xd8, 0x6f, 0x42, 0x2a, 0x03]
+        );
+    }
+
+    #[test]
+    fn claims_known_answer() {
+        let expected = [
+            &[0xa4][..],
+            &[0x01, 0xd8, 0x6f, 0x42, 0x2a, 0x03],
+            &[0x02, 0x82, 0xd8, 0x6f, 0x49],
+            &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+            &[0x42, 0xab, 0xcd],
+            &[0x03, 0x41, 0x01],
+            &[0x04, 0x1a, 0x00, 0x0f, 0x42, 0x40],
+        ]
+        .concat();
+        assert_eq!(CoseTimeStamp::claims(&claims()).unwrap(), expected);
+    }
+
+    #[test]
+    fn optional_claims_are_appended_in_key_order() {
+        let mut claims = claims();
+        claims.accuracy_micros = Some(1_000);
+        claims.nonce = Some(vec![0x2a]);
+        let encoded = CoseTimeStamp::claims(&claims).unwrap();
+        assert_eq!(encoded[0], 0xa6);
+        assert!(encoded.ends_with(&[0x05, 0x19, 0x03, 0xe8, 0x07, 0x41, 0x2a]));
+    }
+}
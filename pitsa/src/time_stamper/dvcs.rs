@@ -0,0 +1,335 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Data Validation and Certification Server (DVCS) requests and responses.
+
+use super::cms_signed_data;
+use super::time_stamp_claims::TimeStampClaims;
+use super::tst_signing_info::SigningKeySnapshot;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+
+/// `id-ct-DVCSRequestData` from RFC 3029.
+pub const OID_CT_DVCS_REQUEST_DATA: &[u32] = &[1, 2, 840, 113549, 1, 9, 16, 1, 7];
+/// `id-ct-DVCSResponseData` from RFC 3029.
+pub const OID_CT_DVCS_RESPONSE_DATA: &[u32] = &[1, 2, 840, 113549, 1, 9, 16, 1, 8];
+
+/// `ServiceType` `cpd`: Certification of Possession of Data.
+pub const SERVICE_CPD: u64 = 1;
+/// `ServiceType` `ccpd`: Certification of Claim of Possession of Data.
+pub const SERVICE_CCPD: u64 = 4;
+
+/// `PKIStatus` `rejection`.
+const PKI_STATUS_REJECTION: u64 = 2;
+/// `PKIFailureInfo` `badRequest`.
+pub const FAILURE_BAD_REQUEST: usize = 2;
+/// `PKIFailureInfo` `badDataFormat`.
+pub const FAILURE_BAD_DATA_FORMAT: usize = 5;
+/// `PKIFailureInfo` `badAlg`.
+pub const FAILURE_BAD_ALG: usize = 0;
+
+/** Data to validate or certify.
+
+```text
+   Data ::= CHOICE {
+         message           OCTET STRING ,
+         messageImprint    DigestInfo,
+         certs             SEQUENCE SIZE (1..MAX) OF
+                               TargetEtcChain
+   }
+```
+*/
+pub enum DvcsData {
+    /// The data itself.
+    Message(Vec<u8>),
+    /// Digest algorithm object identifier and digest of the data.
+    MessageImprint(Vec<u32>, Vec<u8>),
+    /// Certificates to validate.
+    Certs,
+}
+
+/** Decoded DVCS request as defined in
+[RFC 3029 6](https://www.rfc-editor.org/rfc/rfc3029#section-6).
+
+```text
+   DVCSRequest ::= SEQUENCE  {
+       requestInformation         DVCSRequestInformation,
+       data                       Data,
+       transactionIdentifier      GeneralName OPTIONAL
+   }
+
+   DVCSRequestInformation ::= SEQUENCE  {
+       version                      INTEGER DEFAULT 1 ,
+       service                      ServiceType,
+       nonce                        Nonce OPTIONAL,
+       requestTime                  DVCSTime OPTIONAL,
+       requester                    [0] GeneralNames OPTIONAL,
+       requestPolicy                [1] PolicyInformation OPTIONAL,
+       dvcs                         [2] GeneralNames OPTIONAL,
+       dataLocations                [3] GeneralNames OPTIONAL,
+       extensions                   [4] IMPLICIT Extensions OPTIONAL
+   }
+```
+
+The request is accepted either as is or encapsulated in a CMS `SignedData`
+with `id-ct-DVCSRequestData` content. A requester signature is not verified.
+*/
+pub struct DvcsRequest {
+    /// DER encoded `DVCSRequestInformation`.
+    pub request_information: Vec<u8>,
+    /// Requested `ServiceType`.
+    pub service: u64,
+    /// Data to validate or certify.
+    pub data: DvcsData,
+    /// DER encoded `transactionIdentifier`.
+    pub transaction_identifier: Option<Vec<u8>>,
+}
+
+impl DvcsRequest {
+    /// Parse a DER encoded `DVCSRequest` or a `ContentInfo` with `SignedData`
+    /// encapsulating one.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut outer = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        if outer.peek_tag() != Some(tag::OID) {
+            return Self::parse(encoded);
+        }
+        if outer.read_expected(tag::OID)?.as_oid()? != cms_signed_data::OID_SIGNED_DATA {
+            return Err(der::DerError::new("expected signedData content"));
+        }
+        let mut signed_data = outer
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::SEQUENCE)?
+            .reader();
+        let _version = signed_data.read_expected(tag::INTEGER)?;
+        let _digest_algorithms = signed_data.read_expected(tag::SET)?;
+        let mut encap_content_info = signed_data.read_expected(tag::SEQUENCE)?.reader();
+        if encap_content_info.read_expected(tag::OID)?.as_oid()? != OID_CT_DVCS_REQUEST_DATA {
+            return Err(der::DerError::new("expected id-ct-DVCSRequestData content"));
+        }
+        let e_content = encap_content_info
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::OCTET_STRING)?;
+        Self::parse(e_content.content)
+    }
+
+    /// Parse a DER encoded `DVCSRequest`.
+    fn parse(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut dvcs_request = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        let request_information = dvcs_request.read_expected(tag::SEQUENCE)?;
+        let mut request_information_reader = request_information.reader();
+        request_information_reader.read_optional(tag::INTEGER)?;
+        let service = request_information_reader
+            .read_expected(tag::ENUMERATED)?
+            .as_u64()?;
+        let data = dvcs_request.read()?;
+        let data = match data.tag {
+            tag::OCTET_STRING => DvcsData::Message(data.content.to_vec()),
+            tag::SEQUENCE => {
+                let mut data_reader = data.reader();
+                let first = data_reader.read_expected(tag::SEQUENCE)?;
+                match data_reader.read_optional(tag::OCTET_STRING)? {
+                    Some(digest) => DvcsData::MessageImprint(
+                        first.reader().read_expected(tag::OID)?.as_oid()?,
+                        digest.content.to_vec(),
+                    ),
+                    None => DvcsData::Certs,
+                }
+            }
+            _ => return Err(der::DerError::new("unexpected Data choice")),
+        };
+        let transaction_identifier = if dvcs_request.is_empty() {
+            None
+        } else {
+            Some(dvcs_request.read()?.encoded.to_vec())
+        };
+        Ok(Self {
+            request_information: request_information.encoded.to_vec(),
+            service,
+            data,
+            transaction_identifier,
+        })
+    }
+}
+
+/** DVCS response as defined in
+[RFC 3029 7](https://www.rfc-editor.org/rfc/rfc3029#section-7).
+
+```text
+   DVCSResponse ::= CHOICE
+   {
+       dvCertInfo         DVCSCertInfo ,
+       dvErrorNote        [0] DVCSErrorNotice
+   }
+
+   DVCSCertInfo::= SEQUENCE  {
+       version             Integer DEFAULT 1 ,
+       dvReqInfo           DVCSRequestInformation,
+       messageImprint      DigestInfo,
+       serialNumber        Integer,
+       responseTime        DVCSTime,
+       dvStatus            [0] PKIStatusInfo OPTIONAL,
+       policy              [1] PolicyInformation OPTIONAL,
+       reqSignature        [2] SignerInfos  OPTIONAL,
+       certs               [3] SEQUENCE SIZE (1..MAX) OF
+                               TargetEtcChain OPTIONAL,
+       extensions          Extensions OPTIONAL
+   }
+
+   DVCSErrorNotice ::= SEQUENCE {
+       transactionStatus           PKIStatusInfo ,
+       transactionIdentifier       GeneralName OPTIONAL
+   }
+```
+
+The module uses implicit tagging. Responses are encapsulated in a CMS
+`SignedData` with `id-ct-DVCSResponseData` content signed by the TSU key.
+*/
+pub struct DvcsResponse;
+
+impl DvcsResponse {
+    /// Return the signed `ContentInfo` with a `DVCSCertInfo` over the claims
+    /// or `None` if the signing key is unusable.
+    pub fn cert_info(
+        signing_key: &SigningKeySnapshot,
+        request: &DvcsRequest,
+        claims: &TimeStampClaims,
+    ) -> Option<Vec<u8>> {
+        let dvcs_cert_info = der::sequence(&[
+            request.request_information.clone(),
+            der::sequence(&[
+                der::algorithm_identifier(&claims.digest_algorithm_oid),
+                der::octet_string(&claims.digest),
+            ]),
+            der::unsigned_integer(&claims.serial_number),
+            der::generalized_time(claims.gen_time_epoch_micros),
+            der::implicit(1, &der::sequence(&[der::oid(&claims.policy_oid)])),
+        ]);
+        Self::sign(signing_key, &dvcs_cert_info, claims.gen_time_epoch_micros)
+    }
+
+    /// Return the signed `ContentInfo` with a `DVCSErrorNotice` or `None` if
+    /// the signing key is unusable.
+    pub fn error_notice(
+        signing_key: &SigningKeySnapshot,
+        request: &DvcsRequest,
+        status_string: &str,
+        failure_info_bit: usize,
+        point_in_time_epoch_micros: u64,
+    ) -> Option<Vec<u8>> {
+        let mut dvcs_error_notice = vec![der::sequence(&[
+            der::integer(PKI_STATUS_REJECTION),
+            der::sequence(&[der::utf8_string(status_string)]),
+            der::named_bit_string(&[failure_info_bit]),
+        ])];
+        if let Some(transaction_identifier) = &request.transaction_identifier {
+            dvcs_error_notice.push(transaction_identifier.clone());
+        }
+        let dv_error_note = der::implicit(0, &der::sequence(&dvcs_error_notice));
+        Self::sign(signing_key, &dv_error_note, point_in_time_epoch_micros)
+    }
+
+    /// Encapsulate the `DVCSResponse` in a signed `ContentInfo`.
+    fn sign(
+        signing_key: &SigningKeySnapshot,
+        dvcs_response: &[u8],
+        point_in_time_epoch_micros: u64,
+    ) -> Option<Vec<u8>> {
+        cms_signed_data::CmsSignedData::build(
+            signing_key,
+            OID_CT_DVCS_RESPONSE_DATA,
+            dvcs_response,
+            point_in_time_epoch_micros / 1_000_000,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Return an encoded `DVCSRequest` for the service and data.
+    fn dvcs_request(service: u8, data: Vec<u8>) -> Vec<u8> {
+        der::sequence(&[
+            der::sequence(&[der::tlv(tag::ENUMERATED, &[service])]),
+            data,
+        ])
+    }
+
+    fn message_imprint() -> Vec<u8> {
+        der::sequence(&[
+            der::algorithm_identifier(&[2, 16, 840, 1, 101, 3, 4, 2, 1]),
+            der::octet_string(&[0xab; 32]),
+        ])
+    }
+
+    #[test]
+    fn parse_cpd_request_with_message() {
+        let request =
+            DvcsRequest::from_bytes(&dvcs_request(SERVICE_CPD as u8, der::octet_string(b"data")))
+                .unwrap();
+        assert_eq!(request.service, SERVICE_CPD);
+        assert!(matches!(request.data, DvcsData::Message(data) if data == b"data"));
+        assert!(request.transaction_identifier.is_none());
+    }
+
+    #[test]
+    fn parse_ccpd_request_with_message_imprint() {
+        let request =
+            DvcsRequest::from_bytes(&dvcs_request(SERVICE_CCPD as u8, message_imprint())).unwrap();
+        assert_eq!(request.service, SERVICE_CCPD);
+        assert_eq!(
+            request.request_information,
+            der::sequence(&[der::tlv(tag::ENUMERATED, &[SERVICE_CCPD as u8])])
+        );
+        assert!(matches!(
+            request.data,
+            DvcsData::MessageImprint(oid, digest)
+                if oid == [2, 16, 840, 1, 101, 3, 4, 2, 1] && digest == [0xab; 32]
+        ));
+    }
+
+    #[test]
+    fn parse_request_encapsulated_in_signed_data() {
+        let encapsulated = dvcs_request(SERVICE_CCPD as u8, message_imprint());
+        let content_info = der::sequence(&[
+            der::oid(cms_signed_data::OID_SIGNED_DATA),
+            der::explicit(
+                0,
+                &der::sequence(&[
+                    der::integer(3),
+                    der::set_of(&[]),
+                    der::sequence(&[
+                        der::oid(OID_CT_DVCS_REQUEST_DATA),
+                        der::explicit(0, &der::octet_string(&encapsulated)),
+                    ]),
+                    der::set_of(&[]),
+                ]),
+            ),
+        ]);
+        let request = DvcsRequest::from_bytes(&content_info).unwrap();
+        assert_eq!(request.service, SERVICE_CCPD);
+        assert!(matches!(request.data, DvcsData::MessageImprint(..)));
+    }
+
+    #[test]
+    fn reject_unknown_data_choice() {
+        assert!(DvcsRequest::from_bytes(&dvcs_request(SERVICE_CPD as u8, der::null())).is_err());
+    }
+}
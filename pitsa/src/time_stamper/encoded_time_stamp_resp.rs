@@ -0,0 +1,198 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Extraction of the status and time-stamp token from an encoded time-stamp
+//! response.
+
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+
+/// `PKIStatus` `granted`.
+const PKI_STATUS_GRANTED: u64 = 0;
+/// `PKIStatus` `grantedWithMods`.
+const PKI_STATUS_GRANTED_WITH_MODS: u64 = 1;
+/// `PKIStatus` `rejection`.
+const PKI_STATUS_REJECTION: u64 = 2;
+
+/// Names of `PKIStatus` values.
+const PKI_STATUS_NAMES: &[&str] = &[
+    "granted",
+    "grantedWithMods",
+    "rejection",
+    "waiting",
+    "revocationWarning",
+    "revocationNotification",
+];
+/// Names of `PKIFailureInfo` bits used in RFC 3161.
+const PKI_FAILURE_INFO_NAMES: &[(usize, &str)] = &[
+    (0, "badAlg"),
+    (2, "badRequest"),
+    (5, "badDataFormat"),
+    (14, "timeNotAvailable"),
+    (15, "unacceptedPolicy"),
+    (16, "unacceptedExtension"),
+    (17, "addInfoNotAvailable"),
+    (25, "systemFailure"),
+];
+
+/** Decoded `PKIStatusInfo`.
+
+```text
+   PKIStatusInfo ::= SEQUENCE {
+      status        PKIStatus,
+      statusString  PKIFreeText     OPTIONAL,
+      failInfo      PKIFailureInfo  OPTIONAL  }
+```
+*/
+pub struct TimeStampRespStatus {
+    /// `PKIStatus` value.
+    pub status: u64,
+    /// `PKIFreeText` strings.
+    pub status_strings: Vec<String>,
+    /// Names of the set `PKIFailureInfo` bits.
+    pub failure_info: Vec<&'static str>,
+}
+
+impl TimeStampRespStatus {
+    /// Return a `granted` status.
+    pub fn granted() -> Self {
+        Self {
+            status: PKI_STATUS_GRANTED,
+            status_strings: vec![],
+            failure_info: vec![],
+        }
+    }
+
+    /// Return a `rejection` status with a single `PKIFailureInfo` name.
+    pub fn rejection(status_string: &str, failure_info: &'static str) -> Self {
+        Self {
+            status: PKI_STATUS_REJECTION,
+            status_strings: vec![status_string.to_string()],
+            failure_info: vec![failure_info],
+        }
+    }
+
+    /// Return `true` if a time-stamp token was granted.
+    pub fn is_granted(&self) -> bool {
+        self.status == PKI_STATUS_GRANTED || self.status == PKI_STATUS_GRANTED_WITH_MODS
+    }
+
+    /// Return `true` if the `PKIFailureInfo` blames the request rather than
+    /// the time-stamping service.
+    pub fn is_request_failure(&self) -> bool {
+        self.failure_info.iter().any(|name| {
+            matches!(
+                *name,
+                "badAlg"
+                    | "badRequest"
+                    | "badDataFormat"
+                    | "unacceptedPolicy"
+                    | "unacceptedExtension"
+            )
+        })
+    }
+
+    /// Return the name of the `PKIStatus`.
+    pub fn status_name(&self) -> &'static str {
+        usize::try_from(self.status)
+            .ok()
+            .and_then(|index| PKI_STATUS_NAMES.get(index))
+            .copied()
+            .unwrap_or("unknown")
+    }
+}
+
+/** Extraction of the status and time-stamp token from an encoded time-stamp
+response.
+
+[RFC 3161 2.4.2](https://www.rfc-editor.org/rfc/rfc3161#section-2.4.2):
+
+```text
+   TimeStampResp ::= SEQUENCE  {
+      status                  PKIStatusInfo,
+      timeStampToken          TimeStampToken     OPTIONAL  }
+```
+
+Used when a time-stamp token is embedded in another structure.
+*/
+pub struct EncodedTimeStampResp;
+
+impl EncodedTimeStampResp {
+    /// Return the DER encoded `TimeStampToken` (`ContentInfo`) of a granted
+    /// response or the reason why no token was granted.
+    pub fn granted_token(encoded_resp: &[u8]) -> Result<Vec<u8>, String> {
+        let (status, time_stamp_token) =
+            Self::parse(encoded_resp).map_err(|e| format!("Malformed time-stamp response: {e}"))?;
+        if !status.is_granted() {
+            return Err(format!(
+                "Time-stamp was not granted (status {}): {}",
+                status.status_name(),
+                status.status_strings.join(" ")
+            ));
+        }
+        time_stamp_token.ok_or("Granted time-stamp response has no token.".to_string())
+    }
+
+    /// Return the status and the DER encoded `TimeStampToken` if any.
+    pub fn status_and_token(
+        encoded_resp: &[u8],
+    ) -> Result<(TimeStampRespStatus, Option<Vec<u8>>), String> {
+        Self::parse(encoded_resp).map_err(|e| format!("Malformed time-stamp response: {e}"))
+    }
+
+    /// Return a DER encoded granted `TimeStampResp` with the DER encoded
+    /// `TimeStampToken`.
+    pub fn from_token(time_stamp_token: &[u8]) -> Vec<u8> {
+        der::sequence(&[
+            der::sequence(&[der::integer(PKI_STATUS_GRANTED)]),
+            time_stamp_token.to_vec(),
+        ])
+    }
+
+    fn parse(encoded_resp: &[u8]) -> Result<(TimeStampRespStatus, Option<Vec<u8>>), der::DerError> {
+        let mut time_stamp_resp = DerReader::read_single(encoded_resp, tag::SEQUENCE)?.reader();
+        let mut status_info = time_stamp_resp.read_expected(tag::SEQUENCE)?.reader();
+        let status = status_info.read_expected(tag::INTEGER)?.as_u64()?;
+        let mut status_strings = vec![];
+        if let Some(free_text) = status_info.read_optional(tag::SEQUENCE)? {
+            let mut free_text = free_text.reader();
+            while !free_text.is_empty() {
+                status_strings.push(free_text.read()?.as_string()?);
+            }
+        }
+        let mut failure_info = vec![];
+        if let Some(fail_info) = status_info.read_optional(tag::BIT_STRING)? {
+            for (position, name) in PKI_FAILURE_INFO_NAMES {
+                if fail_info.is_bit_set(*position)? {
+                    failure_info.push(*name);
+                }
+            }
+        }
+        let time_stamp_token = time_stamp_resp
+            .read_optional(tag::SEQUENCE)?
+            .map(|time_stamp_token| time_stamp_token.encoded.to_vec());
+        Ok((
+            TimeStampRespStatus {
+                status,
+                status_strings,
+                failure_info,
+            },
+            time_stamp_token,
+        ))
+    }
+}
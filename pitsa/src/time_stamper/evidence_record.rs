@@ -0,0 +1,529 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Evidence Record Syntax (ERS).
+
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::util;
+
+/** Evidence record as defined in
+[RFC 4998 3](https://www.rfc-editor.org/rfc/rfc4998#section-3).
+
+```text
+   EvidenceRecord ::= SEQUENCE {
+      version                   INTEGER { v1(1) } ,
+      digestAlgorithms          SEQUENCE OF AlgorithmIdentifier,
+      cryptoInfos               [0] CryptoInfos OPTIONAL,
+      encryptionInfo            [1] EncryptionInfo OPTIONAL,
+      archiveTimeStampSequence  ArchiveTimeStampSequence
+   }
+
+   ArchiveTimeStampSequence ::= SEQUENCE OF ArchiveTimeStampChain
+   ArchiveTimeStampChain    ::= SEQUENCE OF ArchiveTimeStamp
+```
+
+Each record produced here covers a single data object. Archive time-stamps
+are kept in their original encoding, since renewals are computed over them.
+*/
+pub struct EvidenceRecord {
+    digest_algorithm_oids: Vec<Vec<u32>>,
+    /// Encoded `cryptoInfos` and `encryptionInfo` to retain as is.
+    encoded_infos: Vec<Vec<u8>>,
+    /// Encoded `ArchiveTimeStamp`s of each `ArchiveTimeStampChain`.
+    archive_time_stamp_sequence: Vec<Vec<Vec<u8>>>,
+}
+
+impl EvidenceRecord {
+    /// Return a new record with a single archive time-stamp.
+    pub fn new(archive_time_stamp: &ArchiveTimeStamp) -> Self {
+        Self {
+            digest_algorithm_oids: vec![archive_time_stamp.digest_algorithm_oid.to_vec()],
+            encoded_infos: vec![],
+            archive_time_stamp_sequence: vec![vec![archive_time_stamp.as_bytes()]],
+        }
+    }
+
+    /// Parse a DER encoded `EvidenceRecord`.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut evidence_record = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        if evidence_record.read_expected(tag::INTEGER)?.as_u64()? != 1 {
+            return Err(der::DerError::new("Unsupported EvidenceRecord version."));
+        }
+        let mut digest_algorithm_oids = vec![];
+        let mut digest_algorithms = evidence_record.read_expected(tag::SEQUENCE)?.reader();
+        while !digest_algorithms.is_empty() {
+            digest_algorithm_oids.push(
+                digest_algorithms
+                    .read_expected(tag::SEQUENCE)?
+                    .reader()
+                    .read_expected(tag::OID)?
+                    .as_oid()?,
+            );
+        }
+        let mut encoded_infos = vec![];
+        for n in 0..2 {
+            if let Some(info) = evidence_record.read_optional(tag::context_constructed(n))? {
+                encoded_infos.push(info.encoded.to_vec());
+            }
+        }
+        let mut archive_time_stamp_sequence = vec![];
+        let mut chains = evidence_record.read_expected(tag::SEQUENCE)?.reader();
+        while !chains.is_empty() {
+            let mut chain = chains.read_expected(tag::SEQUENCE)?.reader();
+            let mut archive_time_stamps = vec![];
+            while !chain.is_empty() {
+                archive_time_stamps.push(chain.read_expected(tag::SEQUENCE)?.encoded.to_vec());
+            }
+            if archive_time_stamps.is_empty() {
+                return Err(der::DerError::new("Empty ArchiveTimeStampChain."));
+            }
+            archive_time_stamp_sequence.push(archive_time_stamps);
+        }
+        if archive_time_stamp_sequence.is_empty() {
+            return Err(der::DerError::new("Empty ArchiveTimeStampSequence."));
+        }
+        Ok(Self {
+            digest_algorithm_oids,
+            encoded_infos,
+            archive_time_stamp_sequence,
+        })
+    }
+
+    /// Return the DER encoded `EvidenceRecord`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut evidence_record = vec![
+            der::integer(1),
+            der::sequence(
+                &self
+                    .digest_algorithm_oids
+                    .iter()
+                    .map(|oid| der::algorithm_identifier(oid))
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        evidence_record.extend_from_slice(&self.encoded_infos);
+        evidence_record.push(self.encoded_archive_time_stamp_sequence());
+        der::sequence(&evidence_record)
+    }
+
+    /// Return the DER encoded `ArchiveTimeStampSequence`.
+    pub fn encoded_archive_time_stamp_sequence(&self) -> Vec<u8> {
+        der::sequence(
+            &self
+                .archive_time_stamp_sequence
+                .iter()
+                .map(|chain| der::sequence(chain))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Return the last archive time-stamp of the last chain.
+    pub fn last_archive_time_stamp(&self) -> Result<ArchiveTimeStamp, der::DerError> {
+        self.archive_time_stamp_sequence
+            .last()
+            .and_then(|chain| chain.last())
+            .ok_or(der::DerError::new("No ArchiveTimeStamp."))
+            .and_then(|encoded| ArchiveTimeStamp::from_bytes(encoded))
+    }
+
+    /// Append an archive time-stamp to the last chain (time-stamp renewal).
+    pub fn append_to_last_chain(&mut self, archive_time_stamp: &ArchiveTimeStamp) {
+        if let Some(chain) = self.archive_time_stamp_sequence.last_mut() {
+            chain.push(archive_time_stamp.as_bytes());
+        }
+    }
+
+    /// Append a new chain starting with the archive time-stamp (hash-tree
+    /// renewal).
+    pub fn append_chain(&mut self, archive_time_stamp: &ArchiveTimeStamp) {
+        if !self
+            .digest_algorithm_oids
+            .contains(&archive_time_stamp.digest_algorithm_oid)
+        {
+            self.digest_algorithm_oids
+                .push(archive_time_stamp.digest_algorithm_oid.to_vec());
+        }
+        self.archive_time_stamp_sequence
+            .push(vec![archive_time_stamp.as_bytes()]);
+    }
+}
+
+/** Archive time-stamp as defined in
+[RFC 4998 4.1](https://www.rfc-editor.org/rfc/rfc4998#section-4.1).
+
+```text
+   ArchiveTimeStamp ::= SEQUENCE {
+     digestAlgorithm [0] AlgorithmIdentifier OPTIONAL,
+     attributes      [1] Attributes OPTIONAL,
+     reducedHashtree [2] SEQUENCE OF PartialHashtree OPTIONAL,
+     timeStamp       ContentInfo}
+
+   PartialHashtree ::= SEQUENCE OF OCTET STRING
+```
+
+The ERS module uses implicit tagging. The digest algorithm is always included
+in produced archive time-stamps.
+*/
+pub struct ArchiveTimeStamp {
+    /// Digest algorithm of the hash tree.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Encoded `attributes` to retain as is.
+    encoded_attributes: Option<Vec<u8>>,
+    /// Reduced hash tree from the data object to the time-stamped root.
+    pub reduced_hash_tree: Vec<Vec<Vec<u8>>>,
+    /// DER encoded `TimeStampToken`.
+    pub time_stamp: Vec<u8>,
+}
+
+impl ArchiveTimeStamp {
+    /// Return a new instance.
+    pub fn new(
+        digest_algorithm_oid: &[u32],
+        reduced_hash_tree: Vec<Vec<Vec<u8>>>,
+        time_stamp: Vec<u8>,
+    ) -> Self {
+        Self {
+            digest_algorithm_oid: digest_algorithm_oid.to_vec(),
+            encoded_attributes: None,
+            reduced_hash_tree,
+            time_stamp,
+        }
+    }
+
+    /// Parse a DER encoded `ArchiveTimeStamp`.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut archive_time_stamp = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        let digest_algorithm_oid = archive_time_stamp
+            .read_optional(tag::context_constructed(0))?
+            .ok_or(der::DerError::new(
+                "ArchiveTimeStamp without digestAlgorithm is not supported.",
+            ))?
+            .reader()
+            .read_expected(tag::OID)?
+            .as_oid()?;
+        let encoded_attributes = archive_time_stamp
+            .read_optional(tag::context_constructed(1))?
+            .map(|attributes| attributes.encoded.to_vec());
+        let mut reduced_hash_tree = vec![];
+        if let Some(partial_hash_trees) =
+            archive_time_stamp.read_optional(tag::context_constructed(2))?
+        {
+            let mut partial_hash_trees = partial_hash_trees.reader();
+            while !partial_hash_trees.is_empty() {
+                let mut partial_hash_tree =
+                    partial_hash_trees.read_expected(tag::SEQUENCE)?.reader();
+                let mut hashes = vec![];
+                while !partial_hash_tree.is_empty() {
+                    hashes.push(
+                        partial_hash_tree
+                            .read_expected(tag::OCTET_STRING)?
+                            .content
+                            .to_vec(),
+                    );
+                }
+                reduced_hash_tree.push(hashes);
+            }
+        }
+        let time_stamp = archive_time_stamp
+            .read_expected(tag::SEQUENCE)?
+            .encoded
+            .to_vec();
+        Ok(Self {
+            digest_algorithm_oid,
+            encoded_attributes,
+            reduced_hash_tree,
+            time_stamp,
+        })
+    }
+
+    /// Return the DER encoded `ArchiveTimeStamp`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut archive_time_stamp = vec![der::implicit(
+            0,
+            &der::algorithm_identifier(&self.digest_algorithm_oid),
+        )];
+        if let Some(encoded_attributes) = &self.encoded_attributes {
+            archive_time_stamp.push(encoded_attributes.to_vec());
+        }
+        if !self.reduced_hash_tree.is_empty() {
+            archive_time_stamp.push(der::implicit(
+                2,
+                &der::sequence(
+                    &self
+                        .reduced_hash_tree
+                        .iter()
+                        .map(|partial_hash_tree| {
+                            der::sequence(
+                                &partial_hash_tree
+                                    .iter()
+                                    .map(|hash| der::octet_string(hash))
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+            ));
+        }
+        archive_time_stamp.push(self.time_stamp.to_vec());
+        der::sequence(&archive_time_stamp)
+    }
+}
+
+/** Hash tree as defined in
+[RFC 4998 4.2](https://www.rfc-editor.org/rfc/rfc4998#section-4.2).
+
+```text
+   4. Select, if possible, at least two hash values that have not yet been
+      selected; ... Calculate a hash value of the binary ascending sorted,
+      concatenated hash values.
+```
+
+Hash values are paired, with a group of three at the end of an odd level, so
+that every partial hash tree corresponds to one hashing step.
+*/
+pub struct ArchiveHashTree {
+    digest_algorithm_oid: Vec<u32>,
+}
+
+impl ArchiveHashTree {
+    /// Return a new instance or `None` if the digest algorithm is unknown.
+    pub fn new(digest_algorithm_oid: &[u32]) -> Option<Self> {
+        util::digest_size(digest_algorithm_oid).map(|_| Self {
+            digest_algorithm_oid: digest_algorithm_oid.to_vec(),
+        })
+    }
+
+    /// Return the size of a hash value in bytes.
+    pub fn hash_size(&self) -> usize {
+        util::digest_size(&self.digest_algorithm_oid).unwrap()
+    }
+
+    /// Return the hash of `data`.
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        util::digest(&self.digest_algorithm_oid, data).unwrap()
+    }
+
+    /// Return the root hash and the reduced hash tree of every leaf.
+    ///
+    /// A single leaf is its own root and has no reduced hash tree.
+    pub fn build(&self, leaves: &[Vec<u8>]) -> (Vec<u8>, Vec<Vec<Vec<Vec<u8>>>>) {
+        let mut reduced_hash_trees = vec![vec![]; leaves.len()];
+        // Position of the node that each leaf contributes to on the current level
+        let mut positions = (0..leaves.len()).collect::<Vec<_>>();
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            let mut groups = vec![];
+            let mut start = 0;
+            while start < level.len() {
+                let end = if level.len() - start == 3 {
+                    level.len()
+                } else {
+                    start + 2
+                };
+                groups.push(start..end);
+                start = end;
+            }
+            for (leaf, position) in positions.iter_mut().enumerate() {
+                let group_index = groups
+                    .iter()
+                    .position(|group| group.contains(&*position))
+                    .unwrap();
+                let group = &groups[group_index];
+                // The first partial hash tree contains the leaf itself.
+                let partial_hash_tree = group
+                    .clone()
+                    .filter(|i| reduced_hash_trees[leaf].is_empty() || i != *position)
+                    .map(|i| level[i].to_vec())
+                    .collect();
+                reduced_hash_trees[leaf].push(partial_hash_tree);
+                *position = group_index;
+            }
+            level = groups
+                .into_iter()
+                .map(|group| self.group_hash(&level[group]))
+                .collect();
+        }
+        (level.pop().unwrap_or_default(), reduced_hash_trees)
+    }
+
+    /// Return the hash of the binary ascending sorted, concatenated hashes.
+    fn group_hash(&self, hashes: &[Vec<u8>]) -> Vec<u8> {
+        let mut hashes = hashes.to_vec();
+        hashes.sort();
+        self.hash(&hashes.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::OID_SHA256;
+
+    /// Minimal stand-in for a `ContentInfo`.
+    fn time_stamp(n: u8) -> Vec<u8> {
+        der::sequence(&[der::oid(&[1, 2, 3]), der::octet_string(&[n])])
+    }
+
+    /// Reduce a leaf with its reduced hash tree as a verifier would
+    /// ([RFC 4998 4.3](https://www.rfc-editor.org/rfc/rfc4998#section-4.3)).
+    fn reduce(hash_tree: &ArchiveHashTree, leaf: &[u8], reduced: &[Vec<Vec<u8>>]) -> Vec<u8> {
+        let mut hash = leaf.to_vec();
+        for partial_hash_tree in reduced {
+            let mut group = partial_hash_tree.clone();
+            if !group.contains(&hash) {
+                group.push(hash);
+            }
+            hash = hash_tree.group_hash(&group);
+        }
+        hash
+    }
+
+    #[test]
+    fn every_leaf_reduces_to_the_root() {
+        let hash_tree = ArchiveHashTree::new(OID_SHA256).unwrap();
+        for count in 1..=9u8 {
+            let leaves = (0..count).map(|n| hash_tree.hash(&[n])).collect::<Vec<_>>();
+            let (root, reduced_hash_trees) = hash_tree.build(&leaves);
+            assert_eq!(reduced_hash_trees.len(), leaves.len());
+            for (leaf, reduced) in leaves.iter().zip(&reduced_hash_trees) {
+                assert_eq!(reduce(&hash_tree, leaf, reduced), root, "{count} leaves");
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_leaf_does_not_reduce_to_the_root() {
+        let hash_tree = ArchiveHashTree::new(OID_SHA256).unwrap();
+        let leaves = (0..5u8).map(|n| hash_tree.hash(&[n])).collect::<Vec<_>>();
+        let (root, reduced_hash_trees) = hash_tree.build(&leaves);
+        let mut reduced = reduced_hash_trees[3].clone();
+        // The first partial hash tree carries the leaf itself
+        reduced[0].retain(|hash| hash != &leaves[3]);
+        assert_ne!(reduce(&hash_tree, &hash_tree.hash(b"x"), &reduced), root);
+    }
+
+    #[test]
+    fn unknown_digest_algorithm() {
+        assert!(ArchiveHashTree::new(&[1, 2, 3, 4]).is_none());
+    }
+
+    #[test]
+    fn archive_time_stamp_round_trip() {
+        let hash_tree = ArchiveHashTree::new(OID_SHA256).unwrap();
+        let leaves = (0..3u8).map(|n| hash_tree.hash(&[n])).collect::<Vec<_>>();
+        let (_, reduced_hash_trees) = hash_tree.build(&leaves);
+        let archive_time_stamp =
+            ArchiveTimeStamp::new(OID_SHA256, reduced_hash_trees[1].clone(), time_stamp(1));
+        let encoded = archive_time_stamp.as_bytes();
+        let parsed = ArchiveTimeStamp::from_bytes(&encoded).unwrap();
+        assert_eq!(parsed.digest_algorithm_oid, OID_SHA256);
+        assert_eq!(parsed.reduced_hash_tree, reduced_hash_trees[1]);
+        assert_eq!(parsed.time_stamp, time_stamp(1));
+        assert_eq!(parsed.as_bytes(), encoded);
+    }
+
+    #[test]
+    fn evidence_record_round_trip_with_renewals() {
+        let mut evidence_record =
+            EvidenceRecord::new(&ArchiveTimeStamp::new(OID_SHA256, vec![], time_stamp(1)));
+        evidence_record.append_to_last_chain(&ArchiveTimeStamp::new(
+            OID_SHA256,
+            vec![],
+            time_stamp(2),
+        ));
+        let oid_sha512 = [2, 16, 840, 1, 101, 3, 4, 2, 3];
+        evidence_record.append_chain(&ArchiveTimeStamp::new(&oid_sha512, vec![], time_stamp(3)));
+        let encoded = evidence_record.as_bytes();
+        let parsed = EvidenceRecord::from_bytes(&encoded).unwrap();
+        assert_eq!(
+            parsed.digest_algorithm_oids,
+            vec![OID_SHA256.to_vec(), oid_sha512.to_vec()]
+        );
+        assert_eq!(parsed.archive_time_stamp_sequence.len(), 2);
+        assert_eq!(parsed.archive_time_stamp_sequence[0].len(), 2);
+        assert_eq!(parsed.as_bytes(), encoded);
+        let last = parsed.last_archive_time_stamp().unwrap();
+        assert_eq!(last.digest_algorithm_oid, oid_sha512);
+        assert_eq!(last.time_stamp, time_stamp(3));
+    }
+
+    #[test]
+    fn evidence_record_retains_crypto_infos() {
+        let crypto_infos = der::explicit(0, &der::sequence(&[]));
+        let encoded = der::sequence(&[
+            der::integer(1),
+            der::sequence(&[der::algorithm_identifier(OID_SHA256)]),
+            crypto_infos.clone(),
+            der::sequence(&[der::sequence(&[ArchiveTimeStamp::new(
+                OID_SHA256,
+                vec![],
+                time_stamp(1),
+            )
+            .as_bytes()])]),
+        ]);
+        let parsed = EvidenceRecord::from_bytes(&encoded).unwrap();
+        assert_eq!(parsed.encoded_infos, vec![crypto_infos]);
+        assert_eq!(parsed.as_bytes(), encoded);
+    }
+
+    #[test]
+    fn malformed_evidence_records() {
+        let archive_time_stamp =
+            ArchiveTimeStamp::new(OID_SHA256, vec![], time_stamp(1)).as_bytes();
+        let digest_algorithms = der::sequence(&[der::algorithm_identifier(OID_SHA256)]);
+        // Unsupported version
+        assert!(
+            EvidenceRecord::from_bytes(&der::sequence(&[
+                der::integer(2),
+                digest_algorithms.clone(),
+                der::sequence(&[der::sequence(&[archive_time_stamp.clone()])]),
+            ]))
+            .is_err()
+        );
+        // Empty ArchiveTimeStampSequence
+        assert!(
+            EvidenceRecord::from_bytes(&der::sequence(&[
+                der::integer(1),
+                digest_algorithms.clone(),
+                der::sequence(&[]),
+            ]))
+            .is_err()
+        );
+        // Empty ArchiveTimeStampChain
+        assert!(
+            EvidenceRecord::from_bytes(&der::sequence(&[
+                der::integer(1),
+                digest_algorithms.clone(),
+                der::sequence(&[der::sequence(&[])]),
+            ]))
+            .is_err()
+        );
+        // Trailing data
+        let mut encoded =
+            EvidenceRecord::new(&ArchiveTimeStamp::from_bytes(&archive_time_stamp).unwrap())
+                .as_bytes();
+        encoded.push(0);
+        assert!(EvidenceRecord::from_bytes(&encoded).is_err());
+    }
+
+    #[test]
+    fn archive_time_stamp_without_digest_algorithm_is_refused() {
+        assert!(ArchiveTimeStamp::from_bytes(&der::sequence(&[time_stamp(1)])).is_err());
+    }
+}
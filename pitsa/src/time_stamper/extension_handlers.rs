@@ -0,0 +1,230 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Registry of supported time-stamp request extensions.
+
+mod archive_rev_info_hint;
+mod correlation_id;
+
+use self::archive_rev_info_hint::ArchiveRevInfoHint;
+use self::correlation_id::CorrelationId;
+use crate::conf::AppConfig;
+use crate::der;
+use crate::der::DerError;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::Extension;
+
+/// Handler of a single request extension.
+pub trait ExtensionHandler: Send + Sync {
+    /// Object identifier of the handled extension in dot notation.
+    fn oid(&self) -> &str;
+
+    /// Return `Err` with a reason when the request extension isn't acceptable.
+    fn validate(&self, extension: &Extension) -> Result<(), String>;
+
+    /// Return extensions that should be included in the `TSTInfo`.
+    fn response_extensions(&self, _extension: &Extension) -> Vec<Extension> {
+        vec![]
+    }
+}
+
+/** Registry of supported time-stamp request extensions.
+
+[RFC 3161 2.4.1](https://www.rfc-editor.org/rfc/rfc3161#section-2.4.1):
+
+```text
+   If an extension, whether it is marked critical or not critical, is
+   used by a requester but is not recognized by a time-stamping server,
+   the server SHALL not issue a token and SHALL return a failure
+   (unacceptedExtension).
+```
+
+Unknown critical extensions are rejected. Unknown non-critical extensions are
+silently ignored (only logged at debug level) to stay compatible with clients
+that add informational extensions.
+*/
+pub struct ExtensionHandlers {
+    handlers: Vec<Box<dyn ExtensionHandler>>,
+}
+
+impl ExtensionHandlers {
+    /// Return a new instance with the handlers enabled by the configuration.
+    pub fn new(app_config: &AppConfig) -> Self {
+        let mut handlers: Vec<Box<dyn ExtensionHandler>> = vec![];
+        if let Some(oid) = app_config.sign.archive_hint_extension_oid() {
+            handlers.push(Box::new(ArchiveRevInfoHint::new(oid)));
+        }
+        if let Some(oid) = app_config.sign.correlation_id_extension_oid() {
+            handlers.push(Box::new(CorrelationId::new(oid)));
+        }
+        Self { handlers }
+    }
+
+    /// Validate all extensions of a request and return the extensions that
+    /// should be included in the `TSTInfo`.
+    pub fn process(&self, request_extensions: &[Extension]) -> Result<Vec<Extension>, String> {
+        let mut response_extensions = vec![];
+        for extension in request_extensions {
+            if let Some(handler) = self
+                .handlers
+                .iter()
+                .find(|handler| handler.oid().eq(&extension.oid))
+            {
+                handler.validate(extension)?;
+                response_extensions.append(&mut handler.response_extensions(extension));
+            } else if extension.critical {
+                return Err(format!(
+                    "Requested critical extension '{}' is not supported by this service.",
+                    extension.oid
+                ));
+            } else if log::log_enabled!(log::Level::Debug) {
+                log::debug!(
+                    "Ignoring unsupported non-critical extension '{}'.",
+                    extension.oid
+                );
+            }
+        }
+        Ok(response_extensions)
+    }
+
+    /// Return the extensions of a DER encoded `TimeStampReq`.
+    ///
+    /// ```text
+    /// TimeStampReq ::= SEQUENCE  {
+    ///    version                      INTEGER  { v1(1) },
+    ///    messageImprint               MessageImprint,
+    ///    reqPolicy             TSAPolicyId              OPTIONAL,
+    ///    nonce                 INTEGER                  OPTIONAL,
+    ///    certReq               BOOLEAN                  DEFAULT FALSE,
+    ///    extensions            [0] IMPLICIT Extensions  OPTIONAL  }
+    /// ```
+    pub fn request_extensions(time_stamp_request: &[u8]) -> Result<Vec<Extension>, DerError> {
+        let mut time_stamp_req =
+            DerReader::read_single(time_stamp_request, tag::SEQUENCE)?.reader();
+        while !time_stamp_req.is_empty() {
+            let tlv = time_stamp_req.read()?;
+            if tlv.tag == tag::context_constructed(0) {
+                return der::x509::parse_extensions(tlv.content);
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORRELATION_ID_OID: &str = "1.3.6.1.4.1.99999.1";
+    const ARCHIVE_HINT_OID: &str = "1.3.6.1.4.1.99999.2";
+
+    fn handlers() -> ExtensionHandlers {
+        ExtensionHandlers {
+            handlers: vec![
+                Box::new(ArchiveRevInfoHint::new(ARCHIVE_HINT_OID.to_string())),
+                Box::new(CorrelationId::new(CORRELATION_ID_OID.to_string())),
+            ],
+        }
+    }
+
+    fn extension(oid: &str, critical: bool, value: Vec<u8>) -> Extension {
+        Extension {
+            oid: oid.to_string(),
+            critical,
+            value,
+        }
+    }
+
+    #[test]
+    fn unknown_critical_extension_is_rejected() {
+        let res = handlers().process(&[extension("1.2.3.4", true, der::null())]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn unknown_non_critical_extension_is_ignored() {
+        let res = handlers().process(&[extension("1.2.3.4", false, der::null())]);
+        assert!(res.unwrap().is_empty());
+    }
+
+    #[test]
+    fn archive_rev_info_hint_is_acknowledged() {
+        let res = handlers().process(&[extension(ARCHIVE_HINT_OID, true, der::null())]);
+        assert!(res.unwrap().is_empty());
+    }
+
+    #[test]
+    fn certificate_extension_oid_is_not_an_archive_hint() {
+        // adbe-archiveRevInfo is a certificate extension, not a request extension
+        let res = handlers().process(&[extension("1.2.840.113583.1.1.9.2", true, der::null())]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn correlation_id_is_echoed() {
+        let value = der::utf8_string("order-4711");
+        let res = handlers()
+            .process(&[extension(CORRELATION_ID_OID, false, value.to_vec())])
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].oid, CORRELATION_ID_OID);
+        assert!(!res[0].critical);
+        assert_eq!(res[0].value, value);
+    }
+
+    #[test]
+    fn malformed_correlation_id_is_rejected() {
+        // One byte more than allowed
+        let too_long = der::octet_string(&[0u8; 129]);
+        for value in [
+            der::integer(1),
+            too_long,
+            [der::null(), der::null()].concat(),
+        ] {
+            assert!(
+                handlers()
+                    .process(&[extension(CORRELATION_ID_OID, false, value)])
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn request_extensions_are_parsed() {
+        let time_stamp_req = der::sequence(&[
+            der::integer(1),
+            der::sequence(&[
+                der::algorithm_identifier(&[2, 16, 840, 1, 101, 3, 4, 2, 1]),
+                der::octet_string(&[0u8; 32]),
+            ]),
+            der::tlv(
+                tag::context_constructed(0),
+                &der::sequence(&[
+                    der::oid(&[1, 2, 3, 4]),
+                    der::boolean(true),
+                    der::octet_string(&der::null()),
+                ]),
+            ),
+        ]);
+        let extensions = ExtensionHandlers::request_extensions(&time_stamp_req).unwrap();
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions[0].oid, "1.2.3.4");
+        assert!(extensions[0].critical);
+        assert_eq!(extensions[0].value, der::null());
+    }
+}
@@ -0,0 +1,57 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Archive revocation info hint.
+//!
+//! Only the hint under the configured OID is handled. Microsoft clients
+//! (`signtool`) send no archive hint in the `TimeStampReq`, so a Microsoft
+//! specific hint is out of scope. Any other hint sent as a non-critical
+//! extension is silently ignored like every unknown non-critical extension and
+//! a critical one is rejected.
+
+use super::ExtensionHandler;
+use crate::der::x509::Extension;
+
+/** Archive revocation info hint.
+
+Signing applications for long-term archiving send this to ask for revocation
+information that can be archived together with the time-stamp.
+
+Revocation information for the full TSU certificate chain is always embedded in
+the issued tokens, so the hint is acknowledged without further action.
+*/
+pub struct ArchiveRevInfoHint {
+    oid: String,
+}
+
+impl ArchiveRevInfoHint {
+    /// Return a new instance handling the extension with object identifier
+    /// `oid` in dot notation.
+    pub fn new(oid: String) -> Self {
+        Self { oid }
+    }
+}
+
+impl ExtensionHandler for ArchiveRevInfoHint {
+    fn oid(&self) -> &str {
+        &self.oid
+    }
+
+    fn validate(&self, _extension: &Extension) -> Result<(), String> {
+        Ok(())
+    }
+}
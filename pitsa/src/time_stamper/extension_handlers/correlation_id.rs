@@ -0,0 +1,76 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Client correlation identifier echo.
+
+use super::ExtensionHandler;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::Extension;
+
+/// Largest accepted correlation identifier.
+const MAX_CORRELATION_ID_OCTETS: usize = 128;
+
+/** Client correlation identifier echo.
+
+Clients can tag a request with an identifier of their own choice (`UTF8String`
+or `OCTET STRING`) that is returned unmodified as a non-critical `TSTInfo`
+extension. This allows correlation of tokens with client side transactions.
+*/
+pub struct CorrelationId {
+    oid: String,
+}
+
+impl CorrelationId {
+    /// Return a new instance handling the extension with object identifier
+    /// `oid` in dot notation.
+    pub fn new(oid: String) -> Self {
+        Self { oid }
+    }
+}
+
+impl ExtensionHandler for CorrelationId {
+    fn oid(&self) -> &str {
+        &self.oid
+    }
+
+    fn validate(&self, extension: &Extension) -> Result<(), String> {
+        let value = DerReader::new(&extension.value)
+            .read()
+            .map_err(|e| format!("Malformed correlation identifier: {e}"))?;
+        if value.tag != tag::UTF8_STRING && value.tag != tag::OCTET_STRING {
+            return Err("Correlation identifier must be a UTF8String or OCTET STRING.".to_string());
+        }
+        if value.encoded.len() != extension.value.len() {
+            return Err("Trailing data after correlation identifier.".to_string());
+        }
+        if value.content.len() > MAX_CORRELATION_ID_OCTETS {
+            return Err(format!(
+                "Correlation identifier is longer than {MAX_CORRELATION_ID_OCTETS} bytes."
+            ));
+        }
+        Ok(())
+    }
+
+    fn response_extensions(&self, extension: &Extension) -> Vec<Extension> {
+        vec![Extension {
+            oid: self.oid.to_owned(),
+            critical: false,
+            value: extension.value.to_vec(),
+        }]
+    }
+}
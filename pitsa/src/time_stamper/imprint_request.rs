@@ -0,0 +1,73 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamp request built from a message imprint.
+
+use crate::der;
+
+/** Time-stamp request built from a message imprint.
+
+Used by transports that don't receive a DER encoded `TimeStampReq` from the
+client, so the request can take the same path through the
+[TimeStamper](super::TimeStamper) as any other.
+*/
+pub struct ImprintRequest {
+    /// Message imprint digest algorithm object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Message imprint digest.
+    pub digest: Vec<u8>,
+    /// Requested TSA policy object identifier.
+    pub policy_oid: Option<Vec<u32>>,
+    /// Unsigned big-endian nonce.
+    pub nonce: Option<Vec<u8>>,
+    /// Request that the TSU certificate chain is included in the response.
+    pub cert_req: bool,
+}
+
+impl ImprintRequest {
+    /// Return a new instance without policy or nonce.
+    pub fn new(digest_algorithm_oid: &[u32], digest: &[u8], cert_req: bool) -> Self {
+        Self {
+            digest_algorithm_oid: digest_algorithm_oid.to_vec(),
+            digest: digest.to_vec(),
+            policy_oid: None,
+            nonce: None,
+            cert_req,
+        }
+    }
+
+    /// Return the request as a DER encoded `TimeStampReq`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut time_stamp_req = vec![
+            der::integer(1),
+            der::sequence(&[
+                der::sequence(&[der::oid(&self.digest_algorithm_oid), der::null()]),
+                der::octet_string(&self.digest),
+            ]),
+        ];
+        if let Some(policy_oid) = &self.policy_oid {
+            time_stamp_req.push(der::oid(policy_oid));
+        }
+        if let Some(nonce) = &self.nonce {
+            time_stamp_req.push(der::unsigned_integer(nonce));
+        }
+        if self.cert_req {
+            time_stamp_req.push(der::boolean(true));
+        }
+        der::sequence(&time_stamp_req)
+    }
+}
@@ -0,0 +1,687 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Time-stamps in JSON Web Signature (JWS) format.
+
+use super::time_stamp_claims::TimeStampClaims;
+use super::time_stamp_claims::TimeStampFormatError;
+use super::token_verification::ChainStatus;
+use super::token_verification::RevocationStatus;
+use super::tst_signing_info::SigningKeySnapshot;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::OID_CE_BASIC_CONSTRAINTS;
+use crate::der::x509::OID_CE_EXT_KEY_USAGE;
+use crate::der::x509::OID_CE_KEY_USAGE;
+use crate::der::x509::OID_KP_TIME_STAMPING;
+use crate::util::base64url_decode;
+use crate::util::base64url_encode;
+use serde::Deserialize;
+use serde::Serialize;
+use tyst::Tyst;
+use tyst::encdec::hex::ToHex;
+
+/// Media type of a JWS in compact serialization.
+pub const CONTENT_TYPE_JOSE: &str = "application/jose";
+/// Content type of the JWS payload.
+const PAYLOAD_CONTENT_TYPE: &str = "tst+json";
+/// Position of `keyCertSign` in the key usage `BIT STRING`.
+const KEY_USAGE_KEY_CERT_SIGN: usize = 5;
+
+/// JOSE `alg` of a signature algorithm object identifier and the size of an
+/// ECDSA signature component in bytes (zero for other algorithms).
+pub(super) const JWS_ALGORITHMS: &[(&[u32], &str, usize)] = &[
+    (&[1, 3, 101, 112], "EdDSA", 0),
+    (&[1, 2, 840, 10045, 4, 3, 2], "ES256", 32),
+    (&[1, 2, 840, 10045, 4, 3, 3], "ES384", 48),
+    (&[1, 2, 840, 10045, 4, 3, 4], "ES512", 66),
+    (&[1, 2, 840, 113549, 1, 1, 11], "RS256", 0),
+    (&[1, 2, 840, 113549, 1, 1, 12], "RS384", 0),
+    (&[1, 2, 840, 113549, 1, 1, 13], "RS512", 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 17], "ML-DSA-44", 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 18], "ML-DSA-65", 0),
+    (&[2, 16, 840, 1, 101, 3, 4, 3, 19], "ML-DSA-87", 0),
+];
+
+/// Protected JWS header.
+#[derive(Serialize, Deserialize)]
+struct JwsHeader {
+    /// JOSE signature algorithm.
+    alg: String,
+    /// Content type of the payload.
+    cty: String,
+    /// Base64 (not URL-safe) encoded DER certificate chain with the leaf
+    /// first.
+    x5c: Vec<String>,
+}
+
+/// Time-stamp claims carried as the JWS payload.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JwsTimeStampClaims {
+    /// TSA policy object identifier.
+    pub policy: String,
+    /// Message imprint digest algorithm object identifier.
+    pub hash_algorithm: String,
+    /// Base64url encoded message imprint digest.
+    pub hash_value: String,
+    /// Hex encoded time-stamp serial number.
+    pub serial_number: String,
+    /// RFC 3339 point in time of the time-stamp.
+    pub gen_time: String,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    pub gen_time_epoch_micros: u64,
+    /// Accuracy of the point in time in microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy_micros: Option<u64>,
+    /// Hex encoded nonce.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl From<&TimeStampClaims> for JwsTimeStampClaims {
+    fn from(claims: &TimeStampClaims) -> Self {
+        Self {
+            policy: tyst::encdec::oid::as_string(&claims.policy_oid),
+            hash_algorithm: tyst::encdec::oid::as_string(&claims.digest_algorithm_oid),
+            hash_value: base64url_encode(&claims.digest),
+            serial_number: claims.serial_number.to_hex(),
+            gen_time: der::rfc3339(claims.gen_time_epoch_micros),
+            gen_time_epoch_micros: claims.gen_time_epoch_micros,
+            accuracy_micros: claims.accuracy_micros,
+            nonce: claims.nonce.as_ref().map(|nonce| nonce.to_hex()),
+        }
+    }
+}
+
+impl JwsTimeStampClaims {
+    /// Return the object identifier of the message imprint digest algorithm.
+    pub fn digest_algorithm_oid(&self) -> Result<Vec<u32>, String> {
+        der::oid_from_string(&self.hash_algorithm)
+            .map_err(|e| format!("Invalid hashAlgorithm: {e}"))
+    }
+
+    /// Return the message imprint digest.
+    pub fn digest(&self) -> Result<Vec<u8>, String> {
+        base64url_decode(&self.hash_value)
+    }
+}
+
+/** Time-stamp in
+[RFC 7515](https://www.rfc-editor.org/rfc/rfc7515) JWS compact serialization.
+
+The payload holds the same claims as the `TSTInfo` of an RFC 3161 token and
+the protected header holds the TSU certificate chain in `x5c`:
+
+```text
+   BASE64URL(UTF8(JWS Protected Header)) || '.' ||
+   BASE64URL(JWS Payload) || '.' ||
+   BASE64URL(JWS Signature)
+```
+
+ECDSA signatures use the fixed size `R || S` encoding of
+[RFC 7518 3.4](https://www.rfc-editor.org/rfc/rfc7518#section-3.4).
+*/
+pub struct JwsTimeStamp {
+    /// Time-stamp claims.
+    pub claims: JwsTimeStampClaims,
+    /// DER encoded signer certificate chain with the leaf first.
+    pub certificate_chain: Vec<Vec<u8>>,
+    signing_algorithm_oid: Vec<u32>,
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+impl JwsTimeStamp {
+    /// Sign the claims with the TSU key and return the compact serialization.
+    pub fn sign(
+        claims: &JwsTimeStampClaims,
+        signing_key: &SigningKeySnapshot,
+    ) -> Result<String, TimeStampFormatError> {
+        let (_oid, alg, ecdsa_size) = JWS_ALGORITHMS
+            .iter()
+            .find(|(oid, _alg, _size)| signing_key.signing_algorithm_oid.eq(oid))
+            .copied()
+            .ok_or(TimeStampFormatError::Unavailable(format!(
+                "Signature algorithm {} has no JWS equivalent.",
+                tyst::encdec::oid::as_string(&signing_key.signing_algorithm_oid)
+            )))?;
+        let header = JwsHeader {
+            alg: alg.to_string(),
+            cty: PAYLOAD_CONTENT_TYPE.to_string(),
+            x5c: signing_key
+                .certificate_chain
+                .iter()
+                .map(|encoded| tyst::encdec::base64::encode(encoded))
+                .collect(),
+        };
+        let signing_input = serde_json::to_vec(&header)
+            .and_then(|header| Ok((header, serde_json::to_vec(claims)?)))
+            .map(|(header, payload)| {
+                format!(
+                    "{}.{}",
+                    base64url_encode(&header),
+                    base64url_encode(&payload)
+                )
+            })
+            .map_err(|e| TimeStampFormatError::Unavailable(e.to_string()))?;
+        let signature =
+            signing_key
+                .sign(signing_input.as_bytes())
+                .ok_or(TimeStampFormatError::Unavailable(
+                    "Failed to sign time-stamp.".to_string(),
+                ))?;
+        let signature = if ecdsa_size > 0 {
+            der::ecdsa_sig_value_to_fixed(&signature, ecdsa_size)
+                .map_err(|e| TimeStampFormatError::Unavailable(e.to_string()))?
+        } else {
+            signature
+        };
+        Ok(format!("{signing_input}.{}", base64url_encode(&signature)))
+    }
+
+    /// Parse a JWS time-stamp in compact serialization without verifying it.
+    pub fn from_compact(compact: &str) -> Result<Self, String> {
+        let mut parts = compact.trim().split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err("JWS must have three parts.".to_string());
+        };
+        let jws_header = serde_json::from_slice::<JwsHeader>(&base64url_decode(header)?)
+            .map_err(|e| format!("Malformed JWS header: {e}"))?;
+        if jws_header.cty != PAYLOAD_CONTENT_TYPE {
+            return Err(format!("Unexpected JWS cty '{}'.", jws_header.cty));
+        }
+        let claims = serde_json::from_slice::<JwsTimeStampClaims>(&base64url_decode(payload)?)
+            .map_err(|e| format!("Malformed JWS payload: {e}"))?;
+        let certificate_chain = jws_header
+            .x5c
+            .iter()
+            .map(|encoded| {
+                tyst::encdec::base64::decode(encoded).map_err(|e| format!("Malformed x5c: {e:?}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (signing_algorithm_oid, _alg, ecdsa_size) = JWS_ALGORITHMS
+            .iter()
+            .find(|(_oid, alg, _size)| jws_header.alg.eq(*alg))
+            .copied()
+            .ok_or(format!("Unsupported JWS alg '{}'.", jws_header.alg))?;
+        let signature = base64url_decode(signature)?;
+        let signature = if ecdsa_size > 0 {
+            der::ecdsa_sig_value_from_fixed(&signature, ecdsa_size)
+                .ok_or("Malformed ECDSA signature.".to_string())?
+        } else {
+            signature
+        };
+        Ok(Self {
+            claims,
+            certificate_chain,
+            signing_algorithm_oid: signing_algorithm_oid.to_vec(),
+            signing_input: format!("{header}.{payload}"),
+            signature,
+        })
+    }
+
+    /// Verify the JWS signature with the public key of the leaf certificate
+    /// and that the certificate chain leads to one of the trust anchors and
+    /// was valid at the point in time of the time-stamp.
+    pub fn verify_chain(&self, trust_anchors: &[Vec<u8>]) -> Result<(), String> {
+        let leaf = self
+            .certificate_chain
+            .first()
+            .ok_or("The JWS x5c header is empty.".to_string())?;
+        if !verify_signature(
+            &self.signing_algorithm_oid,
+            leaf,
+            &self.signature,
+            self.signing_input.as_bytes(),
+        )? {
+            return Err("JWS signature is invalid.".to_string());
+        }
+        verify_certificate_chain(
+            &self.certificate_chain,
+            trust_anchors,
+            self.claims.gen_time_epoch_micros,
+            verify_signature,
+        )
+    }
+}
+
+/// Outcome of JWS time-stamp verification.
+pub struct JwsTimeStampVerification {
+    /// The parsed time-stamp.
+    pub time_stamp: JwsTimeStamp,
+    /// Status of the signature and signer's certificate chain.
+    pub chain_status: ChainStatus,
+    /// Revocation status of the signer's certificate chain.
+    pub revocation_status: RevocationStatus,
+    /// Whether the message imprint matches the expected one, if any.
+    pub imprint_matches: Option<bool>,
+}
+
+impl JwsTimeStampVerification {
+    /// Return the reason why the time-stamp can't be trusted or `None` if it
+    /// can.
+    pub fn failure(&self) -> Option<String> {
+        match &self.chain_status {
+            ChainStatus::Valid => {}
+            ChainStatus::Invalid(msg) => return Some(msg.to_owned()),
+            ChainStatus::NoTrustAnchors => {
+                return Some("No trust anchors are available.".to_string());
+            }
+        }
+        if let RevocationStatus::Revoked(msg) = &self.revocation_status {
+            return Some(msg.to_owned());
+        }
+        if self.imprint_matches == Some(false) {
+            return Some("Time-stamp message imprint does not match.".to_string());
+        }
+        None
+    }
+}
+
+/** Verify that a DER encoded certificate chain with the leaf first leads to
+one of the trust anchors and was valid at a point in time.
+
+[RFC 3161 2.3](https://www.rfc-editor.org/rfc/rfc3161#section-2.3):
+
+```text
+   The corresponding certificate MUST contain only one instance of the
+   extended key usage field extension as defined in [RFC2459] Section
+   4.2.1.13 with KeyPurposeID having value:
+
+   id-kp-timeStamping.  This extension MUST be critical.
+```
+
+Every issuer must name the subject of the certificate it issued and be a CA
+that may sign certificates
+([RFC 5280 4.2.1.3](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.3)
+and [4.2.1.9](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.9)).
+`verify_signature` is called with the signature algorithm, the issuer
+certificate, the signature and the signed data.
+*/
+fn verify_certificate_chain(
+    certificate_chain: &[Vec<u8>],
+    trust_anchors: &[Vec<u8>],
+    point_in_time_epoch_micros: u64,
+    verify_signature: impl Fn(&[u32], &[u8], &[u8], &[u8]) -> Result<bool, String>,
+) -> Result<(), String> {
+    let leaf = certificate_chain
+        .first()
+        .ok_or("The certificate chain is empty.".to_string())?;
+    let leaf_extensions =
+        der::x509::certificate_extensions(leaf).map_err(|e| format!("Malformed x5c: {e}"))?;
+    let time_stamping_only = leaf_extensions
+        .iter()
+        .filter(|extension| extension.has_oid(OID_CE_EXT_KEY_USAGE))
+        .map(|extension| {
+            let mut key_purpose_ids =
+                DerReader::read_single(&extension.value, tag::SEQUENCE)?.reader();
+            let mut time_stamping_only = extension.critical;
+            let mut count = 0;
+            while !key_purpose_ids.is_empty() {
+                let key_purpose_id = key_purpose_ids.read_expected(tag::OID)?.as_oid()?;
+                time_stamping_only &= key_purpose_id == OID_KP_TIME_STAMPING;
+                count += 1;
+            }
+            Ok::<_, der::DerError>(time_stamping_only && count == 1)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Malformed x5c: {e}"))?;
+    if time_stamping_only != [true] {
+        return Err(
+            "The signer certificate must have a single critical id-kp-timeStamping extended key usage."
+                .to_string(),
+        );
+    }
+    for (index, certificate) in certificate_chain.iter().enumerate() {
+        let (not_before, not_after) = der::x509::validity_epoch_micros(certificate)
+            .map_err(|e| format!("Malformed x5c: {e}"))?;
+        if point_in_time_epoch_micros < not_before || point_in_time_epoch_micros > not_after {
+            return Err(format!(
+                "Certificate {index} of the chain was not valid at the time of the time-stamp."
+            ));
+        }
+        if trust_anchors.contains(certificate) {
+            return Ok(());
+        }
+        let issuer = certificate_chain
+            .get(index + 1)
+            .ok_or("The certificate chain does not lead to a trust anchor.".to_string())?;
+        let (issuer_name, _subject_name) = der::x509::issuer_and_subject(certificate)
+            .map_err(|e| format!("Malformed x5c: {e}"))?;
+        let (_issuer_issuer_name, issuer_subject_name) =
+            der::x509::issuer_and_subject(issuer).map_err(|e| format!("Malformed x5c: {e}"))?;
+        if issuer_name != issuer_subject_name {
+            return Err(format!(
+                "Certificate {index} of the chain is not issued by the next certificate."
+            ));
+        }
+        if !may_sign_certificates(issuer).map_err(|e| format!("Malformed x5c: {e}"))? {
+            return Err(format!(
+                "Certificate {} of the chain is not a CA allowed to sign certificates.",
+                index + 1
+            ));
+        }
+        let (tbs_certificate, signature_algorithm_oid, signature) =
+            der::x509::signed_parts(certificate).map_err(|e| format!("Malformed x5c: {e}"))?;
+        if !verify_signature(&signature_algorithm_oid, issuer, signature, tbs_certificate)? {
+            return Err(format!(
+                "Certificate {index} of the chain has an invalid signature."
+            ));
+        }
+    }
+    Err("The certificate chain does not lead to a trust anchor.".to_string())
+}
+
+/// Return `true` if a DER encoded certificate has the basic constraints `cA`
+/// flag and the `keyCertSign` key usage.
+fn may_sign_certificates(encoded_certificate: &[u8]) -> Result<bool, der::DerError> {
+    let mut ca = false;
+    let mut key_cert_sign = false;
+    for extension in der::x509::certificate_extensions(encoded_certificate)? {
+        if extension.has_oid(OID_CE_BASIC_CONSTRAINTS) {
+            // BasicConstraints ::= SEQUENCE { cA BOOLEAN DEFAULT FALSE, ... }
+            ca = DerReader::read_single(&extension.value, tag::SEQUENCE)?
+                .reader()
+                .read_optional(tag::BOOLEAN)?
+                .map(|ca| ca.as_bool())
+                .transpose()?
+                .unwrap_or(false);
+        } else if extension.has_oid(OID_CE_KEY_USAGE) {
+            key_cert_sign = DerReader::read_single(&extension.value, tag::BIT_STRING)?
+                .is_bit_set(KEY_USAGE_KEY_CERT_SIGN)?;
+        }
+    }
+    Ok(ca && key_cert_sign)
+}
+
+/// Verify `signature` over `data` with the public key of a DER encoded
+/// certificate.
+pub(super) fn verify_signature(
+    signing_algorithm_oid: &[u32],
+    encoded_certificate: &[u8],
+    signature: &[u8],
+    data: &[u8],
+) -> Result<bool, String> {
+    let subject_public_key_info = der::x509::subject_public_key_info(encoded_certificate)
+        .map_err(|e| format!("Malformed certificate: {e}"))?;
+    verify_signature_with_public_key(
+        signing_algorithm_oid,
+        &subject_public_key_info,
+        signature,
+        data,
+    )
+}
+
+/// Verify `signature` over `data` with a DER encoded `SubjectPublicKeyInfo`.
+pub(super) fn verify_signature_with_public_key(
+    signing_algorithm_oid: &[u32],
+    subject_public_key_info: &[u8],
+    signature: &[u8],
+    data: &[u8],
+) -> Result<bool, String> {
+    let mut se = Tyst::instance()
+        .ses()
+        .by_oid(&tyst::encdec::oid::as_string(signing_algorithm_oid))
+        .ok_or(format!(
+            "Unsupported signature algorithm {}.",
+            tyst::encdec::oid::as_string(signing_algorithm_oid)
+        ))?;
+    let public_key = se
+        .public_key_from_der(subject_public_key_info)
+        .ok_or("Unable to decode public key.".to_string())?;
+    Ok(se.verify(public_key.as_ref(), signature, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Point in time inside the validity of the test certificates.
+    const POINT_IN_TIME_EPOCH_MICROS: u64 = 1_750_000_000_000_000;
+    /// Start of the validity of the test certificates.
+    const NOT_BEFORE_EPOCH_SECONDS: u64 = 1_700_000_000;
+    /// End of the validity of the test certificates.
+    const NOT_AFTER_EPOCH_SECONDS: u64 = 1_800_000_000;
+    /// ECDSA with SHA-256 object identifier.
+    const OID_ECDSA_WITH_SHA256: &[u32] = &[1, 2, 840, 10045, 4, 3, 2];
+
+    fn name(common_name: &str) -> Vec<u8> {
+        der::sequence(&[der::set_of(&[der::sequence(&[
+            der::oid(&[2, 5, 4, 3]),
+            der::utf8_string(common_name),
+        ])])])
+    }
+
+    fn extension(oid: &[u32], critical: bool, value: &[u8]) -> Vec<u8> {
+        if critical {
+            der::sequence(&[der::oid(oid), der::boolean(true), der::octet_string(value)])
+        } else {
+            der::sequence(&[der::oid(oid), der::octet_string(value)])
+        }
+    }
+
+    fn ext_key_usage(critical: bool, key_purpose_ids: &[&[u32]]) -> Vec<u8> {
+        let key_purpose_ids = key_purpose_ids
+            .iter()
+            .map(|key_purpose_id| der::oid(key_purpose_id))
+            .collect::<Vec<_>>();
+        extension(
+            OID_CE_EXT_KEY_USAGE,
+            critical,
+            &der::sequence(&key_purpose_ids),
+        )
+    }
+
+    fn basic_constraints(ca: bool) -> Vec<u8> {
+        let value = if ca {
+            der::sequence(&[der::boolean(true)])
+        } else {
+            der::sequence(&[])
+        };
+        extension(OID_CE_BASIC_CONSTRAINTS, true, &value)
+    }
+
+    fn key_usage(bits: &[usize]) -> Vec<u8> {
+        extension(OID_CE_KEY_USAGE, true, &der::named_bit_string(bits))
+    }
+
+    /// Minimal certificate with a dummy key and signature.
+    fn certificate(
+        issuer: &str,
+        subject: &str,
+        not_after_epoch_seconds: u64,
+        extensions: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let tbs_certificate = der::sequence(&[
+            der::explicit(0, &der::integer(2)),
+            der::integer(1),
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            name(issuer),
+            der::sequence(&[
+                der::time(NOT_BEFORE_EPOCH_SECONDS),
+                der::time(not_after_epoch_seconds),
+            ]),
+            name(subject),
+            der::sequence(&[
+                der::algorithm_identifier(&[1, 2, 840, 10045, 2, 1]),
+                der::bit_string(&[4, 1, 2]),
+            ]),
+            der::explicit(3, &der::sequence(extensions)),
+        ]);
+        der::sequence(&[
+            tbs_certificate,
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            der::bit_string(subject.as_bytes()),
+        ])
+    }
+
+    fn root() -> Vec<u8> {
+        certificate(
+            "Root",
+            "Root",
+            NOT_AFTER_EPOCH_SECONDS,
+            &[
+                basic_constraints(true),
+                key_usage(&[KEY_USAGE_KEY_CERT_SIGN]),
+            ],
+        )
+    }
+
+    fn leaf(extensions: &[Vec<u8>]) -> Vec<u8> {
+        certificate("Root", "TSU", NOT_AFTER_EPOCH_SECONDS, extensions)
+    }
+
+    fn time_stamping_leaf() -> Vec<u8> {
+        leaf(&[ext_key_usage(true, &[OID_KP_TIME_STAMPING])])
+    }
+
+    fn verify(certificate_chain: &[Vec<u8>]) -> Result<(), String> {
+        verify_certificate_chain(
+            certificate_chain,
+            &[root()],
+            POINT_IN_TIME_EPOCH_MICROS,
+            |_, _, _, _| Ok(true),
+        )
+    }
+
+    #[test]
+    fn chain_to_trust_anchor_is_accepted() {
+        assert_eq!(verify(&[time_stamping_leaf(), root()]), Ok(()));
+    }
+
+    #[test]
+    fn signatures_are_verified_with_the_issuer() {
+        let root = root();
+        let result = verify_certificate_chain(
+            &[time_stamping_leaf(), root.clone()],
+            std::slice::from_ref(&root),
+            POINT_IN_TIME_EPOCH_MICROS,
+            |signature_algorithm_oid, issuer, signature, _data| {
+                assert_eq!(signature_algorithm_oid, OID_ECDSA_WITH_SHA256);
+                assert_eq!(issuer, root.as_slice());
+                Ok(signature == b"TSU")
+            },
+        );
+        assert_eq!(result, Ok(()));
+        let result = verify_certificate_chain(
+            &[time_stamping_leaf(), root.clone()],
+            std::slice::from_ref(&root),
+            POINT_IN_TIME_EPOCH_MICROS,
+            |_, _, _, _| Ok(false),
+        );
+        assert!(result.unwrap_err().contains("invalid signature"));
+    }
+
+    #[test]
+    fn leaf_requires_single_critical_time_stamping_eku() {
+        for extensions in [
+            vec![],
+            vec![ext_key_usage(false, &[OID_KP_TIME_STAMPING])],
+            vec![ext_key_usage(
+                true,
+                &[OID_KP_TIME_STAMPING, &[1, 3, 6, 1, 5, 5, 7, 3, 1]],
+            )],
+            vec![
+                ext_key_usage(true, &[OID_KP_TIME_STAMPING]),
+                ext_key_usage(true, &[OID_KP_TIME_STAMPING]),
+            ],
+        ] {
+            let result = verify(&[leaf(&extensions), root()]);
+            assert!(result.unwrap_err().contains("id-kp-timeStamping"));
+        }
+    }
+
+    #[test]
+    fn issuer_must_be_allowed_to_sign_certificates() {
+        let trust_anchor = root();
+        for issuer_extensions in [
+            vec![key_usage(&[KEY_USAGE_KEY_CERT_SIGN])],
+            vec![
+                basic_constraints(false),
+                key_usage(&[KEY_USAGE_KEY_CERT_SIGN]),
+            ],
+            vec![basic_constraints(true), key_usage(&[0])],
+            vec![basic_constraints(true)],
+        ] {
+            let issuer = certificate(
+                "Root",
+                "Intermediate",
+                NOT_AFTER_EPOCH_SECONDS,
+                &issuer_extensions,
+            );
+            let leaf = certificate(
+                "Intermediate",
+                "TSU",
+                NOT_AFTER_EPOCH_SECONDS,
+                &[ext_key_usage(true, &[OID_KP_TIME_STAMPING])],
+            );
+            let result = verify_certificate_chain(
+                &[leaf, issuer, trust_anchor.clone()],
+                std::slice::from_ref(&trust_anchor),
+                POINT_IN_TIME_EPOCH_MICROS,
+                |_, _, _, _| Ok(true),
+            );
+            assert!(result.unwrap_err().contains("not a CA"));
+        }
+    }
+
+    #[test]
+    fn issuer_must_name_the_next_certificate() {
+        let leaf = certificate(
+            "Other",
+            "TSU",
+            NOT_AFTER_EPOCH_SECONDS,
+            &[ext_key_usage(true, &[OID_KP_TIME_STAMPING])],
+        );
+        let result = verify(&[leaf, root()]);
+        assert!(result.unwrap_err().contains("not issued by"));
+    }
+
+    #[test]
+    fn expired_certificate_is_rejected() {
+        let leaf = certificate(
+            "Root",
+            "TSU",
+            NOT_BEFORE_EPOCH_SECONDS + 1,
+            &[ext_key_usage(true, &[OID_KP_TIME_STAMPING])],
+        );
+        let result = verify(&[leaf, root()]);
+        assert!(result.unwrap_err().contains("not valid at the time"));
+    }
+
+    #[test]
+    fn chain_must_lead_to_trust_anchor() {
+        let result = verify(&[time_stamping_leaf()]);
+        assert!(result.unwrap_err().contains("trust anchor"));
+        let other_root = certificate(
+            "Root",
+            "Root",
+            NOT_AFTER_EPOCH_SECONDS - 1,
+            &[
+                basic_constraints(true),
+                key_usage(&[KEY_USAGE_KEY_CERT_SIGN]),
+            ],
+        );
+        let result = verify(&[time_stamping_leaf(), other_root]);
+        assert!(result.unwrap_err().contains("trust anchor"));
+        assert!(verify(&[]).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Merkle hash trees.
+
+use crate::util;
+
+/** Merkle hash tree as defined in
+[RFC 6962 2.1](https://www.rfc-editor.org/rfc/rfc6962#section-2.1).
+
+```text
+   The hash of a list with one entry (also known as a leaf hash) is:
+
+   MTH({d(0)}) = HASH(0x00 || d(0)).
+
+   For n > 1, let k be the largest power of two smaller than n (i.e.,
+   k < n <= 2k).  The Merkle Tree Hash of an n-element list D[n] is then
+   defined recursively as
+
+   MTH(D[n]) = HASH(0x01 || MTH(D[0:k]) || MTH(D[k:n])),
+```
+
+All functions here operate on already computed leaf hashes. Hashes of complete
+subtrees are cached in [MerkleLevels], so roots and proofs for any tree size
+can be produced without rehashing all leaves.
+*/
+pub struct MerkleTree {
+    digest_algorithm_oid: Vec<u32>,
+}
+
+/// Hashes of all complete subtrees of the leaves appended so far.
+#[derive(Default)]
+pub struct MerkleLevels {
+    /// `levels[l][i]` is the hash of the complete subtree of `2^l` leaves
+    /// starting at leaf `i * 2^l`.
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleLevels {
+    /// Return the number of appended leaves.
+    pub fn leaf_count(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+}
+
+impl MerkleTree {
+    /// Return a new instance or `None` if the digest algorithm is unknown.
+    pub fn new(digest_algorithm_oid: &[u32]) -> Option<Self> {
+        util::digest_size(digest_algorithm_oid).map(|_| Self {
+            digest_algorithm_oid: digest_algorithm_oid.to_vec(),
+        })
+    }
+
+    /// Return the object identifier of the digest algorithm used for the tree.
+    pub fn digest_algorithm_oid(&self) -> &[u32] {
+        &self.digest_algorithm_oid
+    }
+
+    /// Return the leaf hash `HASH(0x00 || data)`.
+    pub fn leaf_hash(&self, data: &[u8]) -> Vec<u8> {
+        self.hash(&[&[0x00], data].concat())
+    }
+
+    /// Return the cached complete subtrees of `leaf_hashes`.
+    pub fn levels(&self, leaf_hashes: Vec<Vec<u8>>) -> MerkleLevels {
+        let mut levels = MerkleLevels::default();
+        for leaf_hash in leaf_hashes {
+            self.append(&mut levels, leaf_hash);
+        }
+        levels
+    }
+
+    /// Append a leaf hash and cache each subtree that became complete.
+    pub fn append(&self, levels: &mut MerkleLevels, leaf_hash: Vec<u8>) {
+        let mut hash = leaf_hash;
+        let mut level = 0;
+        loop {
+            if levels.levels.len() == level {
+                levels.levels.push(vec![]);
+            }
+            levels.levels[level].push(hash);
+            let nodes = &levels.levels[level];
+            if nodes.len() % 2 != 0 {
+                break;
+            }
+            hash = self.node_hash(&nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            level += 1;
+        }
+    }
+
+    /// Return the Merkle Tree Hash of the first `tree_size` leaves or `None`
+    /// if out of range.
+    pub fn root(&self, levels: &MerkleLevels, tree_size: usize) -> Option<Vec<u8>> {
+        (tree_size <= levels.leaf_count()).then(|| self.subtree_hash(levels, 0, tree_size))
+    }
+
+    /// Return the audit path (`PATH(m, D[n])`) of the leaf at `index` in the
+    /// tree of the first `tree_size` leaves or `None` if out of range.
+    ///
+    /// See [RFC 6962 2.1.1](https://www.rfc-editor.org/rfc/rfc6962#section-2.1.1).
+    pub fn inclusion_proof(
+        &self,
+        levels: &MerkleLevels,
+        index: usize,
+        tree_size: usize,
+    ) -> Option<Vec<Vec<u8>>> {
+        if index >= tree_size || tree_size > levels.leaf_count() {
+            return None;
+        }
+        let mut path = vec![];
+        self.path(levels, index, 0, tree_size, &mut path);
+        Some(path)
+    }
+
+    /// Return the proof that the tree of the first `first` leaves is a prefix
+    /// of the tree of the first `second` leaves or `None` if out of range.
+    ///
+    /// ```text
+    ///    PROOF(m, D[n]) = SUBPROOF(m, D[n], true)
+    ///
+    ///    SUBPROOF(m, D[m], true) = {}
+    ///    SUBPROOF(m, D[m], false) = {MTH(D[m])}
+    ///
+    ///    m <= k: SUBPROOF(m, D[n], b) = SUBPROOF(m, D[0:k], b) : MTH(D[k:n])
+    ///    m > k:  SUBPROOF(m, D[n], b) = SUBPROOF(m - k, D[k:n], false) : MTH(D[0:k])
+    /// ```
+    ///
+    /// See [RFC 6962 2.1.2](https://www.rfc-editor.org/rfc/rfc6962#section-2.1.2).
+    pub fn consistency_proof(
+        &self,
+        levels: &MerkleLevels,
+        first: usize,
+        second: usize,
+    ) -> Option<Vec<Vec<u8>>> {
+        if first == 0 || first > second || second > levels.leaf_count() {
+            return None;
+        }
+        let mut proof = vec![];
+        self.subproof(levels, first, 0, second, true, &mut proof);
+        Some(proof)
+    }
+
+    /// Append `PATH(index, D[start:end])` to `path`.
+    fn path(
+        &self,
+        levels: &MerkleLevels,
+        index: usize,
+        start: usize,
+        end: usize,
+        path: &mut Vec<Vec<u8>>,
+    ) {
+        let n = end - start;
+        if n <= 1 {
+            return;
+        }
+        let k = Self::split_point(n);
+        if index < start + k {
+            self.path(levels, index, start, start + k, path);
+            path.push(self.subtree_hash(levels, start + k, end));
+        } else {
+            self.path(levels, index, start + k, end, path);
+            path.push(self.subtree_hash(levels, start, start + k));
+        }
+    }
+
+    /// Append `SUBPROOF(m, D[start:end], complete)` to `proof`.
+    fn subproof(
+        &self,
+        levels: &MerkleLevels,
+        m: usize,
+        start: usize,
+        end: usize,
+        complete: bool,
+        proof: &mut Vec<Vec<u8>>,
+    ) {
+        let n = end - start;
+        if m == n {
+            if !complete {
+                proof.push(self.subtree_hash(levels, start, end));
+            }
+            return;
+        }
+        let k = Self::split_point(n);
+        if m <= k {
+            self.subproof(levels, m, start, start + k, complete, proof);
+            proof.push(self.subtree_hash(levels, start + k, end));
+        } else {
+            self.subproof(levels, m - k, start + k, end, false, proof);
+            proof.push(self.subtree_hash(levels, start, start + k));
+        }
+    }
+
+    /// Return `MTH(D[start:end])` using the cached complete subtrees.
+    fn subtree_hash(&self, levels: &MerkleLevels, start: usize, end: usize) -> Vec<u8> {
+        let n = end - start;
+        if n == 0 {
+            return self.hash(&[]);
+        }
+        if n.is_power_of_two() && start % n == 0 {
+            let level = n.trailing_zeros() as usize;
+            return levels.levels[level][start / n].to_owned();
+        }
+        let k = Self::split_point(n);
+        self.node_hash(
+            &self.subtree_hash(levels, start, start + k),
+            &self.subtree_hash(levels, start + k, end),
+        )
+    }
+
+    /// Return the interior node hash `HASH(0x01 || left || right)`.
+    fn node_hash(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        self.hash(&[&[0x01], left, right].concat())
+    }
+
+    /// Return the largest power of two smaller than `n` (`n > 1`).
+    fn split_point(n: usize) -> usize {
+        let mut k = 1;
+        while k << 1 < n {
+            k <<= 1;
+        }
+        k
+    }
+
+    /// Return the hash of `data`.
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        util::digest(&self.digest_algorithm_oid, data).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::OID_SHA256;
+    use tyst::encdec::hex::ToHex;
+
+    /// Test vectors of the RFC 6962 reference implementation
+    /// (certificate-transparency `merkle_tree_test.cc`).
+    fn test_tree() -> (MerkleTree, MerkleLevels) {
+        let merkle_tree = MerkleTree::new(OID_SHA256).unwrap();
+        let leaves: [&[u8]; 8] = [
+            &[],
+            &[0x00],
+            &[0x10],
+            &[0x20, 0x21],
+            &[0x30, 0x31],
+            &[0x40, 0x41, 0x42, 0x43],
+            &[0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57],
+            &[
+                0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d,
+                0x6e, 0x6f,
+            ],
+        ];
+        let leaf_hashes = leaves
+            .iter()
+            .map(|leaf| merkle_tree.leaf_hash(leaf))
+            .collect();
+        let levels = merkle_tree.levels(leaf_hashes);
+        (merkle_tree, levels)
+    }
+
+    fn to_hex(hashes: Vec<Vec<u8>>) -> Vec<String> {
+        hashes.iter().map(|hash| hash.to_hex()).collect()
+    }
+
+    #[test]
+    fn roots_match_rfc6962_vectors() {
+        let (merkle_tree, levels) = test_tree();
+        let expected = [
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+        for (tree_size, expected) in (1..).zip(expected) {
+            assert_eq!(
+                merkle_tree.root(&levels, tree_size).unwrap().to_hex(),
+                expected
+            );
+        }
+        assert!(merkle_tree.root(&levels, 9).is_none());
+    }
+
+    #[test]
+    fn inclusion_proofs_match_rfc6962_vectors() {
+        let (merkle_tree, levels) = test_tree();
+        let vectors: [(usize, usize, &[&str]); 5] = [
+            (0, 1, &[]),
+            (
+                0,
+                8,
+                &[
+                    "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+                ],
+            ),
+            (
+                5,
+                8,
+                &[
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                    "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                    "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                ],
+            ),
+            (
+                2,
+                3,
+                &["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"],
+            ),
+            (
+                1,
+                5,
+                &[
+                    "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                ],
+            ),
+        ];
+        for (index, tree_size, expected) in vectors {
+            assert_eq!(
+                to_hex(
+                    merkle_tree
+                        .inclusion_proof(&levels, index, tree_size)
+                        .unwrap()
+                ),
+                expected
+            );
+        }
+        assert!(merkle_tree.inclusion_proof(&levels, 8, 8).is_none());
+        assert!(merkle_tree.inclusion_proof(&levels, 0, 9).is_none());
+    }
+
+    #[test]
+    fn consistency_proofs_match_rfc6962_vectors() {
+        let (merkle_tree, levels) = test_tree();
+        let vectors: [(usize, usize, &[&str]); 4] = [
+            (1, 1, &[]),
+            (
+                1,
+                8,
+                &[
+                    "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+                ],
+            ),
+            (
+                6,
+                8,
+                &[
+                    "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                    "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                    "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                ],
+            ),
+            (
+                2,
+                5,
+                &[
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                ],
+            ),
+        ];
+        for (first, second, expected) in vectors {
+            assert_eq!(
+                to_hex(
+                    merkle_tree
+                        .consistency_proof(&levels, first, second)
+                        .unwrap()
+                ),
+                expected
+            );
+        }
+        assert!(merkle_tree.consistency_proof(&levels, 0, 8).is_none());
+        assert!(merkle_tree.consistency_proof(&levels, 5, 4).is_none());
+        assert!(merkle_tree.consistency_proof(&levels, 8, 9).is_none());
+    }
+
+    #[test]
+    fn empty_tree_root_is_hash_of_empty_string() {
+        let merkle_tree = MerkleTree::new(OID_SHA256).unwrap();
+        assert_eq!(
+            merkle_tree
+                .root(&MerkleLevels::default(), 0)
+                .unwrap()
+                .to_hex(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
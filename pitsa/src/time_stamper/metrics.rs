@@ -0,0 +1,295 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Prometheus metrics of time-stamp request processing.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::TimeStampRespStatus;
+
+/// Upper bounds of the latency histogram buckets in seconds.
+const LATENCY_BUCKETS_SECONDS: &[f64] = &[
+    0.000_1, 0.000_25, 0.000_5, 0.001, 0.002_5, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+    5.0,
+];
+
+/// Phase of processing a time-stamp request.
+#[derive(Clone, Copy)]
+pub enum Phase {
+    /// Parsing of the request.
+    Parse,
+    /// Acquisition of the current time with accuracy.
+    Time,
+    /// Signing of the time-stamp token.
+    Sign,
+    /// Encoding of the response.
+    Encode,
+    /// Waiting for the aggregated time-stamp of a request, from submission to
+    /// the aggregation window until the response with the inclusion proof.
+    Aggregate,
+}
+
+impl Phase {
+    const ALL: [Self; 5] = [
+        Self::Parse,
+        Self::Time,
+        Self::Sign,
+        Self::Encode,
+        Self::Aggregate,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Time => "time",
+            Self::Sign => "sign",
+            Self::Encode => "encode",
+            Self::Aggregate => "aggregate",
+        }
+    }
+}
+
+/// A point in time measurement exposed as a Prometheus gauge.
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: Option<f64>,
+}
+
+impl Gauge {
+    /// Return a new instance. Gauges without a value are not exposed.
+    pub fn new(name: &'static str, help: &'static str, value: Option<f64>) -> Self {
+        Self { name, help, value }
+    }
+}
+
+/// Counters partitioned by a single label value.
+#[derive(Default)]
+struct LabeledCounter {
+    values: Mutex<BTreeMap<String, u64>>,
+}
+
+impl LabeledCounter {
+    fn increment(&self, label_value: &str) {
+        let mut values = self.values.lock().unwrap();
+        if let Some(value) = values.get_mut(label_value) {
+            *value += 1;
+        } else {
+            values.insert(label_value.to_string(), 1);
+        }
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str, label_name: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for (label_value, value) in self.values.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "{name}{{{label_name}=\"{}\"}} {value}",
+                escape_label_value(label_value)
+            );
+        }
+    }
+}
+
+/// Latency histogram with fixed buckets.
+struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: LATENCY_BUCKETS_SECONDS
+                .iter()
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        // Buckets are stored non-cumulative and summed up when rendered.
+        if let Some(index) = LATENCY_BUCKETS_SECONDS
+            .iter()
+            .position(|upper_bound| seconds <= *upper_bound)
+        {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(
+            u64::try_from(duration.as_micros()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    fn render(&self, out: &mut String, name: &str, label: &str) {
+        let mut cumulative = 0;
+        for (upper_bound, bucket) in LATENCY_BUCKETS_SECONDS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{label},le=\"{upper_bound}\"}} {cumulative}"
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{{label},le=\"+Inf\"}} {count}");
+        let _ = writeln!(
+            out,
+            "{name}_sum{{{label}}} {}",
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000f64
+        );
+        let _ = writeln!(out, "{name}_count{{{label}}} {count}");
+    }
+}
+
+/** Prometheus metrics of time-stamp request processing.
+
+Counters and latency histograms are updated while processing requests. Gauges
+are sampled from the time source and signer when the metrics are scraped.
+
+See [Prometheus text-based exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format).
+*/
+#[derive(Default)]
+pub struct Metrics {
+    responses_by_status: LabeledCounter,
+    responses_by_failure_info: LabeledCounter,
+    requests_by_digest_algorithm: LabeledCounter,
+    requests_by_policy: LabeledCounter,
+    requests_by_client: LabeledCounter,
+    phase_durations: [Histogram; 5],
+}
+
+impl Metrics {
+    /// Content type of the exposition format.
+    pub const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4; charset=utf-8";
+
+    /// Count a parsed request by the message imprint digest algorithm,
+    /// requested policy and client.
+    pub fn record_request(&self, digest_algorithm_oid: &str, policy_oid: &str, client: &str) {
+        self.requests_by_digest_algorithm
+            .increment(digest_algorithm_oid);
+        self.requests_by_policy.increment(policy_oid);
+        self.requests_by_client.increment(client);
+    }
+
+    /// Count a response by status and failure information. A response
+    /// without a status is counted as `unknown`.
+    pub fn record_response(&self, status: Option<&TimeStampRespStatus>) {
+        if let Some(status) = status {
+            self.responses_by_status.increment(status.status_name());
+            for failure_info in &status.failure_info {
+                self.responses_by_failure_info.increment(failure_info);
+            }
+        } else {
+            self.responses_by_status.increment("unknown");
+        }
+    }
+
+    /// Return `true` if a thread panicked while updating a counter.
+    pub fn is_poisoned(&self) -> bool {
+        [
+            &self.responses_by_status,
+            &self.responses_by_failure_info,
+            &self.requests_by_digest_algorithm,
+            &self.requests_by_policy,
+            &self.requests_by_client,
+        ]
+        .iter()
+        .any(|labeled_counter| labeled_counter.values.is_poisoned())
+    }
+
+    /// Add the duration of a processing phase to its latency histogram.
+    pub fn observe(&self, phase: Phase, duration: Duration) {
+        self.phase_durations[phase as usize].observe(duration);
+    }
+
+    /// Return all metrics in the Prometheus text-based exposition format.
+    pub fn render(&self, gauges: &[Gauge]) -> String {
+        let mut out = String::new();
+        self.responses_by_status.render(
+            &mut out,
+            "pitsa_tsp_responses_total",
+            "Time-stamp responses by PKIStatus.",
+            "status",
+        );
+        self.responses_by_failure_info.render(
+            &mut out,
+            "pitsa_tsp_failures_total",
+            "Time-stamp responses by PKIFailureInfo.",
+            "failure_info",
+        );
+        self.requests_by_digest_algorithm.render(
+            &mut out,
+            "pitsa_tsp_requests_by_digest_algorithm_total",
+            "Time-stamp requests by message imprint digest algorithm.",
+            "digest_algorithm",
+        );
+        self.requests_by_policy.render(
+            &mut out,
+            "pitsa_tsp_requests_by_policy_total",
+            "Time-stamp requests by requested policy.",
+            "policy",
+        );
+        self.requests_by_client.render(
+            &mut out,
+            "pitsa_tsp_requests_by_client_total",
+            "Time-stamp requests by authenticated client.",
+            "client",
+        );
+        let name = "pitsa_tsp_phase_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Duration of time-stamp request processing phases."
+        );
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for phase in Phase::ALL {
+            self.phase_durations[phase as usize].render(
+                &mut out,
+                name,
+                &format!("phase=\"{}\"", phase.name()),
+            );
+        }
+        for gauge in gauges {
+            if let Some(value) = gauge.value {
+                let _ = writeln!(out, "# HELP {} {}", gauge.name, gauge.help);
+                let _ = writeln!(out, "# TYPE {} gauge", gauge.name);
+                let _ = writeln!(out, "{} {value}", gauge.name);
+            }
+        }
+        out
+    }
+}
+
+/// Escape a label value for the exposition format.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,217 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Local parsing of time-stamp tokens.
+
+use super::revocation_evaluation::RevocationInfo;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::Extension;
+
+/// `id-ct-TSTInfo` from RFC 3161.
+const OID_CT_TST_INFO: &[u32] = &[1, 2, 840, 113549, 1, 9, 16, 1, 4];
+
+/** The fields of a time-stamp token that are of interest to clients.
+
+[RFC 3161 2.4.2](https://www.rfc-editor.org/rfc/rfc3161#section-2.4.2):
+
+```text
+   TSTInfo ::= SEQUENCE  {
+      version                      INTEGER  { v1(1) },
+      policy                       TSAPolicyId,
+      messageImprint               MessageImprint,
+      serialNumber                 INTEGER,
+      genTime                      GeneralizedTime,
+      accuracy                     Accuracy                 OPTIONAL,
+      ordering                     BOOLEAN             DEFAULT FALSE,
+      nonce                        INTEGER                  OPTIONAL,
+      tsa                          [0] GeneralName          OPTIONAL,
+      extensions                   [1] IMPLICIT Extensions   OPTIONAL  }
+
+   Accuracy ::= SEQUENCE {
+         seconds        INTEGER              OPTIONAL,
+         millis     [0] INTEGER  (1..999)    OPTIONAL,
+         micros     [1] INTEGER  (1..999)    OPTIONAL  }
+```
+
+This does not validate the signature. See
+[TimeStampResponseValidator](upkit_common::x509::tsp::validate::TimeStampResponseValidator).
+*/
+pub struct ParsedTimeStampToken {
+    /// TSA policy object identifier.
+    pub policy_oid: Vec<u32>,
+    /// Message imprint digest algorithm object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Message imprint digest.
+    pub digest: Vec<u8>,
+    /// Unsigned big-endian serial number.
+    pub serial_number: Vec<u8>,
+    /// Point in time in microseconds since the Unix epoch.
+    pub gen_time_epoch_micros: u64,
+    /// Accuracy in microseconds.
+    pub accuracy_micros: Option<u64>,
+    /// Ordering of time-stamps from this TSA is guaranteed by `genTime`.
+    pub ordering: bool,
+    /// Unsigned big-endian nonce.
+    pub nonce: Option<Vec<u8>>,
+    /// TSTInfo extensions.
+    pub extensions: Vec<Extension>,
+    /// DER encoded certificates embedded in the token.
+    pub certificates: Vec<Vec<u8>>,
+    /// Revocation information embedded in the token.
+    pub revocation_infos: Vec<RevocationInfo>,
+    /// DER encoded certificate matching the signer identifier if embedded.
+    pub signer_certificate: Option<Vec<u8>>,
+}
+
+impl ParsedTimeStampToken {
+    /// Parse a DER encoded `TimeStampToken` (`ContentInfo` with `SignedData`).
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut content_info = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        content_info.read_expected(tag::OID)?;
+        let mut signed_data = content_info
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::SEQUENCE)?
+            .reader();
+        signed_data.read_expected(tag::INTEGER)?;
+        signed_data.read_expected(tag::SET)?;
+        let mut encap_content_info = signed_data.read_expected(tag::SEQUENCE)?.reader();
+        if !encap_content_info
+            .read_expected(tag::OID)?
+            .as_oid()?
+            .eq(OID_CT_TST_INFO)
+        {
+            return Err(der::DerError::new("Content type is not TSTInfo."));
+        }
+        let tst_info = encap_content_info
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::OCTET_STRING)?
+            .content;
+        let mut certificates = vec![];
+        if let Some(encoded_certificates) =
+            signed_data.read_optional(tag::context_constructed(0))?
+        {
+            let mut encoded_certificates = encoded_certificates.reader();
+            while !encoded_certificates.is_empty() {
+                certificates.push(encoded_certificates.read()?.encoded.to_vec());
+            }
+        }
+        let revocation_infos = signed_data
+            .read_optional(tag::context_constructed(1))?
+            .map(|crls| RevocationInfo::from_revocation_info_choices(crls.content))
+            .transpose()?
+            .unwrap_or_default();
+        let mut signer_infos = signed_data.read_expected(tag::SET)?.reader();
+        let mut signer_info = signer_infos.read_expected(tag::SEQUENCE)?.reader();
+        signer_info.read_expected(tag::INTEGER)?;
+        let sid = signer_info.read()?;
+        let signer_certificate = certificates
+            .iter()
+            .find(|certificate| {
+                der::x509::issuer_and_serial_number(certificate)
+                    .is_ok_and(|issuer_and_serial_number| issuer_and_serial_number.eq(sid.encoded))
+            })
+            .cloned();
+        let mut ret = Self::parse_tst_info(tst_info)?;
+        ret.certificates = certificates;
+        ret.revocation_infos = revocation_infos;
+        ret.signer_certificate = signer_certificate;
+        Ok(ret)
+    }
+
+    /// Parse a DER encoded `TSTInfo`.
+    fn parse_tst_info(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut tst_info = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        tst_info.read_expected(tag::INTEGER)?;
+        let policy_oid = tst_info.read_expected(tag::OID)?.as_oid()?;
+        let mut message_imprint = tst_info.read_expected(tag::SEQUENCE)?.reader();
+        let digest_algorithm_oid = message_imprint
+            .read_expected(tag::SEQUENCE)?
+            .reader()
+            .read_expected(tag::OID)?
+            .as_oid()?;
+        let digest = message_imprint
+            .read_expected(tag::OCTET_STRING)?
+            .content
+            .to_vec();
+        let serial_number = tst_info
+            .read_expected(tag::INTEGER)?
+            .as_unsigned_integer_bytes()?
+            .to_vec();
+        let gen_time_epoch_micros = tst_info
+            .read_expected(tag::GENERALIZED_TIME)?
+            .as_epoch_micros()?;
+        let accuracy_micros = tst_info
+            .read_optional(tag::SEQUENCE)?
+            .map(|accuracy| Self::parse_accuracy_micros(accuracy.reader()))
+            .transpose()?;
+        let ordering = tst_info
+            .read_optional(tag::BOOLEAN)?
+            .map(|ordering| ordering.as_bool())
+            .transpose()?
+            .unwrap_or(false);
+        let nonce = tst_info
+            .read_optional(tag::INTEGER)?
+            .map(|nonce| nonce.as_unsigned_integer_bytes().map(<[u8]>::to_vec))
+            .transpose()?;
+        tst_info.read_optional(tag::context_constructed(0))?;
+        let extensions = tst_info
+            .read_optional(tag::context_constructed(1))?
+            .map(|extensions| der::x509::parse_extensions(extensions.content))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            policy_oid,
+            digest_algorithm_oid,
+            digest,
+            serial_number,
+            gen_time_epoch_micros,
+            accuracy_micros,
+            ordering,
+            nonce,
+            extensions,
+            certificates: vec![],
+            revocation_infos: vec![],
+            signer_certificate: None,
+        })
+    }
+
+    /// Return the `Accuracy` in microseconds.
+    fn parse_accuracy_micros(mut accuracy: DerReader) -> Result<u64, der::DerError> {
+        let mut ret = 0;
+        for (expected, factor) in [
+            (tag::INTEGER, 1_000_000),
+            (tag::context_primitive(0), 1_000),
+            (tag::context_primitive(1), 1),
+        ] {
+            if let Some(value) = accuracy.read_optional(expected)? {
+                let magnitude = value.as_unsigned_integer_bytes()?;
+                if magnitude.len() > 4 {
+                    return Err(der::DerError::new("Accuracy is too large."));
+                }
+                ret += magnitude
+                    .iter()
+                    .fold(0u64, |acc, b| (acc << 8) | u64::from(*b))
+                    * factor;
+            }
+        }
+        Ok(ret)
+    }
+}
@@ -0,0 +1,144 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Qualified electronic time-stamps.
+
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509::OID_CE_EXT_KEY_USAGE;
+use crate::der::x509::OID_CE_KEY_USAGE;
+use crate::der::x509::OID_KP_TIME_STAMPING;
+use crate::der::x509::extension_by_oid;
+
+/// `id-pe-qcStatements` from RFC 3739.
+pub const OID_PE_QC_STATEMENTS: &[u32] = &[1, 3, 6, 1, 5, 5, 7, 1, 3];
+/// `esi4-qtstStatement-1` from ETSI EN 319 422.
+const OID_ESI4_QTST_STATEMENT_1: &[u32] = &[0, 4, 0, 19422, 1, 1];
+/// `esi4-qcStatement-1` (`QcCompliance`) from ETSI EN 319 412-5.
+const OID_ESI4_QC_STATEMENT_1: &[u32] = &[0, 4, 0, 1862, 1, 1];
+/// `KeyUsage` named bit `digitalSignature`.
+const KEY_USAGE_DIGITAL_SIGNATURE: usize = 0;
+/// `KeyUsage` named bit `nonRepudiation` (a.k.a. `contentCommitment`).
+const KEY_USAGE_NON_REPUDIATION: usize = 1;
+
+/** Marking of time-stamp tokens as qualified electronic time-stamps.
+
+[ETSI EN 319 422 9.1](https://www.etsi.org/deliver/etsi_en/319400_319499/319422/01.01.01_60/en_319422v010101p.pdf):
+
+```text
+   A TSA issuing qualified electronic time-stamps ... shall include the
+   qcStatements extension with an esi4-qtstStatement-1 statement in the
+   TSTInfo extensions.
+```
+
+The mark is only justified when the TSU certificate itself has been issued for
+this purpose, so the signing certificate is checked before it is used.
+*/
+pub struct QualifiedTimeStamp;
+
+impl QualifiedTimeStamp {
+    /// Return the DER encoded `QCStatements` value of the `qcStatements`
+    /// TSTInfo extension.
+    ///
+    /// ```text
+    /// QCStatements ::= SEQUENCE OF QCStatement
+    /// QCStatement ::= SEQUENCE {
+    ///     statementId        QC-STATEMENT.&id({SupportedStatements}),
+    ///     statementInfo      QC-STATEMENT.&Type({SupportedStatements}{@statementId}) OPTIONAL }
+    /// ```
+    pub fn tst_info_extension_value() -> Vec<u8> {
+        der::sequence(&[der::sequence(&[der::oid(OID_ESI4_QTST_STATEMENT_1)])])
+    }
+
+    /// Ensure that the DER encoded TSU certificate is suitable for issuing
+    /// qualified electronic time-stamps.
+    ///
+    /// The certificate must have
+    ///
+    /// * a `qcStatements` extension with the `QcCompliance` statement,
+    /// * a `keyUsage` extension allowing `digitalSignature` or `nonRepudiation`
+    /// * a critical `extKeyUsage` extension with only `id-kp-timeStamping`
+    ///   (RFC 3161 2.3).
+    pub fn assert_signing_certificate(encoded_certificate: &[u8]) -> Result<(), String> {
+        let extensions = der::x509::certificate_extensions(encoded_certificate)
+            .map_err(|e| format!("Unable to parse TSU certificate: {e}"))?;
+        let qc_statements = extension_by_oid(&extensions, OID_PE_QC_STATEMENTS)
+            .ok_or("TSU certificate has no qcStatements extension.")?;
+        if !Self::statement_ids(&qc_statements.value)
+            .map_err(|e| format!("Malformed qcStatements extension: {e}"))?
+            .iter()
+            .any(|statement_id| statement_id.eq(OID_ESI4_QC_STATEMENT_1))
+        {
+            return Err(
+                "TSU certificate qcStatements lacks the QcCompliance statement.".to_string(),
+            );
+        }
+        let key_usage = extension_by_oid(&extensions, OID_CE_KEY_USAGE)
+            .ok_or("TSU certificate has no keyUsage extension.")?;
+        let key_usage = DerReader::read_single(&key_usage.value, tag::BIT_STRING)
+            .map_err(|e| format!("Malformed keyUsage extension: {e}"))?;
+        if !key_usage
+            .is_bit_set(KEY_USAGE_DIGITAL_SIGNATURE)
+            .unwrap_or(false)
+            && !key_usage
+                .is_bit_set(KEY_USAGE_NON_REPUDIATION)
+                .unwrap_or(false)
+        {
+            return Err(
+                "TSU certificate keyUsage allows neither digitalSignature nor nonRepudiation."
+                    .to_string(),
+            );
+        }
+        let ext_key_usage = extension_by_oid(&extensions, OID_CE_EXT_KEY_USAGE)
+            .filter(|extension| extension.critical)
+            .ok_or("TSU certificate has no critical extKeyUsage extension.")?;
+        let mut key_purposes = DerReader::read_single(&ext_key_usage.value, tag::SEQUENCE)
+            .map_err(|e| format!("Malformed extKeyUsage extension: {e}"))?
+            .reader();
+        let mut key_purpose_ids = vec![];
+        while !key_purposes.is_empty() {
+            key_purpose_ids.push(
+                key_purposes
+                    .read()
+                    .and_then(|key_purpose| key_purpose.as_oid())
+                    .map_err(|e| format!("Malformed extKeyUsage extension: {e}"))?,
+            );
+        }
+        if !key_purpose_ids.eq(&[OID_KP_TIME_STAMPING.to_vec()]) {
+            return Err(
+                "TSU certificate extKeyUsage must only contain id-kp-timeStamping.".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Return the `statementId` of every `QCStatement`.
+    fn statement_ids(qc_statements: &[u8]) -> Result<Vec<Vec<u32>>, der::DerError> {
+        let mut ret = vec![];
+        let mut qc_statements = DerReader::read_single(qc_statements, tag::SEQUENCE)?.reader();
+        while !qc_statements.is_empty() {
+            let statement_id = qc_statements
+                .read_expected(tag::SEQUENCE)?
+                .reader()
+                .read_expected(tag::OID)?
+                .as_oid()?;
+            ret.push(statement_id);
+        }
+        Ok(ret)
+    }
+}
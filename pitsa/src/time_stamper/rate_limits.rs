@@ -0,0 +1,343 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Token bucket request rate limits per client IP address, per
+//! authenticated client identity and globally.
+
+use super::client_authorization::ClientIdentity;
+use crate::der;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Largest number of tracked buckets per rate limit.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// Number of buckets considered when one must be evicted.
+const EVICTION_SAMPLE_SIZE: usize = 8;
+
+/// How a request over the rate limit is answered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateLimitMode {
+    /// HTTP `429 Too Many Requests` with a `Retry-After` header.
+    Http,
+    /// A `TimeStampResp` with status `waiting` and failure info
+    /// `systemFailure` for DER encoded TSP requests. Other requests are
+    /// answered as in [RateLimitMode::Http].
+    Tsp,
+}
+
+/// Requests per second and the largest burst of a token bucket.
+#[derive(Clone, Copy)]
+pub struct RateLimit {
+    /// Sustained requests per second.
+    pub rate: f64,
+    /// Bucket size.
+    pub burst: f64,
+}
+
+/// A bucket of tokens refilled at a fixed rate.
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Return a new full bucket.
+    fn new(rate_limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: rate_limit.burst,
+            updated: now,
+        }
+    }
+
+    /// Refill the bucket up to `now`.
+    fn refill(&mut self, rate_limit: &RateLimit, now: Instant) {
+        let elapsed_seconds = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed_seconds * rate_limit.rate).min(rate_limit.burst);
+        self.updated = now;
+    }
+
+    /// Return the seconds until a token is available if the bucket is empty.
+    fn check(&mut self, rate_limit: &RateLimit, now: Instant) -> Result<(), u64> {
+        self.refill(rate_limit, now);
+        if self.tokens >= 1.0 {
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / rate_limit.rate).ceil().max(1.0) as u64)
+        }
+    }
+
+    /// Take a token. A concurrent request may have taken the last one since
+    /// the [check](Self::check()), which is repaid by a later refill.
+    fn take(&mut self, rate_limit: &RateLimit, now: Instant) {
+        self.refill(rate_limit, now);
+        self.tokens -= 1.0;
+    }
+}
+
+/// Token buckets of a rate limit per key.
+struct KeyedBuckets<K> {
+    rate_limit: RateLimit,
+    buckets: Mutex<HashMap<K, TokenBucket>>,
+}
+
+impl<K: Clone + Eq + Hash> KeyedBuckets<K> {
+    fn new(rate_limit: RateLimit) -> Self {
+        Self {
+            rate_limit,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Return the seconds until a token is available if the bucket of `key`
+    /// is empty. An untracked key has a full bucket.
+    fn check(&self, key: &K, now: Instant) -> Result<(), u64> {
+        match self.buckets.lock().unwrap().get_mut(key) {
+            Some(bucket) => bucket.check(&self.rate_limit, now),
+            None => Ok(()),
+        }
+    }
+
+    /// Take a token from the bucket of `key`.
+    ///
+    /// When [MAX_TRACKED_BUCKETS] are tracked, the least recently used of a
+    /// few arbitrary buckets is evicted first. An evicted client starts over
+    /// with a full bucket.
+    fn take(&self, key: K, now: Instant) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_BUCKETS
+            && !buckets.contains_key(&key)
+            && let Some(evicted) = buckets
+                .iter()
+                .take(EVICTION_SAMPLE_SIZE)
+                .min_by_key(|(_key, bucket)| bucket.updated)
+                .map(|(key, _bucket)| key.clone())
+        {
+            buckets.remove(&evicted);
+        }
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(&self.rate_limit, now))
+            .take(&self.rate_limit, now)
+    }
+}
+
+/** Request rate limits.
+
+Each request takes a token from the bucket of its client IP address, the
+bucket of its authenticated client identity (if any) and the global bucket.
+A request is refused without taking any token when any bucket is empty.
+
+The client IP address is the peer address of the connection. Behind a reverse
+proxy or load balancer that is the address of the proxy, unless the proxy is
+trusted to report the client address in the `X-Forwarded-For` header.
+*/
+pub struct RateLimits {
+    mode: RateLimitMode,
+    trusted_proxies: Vec<IpAddr>,
+    global: Option<(RateLimit, Mutex<TokenBucket>)>,
+    per_ip_address: Option<KeyedBuckets<IpAddr>>,
+    per_client: Option<KeyedBuckets<String>>,
+}
+
+impl RateLimits {
+    /// Return a new instance. A `None` limit is unlimited.
+    pub fn new(
+        mode: RateLimitMode,
+        trusted_proxies: Vec<IpAddr>,
+        global: Option<RateLimit>,
+        per_ip_address: Option<RateLimit>,
+        per_client: Option<RateLimit>,
+    ) -> Self {
+        Self {
+            mode,
+            trusted_proxies,
+            global: global.map(|rate_limit| {
+                (
+                    rate_limit,
+                    Mutex::new(TokenBucket::new(&rate_limit, Instant::now())),
+                )
+            }),
+            per_ip_address: per_ip_address.map(KeyedBuckets::new),
+            per_client: per_client.map(KeyedBuckets::new),
+        }
+    }
+
+    /// How a request over the rate limit is answered.
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Return the client IP address of a connection from `peer_address`.
+    ///
+    /// When the peer is a trusted proxy, this is the last address in the
+    /// `X-Forwarded-For` header that isn't a trusted proxy.
+    pub fn client_ip_address(
+        &self,
+        peer_address: Option<IpAddr>,
+        forwarded_for: Option<&str>,
+    ) -> Option<IpAddr> {
+        let peer_address = peer_address?;
+        if !self.trusted_proxies.contains(&peer_address) {
+            return Some(peer_address);
+        }
+        let Some(forwarded_for) = forwarded_for else {
+            return Some(peer_address);
+        };
+        let mut client_address = peer_address;
+        for forwarded in forwarded_for.rsplit(',') {
+            let Ok(forwarded) = forwarded.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client_address = forwarded;
+            if !self.trusted_proxies.contains(&forwarded) {
+                break;
+            }
+        }
+        Some(client_address)
+    }
+
+    /// Admit a request or return the number of seconds the client should wait
+    /// before retrying.
+    pub fn admit(
+        &self,
+        ip_address: Option<IpAddr>,
+        client_identity: Option<&ClientIdentity>,
+    ) -> Result<(), u64> {
+        let now = Instant::now();
+        let per_ip_address = self.per_ip_address.as_ref().zip(ip_address);
+        let per_client = self
+            .per_client
+            .as_ref()
+            .zip(client_identity.map(ClientIdentity::to_string));
+        let retry_after_seconds = [
+            per_ip_address.and_then(|(buckets, key)| buckets.check(&key, now).err()),
+            per_client
+                .as_ref()
+                .and_then(|(buckets, key)| buckets.check(key, now).err()),
+            self.global.as_ref().and_then(|(rate_limit, bucket)| {
+                bucket.lock().unwrap().check(rate_limit, now).err()
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .max();
+        if let Some(retry_after_seconds) = retry_after_seconds {
+            return Err(retry_after_seconds);
+        }
+        if let Some((buckets, key)) = per_ip_address {
+            buckets.take(key, now);
+        }
+        if let Some((buckets, key)) = per_client {
+            buckets.take(key, now);
+        }
+        if let Some((rate_limit, bucket)) = &self.global {
+            bucket.lock().unwrap().take(rate_limit, now);
+        }
+        Ok(())
+    }
+
+    /** Return an encoded `TimeStampResp` asking the client to retry later.
+
+    ```text
+    TimeStampResp ::= SEQUENCE  {
+        status                  PKIStatusInfo,
+        timeStampToken          TimeStampToken     OPTIONAL  }
+
+    PKIStatusInfo ::= SEQUENCE {
+        status        PKIStatus,
+        statusString  PKIFreeText     OPTIONAL,
+        failInfo      PKIFailureInfo  OPTIONAL  }
+    ```
+    */
+    pub fn waiting_time_stamp_resp(retry_after_seconds: u64) -> Vec<u8> {
+        // PKIStatus waiting (3) and PKIFailureInfo systemFailure (25)
+        der::sequence(&[der::sequence(&[
+            der::integer(3),
+            der::sequence(&[der::utf8_string(&format!(
+                "Rate limit exceeded. Retry after {retry_after_seconds} seconds."
+            ))]),
+            der::named_bit_string(&[25]),
+        ])])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_PER_SECOND: RateLimit = RateLimit {
+        rate: 1.0,
+        burst: 1.0,
+    };
+
+    #[test]
+    fn refused_request_takes_no_token() {
+        let rate_limits = RateLimits::new(
+            RateLimitMode::Http,
+            vec![],
+            Some(ONE_PER_SECOND),
+            Some(RateLimit {
+                rate: 1.0,
+                burst: 2.0,
+            }),
+            None,
+        );
+        let ip_address: IpAddr = "192.0.2.1".parse().unwrap();
+        assert!(rate_limits.admit(Some(ip_address), None).is_ok());
+        // The global bucket is empty, so the per IP address token is kept
+        assert!(rate_limits.admit(Some(ip_address), None).is_err());
+        assert!(rate_limits.admit(Some(ip_address), None).is_err());
+        let per_ip_address = rate_limits.per_ip_address.as_ref().unwrap();
+        assert!(per_ip_address.check(&ip_address, Instant::now()).is_ok());
+    }
+
+    #[test]
+    fn tracked_buckets_are_capped() {
+        let buckets = KeyedBuckets::new(ONE_PER_SECOND);
+        let now = Instant::now();
+        for key in 0..MAX_TRACKED_BUCKETS + 10 {
+            buckets.take(key, now);
+        }
+        assert_eq!(buckets.buckets.lock().unwrap().len(), MAX_TRACKED_BUCKETS);
+        assert!(buckets.check(&(MAX_TRACKED_BUCKETS + 9), now).is_err());
+    }
+
+    #[test]
+    fn forwarded_for_is_only_honored_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let rate_limits = RateLimits::new(RateLimitMode::Http, vec![proxy], None, None, None);
+        let client: IpAddr = "192.0.2.1".parse().unwrap();
+        let forwarded_for = Some("198.51.100.7, 192.0.2.1, 10.0.0.1");
+        assert_eq!(
+            rate_limits.client_ip_address(Some(proxy), forwarded_for),
+            Some(client)
+        );
+        let untrusted: IpAddr = "203.0.113.9".parse().unwrap();
+        assert_eq!(
+            rate_limits.client_ip_address(Some(untrusted), forwarded_for),
+            Some(untrusted)
+        );
+        assert_eq!(
+            rate_limits.client_ip_address(Some(proxy), Some("garbage")),
+            Some(proxy)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Readiness conditions, self-tests and configuration warnings.
+
+use tyst::encdec::hex::ToHex;
+
+use super::time_keeper::TimeSourceStatus;
+use super::tst_signing_info::SignerStatus;
+use crate::conf::AppConfig;
+use crate::util;
+
+/// Worst accuracy allowed by ETSI EN 319 421 7.7.2.b.
+const ETSI_MAX_ACCURACY_MICROS: u64 = 1_000_000;
+
+/// Outcome of a built-in self-test.
+#[derive(Clone)]
+pub struct SelfTest {
+    /// Name of the test.
+    pub name: &'static str,
+    /// `Ok` if the test passed or the reason it failed.
+    pub result: Result<(), String>,
+}
+
+impl SelfTest {
+    /// Return the result of a SHA-256 known answer test (FIPS 180-2 B.1).
+    pub fn digest_known_answer() -> Self {
+        let result = util::digest(util::OID_SHA256, b"abc")
+            .ok_or("SHA-256 is not available.".to_string())
+            .and_then(|digest| {
+                let actual = digest.to_hex();
+                if actual == "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad" {
+                    Ok(())
+                } else {
+                    Err(format!("Unexpected SHA-256 digest {actual}."))
+                }
+            });
+        Self {
+            name: "digest",
+            result,
+        }
+    }
+}
+
+/// A condition that must be fulfilled for the instance to be ready.
+pub struct ReadinessCondition {
+    /// Name of the condition.
+    pub name: &'static str,
+    /// `true` if the condition is fulfilled.
+    pub ready: bool,
+    /// Human readable explanation.
+    pub message: String,
+}
+
+impl ReadinessCondition {
+    fn new(name: &'static str, ready: bool, message: String) -> Self {
+        Self {
+            name,
+            ready,
+            message,
+        }
+    }
+
+    /// Return all readiness conditions.
+    pub fn evaluate(
+        time_source_status: &TimeSourceStatus,
+        signer_status: Option<&SignerStatus>,
+        enrollment_error: Option<String>,
+        self_tests: &[SelfTest],
+        transparency_log_error: Option<Option<String>>,
+        now_epoch_micros: u64,
+    ) -> Vec<Self> {
+        let mut ret = vec![Self::new(
+            "timeSource",
+            time_source_status.within_tolerance,
+            match time_source_status.declared_accuracy_micros {
+                Some(accuracy_micros) if time_source_status.within_tolerance => format!(
+                    "Accuracy of {accuracy_micros} µs is within the tolerable {} µs.",
+                    time_source_status.tolerable_accuracy_micros
+                ),
+                _ => format!(
+                    "No time with the tolerable accuracy of {} µs is available.",
+                    time_source_status.tolerable_accuracy_micros
+                ),
+            },
+        )];
+        if let Some(signer_status) = signer_status {
+            let valid = signer_status.not_before_epoch_micros <= now_epoch_micros
+                && now_epoch_micros < signer_status.not_after_epoch_micros;
+            ret.push(Self::new(
+                "signingCertificate",
+                valid,
+                if valid {
+                    "The signing certificate is valid.".to_string()
+                } else {
+                    "The signing certificate is not valid at this time.".to_string()
+                },
+            ));
+            ret.push(Self::new(
+                "revocationInfo",
+                !signer_status.revocation_info_missing,
+                if signer_status.revocation_info_missing {
+                    "Revocation information is missing for the signing certificate chain."
+                        .to_string()
+                } else {
+                    "Revocation information is available for the signing certificate chain."
+                        .to_string()
+                },
+            ));
+        } else {
+            ret.push(Self::new(
+                "signingCertificate",
+                false,
+                enrollment_error.unwrap_or("No signing certificate is enrolled yet.".to_string()),
+            ));
+        }
+        let failed_self_tests = self_tests
+            .iter()
+            .filter(|self_test| self_test.result.is_err())
+            .map(|self_test| self_test.name)
+            .collect::<Vec<_>>();
+        ret.push(Self::new(
+            "selfTests",
+            failed_self_tests.is_empty(),
+            if failed_self_tests.is_empty() {
+                "All self-tests passed.".to_string()
+            } else {
+                format!("Failed self-tests: {}", failed_self_tests.join(", "))
+            },
+        ));
+        if let Some(transparency_log_error) = transparency_log_error {
+            ret.push(Self::new(
+                "transparencyLog",
+                transparency_log_error.is_none(),
+                transparency_log_error.unwrap_or(
+                    "Issued time-stamps are appended to the transparency log.".to_string(),
+                ),
+            ));
+        }
+        ret
+    }
+}
+
+/// Return warnings about configuration that works, but is probably not what
+/// a production deployment wants.
+pub fn configuration_warnings(app_config: &AppConfig) -> Vec<String> {
+    let mut ret = vec![];
+    if app_config.time.ntp_host().is_none() {
+        ret.push(
+            "No NTP host is configured. The local system time is trusted within its declared accuracy."
+                .to_string(),
+        );
+    }
+    let tolerable_accuracy_micros = app_config.time.tolerable_accuracy_micros();
+    if tolerable_accuracy_micros > ETSI_MAX_ACCURACY_MICROS {
+        ret.push(format!(
+            "The tolerable accuracy of {tolerable_accuracy_micros} µs is worse than the one second required by ETSI EN 319 421."
+        ));
+    }
+    if app_config.sign.allowed_digest_oids().is_empty() {
+        ret.push("Any known message imprint digest algorithm is accepted.".to_string());
+    }
+    let policy_oid = tyst::encdec::oid::as_string(&app_config.sign.policy_oid());
+    for qualified_policy_oid in app_config.sign.qualified_policy_oids() {
+        if qualified_policy_oid != policy_oid {
+            ret.push(format!(
+                "Qualified time-stamps are configured for '{qualified_policy_oid}', which is not a policy of this service."
+            ));
+        }
+    }
+    if app_config.api.tls_pem_paths().is_none() {
+        ret.push("The REST API is served without TLS.".to_string());
+    }
+    if app_config.api.client_rules_path().is_some() && app_config.api.tcp_bind_port().is_some() {
+        ret.push(
+            "The TCP transport does not authenticate clients, so the client rules refuse all its requests."
+                .to_string(),
+        );
+    }
+    ret
+}
@@ -0,0 +1,556 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Revocation status of a certificate chain at the point in time of a
+//! time-stamp.
+
+use super::token_verification::RevocationStatus;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use crate::der::x509;
+use crate::der::x509::OcspCertStatus;
+use crate::util;
+
+/// `id-ri-ocsp-response` from RFC 5940.
+const OID_RI_OCSP_RESPONSE: &[u32] = &[1, 3, 6, 1, 5, 5, 7, 16, 2];
+
+/// DER encoded revocation information.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RevocationInfo {
+    /// An X.509 `CertificateList`.
+    Crl(Vec<u8>),
+    /// An `OCSPResponse`.
+    OcspResponse(Vec<u8>),
+}
+
+impl RevocationInfo {
+    /** Parse the content of the `crls` field of a CMS `SignedData`.
+
+    [RFC 5652 10.2.1](https://www.rfc-editor.org/rfc/rfc5652#section-10.2.1)
+    and [RFC 5940 3](https://www.rfc-editor.org/rfc/rfc5940#section-3):
+
+    ```text
+      RevocationInfoChoice ::= CHOICE {
+        crl CertificateList,
+        other [1] IMPLICIT OtherRevocationInfoFormat }
+
+      OtherRevocationInfoFormat ::= SEQUENCE {
+        otherRevInfoFormat OBJECT IDENTIFIER,
+        otherRevInfo ANY DEFINED BY otherRevInfoFormat }
+    ```
+
+    Other formats than `id-ri-ocsp-response` are ignored.
+    */
+    pub fn from_revocation_info_choices(content: &[u8]) -> Result<Vec<Self>, der::DerError> {
+        let mut ret = vec![];
+        let mut revocation_info_choices = DerReader::new(content);
+        while !revocation_info_choices.is_empty() {
+            let revocation_info_choice = revocation_info_choices.read()?;
+            if revocation_info_choice.tag == tag::SEQUENCE {
+                ret.push(Self::Crl(revocation_info_choice.encoded.to_vec()));
+            } else if revocation_info_choice.tag == tag::context_constructed(1) {
+                let mut other = revocation_info_choice.reader();
+                if other.read_expected(tag::OID)?.as_oid()? == OID_RI_OCSP_RESPONSE {
+                    ret.push(Self::OcspResponse(
+                        other.read_expected(tag::SEQUENCE)?.encoded.to_vec(),
+                    ));
+                }
+            } else {
+                return Err(der::DerError::new("malformed RevocationInfoChoice"));
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Return the chain from the signer certificate over its issuers found among
+/// `certificates` and `trust_anchors` up to a trust anchor or a self-issued
+/// certificate.
+pub fn certificate_chain(
+    signer_certificate: &[u8],
+    certificates: &[Vec<u8>],
+    trust_anchors: &[Vec<u8>],
+) -> Vec<Vec<u8>> {
+    let mut ret = vec![signer_certificate.to_vec()];
+    while let Some(certificate) = ret.last() {
+        if trust_anchors.contains(certificate) {
+            break;
+        }
+        let Ok((issuer_name, subject_name)) = x509::issuer_and_subject(certificate) else {
+            break;
+        };
+        if issuer_name == subject_name {
+            break;
+        }
+        let issuer = certificates
+            .iter()
+            .chain(trust_anchors)
+            .filter(|candidate| !ret.contains(*candidate))
+            .find(|candidate| {
+                x509::issuer_and_subject(candidate)
+                    .is_ok_and(|(_, candidate_subject_name)| candidate_subject_name == issuer_name)
+            });
+        match issuer {
+            Some(issuer) => ret.push(issuer.to_owned()),
+            None => break,
+        }
+    }
+    ret
+}
+
+/** Return the revocation status of a certificate chain at a point in time.
+
+`certificate_chain` starts with the signer certificate followed by its issuers.
+Every certificate except the last (trust anchor) must be covered by a CRL
+signed by its issuer or an OCSP response signed by its issuer or a delegated
+responder ([RFC 6960 4.2.2.2](https://www.rfc-editor.org/rfc/rfc6960#section-4.2.2.2)).
+A certificate revoked after the point in time was still valid when the
+time-stamp was issued.
+
+`verify_signature` is called with the signature algorithm, the signer
+certificate, the signature and the signed data.
+*/
+pub fn evaluate(
+    certificate_chain: &[Vec<u8>],
+    revocation_infos: &[RevocationInfo],
+    point_in_time_epoch_micros: u64,
+    verify_signature: impl Fn(&[u32], &[u8], &[u8], &[u8]) -> Result<bool, String>,
+) -> RevocationStatus {
+    if revocation_infos.is_empty() {
+        return RevocationStatus::NotEmbedded;
+    }
+    let mut unknown = None;
+    for (index, pair) in certificate_chain.windows(2).enumerate() {
+        let (certificate, issuer) = (&pair[0], &pair[1]);
+        let statuses = revocation_infos
+            .iter()
+            .filter_map(|revocation_info| {
+                certificate_status(certificate, issuer, revocation_info, &verify_signature)
+                    .ok()
+                    .flatten()
+            })
+            .collect::<Vec<_>>();
+        if let Some(revocation_epoch_micros) = statuses
+            .iter()
+            .filter_map(|status| match status {
+                OcspCertStatus::Revoked(revocation_epoch_micros) => Some(*revocation_epoch_micros),
+                _ => None,
+            })
+            .filter(|revocation_epoch_micros| {
+                *revocation_epoch_micros <= point_in_time_epoch_micros
+            })
+            .min()
+        {
+            return RevocationStatus::Revoked(format!(
+                "Certificate {index} of the chain was revoked at {} before the time-stamp.",
+                der::rfc3339(revocation_epoch_micros)
+            ));
+        }
+        if unknown.is_none()
+            && !statuses
+                .iter()
+                .any(|status| status != &OcspCertStatus::Unknown)
+        {
+            unknown = Some(format!(
+                "No verifiable revocation information for certificate {index} of the chain."
+            ));
+        }
+    }
+    match unknown {
+        Some(msg) => RevocationStatus::Unknown(msg),
+        None => RevocationStatus::Good,
+    }
+}
+
+/// Return the status of a certificate according to revocation information
+/// or `None` if the information doesn't cover the certificate or isn't
+/// signed by a responsible party.
+fn certificate_status(
+    certificate: &[u8],
+    issuer: &[u8],
+    revocation_info: &RevocationInfo,
+    verify_signature: impl Fn(&[u32], &[u8], &[u8], &[u8]) -> Result<bool, String>,
+) -> Result<Option<OcspCertStatus>, String> {
+    let (issuer_name, _subject_name) =
+        x509::issuer_and_subject(certificate).map_err(|e| e.to_string())?;
+    let serial_number = x509::serial_number(certificate).map_err(|e| e.to_string())?;
+    match revocation_info {
+        RevocationInfo::Crl(encoded_crl) => {
+            let (crl_issuer_name, revoked_certificates) =
+                x509::crl_issuer_and_revoked_certificates(encoded_crl)
+                    .map_err(|e| e.to_string())?;
+            if crl_issuer_name != issuer_name {
+                return Ok(None);
+            }
+            let (tbs_cert_list, signature_algorithm_oid, signature) =
+                x509::signed_parts(encoded_crl).map_err(|e| e.to_string())?;
+            if !verify_signature(&signature_algorithm_oid, issuer, signature, tbs_cert_list)? {
+                return Ok(None);
+            }
+            Ok(Some(
+                revoked_certificates
+                    .iter()
+                    .find(|(revoked_serial_number, _)| revoked_serial_number == serial_number)
+                    .map_or(OcspCertStatus::Good, |(_, revocation_epoch_micros)| {
+                        OcspCertStatus::Revoked(*revocation_epoch_micros)
+                    }),
+            ))
+        }
+        RevocationInfo::OcspResponse(encoded_ocsp_response) => {
+            let basic_ocsp_response =
+                x509::basic_ocsp_response(encoded_ocsp_response).map_err(|e| e.to_string())?;
+            let issuer_key = x509::subject_public_key(issuer).map_err(|e| e.to_string())?;
+            let Some(single_response) = x509::basic_ocsp_single_responses(basic_ocsp_response)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|single_response| {
+                    single_response.serial_number == serial_number
+                        && util::digest(&single_response.hash_algorithm_oid, &issuer_name)
+                            .is_some_and(|digest| digest == single_response.issuer_name_hash)
+                        && util::digest(&single_response.hash_algorithm_oid, &issuer_key)
+                            .is_some_and(|digest| digest == single_response.issuer_key_hash)
+                })
+            else {
+                return Ok(None);
+            };
+            let (tbs_response_data, signature_algorithm_oid, signature) =
+                x509::signed_parts(basic_ocsp_response).map_err(|e| e.to_string())?;
+            let delegated_responders = x509::basic_ocsp_certificates(basic_ocsp_response)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|responder| is_delegated_responder(responder, issuer, &verify_signature));
+            let signed_by_responder = std::iter::once(issuer.to_vec())
+                .chain(delegated_responders)
+                .any(|responder| {
+                    verify_signature(
+                        &signature_algorithm_oid,
+                        &responder,
+                        signature,
+                        tbs_response_data,
+                    ) == Ok(true)
+                });
+            Ok(signed_by_responder.then_some(single_response.cert_status))
+        }
+    }
+}
+
+/// Return `true` if a certificate is issued and signed by `issuer` for
+/// signing OCSP responses on its behalf.
+fn is_delegated_responder(
+    responder: &[u8],
+    issuer: &[u8],
+    verify_signature: impl Fn(&[u32], &[u8], &[u8], &[u8]) -> Result<bool, String>,
+) -> bool {
+    let issued_by_issuer = x509::issuer_and_subject(responder)
+        .ok()
+        .zip(x509::issuer_and_subject(issuer).ok())
+        .is_some_and(|((responder_issuer_name, _), (_, issuer_subject_name))| {
+            responder_issuer_name == issuer_subject_name
+        });
+    let ocsp_signing = x509::ext_key_usages(responder).is_ok_and(|key_purpose_ids| {
+        key_purpose_ids
+            .iter()
+            .any(|key_purpose_id| key_purpose_id == x509::OID_KP_OCSP_SIGNING)
+    });
+    issued_by_issuer
+        && ocsp_signing
+        && x509::signed_parts(responder).is_ok_and(
+            |(tbs_certificate, signature_algorithm_oid, signature)| {
+                verify_signature(&signature_algorithm_oid, issuer, signature, tbs_certificate)
+                    == Ok(true)
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Point in time of the time-stamp.
+    const POINT_IN_TIME_EPOCH_MICROS: u64 = 1_750_000_000_000_000;
+    /// ECDSA with SHA-256 object identifier.
+    const OID_ECDSA_WITH_SHA256: &[u32] = &[1, 2, 840, 10045, 4, 3, 2];
+    /// SHA-256 object identifier.
+    const OID_SHA256: &[u32] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+
+    fn name(common_name: &str) -> Vec<u8> {
+        der::sequence(&[der::set_of(&[der::sequence(&[
+            der::oid(&[2, 5, 4, 3]),
+            der::utf8_string(common_name),
+        ])])])
+    }
+
+    /// Minimal certificate with a dummy key and the issuer name as signature.
+    fn certificate(issuer: &str, subject: &str, serial: u64, extensions: &[Vec<u8>]) -> Vec<u8> {
+        let tbs_certificate = der::sequence(&[
+            der::explicit(0, &der::integer(2)),
+            der::integer(serial),
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            name(issuer),
+            der::sequence(&[der::time(1_700_000_000), der::time(1_800_000_000)]),
+            name(subject),
+            der::sequence(&[
+                der::algorithm_identifier(&[1, 2, 840, 10045, 2, 1]),
+                der::bit_string(subject.as_bytes()),
+            ]),
+            der::explicit(3, &der::sequence(extensions)),
+        ]);
+        der::sequence(&[
+            tbs_certificate,
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            der::bit_string(issuer.as_bytes()),
+        ])
+    }
+
+    /// CRL from an issuer listing revoked serial numbers and revocation
+    /// dates in seconds since the Unix epoch.
+    fn crl(issuer: &str, signer: &str, revoked: &[(u64, u64)]) -> Vec<u8> {
+        let revoked_certificates = revoked
+            .iter()
+            .map(|(serial, revocation_epoch_seconds)| {
+                der::sequence(&[der::integer(*serial), der::time(*revocation_epoch_seconds)])
+            })
+            .collect::<Vec<_>>();
+        let mut tbs_cert_list = vec![
+            der::integer(1),
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            name(issuer),
+            der::time(1_740_000_000),
+            der::time(1_760_000_000),
+        ];
+        if !revoked_certificates.is_empty() {
+            tbs_cert_list.push(der::sequence(&revoked_certificates));
+        }
+        der::sequence(&[
+            der::sequence(&tbs_cert_list),
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            der::bit_string(signer.as_bytes()),
+        ])
+    }
+
+    /// Successful OCSP response for a certificate with the encoded status.
+    fn ocsp_response(
+        issuer: &str,
+        serial: u64,
+        cert_status: Vec<u8>,
+        signer: &str,
+        certs: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let cert_id = der::sequence(&[
+            der::algorithm_identifier(OID_SHA256),
+            der::octet_string(&util::digest(OID_SHA256, &name(issuer)).unwrap()),
+            der::octet_string(&util::digest(OID_SHA256, issuer.as_bytes()).unwrap()),
+            der::integer(serial),
+        ]);
+        let tbs_response_data = der::sequence(&[
+            der::explicit(1, &name(signer)),
+            der::generalized_time(1_740_000_000_000_000),
+            der::sequence(&[der::sequence(&[
+                cert_id,
+                cert_status,
+                der::generalized_time(1_740_000_000_000_000),
+            ])]),
+        ]);
+        let mut basic_ocsp_response = vec![
+            tbs_response_data,
+            der::algorithm_identifier(OID_ECDSA_WITH_SHA256),
+            der::bit_string(signer.as_bytes()),
+        ];
+        if !certs.is_empty() {
+            basic_ocsp_response.push(der::explicit(0, &der::sequence(certs)));
+        }
+        der::sequence(&[
+            der::tlv(tag::ENUMERATED, &[0]),
+            der::explicit(
+                0,
+                &der::sequence(&[
+                    der::oid(&[1, 3, 6, 1, 5, 5, 7, 48, 1, 1]),
+                    der::octet_string(&der::sequence(&basic_ocsp_response)),
+                ]),
+            ),
+        ])
+    }
+
+    fn revoked_status(revocation_epoch_seconds: u64) -> Vec<u8> {
+        der::implicit(
+            1,
+            &der::sequence(&[der::generalized_time(revocation_epoch_seconds * 1_000_000)]),
+        )
+    }
+
+    fn chain() -> Vec<Vec<u8>> {
+        vec![
+            certificate("CA", "TSU", 7, &[]),
+            certificate("Root", "CA", 2, &[]),
+            certificate("Root", "Root", 1, &[]),
+        ]
+    }
+
+    /// The dummy signature is the name of the signer, whose dummy key is its
+    /// name.
+    fn evaluate_chain(revocation_infos: &[RevocationInfo]) -> RevocationStatus {
+        evaluate(
+            &chain(),
+            revocation_infos,
+            POINT_IN_TIME_EPOCH_MICROS,
+            |signature_algorithm_oid, signer, signature, _data| {
+                assert_eq!(signature_algorithm_oid, OID_ECDSA_WITH_SHA256);
+                Ok(x509::subject_public_key(signer).unwrap() == signature)
+            },
+        )
+    }
+
+    #[test]
+    fn revocation_info_choices_are_parsed() {
+        let crl = crl("CA", "CA", &[]);
+        let ocsp_response = ocsp_response("CA", 7, der::implicit(0, &der::null()), "CA", &[]);
+        let content = [
+            crl.clone(),
+            der::implicit(
+                1,
+                &der::sequence(&[der::oid(OID_RI_OCSP_RESPONSE), ocsp_response.clone()]),
+            ),
+            der::implicit(1, &der::sequence(&[der::oid(&[1, 2, 3]), der::null()])),
+        ]
+        .concat();
+        assert_eq!(
+            RevocationInfo::from_revocation_info_choices(&content).unwrap(),
+            vec![
+                RevocationInfo::Crl(crl),
+                RevocationInfo::OcspResponse(ocsp_response)
+            ]
+        );
+    }
+
+    #[test]
+    fn chain_is_built_from_unordered_certificates() {
+        let chain = chain();
+        let certificates = [chain[2].clone(), chain[1].clone()];
+        assert_eq!(certificate_chain(&chain[0], &certificates, &[]), chain);
+        assert_eq!(
+            certificate_chain(&chain[0], &certificates, &chain[1..2]),
+            chain[0..2]
+        );
+        assert_eq!(certificate_chain(&chain[0], &[], &[]), chain[0..1]);
+    }
+
+    #[test]
+    fn missing_revocation_info_is_reported() {
+        assert_eq!(evaluate_chain(&[]), RevocationStatus::NotEmbedded);
+        let status = evaluate_chain(&[RevocationInfo::Crl(crl("CA", "CA", &[]))]);
+        assert_eq!(
+            status,
+            RevocationStatus::Unknown(
+                "No verifiable revocation information for certificate 1 of the chain.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn crls_of_every_issuer_are_good() {
+        let status = evaluate_chain(&[
+            RevocationInfo::Crl(crl("CA", "CA", &[(8, 1_745_000_000)])),
+            RevocationInfo::Crl(crl("Root", "Root", &[])),
+        ]);
+        assert_eq!(status, RevocationStatus::Good);
+    }
+
+    #[test]
+    fn revocation_before_the_time_stamp_is_reported() {
+        let status = evaluate_chain(&[
+            RevocationInfo::Crl(crl("CA", "CA", &[(7, 1_745_000_000)])),
+            RevocationInfo::Crl(crl("Root", "Root", &[])),
+        ]);
+        assert!(matches!(status, RevocationStatus::Revoked(msg) if msg.contains("Certificate 0")));
+    }
+
+    #[test]
+    fn revocation_after_the_time_stamp_is_good() {
+        let status = evaluate_chain(&[
+            RevocationInfo::Crl(crl("CA", "CA", &[(7, 1_755_000_000)])),
+            RevocationInfo::Crl(crl("Root", "Root", &[])),
+        ]);
+        assert_eq!(status, RevocationStatus::Good);
+    }
+
+    #[test]
+    fn crl_with_invalid_signature_is_ignored() {
+        let status = evaluate_chain(&[
+            RevocationInfo::Crl(crl("CA", "Root", &[])),
+            RevocationInfo::Crl(crl("Root", "Root", &[])),
+        ]);
+        assert!(matches!(status, RevocationStatus::Unknown(msg) if msg.contains("certificate 0")));
+    }
+
+    #[test]
+    fn ocsp_responses_are_evaluated() {
+        let root_crl = RevocationInfo::Crl(crl("Root", "Root", &[]));
+        let good = ocsp_response("CA", 7, der::implicit(0, &der::null()), "CA", &[]);
+        assert_eq!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(good), root_crl.clone()]),
+            RevocationStatus::Good
+        );
+        let revoked = ocsp_response("CA", 7, revoked_status(1_745_000_000), "CA", &[]);
+        assert!(matches!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(revoked), root_crl.clone()]),
+            RevocationStatus::Revoked(_)
+        ));
+        let unknown = ocsp_response("CA", 7, der::implicit(2, &der::null()), "CA", &[]);
+        assert!(matches!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(unknown), root_crl.clone()]),
+            RevocationStatus::Unknown(_)
+        ));
+        let other_serial = ocsp_response("CA", 8, revoked_status(1_745_000_000), "CA", &[]);
+        assert!(matches!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(other_serial), root_crl]),
+            RevocationStatus::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn ocsp_responses_from_delegated_responders_are_accepted() {
+        let root_crl = RevocationInfo::Crl(crl("Root", "Root", &[]));
+        let ocsp_signing = der::sequence(&[
+            der::oid(x509::OID_CE_EXT_KEY_USAGE),
+            der::octet_string(&der::sequence(&[der::oid(x509::OID_KP_OCSP_SIGNING)])),
+        ]);
+        let responder = certificate("CA", "Responder", 9, &[ocsp_signing]);
+        let delegated = ocsp_response(
+            "CA",
+            7,
+            der::implicit(0, &der::null()),
+            "Responder",
+            &[responder],
+        );
+        assert_eq!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(delegated), root_crl.clone()]),
+            RevocationStatus::Good
+        );
+        let not_delegated = ocsp_response(
+            "CA",
+            7,
+            der::implicit(0, &der::null()),
+            "Responder",
+            &[certificate("CA", "Responder", 9, &[])],
+        );
+        assert!(matches!(
+            evaluate_chain(&[RevocationInfo::OcspResponse(not_delegated), root_crl]),
+            RevocationStatus::Unknown(_)
+        ));
+    }
+}
@@ -22,9 +22,12 @@ mod ntp_client;
 
 use self::local_system_time::LocalSystemTime;
 use self::ntp_client::NtpClient;
+use crate::logging::AuditEvent;
+use crate::logging::AuditEventType;
+use crate::telemetry::Span;
 use sntpc::NtpResult;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 
 /* Keeper of current time with accuracy measurements.
 
@@ -64,6 +67,24 @@ trusted within the declared accuracy.
 * [ETSI EN 319 422](https://www.etsi.org/deliver/etsi_en/319400_319499/319422/01.01.01_60/en_319422v010101p.pdf) Time-stamping protocol and time-stamp token profiles
 */
 
+/// Snapshot of the state of the time source.
+pub struct TimeSourceStatus {
+    /// NTP host used as time source or `None` when only the local system time is used.
+    pub ntp_host: Option<String>,
+    /// Accuracy declared for the most recent time measurement.
+    pub declared_accuracy_micros: Option<u64>,
+    /// Worst accuracy accepted when issuing time-stamps.
+    pub tolerable_accuracy_micros: u64,
+    /// Offset to the NTP host in the most recent successful NTP response.
+    pub ntp_offset_micros: Option<i64>,
+    /// Roundtrip time of the most recent successful NTP response.
+    pub ntp_roundtrip_micros: Option<u64>,
+    /// Local system time of the most recent successful NTP response.
+    pub last_ntp_sync_epoch_micros: Option<u64>,
+    /// `true` if the most recent time measurement was within tolerance.
+    pub within_tolerance: bool,
+}
+
 /// Guardian of space and time.
 pub struct TimeKeeper {
     ntp_host: Option<String>,
@@ -72,6 +93,12 @@ pub struct TimeKeeper {
     local_system_time: Arc<LocalSystemTime>,
     ntp_client: Option<Arc<NtpClient>>,
     within_tolerance: AtomicBool,
+    /// Last measured accuracy or `u64::MAX` if unknown.
+    last_accuracy_micros: AtomicU64,
+    last_ntp_offset_micros: AtomicI64,
+    last_ntp_roundtrip_micros: AtomicU64,
+    /// Local system time of last NTP response or `0` if there was none.
+    last_ntp_sync_epoch_micros: AtomicU64,
 }
 
 impl TimeKeeper {
@@ -98,14 +125,44 @@ impl TimeKeeper {
             local_system_time: LocalSystemTime::new(system_time_accuracy_micros),
             ntp_client,
             within_tolerance: AtomicBool::new(false),
+            last_accuracy_micros: AtomicU64::new(u64::MAX),
+            last_ntp_offset_micros: AtomicI64::new(0),
+            last_ntp_roundtrip_micros: AtomicU64::new(0),
+            last_ntp_sync_epoch_micros: AtomicU64::new(0),
         })
         .init(ntp_sync_interval_micros)
         .await
     }
 
-    /// Return true if measured accuracy is within the tolerable limit.
-    pub fn is_within_tolerance(self: &Arc<Self>) -> bool {
-        self.within_tolerance.load(Ordering::Relaxed)
+    /// Return a snapshot of the current state of the time source.
+    pub fn status(&self) -> TimeSourceStatus {
+        let last_ntp_sync_epoch_micros =
+            Some(self.last_ntp_sync_epoch_micros.load(Ordering::Relaxed))
+                .filter(|epoch_micros| *epoch_micros != 0);
+        TimeSourceStatus {
+            ntp_host: self.ntp_host.clone(),
+            declared_accuracy_micros: Some(self.last_accuracy_micros.load(Ordering::Relaxed))
+                .filter(|accuracy_micros| *accuracy_micros != u64::MAX),
+            tolerable_accuracy_micros: self.tolerable_accuracy_micros,
+            ntp_offset_micros: last_ntp_sync_epoch_micros
+                .map(|_| self.last_ntp_offset_micros.load(Ordering::Relaxed)),
+            ntp_roundtrip_micros: last_ntp_sync_epoch_micros
+                .map(|_| self.last_ntp_roundtrip_micros.load(Ordering::Relaxed)),
+            last_ntp_sync_epoch_micros,
+            within_tolerance: self.within_tolerance.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Keep track of the most recent NTP response for status reporting.
+    fn record_ntp_time(&self, ntp_time: &NtpResult) {
+        self.last_ntp_offset_micros
+            .store(ntp_time.offset(), Ordering::Relaxed);
+        self.last_ntp_roundtrip_micros
+            .store(ntp_time.roundtrip(), Ordering::Relaxed);
+        self.last_ntp_sync_epoch_micros.store(
+            upkit_common::util::time::now_epoch_micros(),
+            Ordering::Relaxed,
+        );
     }
 
     /// Initialize background tasks like periodic time sync.
@@ -131,6 +188,7 @@ impl TimeKeeper {
         if let Some(ntp_client) = self.ntp_client.as_ref() {
             if let Some(ntp_time) = ntp_client.request_ntp_time().await {
                 self.local_system_time.update_delta_from_ntp_time(&ntp_time);
+                self.record_ntp_time(&ntp_time);
                 log::info!(
                     "NTP server '{}' status: stratum: {}, offset: {} µs, roundtrip: {} µs, precision: 2^{} s ({} µs).",
                     self.ntp_host.as_ref().unwrap(),
@@ -153,10 +211,12 @@ impl TimeKeeper {
 
     /// Return the current time in microseconds with accuracy measurement.
     pub async fn get_epoch_time_with_accuracy_micros(&self) -> Option<(u64, u64)> {
+        let mut span = Span::start("TimeKeeper::get_epoch_time_with_accuracy_micros");
         let mut res = None;
         if self.ntp_query_for_every_request
             && let Some(ntp_time) = self.ntp_client.as_ref().unwrap().request_ntp_time().await
         {
+            self.record_ntp_time(&ntp_time);
             // https://datatracker.ietf.org/doc/html/rfc4330#section-3 NTPv4 Timestamp Format
             let epoch_micros = Self::get_epoch_micros_from_ntp_time(&ntp_time);
             let precision_micros = Self::get_precision_micros_from_ntp_time(&ntp_time);
@@ -173,8 +233,23 @@ impl TimeKeeper {
             accuracy_micros <= &self.tolerable_accuracy_micros
         });
         // Set last failure for healthcheck here if accurracy was too low
-        self.within_tolerance
-            .store(res.is_some(), Ordering::Relaxed);
+        let was_within_tolerance = self.within_tolerance.swap(res.is_some(), Ordering::Relaxed);
+        if was_within_tolerance != res.is_some() {
+            let mut audit_event = AuditEvent::new(AuditEventType::TimeToleranceChanged)
+                .with("withinTolerance", res.is_some())
+                .with("tolerableAccuracyMicros", self.tolerable_accuracy_micros);
+            if let Some((_epoch_micros, accuracy_micros)) = res {
+                audit_event = audit_event.with("accuracyMicros", accuracy_micros);
+            }
+            audit_event.emit();
+        }
+        if let Some((_epoch_micros, accuracy_micros)) = res {
+            self.last_accuracy_micros
+                .store(accuracy_micros, Ordering::Relaxed);
+            span.set_attribute("time.accuracy_micros", accuracy_micros);
+        } else {
+            span.set_error();
+        }
         res
     }
 
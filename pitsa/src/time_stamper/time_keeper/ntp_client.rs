@@ -17,6 +17,7 @@
 
 //! Network Time Protocol (NTP) client abstraction.
 
+use crate::telemetry::Span;
 use sntpc::NtpContext;
 pub use sntpc::NtpResult;
 use sntpc::StdTimestampGen;
@@ -78,6 +79,8 @@ impl NtpClient {
 
     /// Request a NTP packet.
     pub async fn request_ntp_time(&self) -> Option<NtpResult> {
+        let mut span = Span::start("NtpClient::request_ntp_time");
+        span.set_attribute("server.address", self.server_addr);
         let deadline =
             tokio::time::Instant::now() + tokio::time::Duration::from_micros(self.timeout_micros);
         let res_res = tokio::time::timeout_at(
@@ -93,9 +96,11 @@ impl NtpClient {
                 log::warn!("Failed NTP request: {e:?}");
             }
             Ok(Ok(ntp_result)) => {
+                span.set_attribute("ntp.roundtrip_micros", ntp_result.roundtrip());
                 return Some(ntp_result);
             }
         }
+        span.set_error();
         None
     }
 }
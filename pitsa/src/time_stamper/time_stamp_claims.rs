@@ -0,0 +1,132 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Claims of time-stamps issued in other formats than RFC 3161 tokens.
+
+use super::parsed_time_stamp_token::ParsedTimeStampToken;
+use super::tst_signing_info::SigningKeySnapshot;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Counter that makes serial numbers unique within the same microsecond.
+static SERIAL_NUMBER_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Error while handling a request that is not answered with an RFC 3161
+/// `TimeStampResp`, e.g. a JWS, COSE, DVCS, Authenticode or evidence record
+/// request.
+#[derive(Debug)]
+pub enum TimeStampFormatError {
+    /// The request is malformed or was rejected.
+    BadRequest(String),
+    /// No time-stamp could be produced right now.
+    Unavailable(String),
+}
+
+impl std::fmt::Display for TimeStampFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadRequest(msg) | Self::Unavailable(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+/// Format of an issued time-stamp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeStampFormat {
+    /// DER encoded RFC 3161 `TimeStampToken`.
+    Cms,
+    /// JWS in compact serialization.
+    Jws,
+    /// Encoded `COSE_Sign1`.
+    Cose,
+    /// DER encoded PKCS #7 `ContentInfo` with an Authenticode
+    /// counter-signature.
+    Authenticode,
+    /// DER encoded CMS `ContentInfo` with a `DVCSCertInfo`.
+    Dvcs,
+}
+
+impl TimeStampFormat {
+    /// Return the name of the format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cms => "cms",
+            Self::Jws => "jws",
+            Self::Cose => "cose",
+            Self::Authenticode => "authenticode",
+            Self::Dvcs => "dvcs",
+        }
+    }
+}
+
+/// The `TSTInfo` fields of a time-stamp that every format carries.
+#[derive(Clone)]
+pub struct TimeStampClaims {
+    /// TSA policy object identifier.
+    pub policy_oid: Vec<u32>,
+    /// Message imprint digest algorithm object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Message imprint digest.
+    pub digest: Vec<u8>,
+    /// Unsigned big-endian serial number.
+    pub serial_number: Vec<u8>,
+    /// Point in time in microseconds since the Unix epoch.
+    pub gen_time_epoch_micros: u64,
+    /// Accuracy in microseconds.
+    pub accuracy_micros: Option<u64>,
+    /// Unsigned big-endian nonce.
+    pub nonce: Option<Vec<u8>>,
+}
+
+impl From<&ParsedTimeStampToken> for TimeStampClaims {
+    fn from(token: &ParsedTimeStampToken) -> Self {
+        Self {
+            policy_oid: token.policy_oid.to_owned(),
+            digest_algorithm_oid: token.digest_algorithm_oid.to_owned(),
+            digest: token.digest.to_owned(),
+            serial_number: token.serial_number.to_owned(),
+            gen_time_epoch_micros: token.gen_time_epoch_micros,
+            accuracy_micros: token.accuracy_micros,
+            nonce: token.nonce.to_owned(),
+        }
+    }
+}
+
+impl TimeStampClaims {
+    /// Return a positive 127-bit serial number derived from the point in
+    /// time, a counter and the encoded request.
+    pub fn serial_number(
+        signing_key: &SigningKeySnapshot,
+        point_in_time_epoch_micros: u64,
+        encoded_request: &[u8],
+    ) -> Option<Vec<u8>> {
+        let counter = SERIAL_NUMBER_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut serial_number = signing_key.digest(
+            &[
+                &point_in_time_epoch_micros.to_be_bytes()[..],
+                &counter.to_be_bytes(),
+                encoded_request,
+            ]
+            .concat(),
+        )?;
+        serial_number.truncate(16);
+        if let Some(first) = serial_number.first_mut() {
+            *first &= 0x7f;
+        }
+        Some(serial_number)
+    }
+}
@@ -0,0 +1,364 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! CMS TimeStampedData envelope.
+
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+
+/// `id-ct-timestampedData` from RFC 5544.
+pub const OID_CT_TIMESTAMPED_DATA: &[u32] = &[1, 2, 840, 113549, 1, 9, 16, 1, 31];
+
+/// Error while handling a TimeStampedData request.
+#[derive(Debug)]
+pub enum TimeStampedDataError {
+    /// The request is malformed.
+    BadRequest(String),
+    /// The TimeStampedData failed validation.
+    Invalid(String),
+    /// No time-stamp could be produced right now.
+    Unavailable(String),
+}
+
+impl std::fmt::Display for TimeStampedDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadRequest(msg) | Self::Invalid(msg) | Self::Unavailable(msg) => {
+                write!(f, "{msg}")
+            }
+        }
+    }
+}
+
+/** CMS TimeStampedData as defined in
+[RFC 5544 2](https://www.rfc-editor.org/rfc/rfc5544#section-2).
+
+```text
+   TimeStampedData ::= SEQUENCE {
+      version              INTEGER { v1(1) },
+      dataUri              IA5String OPTIONAL,
+      metaData             MetaData OPTIONAL,
+      content              OCTET STRING OPTIONAL,
+      temporalEvidence     Evidence }
+
+   MetaData ::= SEQUENCE {
+      hashProtected        BOOLEAN,
+      fileName             UTF8String OPTIONAL,
+      mediaType            IA5String OPTIONAL,
+      otherMetaData        Attributes OPTIONAL }
+
+   Evidence ::= CHOICE {
+      tstEvidence    [0] TimeStampTokenEvidence,   -- see RFC 3161
+      ersEvidence    [1] EvidenceRecord,           -- see RFC 4998
+      otherEvidence  [2] OtherEvidence }
+
+   TimeStampTokenEvidence ::= SEQUENCE SIZE(1..MAX) OF TimeStampAndCRL
+
+   TimeStampAndCRL ::= SEQUENCE {
+      timeStamp   TimeStampToken,          -- according to RFC 3161
+      crl         CertificateList OPTIONAL -- according to RFC 5280 }
+```
+
+Only `tstEvidence` is supported. Produced structures never protect the
+metadata by the time-stamp hash, but parsed structures may.
+*/
+pub struct TimeStampedData {
+    /// URI where the data can be retrieved when not embedded.
+    pub data_uri: Option<String>,
+    /// Original file name of the data.
+    pub file_name: Option<String>,
+    /// Media type of the data.
+    pub media_type: Option<String>,
+    /// The embedded data.
+    pub content: Option<Vec<u8>>,
+    /// DER encoded `TimeStampToken`s with the one over the data first.
+    pub time_stamp_tokens: Vec<Vec<u8>>,
+    /// DER encoded `MetaData` when covered by the time-stamp hash.
+    hash_protected_meta_data: Option<Vec<u8>>,
+    /// DER encoded `TimeStampAndCRL`s as parsed, including any CRL.
+    encoded_time_stamp_and_crls: Vec<Vec<u8>>,
+}
+
+impl TimeStampedData {
+    /// Return a new instance without any time-stamp tokens.
+    pub fn new(
+        data_uri: Option<String>,
+        file_name: Option<String>,
+        media_type: Option<String>,
+        content: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            data_uri,
+            file_name,
+            media_type,
+            content,
+            time_stamp_tokens: vec![],
+            hash_protected_meta_data: None,
+            encoded_time_stamp_and_crls: vec![],
+        }
+    }
+
+    /// Return the data that the first time-stamp token's message imprint
+    /// covers, if the content is embedded.
+    pub fn time_stamped_content(&self) -> Option<Vec<u8>> {
+        self.content.as_ref().map(|content| {
+            [
+                self.hash_protected_meta_data.as_deref().unwrap_or_default(),
+                content,
+            ]
+            .concat()
+        })
+    }
+
+    /// Return the data that the message imprint of the time-stamp token at
+    /// `index` must cover, if the token is a renewal.
+    ///
+    /// [RFC 5544 4.2](https://www.rfc-editor.org/rfc/rfc5544#section-4.2):
+    /// each renewal time-stamps the DER encoding of the previous
+    /// `TimeStampAndCRL`, so the chain can't be reordered or truncated in
+    /// the middle.
+    pub fn renewed_time_stamp_and_crl(&self, index: usize) -> Option<Vec<u8>> {
+        let previous = index.checked_sub(1)?;
+        self.encoded_time_stamp_and_crls
+            .get(previous)
+            .cloned()
+            .or_else(|| {
+                self.time_stamp_tokens
+                    .get(previous)
+                    .map(|time_stamp_token| der::sequence(&[time_stamp_token.to_vec()]))
+            })
+    }
+
+    /// Parse a DER encoded `ContentInfo` with `TimeStampedData` content.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut content_info = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        if !content_info
+            .read_expected(tag::OID)?
+            .as_oid()?
+            .eq(OID_CT_TIMESTAMPED_DATA)
+        {
+            return Err(der::DerError::new("Content type is not TimeStampedData."));
+        }
+        let time_stamped_data = content_info
+            .read_expected(tag::context_constructed(0))?
+            .reader()
+            .read_expected(tag::SEQUENCE)?;
+        let mut time_stamped_data = time_stamped_data.reader();
+        if time_stamped_data.read_expected(tag::INTEGER)?.as_u64()? != 1 {
+            return Err(der::DerError::new("Unsupported TimeStampedData version."));
+        }
+        let mut ret = Self::new(None, None, None, None);
+        ret.data_uri = time_stamped_data
+            .read_optional(tag::IA5_STRING)?
+            .map(|data_uri| data_uri.as_string())
+            .transpose()?;
+        if let Some(meta_data_tlv) = time_stamped_data.read_optional(tag::SEQUENCE)? {
+            let mut meta_data = meta_data_tlv.reader();
+            if meta_data.read_expected(tag::BOOLEAN)?.as_bool()? {
+                ret.hash_protected_meta_data = Some(meta_data_tlv.encoded.to_vec());
+            }
+            ret.file_name = meta_data
+                .read_optional(tag::UTF8_STRING)?
+                .map(|file_name| file_name.as_string())
+                .transpose()?;
+            ret.media_type = meta_data
+                .read_optional(tag::IA5_STRING)?
+                .map(|media_type| media_type.as_string())
+                .transpose()?;
+        }
+        ret.content = time_stamped_data
+            .read_optional(tag::OCTET_STRING)?
+            .map(|content| content.content.to_vec());
+        let mut time_stamp_token_evidence = time_stamped_data
+            .read_optional(tag::context_constructed(0))?
+            .ok_or(der::DerError::new(
+                "Only time-stamp token evidence is supported.",
+            ))?
+            .reader();
+        while !time_stamp_token_evidence.is_empty() {
+            let time_stamp_and_crl = time_stamp_token_evidence.read_expected(tag::SEQUENCE)?;
+            ret.time_stamp_tokens.push(
+                time_stamp_and_crl
+                    .reader()
+                    .read_expected(tag::SEQUENCE)?
+                    .encoded
+                    .to_vec(),
+            );
+            ret.encoded_time_stamp_and_crls
+                .push(time_stamp_and_crl.encoded.to_vec());
+        }
+        if ret.time_stamp_tokens.is_empty() {
+            return Err(der::DerError::new("No time-stamp token evidence."));
+        }
+        Ok(ret)
+    }
+
+    /// Return the DER encoded `ContentInfo` with `TimeStampedData` content.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut time_stamped_data = vec![der::integer(1)];
+        if let Some(data_uri) = &self.data_uri {
+            time_stamped_data.push(der::ia5_string(data_uri));
+        }
+        if self.file_name.is_some() || self.media_type.is_some() {
+            let mut meta_data = vec![der::boolean(false)];
+            if let Some(file_name) = &self.file_name {
+                meta_data.push(der::utf8_string(file_name));
+            }
+            if let Some(media_type) = &self.media_type {
+                meta_data.push(der::ia5_string(media_type));
+            }
+            time_stamped_data.push(der::sequence(&meta_data));
+        }
+        if let Some(content) = &self.content {
+            time_stamped_data.push(der::octet_string(content));
+        }
+        time_stamped_data.push(der::implicit(
+            0,
+            &der::sequence(
+                &self
+                    .time_stamp_tokens
+                    .iter()
+                    .map(|time_stamp_token| der::sequence(&[time_stamp_token.to_vec()]))
+                    .collect::<Vec<_>>(),
+            ),
+        ));
+        der::sequence(&[
+            der::oid(OID_CT_TIMESTAMPED_DATA),
+            der::explicit(0, &der::sequence(&time_stamped_data)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal stand-in for a `TimeStampToken`.
+    fn time_stamp_token(n: u8) -> Vec<u8> {
+        der::sequence(&[der::oid(&[1, 2, 3]), der::octet_string(&[n])])
+    }
+
+    fn content_info(time_stamped_data: &[Vec<u8>]) -> Vec<u8> {
+        der::sequence(&[
+            der::oid(OID_CT_TIMESTAMPED_DATA),
+            der::explicit(0, &der::sequence(time_stamped_data)),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut time_stamped_data = TimeStampedData::new(
+            Some("https://example.com/data".to_string()),
+            Some("data.txt".to_string()),
+            Some("text/plain".to_string()),
+            Some(b"data".to_vec()),
+        );
+        time_stamped_data.time_stamp_tokens = vec![time_stamp_token(1), time_stamp_token(2)];
+        let encoded = time_stamped_data.as_bytes();
+        let parsed = TimeStampedData::from_bytes(&encoded).unwrap();
+        assert_eq!(parsed.data_uri.as_deref(), Some("https://example.com/data"));
+        assert_eq!(parsed.file_name.as_deref(), Some("data.txt"));
+        assert_eq!(parsed.media_type.as_deref(), Some("text/plain"));
+        assert_eq!(parsed.content.as_deref(), Some(&b"data"[..]));
+        assert_eq!(
+            parsed.time_stamp_tokens,
+            time_stamped_data.time_stamp_tokens
+        );
+        // Metadata is not hash protected in produced structures
+        assert_eq!(parsed.time_stamped_content(), Some(b"data".to_vec()));
+        assert_eq!(parsed.as_bytes(), encoded);
+    }
+
+    #[test]
+    fn hash_protected_meta_data_is_time_stamped() {
+        let meta_data = der::sequence(&[der::boolean(true), der::utf8_string("data.txt")]);
+        let encoded = content_info(&[
+            der::integer(1),
+            meta_data.clone(),
+            der::octet_string(b"data"),
+            der::implicit(0, &der::sequence(&[der::sequence(&[time_stamp_token(1)])])),
+        ]);
+        let parsed = TimeStampedData::from_bytes(&encoded).unwrap();
+        assert_eq!(
+            parsed.time_stamped_content(),
+            Some([meta_data, b"data".to_vec()].concat())
+        );
+    }
+
+    #[test]
+    fn renewal_covers_previous_time_stamp_and_crl() {
+        let crl = der::sequence(&[der::integer(7)]);
+        let first = der::sequence(&[time_stamp_token(1), crl]);
+        let second = der::sequence(&[time_stamp_token(2)]);
+        let encoded = content_info(&[
+            der::integer(1),
+            der::octet_string(b"data"),
+            der::implicit(0, &der::sequence(&[first.clone(), second.clone()])),
+        ]);
+        let parsed = TimeStampedData::from_bytes(&encoded).unwrap();
+        assert_eq!(parsed.renewed_time_stamp_and_crl(0), None);
+        assert_eq!(parsed.renewed_time_stamp_and_crl(1), Some(first));
+        assert_eq!(parsed.renewed_time_stamp_and_crl(2), Some(second));
+        assert_eq!(parsed.renewed_time_stamp_and_crl(3), None);
+    }
+
+    #[test]
+    fn renewal_of_new_structure() {
+        let mut time_stamped_data = TimeStampedData::new(None, None, None, Some(b"data".to_vec()));
+        time_stamped_data.time_stamp_tokens = vec![time_stamp_token(1)];
+        assert_eq!(
+            time_stamped_data.renewed_time_stamp_and_crl(1),
+            Some(der::sequence(&[time_stamp_token(1)]))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let evidence = der::implicit(0, &der::sequence(&[der::sequence(&[time_stamp_token(1)])]));
+        // Wrong content type
+        assert!(
+            TimeStampedData::from_bytes(&der::sequence(&[
+                der::oid(&[1, 2, 840, 113549, 1, 7, 1]),
+                der::explicit(0, &der::sequence(&[der::integer(1), evidence.clone()])),
+            ]))
+            .is_err()
+        );
+        // Unsupported version
+        assert!(
+            TimeStampedData::from_bytes(&content_info(&[der::integer(2), evidence.clone()]))
+                .is_err()
+        );
+        // No time-stamp tokens
+        assert!(
+            TimeStampedData::from_bytes(&content_info(&[
+                der::integer(1),
+                der::implicit(0, &der::sequence(&[])),
+            ]))
+            .is_err()
+        );
+        // Evidence record evidence
+        assert!(
+            TimeStampedData::from_bytes(&content_info(&[
+                der::integer(1),
+                der::implicit(1, &der::sequence(&[])),
+            ]))
+            .is_err()
+        );
+        assert!(TimeStampedData::from_bytes(&content_info(&[der::integer(1), evidence])).is_ok());
+    }
+}
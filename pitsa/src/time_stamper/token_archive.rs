@@ -0,0 +1,753 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Persistent append-only archive of issued time-stamp tokens.
+
+use super::time_stamp_claims::TimeStampClaims;
+use super::time_stamp_claims::TimeStampFormat;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// File name extension of archive segments.
+const SEGMENT_EXTENSION: &str = "tsa";
+/// Size in bytes after which an export chunk is returned.
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+/// Largest archived token in bytes that is exported.
+const MAX_ARCHIVED_TOKEN_SIZE: usize = 1024 * 1024;
+
+/** Archived time-stamp token.
+
+```text
+   ArchivedToken ::= SEQUENCE {
+       serialNumber                INTEGER,
+       genTime                     GeneralizedTime,
+       messageImprint              SEQUENCE {
+           hashAlgorithm               OBJECT IDENTIFIER,
+           hashedMessage               OCTET STRING },
+       policy                      OBJECT IDENTIFIER,
+       tsuCertificateFingerprint   OCTET STRING,
+       timeStamp                   TimeStamp }
+
+   TimeStamp ::= CHOICE {
+       timeStampToken              ContentInfo,
+       jws                     [0] IMPLICIT OCTET STRING,
+       cose                    [1] IMPLICIT OCTET STRING,
+       authenticode            [2] IMPLICIT OCTET STRING,
+       dvcs                    [3] IMPLICIT OCTET STRING }
+```
+
+The fingerprint is the SHA-256 hash of the DER encoded TSU certificate. The
+time-stamp is archived in the format it was returned to the client, except for
+Authenticode counter-signatures that are archived without the base64 encoding.
+*/
+#[derive(Clone)]
+pub struct ArchivedToken {
+    /// Unsigned big-endian time-stamp serial number.
+    pub serial_number: Vec<u8>,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    pub gen_time_epoch_micros: u64,
+    /// Message imprint digest algorithm object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Message imprint digest.
+    pub digest: Vec<u8>,
+    /// TSA policy object identifier.
+    pub policy_oid: Vec<u32>,
+    /// SHA-256 fingerprint of the TSU certificate.
+    pub tsu_certificate_fingerprint: Vec<u8>,
+    /// Format of the time-stamp.
+    pub format: TimeStampFormat,
+    /// The time-stamp as returned to the client, e.g. a DER encoded
+    /// `TimeStampToken`.
+    pub time_stamp_token: Vec<u8>,
+}
+
+impl ArchivedToken {
+    /// Return a new instance.
+    pub fn new(
+        format: TimeStampFormat,
+        time_stamp_token: &[u8],
+        claims: &TimeStampClaims,
+        tsu_certificate_fingerprint: &[u8],
+    ) -> Self {
+        Self {
+            serial_number: claims.serial_number.to_owned(),
+            gen_time_epoch_micros: claims.gen_time_epoch_micros,
+            digest_algorithm_oid: claims.digest_algorithm_oid.to_owned(),
+            digest: claims.digest.to_owned(),
+            policy_oid: claims.policy_oid.to_owned(),
+            tsu_certificate_fingerprint: tsu_certificate_fingerprint.to_vec(),
+            format,
+            time_stamp_token: time_stamp_token.to_vec(),
+        }
+    }
+
+    /// Return the DER encoded `ArchivedToken`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        der::sequence(&[
+            der::unsigned_integer(&self.serial_number),
+            der::generalized_time(self.gen_time_epoch_micros),
+            der::sequence(&[
+                der::oid(&self.digest_algorithm_oid),
+                der::octet_string(&self.digest),
+            ]),
+            der::oid(&self.policy_oid),
+            der::octet_string(&self.tsu_certificate_fingerprint),
+            match self.format {
+                TimeStampFormat::Cms => self.time_stamp_token.to_owned(),
+                TimeStampFormat::Jws => {
+                    der::implicit(0, &der::octet_string(&self.time_stamp_token))
+                }
+                TimeStampFormat::Cose => {
+                    der::implicit(1, &der::octet_string(&self.time_stamp_token))
+                }
+                TimeStampFormat::Authenticode => {
+                    der::implicit(2, &der::octet_string(&self.time_stamp_token))
+                }
+                TimeStampFormat::Dvcs => {
+                    der::implicit(3, &der::octet_string(&self.time_stamp_token))
+                }
+            },
+        ])
+    }
+
+    /// Parse a DER encoded `ArchivedToken`.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut archived_token = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        let serial_number = archived_token
+            .read_expected(tag::INTEGER)?
+            .as_unsigned_integer_bytes()?
+            .to_vec();
+        let gen_time_epoch_micros = archived_token
+            .read_expected(tag::GENERALIZED_TIME)?
+            .as_epoch_micros()?;
+        let mut message_imprint = archived_token.read_expected(tag::SEQUENCE)?.reader();
+        let digest_algorithm_oid = message_imprint.read_expected(tag::OID)?.as_oid()?;
+        let digest = message_imprint
+            .read_expected(tag::OCTET_STRING)?
+            .content
+            .to_vec();
+        let policy_oid = archived_token.read_expected(tag::OID)?.as_oid()?;
+        let tsu_certificate_fingerprint = archived_token
+            .read_expected(tag::OCTET_STRING)?
+            .content
+            .to_vec();
+        let time_stamp = archived_token.read()?;
+        let (format, time_stamp_token) = match time_stamp.tag {
+            tag::SEQUENCE => (TimeStampFormat::Cms, time_stamp.encoded.to_vec()),
+            tag if tag == tag::context_primitive(0) => {
+                (TimeStampFormat::Jws, time_stamp.content.to_vec())
+            }
+            tag if tag == tag::context_primitive(1) => {
+                (TimeStampFormat::Cose, time_stamp.content.to_vec())
+            }
+            tag if tag == tag::context_primitive(2) => {
+                (TimeStampFormat::Authenticode, time_stamp.content.to_vec())
+            }
+            tag if tag == tag::context_primitive(3) => {
+                (TimeStampFormat::Dvcs, time_stamp.content.to_vec())
+            }
+            _ => return Err(der::DerError::new("Unknown archived time-stamp format.")),
+        };
+        Ok(Self {
+            serial_number,
+            gen_time_epoch_micros,
+            digest_algorithm_oid,
+            digest,
+            policy_oid,
+            tsu_certificate_fingerprint,
+            format,
+            time_stamp_token,
+        })
+    }
+}
+
+/// Position of an archived token.
+#[derive(Clone, Copy, PartialEq)]
+struct Location {
+    segment_id: u64,
+    offset: u64,
+    len: u64,
+}
+
+/// Size and time span of a segment file.
+struct SegmentInfo {
+    size: u64,
+    last_gen_time_epoch_micros: u64,
+}
+
+/// Segments, the open segment and lookup indexes.
+#[derive(Default)]
+struct ArchiveState {
+    segments: BTreeMap<u64, SegmentInfo>,
+    writer: Option<(u64, File)>,
+    by_serial_number: HashMap<Vec<u8>, Location>,
+    by_digest: HashMap<Vec<u8>, Vec<Location>>,
+}
+
+/** Persistent archive of issued time-stamp tokens.
+
+Tokens are appended as DER encoded [ArchivedToken]s to segment files named
+after the `genTime` (in microseconds) of their first token. A new segment is
+started when the current one exceeds the configured size. Retention and the
+total size limit are enforced by removing whole segments, oldest first.
+
+Indexes by serial number and message imprint digest are rebuilt from the
+segments on start-up and kept in memory.
+*/
+pub struct TokenArchive {
+    directory: PathBuf,
+    retention_micros: Option<u64>,
+    max_size_bytes: Option<u64>,
+    segment_size_bytes: u64,
+    state: Mutex<ArchiveState>,
+}
+
+impl TokenArchive {
+    /// Return a new instance with the segments found in `directory` indexed.
+    pub fn new(
+        directory: PathBuf,
+        retention_micros: Option<u64>,
+        max_size_bytes: Option<u64>,
+        segment_size_bytes: u64,
+    ) -> Result<Self, std::io::Error> {
+        std::fs::create_dir_all(&directory)?;
+        let token_archive = Self {
+            directory,
+            retention_micros,
+            max_size_bytes,
+            segment_size_bytes,
+            state: Mutex::default(),
+        };
+        let mut segment_ids = std::fs::read_dir(&token_archive.directory)?
+            .filter_map(Result::ok)
+            .map(|dir_entry| dir_entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == SEGMENT_EXTENSION)
+            })
+            .filter_map(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+            })
+            .collect::<Vec<_>>();
+        segment_ids.sort_unstable();
+        let mut count = 0;
+        {
+            let mut state = token_archive.state.lock().unwrap();
+            for segment_id in segment_ids {
+                count += token_archive.index_segment(&mut state, segment_id)?;
+            }
+        }
+        log::info!(
+            "Token archive in '{}' has {count} tokens.",
+            token_archive.directory.display()
+        );
+        Ok(token_archive)
+    }
+
+    /// Append an issued token.
+    ///
+    /// The token is synced to disk before returning. This blocks, so call it
+    /// outside of the async executor.
+    pub fn append(&self, archived_token: &ArchivedToken) -> Result<(), std::io::Error> {
+        let encoded = archived_token.as_bytes();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let roll_over = match &state.writer {
+            Some((segment_id, _file)) => state
+                .segments
+                .get(segment_id)
+                .is_none_or(|segment_info| segment_info.size >= self.segment_size_bytes),
+            None => true,
+        };
+        if roll_over {
+            let mut segment_id = archived_token.gen_time_epoch_micros;
+            while state.segments.contains_key(&segment_id) {
+                segment_id += 1;
+            }
+            let file = OpenOptions::new()
+                .create_new(true)
+                .append(true)
+                .open(self.segment_path(segment_id))?;
+            // Make the new directory entry durable
+            File::open(&self.directory)?.sync_all()?;
+            state.segments.insert(
+                segment_id,
+                SegmentInfo {
+                    size: 0,
+                    last_gen_time_epoch_micros: archived_token.gen_time_epoch_micros,
+                },
+            );
+            state.writer = Some((segment_id, file));
+            self.enforce_limits(state, archived_token.gen_time_epoch_micros);
+        }
+        let Some((segment_id, file)) = state.writer.as_mut() else {
+            return Err(std::io::Error::other("No archive segment is open."));
+        };
+        let segment_id = *segment_id;
+        let Some(segment_info) = state.segments.get_mut(&segment_id) else {
+            return Err(std::io::Error::other(format!(
+                "Archive segment {segment_id} is not indexed."
+            )));
+        };
+        if let Err(e) = file.write_all(&encoded).and_then(|()| file.sync_data()) {
+            // Don't append after a partially written token
+            state.writer = None;
+            return Err(e);
+        }
+        let location = Location {
+            segment_id,
+            offset: segment_info.size,
+            len: encoded.len() as u64,
+        };
+        segment_info.size += location.len;
+        segment_info.last_gen_time_epoch_micros = archived_token.gen_time_epoch_micros;
+        Self::index(state, archived_token, location);
+        Ok(())
+    }
+
+    /// Return `true` if a thread panicked while holding the archive state.
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// Return the archived token with the serial number.
+    pub fn by_serial_number(&self, serial_number: &[u8]) -> Option<ArchivedToken> {
+        let location = *self
+            .state
+            .lock()
+            .unwrap()
+            .by_serial_number
+            .get(serial_number)?;
+        self.read(location)
+    }
+
+    /// Return all archived tokens over the message imprint digest.
+    pub fn by_digest(&self, digest: &[u8]) -> Vec<ArchivedToken> {
+        let locations = self
+            .state
+            .lock()
+            .unwrap()
+            .by_digest
+            .get(digest)
+            .cloned()
+            .unwrap_or_default();
+        locations
+            .into_iter()
+            .filter_map(|location| self.read(location))
+            .collect()
+    }
+
+    /// Return an export of the tokens issued within the time range
+    /// (inclusive).
+    pub fn export(&self, from_epoch_micros: u64, to_epoch_micros: u64) -> ArchiveExport {
+        let state = self.state.lock().unwrap();
+        // A segment only holds tokens issued after the one that started it
+        let first = state
+            .segments
+            .range(..=from_epoch_micros)
+            .next_back()
+            .map(|(segment_id, _)| *segment_id)
+            .unwrap_or(from_epoch_micros);
+        ArchiveExport {
+            directory: self.directory.clone(),
+            segment_ids: state
+                .segments
+                .range(first..=to_epoch_micros)
+                .map(|(segment_id, _)| *segment_id)
+                .collect(),
+            segment: None,
+            from_epoch_micros,
+            to_epoch_micros,
+        }
+    }
+
+    /// Read the token at `location`.
+    fn read(&self, location: Location) -> Option<ArchivedToken> {
+        let mut file = File::open(self.segment_path(location.segment_id)).ok()?;
+        file.seek(SeekFrom::Start(location.offset)).ok()?;
+        let mut encoded = vec![0u8; usize::try_from(location.len).ok()?];
+        file.read_exact(&mut encoded).ok()?;
+        ArchivedToken::from_bytes(&encoded)
+            .map_err(|e| log::warn!("Corrupt archived token: {e}"))
+            .ok()
+    }
+
+    /// Index all tokens of a segment and return the number of tokens.
+    fn index_segment(
+        &self,
+        state: &mut ArchiveState,
+        segment_id: u64,
+    ) -> Result<usize, std::io::Error> {
+        let content = std::fs::read(self.segment_path(segment_id))?;
+        let mut reader = DerReader::new(&content);
+        let mut offset = 0;
+        let mut count = 0;
+        let mut last_gen_time_epoch_micros = segment_id;
+        while !reader.is_empty() {
+            // A partially written last token is ignored
+            let Ok(tlv) = reader.read() else {
+                log::warn!("Ignoring truncated tail of archive segment {segment_id}.");
+                break;
+            };
+            let location = Location {
+                segment_id,
+                offset,
+                len: tlv.encoded.len() as u64,
+            };
+            offset += location.len;
+            match ArchivedToken::from_bytes(tlv.encoded) {
+                Ok(archived_token) => {
+                    last_gen_time_epoch_micros = archived_token.gen_time_epoch_micros;
+                    Self::index(state, &archived_token, location);
+                    count += 1;
+                }
+                Err(e) => log::warn!("Skipping corrupt token in archive segment {segment_id}: {e}"),
+            }
+        }
+        state.segments.insert(
+            segment_id,
+            SegmentInfo {
+                size: content.len() as u64,
+                last_gen_time_epoch_micros,
+            },
+        );
+        Ok(count)
+    }
+
+    /// Add a token to the lookup indexes.
+    fn index(state: &mut ArchiveState, archived_token: &ArchivedToken, location: Location) {
+        state
+            .by_serial_number
+            .insert(archived_token.serial_number.to_owned(), location);
+        state
+            .by_digest
+            .entry(archived_token.digest.to_owned())
+            .or_default()
+            .push(location);
+    }
+
+    /// Remove the oldest segments (except the open one) that are past the
+    /// retention or exceed the size limit.
+    fn enforce_limits(&self, state: &mut ArchiveState, now_epoch_micros: u64) {
+        let open_segment_id = state.writer.as_ref().map(|(segment_id, _file)| *segment_id);
+        let mut total_size = state
+            .segments
+            .values()
+            .map(|segment_info| segment_info.size)
+            .sum::<u64>();
+        let mut expired = vec![];
+        for (segment_id, segment_info) in &state.segments {
+            if Some(*segment_id) == open_segment_id {
+                break;
+            }
+            let past_retention = self.retention_micros.is_some_and(|retention_micros| {
+                segment_info
+                    .last_gen_time_epoch_micros
+                    .saturating_add(retention_micros)
+                    < now_epoch_micros
+            });
+            let over_size = self
+                .max_size_bytes
+                .is_some_and(|max_size_bytes| total_size > max_size_bytes);
+            if !past_retention && !over_size {
+                break;
+            }
+            total_size -= segment_info.size;
+            expired.push(*segment_id);
+        }
+        for segment_id in expired {
+            if let Err(e) = std::fs::remove_file(self.segment_path(segment_id)) {
+                log::warn!("Failed to remove archive segment {segment_id}: {e}");
+                continue;
+            }
+            log::info!("Removed archive segment {segment_id}.");
+            state.segments.remove(&segment_id);
+            state
+                .by_serial_number
+                .retain(|_, location| location.segment_id != segment_id);
+            state.by_digest.retain(|_, locations| {
+                locations.retain(|location| location.segment_id != segment_id);
+                !locations.is_empty()
+            });
+        }
+    }
+
+    /// Return the path of a segment file.
+    fn segment_path(&self, segment_id: u64) -> PathBuf {
+        segment_path(&self.directory, segment_id)
+    }
+}
+
+/// Return the path of a segment file in `directory`.
+fn segment_path(directory: &Path, segment_id: u64) -> PathBuf {
+    directory.join(format!("{segment_id:020}.{SEGMENT_EXTENSION}"))
+}
+
+/** Export of the archived tokens issued within a time range.
+
+Segments are read one at a time with bounded reads, so the export can be
+streamed without holding whole segments in memory. Reading blocks, so call
+[next_chunk()](Self::next_chunk()) outside of the async executor.
+*/
+pub struct ArchiveExport {
+    directory: PathBuf,
+    segment_ids: VecDeque<u64>,
+    segment: Option<(u64, BufReader<File>)>,
+    from_epoch_micros: u64,
+    to_epoch_micros: u64,
+}
+
+impl ArchiveExport {
+    /// Return the next concatenated DER encoded [ArchivedToken]s of about
+    /// [EXPORT_CHUNK_SIZE] bytes or `None` when the export is complete.
+    pub fn next_chunk(&mut self) -> Option<Vec<u8>> {
+        let mut chunk = vec![];
+        while chunk.len() < EXPORT_CHUNK_SIZE {
+            let Some((segment_id, reader)) = self.segment.as_mut() else {
+                let segment_id = self.segment_ids.pop_front()?;
+                // The segment might have been removed since the export started
+                match File::open(segment_path(&self.directory, segment_id)) {
+                    Ok(file) => self.segment = Some((segment_id, BufReader::new(file))),
+                    Err(e) => log::warn!("Failed to read archive segment {segment_id}: {e}"),
+                }
+                continue;
+            };
+            match read_tlv(reader) {
+                Ok(Some(encoded)) => {
+                    if ArchivedToken::from_bytes(&encoded).is_ok_and(|archived_token| {
+                        (self.from_epoch_micros..=self.to_epoch_micros)
+                            .contains(&archived_token.gen_time_epoch_micros)
+                    }) {
+                        chunk.extend_from_slice(&encoded);
+                    }
+                }
+                Ok(None) => self.segment = None,
+                Err(e) => {
+                    log::warn!("Stopped reading archive segment {segment_id}: {e}");
+                    self.segment = None;
+                }
+            }
+        }
+        Some(chunk)
+    }
+}
+
+/// Read the next DER TLV of at most [MAX_ARCHIVED_TOKEN_SIZE] bytes or `None`
+/// at the end of the input.
+fn read_tlv(reader: &mut impl Read) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let mut tag = [0u8; 1];
+    if reader.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    let mut encoded = tag.to_vec();
+    let mut length = [0u8; 1];
+    reader.read_exact(&mut length)?;
+    encoded.push(length[0]);
+    let content_len = if length[0] < 0x80 {
+        usize::from(length[0])
+    } else {
+        let octets = usize::from(length[0] & 0x7f);
+        if octets == 0 || octets > 4 {
+            return Err(std::io::Error::other("Unsupported DER length."));
+        }
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut length[4 - octets..])?;
+        encoded.extend_from_slice(&length[4 - octets..]);
+        usize::try_from(u32::from_be_bytes(length)).unwrap_or(usize::MAX)
+    };
+    if content_len > MAX_ARCHIVED_TOKEN_SIZE {
+        return Err(std::io::Error::other(format!(
+            "Archived token of {content_len} bytes exceeds the limit."
+        )));
+    }
+    let header_len = encoded.len();
+    encoded.resize(header_len + content_len, 0);
+    reader.read_exact(&mut encoded[header_len..])?;
+    Ok(Some(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> TimeStampClaims {
+        TimeStampClaims {
+            policy_oid: vec![1, 2, 3, 4],
+            digest_algorithm_oid: vec![2, 16, 840, 1, 101, 3, 4, 2, 1],
+            digest: vec![0xab; 32],
+            serial_number: vec![0x7f, 0x01, 0x02],
+            gen_time_epoch_micros: 1_750_000_000_123_456,
+            accuracy_micros: Some(1_000),
+            nonce: None,
+        }
+    }
+
+    #[test]
+    fn round_trip_in_every_format() {
+        for (format, time_stamp_token) in [
+            (
+                TimeStampFormat::Cms,
+                der::sequence(&[der::oid(&[1, 2, 840, 113549, 1, 7, 2])]),
+            ),
+            (
+                TimeStampFormat::Jws,
+                b"eyJhbGciOiJFUzI1NiJ9.e30.c2ln".to_vec(),
+            ),
+            (
+                TimeStampFormat::Cose,
+                vec![0xd2, 0x84, 0x40, 0xa0, 0xf6, 0x40],
+            ),
+            (
+                TimeStampFormat::Authenticode,
+                der::sequence(&[der::oid(&[1, 2, 840, 113549, 1, 7, 2])]),
+            ),
+            (
+                TimeStampFormat::Dvcs,
+                der::sequence(&[der::oid(&[1, 2, 840, 113549, 1, 7, 2])]),
+            ),
+        ] {
+            let archived_token =
+                ArchivedToken::new(format, &time_stamp_token, &claims(), &[0x11; 32]);
+            let encoded = archived_token.as_bytes();
+            let parsed = ArchivedToken::from_bytes(&encoded).unwrap();
+            assert_eq!(parsed.format, format);
+            assert_eq!(parsed.time_stamp_token, time_stamp_token);
+            assert_eq!(parsed.serial_number, claims().serial_number);
+            assert_eq!(parsed.gen_time_epoch_micros, claims().gen_time_epoch_micros);
+            assert_eq!(parsed.digest_algorithm_oid, claims().digest_algorithm_oid);
+            assert_eq!(parsed.digest, claims().digest);
+            assert_eq!(parsed.policy_oid, claims().policy_oid);
+            assert_eq!(parsed.tsu_certificate_fingerprint, vec![0x11; 32]);
+            assert_eq!(parsed.as_bytes(), encoded);
+        }
+    }
+
+    #[test]
+    fn unknown_format_is_rejected() {
+        let archived_token = ArchivedToken::new(TimeStampFormat::Cose, b"x", &claims(), &[]);
+        let mut encoded = archived_token.as_bytes();
+        // Retag the time-stamp as [2]
+        let time_stamp_offset = encoded.len() - 3;
+        encoded[time_stamp_offset] = tag::context_primitive(2);
+        assert!(ArchivedToken::from_bytes(&encoded).is_err());
+    }
+
+    /// Return a directory for an archive that doesn't exist yet.
+    fn archive_directory(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("pitsa-archive-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    fn archived_token(serial_number: u8, gen_time_epoch_micros: u64) -> ArchivedToken {
+        let claims = TimeStampClaims {
+            serial_number: vec![serial_number],
+            gen_time_epoch_micros,
+            ..claims()
+        };
+        ArchivedToken::new(TimeStampFormat::Cose, &[serial_number; 4096], &claims, &[])
+    }
+
+    fn export_all(token_archive: &TokenArchive, from: u64, to: u64) -> Vec<u8> {
+        let mut export = token_archive.export(from, to);
+        let mut exported = vec![];
+        while let Some(chunk) = export.next_chunk() {
+            assert!(chunk.len() <= EXPORT_CHUNK_SIZE + MAX_ARCHIVED_TOKEN_SIZE);
+            exported.extend(chunk);
+        }
+        exported
+    }
+
+    #[test]
+    fn appended_tokens_are_indexed_and_exported() {
+        let directory = archive_directory("export");
+        let token_archive = TokenArchive::new(directory.clone(), None, None, 0).unwrap();
+        for serial_number in 1..=3 {
+            token_archive
+                .append(&archived_token(
+                    serial_number,
+                    u64::from(serial_number) * 1_000,
+                ))
+                .unwrap();
+        }
+        assert_eq!(
+            token_archive
+                .by_serial_number(&[2])
+                .unwrap()
+                .time_stamp_token,
+            vec![2; 4096]
+        );
+        assert_eq!(token_archive.by_digest(&claims().digest).len(), 3);
+        let expected = [archived_token(2, 2_000), archived_token(3, 3_000)]
+            .iter()
+            .flat_map(ArchivedToken::as_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(export_all(&token_archive, 1_500, 3_000), expected);
+        drop(token_archive);
+        // Indexes are rebuilt from the segments
+        let token_archive = TokenArchive::new(directory.clone(), None, None, 0).unwrap();
+        assert!(token_archive.by_serial_number(&[3]).is_some());
+        assert_eq!(export_all(&token_archive, 1_500, 3_000), expected);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn export_is_chunked_and_stops_at_truncated_tail() {
+        let directory = archive_directory("truncated");
+        let token_archive = TokenArchive::new(directory.clone(), None, None, u64::MAX).unwrap();
+        let count = EXPORT_CHUNK_SIZE / archived_token(0, 0).as_bytes().len() * 2;
+        for i in 0..count {
+            token_archive
+                .append(&archived_token(i as u8, 1_000 + i as u64))
+                .unwrap();
+        }
+        let complete = export_all(&token_archive, 0, u64::MAX);
+        let mut export = token_archive.export(0, u64::MAX);
+        assert!(export.next_chunk().unwrap().len() < complete.len());
+        drop(token_archive);
+        let segment = std::fs::read_dir(&directory)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut file = OpenOptions::new().append(true).open(&segment).unwrap();
+        file.write_all(&archived_token(0, 2_000_000).as_bytes()[..10])
+            .unwrap();
+        drop(file);
+        let token_archive = TokenArchive::new(directory.clone(), None, None, u64::MAX).unwrap();
+        assert_eq!(export_all(&token_archive, 0, u64::MAX), complete);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
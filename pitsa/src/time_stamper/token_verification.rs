@@ -0,0 +1,124 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Outcome of time-stamp token verification.
+
+use super::parsed_time_stamp_token::ParsedTimeStampToken;
+use crate::util;
+
+/// What the message imprint of a time-stamp token is expected to cover.
+pub enum ExpectedImprint {
+    /// The original data, hashed with the token's digest algorithm.
+    Data(Vec<u8>),
+    /// The digest algorithm object identifier and digest of the data.
+    Digest(Vec<u32>, Vec<u8>),
+}
+
+impl ExpectedImprint {
+    /// Return `true` if the message imprint of a time-stamp matches.
+    pub fn matches(&self, digest_algorithm_oid: &[u32], digest: &[u8]) -> bool {
+        match self {
+            Self::Data(data) => {
+                util::digest(digest_algorithm_oid, data).is_some_and(|actual| actual.eq(digest))
+            }
+            Self::Digest(expected_oid, expected_digest) => {
+                expected_oid.eq(digest_algorithm_oid) && expected_digest.eq(digest)
+            }
+        }
+    }
+}
+
+/// Status of the signer's certificate chain.
+pub enum ChainStatus {
+    /// The signature and certificate chain validated against a trust anchor.
+    Valid,
+    /// The validation failed for the provided reason.
+    Invalid(String),
+    /// No trust anchor is available to validate against.
+    NoTrustAnchors,
+}
+
+/// Revocation status of the signer's certificate chain at the point in time
+/// of the time-stamp.
+///
+/// The revocation information embedded in the token and the most recently
+/// fetched for the current TSU certificate chain is evaluated.
+#[derive(Debug, PartialEq)]
+pub enum RevocationStatus {
+    /// No certificate of the chain was revoked at the point in time.
+    Good,
+    /// A certificate of the chain was revoked before the point in time.
+    Revoked(String),
+    /// No verifiable revocation information covers a certificate of the
+    /// chain.
+    Unknown(String),
+    /// No revocation information is embedded in the token and none was
+    /// fetched for its chain.
+    NotEmbedded,
+}
+
+impl RevocationStatus {
+    /// Return the name of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Good => "good",
+            Self::Revoked(_) => "revoked",
+            Self::Unknown(_) => "unknown",
+            Self::NotEmbedded => "notEmbedded",
+        }
+    }
+
+    /// Return why a certificate is revoked or the status is unknown.
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            Self::Revoked(msg) | Self::Unknown(msg) => Some(msg.to_owned()),
+            Self::Good | Self::NotEmbedded => None,
+        }
+    }
+}
+
+/// Outcome of time-stamp token verification.
+pub struct TokenVerification {
+    /// The parsed token.
+    pub token: ParsedTimeStampToken,
+    /// Status of the signer's certificate chain.
+    pub chain_status: ChainStatus,
+    /// Revocation status of the signer's certificate chain.
+    pub revocation_status: RevocationStatus,
+    /// Whether the message imprint matches the expected one, if any.
+    pub imprint_matches: Option<bool>,
+}
+
+impl TokenVerification {
+    /// Return the reason why the token can't be trusted or `None` if it can.
+    pub fn failure(&self) -> Option<String> {
+        match &self.chain_status {
+            ChainStatus::Valid => {}
+            ChainStatus::Invalid(msg) => return Some(msg.to_owned()),
+            ChainStatus::NoTrustAnchors => {
+                return Some("No trust anchors are available.".to_string());
+            }
+        }
+        if let RevocationStatus::Revoked(msg) = &self.revocation_status {
+            return Some(msg.to_owned());
+        }
+        if self.imprint_matches == Some(false) {
+            return Some("Time-stamp message imprint does not match.".to_string());
+        }
+        None
+    }
+}
@@ -0,0 +1,582 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Append-only Merkle tree transparency log of issued time-stamp tokens.
+
+use super::merkle_tree::MerkleLevels;
+use super::merkle_tree::MerkleTree;
+use super::time_stamp_claims::TimeStampClaims;
+use super::tst_signing_info::SigningKeySnapshot;
+use crate::der;
+use crate::der::DerReader;
+use crate::der::tag;
+use std::collections::HashMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tyst::encdec::hex::ToHex;
+
+/// Size of a log identifier in bytes.
+const LOG_ID_SIZE: usize = 16;
+
+/** Entry of the transparency log.
+
+```text
+   TransparencyLogEntry ::= SEQUENCE {
+       tokenHash       OCTET STRING,
+       serialNumber    INTEGER,
+       genTime         GeneralizedTime }
+```
+
+The `tokenHash` is the hash of the DER encoded `TimeStampToken` using the
+digest algorithm of the log.
+*/
+#[derive(Clone)]
+pub struct TransparencyLogEntry {
+    /// Hash of the DER encoded `TimeStampToken`.
+    pub token_hash: Vec<u8>,
+    /// Unsigned big-endian time-stamp serial number.
+    pub serial_number: Vec<u8>,
+    /// Point in time of the time-stamp in microseconds since the Unix epoch.
+    pub gen_time_epoch_micros: u64,
+}
+
+impl TransparencyLogEntry {
+    /// Return the DER encoded `TransparencyLogEntry`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        der::sequence(&[
+            der::octet_string(&self.token_hash),
+            der::unsigned_integer(&self.serial_number),
+            der::generalized_time(self.gen_time_epoch_micros),
+        ])
+    }
+
+    /// Parse a DER encoded `TransparencyLogEntry`.
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, der::DerError> {
+        let mut entry = DerReader::read_single(encoded, tag::SEQUENCE)?.reader();
+        let token_hash = entry.read_expected(tag::OCTET_STRING)?.content.to_vec();
+        let serial_number = entry
+            .read_expected(tag::INTEGER)?
+            .as_unsigned_integer_bytes()?
+            .to_vec();
+        let gen_time_epoch_micros = entry
+            .read_expected(tag::GENERALIZED_TIME)?
+            .as_epoch_micros()?;
+        if !entry.is_empty() {
+            return Err(der::DerError::new("trailing data in TransparencyLogEntry"));
+        }
+        Ok(Self {
+            token_hash,
+            serial_number,
+            gen_time_epoch_micros,
+        })
+    }
+}
+
+/** Header of a persisted transparency log file, followed by the DER encoded
+[TransparencyLogEntry]s in leaf order.
+
+```text
+   TransparencyLogHeader ::= SEQUENCE {
+       logId           OCTET STRING,
+       hashAlgorithm   OBJECT IDENTIFIER }
+```
+*/
+fn log_header(log_id: &[u8], digest_algorithm_oid: &[u32]) -> Vec<u8> {
+    der::sequence(&[der::octet_string(log_id), der::oid(digest_algorithm_oid)])
+}
+
+/** Signed tree head.
+
+The signature covers the
+[RFC 6962 3.5](https://www.rfc-editor.org/rfc/rfc6962#section-3.5)
+`TreeHeadSignature` where the root hash uses the digest algorithm of the log,
+followed by the identifier of the log:
+
+```text
+   digitally-signed struct {
+       Version version;                 -- v1(0)
+       SignatureType signature_type;    -- tree_hash(1)
+       uint64 timestamp;                -- milliseconds since the epoch
+       uint64 tree_size;
+       opaque root_hash[hash size];
+       opaque log_id<0..255>;
+   } TreeHeadSignature;
+```
+
+Tree heads with different log identifiers belong to different logs, e.g. of
+another instance or of an instance that started over without persistence, and
+no consistency proof exists between them.
+*/
+pub struct SignedTreeHead {
+    /// Identifier of the log.
+    pub log_id: Vec<u8>,
+    /// Number of entries in the tree.
+    pub tree_size: u64,
+    /// Milliseconds since the Unix epoch when the tree head was signed.
+    pub timestamp_millis: u64,
+    /// Merkle Tree Hash of the entries.
+    pub root_hash: Vec<u8>,
+    /// Digest algorithm object identifier of the tree.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Signature algorithm object identifier of the signature.
+    pub signing_algorithm_oid: Vec<u32>,
+    /// Signature over the `TreeHeadSignature`.
+    pub signature: Vec<u8>,
+    /// DER encoded signing certificate chain with the leaf first.
+    pub certificate_chain: Vec<Vec<u8>>,
+}
+
+/// Entries, cached complete subtree hashes and the file leaves are persisted
+/// to.
+#[derive(Default)]
+struct LogState {
+    entries: Vec<TransparencyLogEntry>,
+    levels: MerkleLevels,
+    /// Leaf index by token hash.
+    index_by_token_hash: HashMap<Vec<u8>, usize>,
+    writer: Option<File>,
+    /// Why the log file can no longer be appended to, if a write failed.
+    write_error: Option<String>,
+}
+
+/** Append-only [RFC 6962](https://www.rfc-editor.org/rfc/rfc6962) /
+[RFC 9162](https://www.rfc-editor.org/rfc/rfc9162) style Merkle tree of all
+issued time-stamp tokens.
+
+Hashes of complete subtrees are cached, so tree heads and proofs for any
+earlier tree size can be produced without rehashing the log.
+
+When a file is configured, every leaf is synced to it before it becomes part
+of the tree and the log is rebuilt from it on start-up. Otherwise the log
+starts over with a new log identifier.
+*/
+pub struct TransparencyLog {
+    merkle_tree: MerkleTree,
+    log_id: Vec<u8>,
+    state: RwLock<LogState>,
+}
+
+impl TransparencyLog {
+    /// Return a new instance with the leaves found in `file`, if any.
+    pub fn new(digest_algorithm_oid: &[u32], file: Option<PathBuf>) -> Result<Self, String> {
+        let merkle_tree = MerkleTree::new(digest_algorithm_oid).ok_or(format!(
+            "Unknown digest algorithm {}.",
+            tyst::encdec::oid::as_string(digest_algorithm_oid)
+        ))?;
+        let mut state = LogState::default();
+        let log_id = match file {
+            Some(path) => {
+                let (log_id, entries, valid_len) = Self::read_file(&path, digest_algorithm_oid)
+                    .map_err(|e| format!("Unable to read '{}': {e}", path.display()))?;
+                let mut writer = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("Unable to open '{}': {e}", path.display()))?;
+                let file_len = writer.metadata().map_err(|e| e.to_string())?.len();
+                let log_id = match log_id {
+                    Some(log_id) => {
+                        if valid_len < file_len {
+                            // An append was interrupted: that leaf was never
+                            // part of a tree head.
+                            log::warn!(
+                                "Dropping {} bytes of an incomplete entry at the end of '{}'.",
+                                file_len - valid_len,
+                                path.display()
+                            );
+                            writer.set_len(valid_len).map_err(|e| e.to_string())?;
+                        }
+                        log_id
+                    }
+                    None => {
+                        let log_id = Self::new_log_id(&merkle_tree);
+                        writer.set_len(0).map_err(|e| e.to_string())?;
+                        writer
+                            .write_all(&log_header(&log_id, digest_algorithm_oid))
+                            .and_then(|()| writer.sync_data())
+                            .map_err(|e| e.to_string())?;
+                        log_id
+                    }
+                };
+                for entry in entries {
+                    Self::insert(&merkle_tree, &mut state, entry);
+                }
+                log::info!(
+                    "Transparency log '{}' in '{}' has {} entries.",
+                    log_id.to_hex(),
+                    path.display(),
+                    state.entries.len()
+                );
+                state.writer = Some(writer);
+                log_id
+            }
+            None => Self::new_log_id(&merkle_tree),
+        };
+        Ok(Self {
+            merkle_tree,
+            log_id,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Return the log identifier, the entries and the length of the valid
+    /// prefix of a log file, or no identifier if the file is missing or
+    /// empty.
+    fn read_file(
+        path: &Path,
+        digest_algorithm_oid: &[u32],
+    ) -> Result<(Option<Vec<u8>>, Vec<TransparencyLogEntry>, u64), String> {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.to_string()),
+        };
+        if content.is_empty() {
+            return Ok((None, vec![], 0));
+        }
+        let mut reader = DerReader::new(&content);
+        let header = reader
+            .read_expected(tag::SEQUENCE)
+            .map_err(|e| format!("Malformed log header: {e}"))?;
+        let mut header_reader = header.reader();
+        let log_id = header_reader
+            .read_expected(tag::OCTET_STRING)
+            .map_err(|e| format!("Malformed log header: {e}"))?
+            .content
+            .to_vec();
+        let hash_algorithm = header_reader
+            .read_expected(tag::OID)
+            .and_then(|oid| oid.as_oid())
+            .map_err(|e| format!("Malformed log header: {e}"))?;
+        if hash_algorithm != digest_algorithm_oid {
+            return Err(format!(
+                "The log uses {} instead of the configured digest algorithm.",
+                tyst::encdec::oid::as_string(&hash_algorithm)
+            ));
+        }
+        let mut valid_len = header.encoded.len();
+        let mut entries = vec![];
+        while !reader.is_empty() {
+            let Some((entry, len)) = reader.read().ok().and_then(|tlv| {
+                TransparencyLogEntry::from_bytes(tlv.encoded)
+                    .ok()
+                    .map(|entry| (entry, tlv.encoded.len()))
+            }) else {
+                break;
+            };
+            valid_len += len;
+            entries.push(entry);
+        }
+        Ok((Some(log_id), entries, valid_len as u64))
+    }
+
+    /// Return an identifier that is unique for each new log.
+    fn new_log_id(merkle_tree: &MerkleTree) -> Vec<u8> {
+        let now_epoch_nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let mut log_id = merkle_tree.hash(
+            &[
+                &now_epoch_nanos.to_be_bytes()[..],
+                &std::process::id().to_be_bytes(),
+                std::env::var("HOSTNAME").unwrap_or_default().as_bytes(),
+            ]
+            .concat(),
+        );
+        log_id.truncate(LOG_ID_SIZE);
+        log_id
+    }
+
+    /// Add an entry to the tree and indexes and return the leaf index.
+    fn insert(merkle_tree: &MerkleTree, state: &mut LogState, entry: TransparencyLogEntry) -> u64 {
+        let leaf_hash = merkle_tree.leaf_hash(&entry.as_bytes());
+        let index = state.entries.len();
+        state
+            .index_by_token_hash
+            .insert(entry.token_hash.to_owned(), index);
+        state.entries.push(entry);
+        merkle_tree.append(&mut state.levels, leaf_hash);
+        index as u64
+    }
+
+    /// Append an issued time-stamp, encoded as returned to the client, and
+    /// return the leaf index.
+    ///
+    /// With a log file the entry is synced to it first, so a leaf is never
+    /// part of a tree head that a restart would lose. After a failed write
+    /// the end of the file is unknown and every later append fails too.
+    pub fn append(
+        &self,
+        time_stamp_token: &[u8],
+        claims: &TimeStampClaims,
+    ) -> Result<u64, std::io::Error> {
+        let entry = TransparencyLogEntry {
+            token_hash: self.merkle_tree.hash(time_stamp_token),
+            serial_number: claims.serial_number.to_owned(),
+            gen_time_epoch_micros: claims.gen_time_epoch_micros,
+        };
+        let mut state = self.state.write().unwrap();
+        if let Some(write_error) = &state.write_error {
+            return Err(std::io::Error::other(write_error.to_owned()));
+        }
+        if let Some(writer) = state.writer.as_mut()
+            && let Err(e) = writer
+                .write_all(&entry.as_bytes())
+                .and_then(|()| writer.sync_data())
+        {
+            state.write_error = Some(e.to_string());
+            return Err(e);
+        }
+        Ok(Self::insert(&self.merkle_tree, &mut state, entry))
+    }
+
+    /// Return why the log can no longer be appended to, if it can't.
+    pub fn write_error(&self) -> Option<String> {
+        self.state.read().unwrap().write_error.to_owned()
+    }
+
+    /// Return the identifier of the log.
+    pub fn log_id(&self) -> &[u8] {
+        &self.log_id
+    }
+
+    /// Return the current number of entries.
+    pub fn tree_size(&self) -> u64 {
+        self.state.read().unwrap().entries.len() as u64
+    }
+
+    /// Return `true` if a thread panicked while holding the log state.
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// Return the digest algorithm object identifier of the tree.
+    pub fn digest_algorithm_oid(&self) -> &[u32] {
+        self.merkle_tree.digest_algorithm_oid()
+    }
+
+    /// Return the hash of a DER encoded `TimeStampToken` as used in the log.
+    pub fn token_hash(&self, time_stamp_token: &[u8]) -> Vec<u8> {
+        self.merkle_tree.hash(time_stamp_token)
+    }
+
+    /// Return the entry at `index`.
+    pub fn entry(&self, index: u64) -> Option<TransparencyLogEntry> {
+        let state = self.state.read().unwrap();
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| state.entries.get(index))
+            .cloned()
+    }
+
+    /// Return the leaf index of a token by its hash.
+    pub fn index_of(&self, token_hash: &[u8]) -> Option<u64> {
+        self.state
+            .read()
+            .unwrap()
+            .index_by_token_hash
+            .get(token_hash)
+            .map(|index| *index as u64)
+    }
+
+    /// Return a signed head of the current tree or `None` if signing fails.
+    pub fn signed_tree_head(
+        &self,
+        signing_key: &SigningKeySnapshot,
+        timestamp_millis: u64,
+    ) -> Option<SignedTreeHead> {
+        let (tree_size, root_hash) = {
+            let state = self.state.read().unwrap();
+            let tree_size = state.levels.leaf_count();
+            (tree_size, self.merkle_tree.root(&state.levels, tree_size)?)
+        };
+        let tree_head_signature = [
+            &[0u8, 1u8][..],
+            &timestamp_millis.to_be_bytes(),
+            &(tree_size as u64).to_be_bytes(),
+            &root_hash,
+            &[self.log_id.len() as u8],
+            &self.log_id,
+        ]
+        .concat();
+        let signature = signing_key.sign(&tree_head_signature)?;
+        Some(SignedTreeHead {
+            log_id: self.log_id.to_owned(),
+            tree_size: tree_size as u64,
+            timestamp_millis,
+            root_hash,
+            digest_algorithm_oid: self.merkle_tree.digest_algorithm_oid().to_vec(),
+            signing_algorithm_oid: signing_key.signing_algorithm_oid.to_owned(),
+            signature,
+            certificate_chain: signing_key.certificate_chain.to_owned(),
+        })
+    }
+
+    /// Return the audit path of the leaf at `index` in the tree of the first
+    /// `tree_size` entries or `None` if out of range.
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> Option<Vec<Vec<u8>>> {
+        self.merkle_tree.inclusion_proof(
+            &self.state.read().unwrap().levels,
+            usize::try_from(index).ok()?,
+            usize::try_from(tree_size).ok()?,
+        )
+    }
+
+    /// Return the proof that the tree of the first `first` entries is a
+    /// prefix of the tree of the first `second` entries or `None` if out of
+    /// range.
+    pub fn consistency_proof(&self, first: u64, second: u64) -> Option<Vec<Vec<u8>>> {
+        self.merkle_tree.consistency_proof(
+            &self.state.read().unwrap().levels,
+            usize::try_from(first).ok()?,
+            usize::try_from(second).ok()?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::OID_SHA256;
+
+    /// SHA-384 object identifier.
+    const OID_SHA384: &[u32] = &[2, 16, 840, 1, 101, 3, 4, 2, 2];
+
+    fn claims(serial_number: u8) -> TimeStampClaims {
+        TimeStampClaims {
+            policy_oid: vec![1, 2, 3, 4],
+            digest_algorithm_oid: OID_SHA256.to_vec(),
+            digest: vec![0xab; 32],
+            serial_number: vec![serial_number],
+            gen_time_epoch_micros: 1_750_000_000_000_000 + u64::from(serial_number),
+            accuracy_micros: None,
+            nonce: None,
+        }
+    }
+
+    /// Return a path for a log file that doesn't exist yet.
+    fn log_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pitsa-transparency-{}-{name}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn root(transparency_log: &TransparencyLog) -> Vec<u8> {
+        let state = transparency_log.state.read().unwrap();
+        transparency_log
+            .merkle_tree
+            .root(&state.levels, state.levels.leaf_count())
+            .unwrap()
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let entry = TransparencyLogEntry {
+            token_hash: vec![0x11; 32],
+            serial_number: vec![0x80, 0x01],
+            gen_time_epoch_micros: 1_750_000_000_123_456,
+        };
+        let parsed = TransparencyLogEntry::from_bytes(&entry.as_bytes()).unwrap();
+        assert_eq!(parsed.token_hash, entry.token_hash);
+        assert_eq!(parsed.serial_number, entry.serial_number);
+        assert_eq!(parsed.gen_time_epoch_micros, entry.gen_time_epoch_micros);
+        let mut trailing = entry.as_bytes();
+        trailing.push(0);
+        assert!(TransparencyLogEntry::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn in_memory_logs_get_new_log_ids() {
+        let first = TransparencyLog::new(OID_SHA256, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let second = TransparencyLog::new(OID_SHA256, None).unwrap();
+        assert_eq!(first.log_id().len(), LOG_ID_SIZE);
+        assert_ne!(first.log_id(), second.log_id());
+    }
+
+    #[test]
+    fn persisted_log_is_rebuilt() {
+        let path = log_file("rebuilt");
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        for serial_number in 0..5 {
+            assert_eq!(
+                transparency_log
+                    .append(&[serial_number], &claims(serial_number))
+                    .unwrap(),
+                u64::from(serial_number)
+            );
+        }
+        let log_id = transparency_log.log_id().to_vec();
+        let root_hash = root(&transparency_log);
+        drop(transparency_log);
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        assert_eq!(transparency_log.log_id(), log_id);
+        assert_eq!(transparency_log.tree_size(), 5);
+        assert_eq!(root(&transparency_log), root_hash);
+        let token_hash = transparency_log.token_hash(&[3]);
+        assert_eq!(transparency_log.index_of(&token_hash), Some(3));
+        assert_eq!(transparency_log.append(&[5], &claims(5)).unwrap(), 5);
+        assert!(transparency_log.consistency_proof(5, 6).is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn incomplete_entry_is_dropped() {
+        let path = log_file("incomplete");
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        transparency_log.append(&[0], &claims(0)).unwrap();
+        drop(transparency_log);
+        let complete_len = std::fs::metadata(&path).unwrap().len();
+        let partial_entry = TransparencyLogEntry {
+            token_hash: vec![0x22; 32],
+            serial_number: vec![1],
+            gen_time_epoch_micros: 1,
+        }
+        .as_bytes();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&partial_entry[..10]).unwrap();
+        drop(file);
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        assert_eq!(transparency_log.tree_size(), 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), complete_len);
+        assert_eq!(transparency_log.append(&[1], &claims(1)).unwrap(), 1);
+        drop(transparency_log);
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        assert_eq!(transparency_log.tree_size(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn other_digest_algorithm_is_refused() {
+        let path = log_file("digest");
+        let transparency_log = TransparencyLog::new(OID_SHA256, Some(path.clone())).unwrap();
+        transparency_log.append(&[0], &claims(0)).unwrap();
+        drop(transparency_log);
+        assert!(TransparencyLog::new(OID_SHA384, Some(path.clone())).is_err());
+        std::fs::write(&path, [0x04, 0x00]).unwrap();
+        assert!(TransparencyLog::new(OID_SHA256, Some(path.clone())).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
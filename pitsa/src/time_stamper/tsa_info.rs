@@ -0,0 +1,110 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Capabilities of this Time-Stamping Authority for discovery by relying
+//! parties.
+
+use crate::conf::AppConfig;
+
+/// A way to submit requests to this TSA.
+#[derive(Clone)]
+pub struct Transport {
+    /// Request and response format.
+    pub format: &'static str,
+    /// `http`, `https` or `tcp`.
+    pub protocol: &'static str,
+    /// Path of the HTTP resource or `None` for the TCP-based transport.
+    pub path: Option<&'static str>,
+    /// Listening port.
+    pub port: u16,
+}
+
+impl Transport {
+    /// HTTP resources that issue time-stamps as `(format, path)`.
+    const HTTP_RESOURCES: &'static [(&'static str, &'static str)] = &[
+        ("rfc3161", "/api/v1/tsp"),
+        ("json", "/api/v1/tsp/json"),
+        ("hash", "/api/v1/tsp/hash"),
+        ("jws", "/api/v1/tsp/jws"),
+        ("cose", "/api/v1/tsp/cose"),
+        ("authenticode", "/api/v1/authenticode"),
+        ("dvcs", "/api/v1/dvcs"),
+        ("sigstore", "/api/v1/timestamp"),
+    ];
+
+    /// Return the transports enabled by the configuration.
+    pub fn configured(app_config: &AppConfig) -> Vec<Self> {
+        let protocol = if app_config.api.tls_pem_paths().is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let port = app_config.api.bind_port();
+        let mut transports = Self::HTTP_RESOURCES
+            .iter()
+            .map(|&(format, path)| Self {
+                format,
+                protocol,
+                path: Some(path),
+                port,
+            })
+            .collect::<Vec<_>>();
+        if app_config.sign.aggregation_window_micros().is_some()
+            && app_config.sign.aggregation_proof_attribute_oid().is_some()
+        {
+            transports.push(Self {
+                format: "rfc3161-aggregated",
+                protocol,
+                path: Some("/api/v1/tsp/aggregated"),
+                port,
+            });
+        }
+        if let Some(tcp_port) = app_config.api.tcp_bind_port() {
+            transports.push(Self {
+                format: "rfc3161",
+                protocol: "tcp",
+                path: None,
+                port: tcp_port,
+            });
+        }
+        transports
+    }
+}
+
+/// Capabilities of this TSA.
+pub struct TsaInfo {
+    /// Object identifiers of the TSA policies that requests may ask for.
+    pub policy_oids: Vec<String>,
+    /// Object identifiers of the allowed message imprint digest algorithms
+    /// or empty when any supported algorithm is allowed.
+    pub allowed_digest_oids: Vec<String>,
+    /// Object identifier of the signature algorithm of the current signing
+    /// key.
+    pub signature_algorithm_oid: Option<String>,
+    /// Object identifier of the digest algorithm used in the time-stamp
+    /// token signatures.
+    pub digest_algorithm_oid: Option<String>,
+    /// Accuracy declared for the most recent time measurement.
+    pub declared_accuracy_micros: Option<u64>,
+    /// Worst accuracy declared in issued time-stamps.
+    pub tolerable_accuracy_micros: u64,
+    /// Policy object identifiers under which qualified electronic
+    /// time-stamps are issued.
+    pub qualified_policy_oids: Vec<String>,
+    /// Ways to submit requests.
+    pub transports: Vec<Transport>,
+}
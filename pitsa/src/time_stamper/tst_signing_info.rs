@@ -18,7 +18,11 @@
 //! Signature certificate chain and private key.
 
 use crossbeam_skiplist::SkipMap;
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use tyst::Tyst;
 use tyst::traits::se::PrivateKey;
 use upkit_common::x509::cert::parse::CertificateParser;
@@ -29,7 +33,16 @@ use upkit_leafops::enprov::EnrollmentProvider;
 use upkit_leafops::enprov::MonitoredChain;
 use upkit_leafops::enprov::MonitoredRevocationInfo;
 
+use super::jws_time_stamp;
+use super::qualified::QualifiedTimeStamp;
+use super::revocation_evaluation::RevocationInfo;
 use crate::conf::AppConfig;
+use crate::der;
+use crate::der::x509;
+use crate::logging::AuditEvent;
+use crate::logging::AuditEventType;
+use crate::telemetry::Span;
+use crate::util;
 
 /// The currently used time-stamp signing information.
 struct CurrentSigningInfo {
@@ -41,6 +54,64 @@ struct CurrentSigningInfo {
     pub private_key: Arc<Box<dyn PrivateKey>>,
     /// Ordered signing certificate chain with the leaf first.
     pub signing_certificate_chain: Arc<MonitoredChain>,
+    /// Number of signatures made with the private key.
+    pub signature_count: Arc<AtomicU64>,
+    /// Result of the signature self-test when the key was taken into use.
+    pub signature_self_test: Result<(), String>,
+}
+
+/// Snapshot of the state of the current signing key and certificate.
+pub struct SignerStatus {
+    /// Signing certificate `notBefore` in microseconds since the Unix epoch.
+    pub not_before_epoch_micros: u64,
+    /// Signing certificate `notAfter` in microseconds since the Unix epoch.
+    pub not_after_epoch_micros: u64,
+    /// Production time of the oldest revocation information for the chain.
+    pub oldest_revocation_info_epoch_micros: Option<u64>,
+    /// `true` if revocation information is missing for any certificate in the chain.
+    pub revocation_info_missing: bool,
+    /// Number of signatures made with the current private key.
+    pub signature_count: u64,
+    /// Result of the signature self-test of the current key and certificate.
+    pub signature_self_test: Result<(), String>,
+}
+
+/// Signing certificate chain that is no longer used for new signatures.
+#[derive(Clone)]
+pub struct RetiredCertificateChain {
+    /// Local system time when the chain was replaced.
+    pub retired_epoch_micros: u64,
+    /// Ordered DER encoded signing certificate chain with the leaf first.
+    pub certificate_chain: Vec<Vec<u8>>,
+}
+
+/// Snapshot of the current private key and signing certificate chain for
+/// signing other structures than time-stamp tokens.
+pub struct SigningKeySnapshot {
+    /// Content digest algorithms object identifier.
+    pub digest_algorithm_oid: Vec<u32>,
+    /// Digital signature algorithm object identifier.
+    pub signing_algorithm_oid: Vec<u32>,
+    /// Ordered DER encoded signing certificate chain with the leaf first.
+    pub certificate_chain: Vec<Vec<u8>>,
+    private_key: Arc<Box<dyn PrivateKey>>,
+    signature_count: Arc<AtomicU64>,
+}
+
+impl SigningKeySnapshot {
+    /// Return the digest of `data` using the content digest algorithm.
+    pub fn digest(&self, data: &[u8]) -> Option<Vec<u8>> {
+        util::digest(&self.digest_algorithm_oid, data)
+    }
+
+    /// Return the signature of `data` using the digital signature algorithm.
+    pub fn sign(&self, data: &[u8]) -> Option<Vec<u8>> {
+        self.signature_count.fetch_add(1, Ordering::Relaxed);
+        Tyst::instance()
+            .ses()
+            .by_oid(&tyst::encdec::oid::as_string(&self.signing_algorithm_oid))
+            .map(|mut se| se.sign(self.private_key.as_ref().as_ref(), data))
+    }
 }
 
 /** Maintains up to date private key, signing certificate chain and revocation
@@ -64,12 +135,24 @@ pub struct TimeStampTokenSigningInfo {
     current_signing_info: SkipMap<(), Arc<CurrentSigningInfo>>,
     certificate_signature_algo_oid: Vec<u32>,
     supported_digest_algorithm_oid: Vec<u32>,
+    enrollment_error: Mutex<Option<String>>,
+    /// Most recently retired signing certificate chains with the newest first.
+    retired_certificate_chains: Mutex<VecDeque<RetiredCertificateChain>>,
 }
 
 impl TimeStampTokenSigningInfo {
-    /// Return a new instance.
-    pub async fn new(app_config: &Arc<AppConfig>) -> Arc<Self> {
-        Arc::new(Self {
+    /// Data signed by the signature self-test.
+    const SELF_TEST_DATA: &'static [u8] = b"PiTSA signature self-test";
+    /// Number of retired signing certificate chains to keep track of.
+    const MAX_RETIRED_CERTIFICATE_CHAINS: usize = 5;
+
+    /// Return a new instance or why the signing key can't be used.
+    ///
+    /// When qualified time-stamps are configured, the first TSU certificate is
+    /// enrolled before this returns and refused if it is not suitable, since
+    /// every later enrollment results in the same certificate profile.
+    pub async fn new(app_config: &Arc<AppConfig>) -> Result<Arc<Self>, String> {
+        let tst_signing_info = Arc::new(Self {
             app_config: Arc::clone(app_config),
             cep: CertificateEnrollmentProvider::with_options(
                 &app_config.sign.enrollment_provider_options(),
@@ -77,22 +160,47 @@ impl TimeStampTokenSigningInfo {
             current_signing_info: SkipMap::default(),
             certificate_signature_algo_oid: app_config.sign.signature_algorithm_oid(),
             supported_digest_algorithm_oid: app_config.sign.digest_algorithm_oid(),
-        })
-        .init()
-        .await
+            enrollment_error: Mutex::new(None),
+            retired_certificate_chains: Mutex::new(VecDeque::new()),
+        });
+        let enrolled_chain = if app_config.sign.qualified_policy_oids().is_empty() {
+            None
+        } else {
+            tst_signing_info.enroll_signing_info().await?
+        };
+        Ok(tst_signing_info.init(enrolled_chain))
     }
 
     /// Start background task to ensure that we always have a valid signing
     /// certificate at hand.
-    async fn init(self: Arc<Self>) -> Arc<Self> {
+    ///
+    /// A failed enrollment is retried every 30 seconds. A TSU certificate that
+    /// is refused for qualified time-stamps ends the task: The current
+    /// certificate is used until it expires and the refusal is reported as
+    /// enrollment error.
+    fn init(self: Arc<Self>, enrolled_chain: Option<Arc<MonitoredChain>>) -> Arc<Self> {
         let self_clone = Arc::clone(&self);
         tokio::spawn(async move {
+            let mut enrolled_chain = enrolled_chain;
             loop {
-                self_clone.maintain_signing_info().await;
-                tokio::time::sleep(tokio::time::Duration::from_micros(30_000_000)).await;
+                if let Some(signing_certificate_chain) = enrolled_chain.take() {
+                    // Even if crazy short lived certs are used or certs that are revoked upon issuance, we limit the renewals to at least 1 second internvals.
+                    tokio::time::sleep(tokio::time::Duration::from_millis(1_000)).await;
+                    signing_certificate_chain
+                        .await_leaf_expiration_or_chain_revocation(3 * 60)
+                        .await;
+                }
+                match self_clone.enroll_signing_info().await {
+                    Ok(Some(signing_certificate_chain)) => {
+                        enrolled_chain = Some(signing_certificate_chain);
+                    }
+                    Ok(None) => {
+                        tokio::time::sleep(tokio::time::Duration::from_micros(30_000_000)).await;
+                    }
+                    Err(_) => break,
+                }
             }
         });
-
         self
     }
 
@@ -113,82 +221,281 @@ impl TimeStampTokenSigningInfo {
         ret
     }
 
-    /// Continiously keep signing certificate up to date
-    async fn maintain_signing_info(self: &Arc<Self>) {
-        log::debug!("Checking for newer signing certificate.");
-        // new
-        let sing_algo_oid_str = tyst::encdec::oid::as_string(&self.certificate_signature_algo_oid);
-        if let Some(mut se) = Tyst::instance().ses().by_oid(&sing_algo_oid_str) {
-            loop {
-                let (public_key, private_key) = se.generate_key_pair();
-                let signing_certificate_chain = self.cep.enroll_from_key_pair(
-                    &self.certificate_signature_algo_oid,
-                    public_key.as_ref(),
-                    private_key.as_ref(),
-                );
-                let signing_certificate_chain = MonitoredChain::new(
-                    signing_certificate_chain,
-                    &self.supported_digest_algorithm_oid,
-                )
-                .track_chain_status(3_000)
-                .await;
-                // Log certificate to allow correlation to this instance.
-                if let Some(signing_cert) = signing_certificate_chain
-                    .get_parsed_certificate_chain()
-                    .first()
-                {
-                    let issuer_dn = signing_cert
-                        .get_issuer()
-                        .ok()
-                        .and_then(|value| serde_json::to_string(&value).ok())
-                        .unwrap_or("unknown".to_string());
-                    log::info!(
-                        "This instance ('{}') was issued a certificate with issuer '{issuer_dn}' and serial number 0x{}.",
-                        self.app_config
-                            .context
-                            .as_ref()
-                            .map(|context_config| context_config.get_kubernetes_context())
-                            .unwrap_or("(no k8s context detected)".to_string()),
-                        signing_cert.get_serial_number().to_hex(),
-                    );
-                }
+    /// Keep track of a signing certificate chain that was replaced.
+    fn retire_certificate_chain(&self, certificate_chain: Vec<Vec<u8>>) {
+        let mut retired_certificate_chains = self.retired_certificate_chains.lock().unwrap();
+        retired_certificate_chains.push_front(RetiredCertificateChain {
+            retired_epoch_micros: upkit_common::util::time::now_epoch_micros(),
+            certificate_chain,
+        });
+        retired_certificate_chains.truncate(Self::MAX_RETIRED_CERTIFICATE_CHAINS);
+    }
 
-                if let Some(old) = self.set_current_signing_info(Arc::new(CurrentSigningInfo {
-                    digest_algorithm_oid: self.supported_digest_algorithm_oid.to_vec(),
-                    signing_algorithm_oid: self.certificate_signature_algo_oid.to_vec(),
-                    private_key: Arc::new(private_key),
-                    signing_certificate_chain: Arc::clone(&signing_certificate_chain),
-                })) {
-                    old.signing_certificate_chain.stop_tracking();
-                }
-                // Even if crazy short lived certs are used or certs that are revoked upon issuance, we limit the renewals to at least 1 second internvals.
-                tokio::time::sleep(tokio::time::Duration::from_millis(1_000)).await;
-                signing_certificate_chain
-                    .await_leaf_expiration_or_chain_revocation(3 * 60)
-                    .await;
-            }
+    /// Return the most recently retired signing certificate chains with the
+    /// newest first.
+    pub fn retired_certificate_chains(&self) -> Vec<RetiredCertificateChain> {
+        self.retired_certificate_chains
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Keep track of why no new signing certificate could be taken into use.
+    fn set_enrollment_error(&self, enrollment_error: Option<String>) {
+        *self.enrollment_error.lock().unwrap() = enrollment_error;
+    }
+
+    /// Return why the most recent attempt to take a new signing certificate
+    /// into use failed, if it did.
+    pub fn enrollment_error(&self) -> Option<String> {
+        self.enrollment_error.lock().unwrap().clone()
+    }
+
+    /// Return `true` if a thread panicked while holding a lock of the
+    /// signing state.
+    pub fn is_poisoned(&self) -> bool {
+        self.enrollment_error.is_poisoned() || self.retired_certificate_chains.is_poisoned()
+    }
+
+    /// Sign known data with the private key and verify the signature with
+    /// the public key of the signing certificate.
+    fn signature_self_test(
+        signing_algorithm_oid: &[u32],
+        private_key: &dyn PrivateKey,
+        encoded_signing_certificate: &[u8],
+    ) -> Result<(), String> {
+        let signature = Tyst::instance()
+            .ses()
+            .by_oid(&tyst::encdec::oid::as_string(signing_algorithm_oid))
+            .map(|mut se| se.sign(private_key, Self::SELF_TEST_DATA))
+            .ok_or("Unknown signature algorithm.".to_string())?;
+        let subject_public_key_info = x509::subject_public_key_info(encoded_signing_certificate)
+            .map_err(|e| format!("Malformed signing certificate: {e}"))?;
+        if jws_time_stamp::verify_signature_with_public_key(
+            signing_algorithm_oid,
+            &subject_public_key_info,
+            &signature,
+            Self::SELF_TEST_DATA,
+        )? {
+            Ok(())
         } else {
-            log::error!("Unknown signature algorithm '{sing_algo_oid_str}'.");
+            Err("Signature does not verify with the signing certificate public key.".to_string())
         }
     }
 
-    /// Return `true`if the current signing certificate is valid.
+    /// Enroll a new key pair and take it into use.
     ///
-    /// Useful for health checking.
-    // TODO: Also check revocation!
-    pub fn valid_signing_info_available(self: &Arc<Self>) -> bool {
-        self.get_current_signing_info().is_some_and(|csi| {
-            csi.signing_certificate_chain
-                .get_parsed_certificate_chain()
+    /// Return the enrolled certificate chain to monitor, `None` if the
+    /// enrollment failed or why the TSU certificate is not suitable for
+    /// qualified time-stamps.
+    async fn enroll_signing_info(self: &Arc<Self>) -> Result<Option<Arc<MonitoredChain>>, String> {
+        log::debug!("Checking for newer signing certificate.");
+        let sing_algo_oid_str = tyst::encdec::oid::as_string(&self.certificate_signature_algo_oid);
+        let Some(mut se) = Tyst::instance().ses().by_oid(&sing_algo_oid_str) else {
+            log::error!("Unknown signature algorithm '{sing_algo_oid_str}'.");
+            self.set_enrollment_error(Some(format!(
+                "Unknown signature algorithm '{sing_algo_oid_str}'."
+            )));
+            return Ok(None);
+        };
+        let (public_key, private_key) = se.generate_key_pair();
+        let signing_certificate_chain = self.cep.enroll_from_key_pair(
+            &self.certificate_signature_algo_oid,
+            public_key.as_ref(),
+            private_key.as_ref(),
+        );
+        let signing_certificate_chain = MonitoredChain::new(
+            signing_certificate_chain,
+            &self.supported_digest_algorithm_oid,
+        )
+        .track_chain_status(3_000)
+        .await;
+        let Some(encoded_signing_certificate) = signing_certificate_chain
+            .get_encoded_certificate_chain()
+            .first()
+            .cloned()
+        else {
+            log::error!("Enrollment did not result in a signing certificate.");
+            self.set_enrollment_error(Some(
+                "Enrollment did not result in a signing certificate.".to_string(),
+            ));
+            signing_certificate_chain.stop_tracking();
+            return Ok(None);
+        };
+        // Log certificate to allow correlation to this instance.
+        if let Some(signing_cert) = signing_certificate_chain
+            .get_parsed_certificate_chain()
+            .first()
+        {
+            let issuer_dn = signing_cert
+                .get_issuer()
+                .ok()
+                .and_then(|value| serde_json::to_string(&value).ok())
+                .unwrap_or("unknown".to_string());
+            log::info!(
+                "This instance ('{}') was issued a certificate with issuer '{issuer_dn}' and serial number 0x{}.",
+                self.app_config
+                    .context
+                    .as_ref()
+                    .map(|context_config| context_config.get_kubernetes_context())
+                    .unwrap_or("(no k8s context detected)".to_string()),
+                signing_cert.get_serial_number().to_hex(),
+            );
+        }
+        // Refuse to mark time-stamps as qualified with a certificate that isn't.
+        if !self.app_config.sign.qualified_policy_oids().is_empty()
+            && let Err(msg) =
+                QualifiedTimeStamp::assert_signing_certificate(&encoded_signing_certificate)
+        {
+            log::error!(
+                "Qualified time-stamps are configured, but the TSU certificate is not suitable: {msg}"
+            );
+            let enrollment_error =
+                format!("TSU certificate is not suitable for qualified time-stamps: {msg}");
+            self.set_enrollment_error(Some(enrollment_error.to_owned()));
+            AuditEvent::new(AuditEventType::SignerRefused)
+                .with("reason", msg)
+                .emit();
+            signing_certificate_chain.stop_tracking();
+            return Err(enrollment_error);
+        }
+        let signature_self_test = Self::signature_self_test(
+            &self.certificate_signature_algo_oid,
+            private_key.as_ref(),
+            &encoded_signing_certificate,
+        );
+        if let Err(msg) = &signature_self_test {
+            log::error!("Signature self-test failed: {msg}");
+        }
+        self.set_enrollment_error(None);
+        if let Some(old) = self.set_current_signing_info(Arc::new(CurrentSigningInfo {
+            digest_algorithm_oid: self.supported_digest_algorithm_oid.to_vec(),
+            signing_algorithm_oid: self.certificate_signature_algo_oid.to_vec(),
+            private_key: Arc::new(private_key),
+            signing_certificate_chain: Arc::clone(&signing_certificate_chain),
+            signature_count: Arc::new(AtomicU64::new(0)),
+            signature_self_test,
+        })) {
+            self.retire_certificate_chain(
+                old.signing_certificate_chain
+                    .get_encoded_certificate_chain()
+                    .to_vec(),
+            );
+            old.signing_certificate_chain.stop_tracking();
+        }
+        if let Some(signing_cert) = signing_certificate_chain
+            .get_parsed_certificate_chain()
+            .first()
+        {
+            let mut audit_event = AuditEvent::new(AuditEventType::SignerRotated).with(
+                "tsuCertificateSerialNumber",
+                signing_cert.get_serial_number().to_hex(),
+            );
+            if let Some((_not_before, not_after)) = signing_certificate_chain
+                .get_encoded_certificate_chain()
                 .first()
-                .unwrap()
-                .get_validity()
-                .is_valid_at(upkit_common::util::time::now_epoch_seconds())
+                .and_then(|encoded| x509::validity_epoch_micros(encoded).ok())
+            {
+                audit_event = audit_event.with("notAfter", der::rfc3339(not_after));
+            }
+            audit_event.emit();
+        }
+        Ok(Some(signing_certificate_chain))
+    }
+
+    /// Get a snapshot of the current private key and certificate chain.
+    pub fn get_signing_key_snapshot(self: &Arc<Self>) -> Option<SigningKeySnapshot> {
+        self.get_current_signing_info()
+            .map(|csi| SigningKeySnapshot {
+                digest_algorithm_oid: csi.digest_algorithm_oid.to_vec(),
+                signing_algorithm_oid: csi.signing_algorithm_oid.to_vec(),
+                certificate_chain: csi
+                    .signing_certificate_chain
+                    .get_encoded_certificate_chain()
+                    .to_vec(),
+                private_key: Arc::clone(&csi.private_key),
+                signature_count: Arc::clone(&csi.signature_count),
+            })
+    }
+
+    /// Return the state of the current signing key and certificate if any.
+    pub fn signer_status(self: &Arc<Self>) -> Option<SignerStatus> {
+        let csi = self.get_current_signing_info()?;
+        let (not_before_epoch_micros, not_after_epoch_micros) = csi
+            .signing_certificate_chain
+            .get_encoded_certificate_chain()
+            .first()
+            .and_then(|encoded| x509::validity_epoch_micros(encoded).ok())?;
+        let mut oldest_revocation_info_epoch_micros: Option<u64> = None;
+        let mut revocation_info_missing = false;
+        for fingerprint in csi
+            .signing_certificate_chain
+            .get_parsed_certificate_chain()
+            .iter()
+            .map(CertificateParser::fingerprint)
+        {
+            let produced_epoch_micros = match csi
+                .signing_certificate_chain
+                .get_revocation_info(fingerprint)
+            {
+                MonitoredRevocationInfo::Crl { encoded } => {
+                    x509::crl_this_update_epoch_micros(&encoded).ok()
+                }
+                MonitoredRevocationInfo::OcspResponse { encoded } => {
+                    x509::ocsp_produced_at_epoch_micros(&encoded).ok()
+                }
+                MonitoredRevocationInfo::NotDefinedInCertificate => None,
+                MonitoredRevocationInfo::Missing => {
+                    revocation_info_missing = true;
+                    None
+                }
+            };
+            if let Some(produced_epoch_micros) = produced_epoch_micros {
+                oldest_revocation_info_epoch_micros = Some(
+                    oldest_revocation_info_epoch_micros.map_or(produced_epoch_micros, |oldest| {
+                        oldest.min(produced_epoch_micros)
+                    }),
+                );
+            }
+        }
+        Some(SignerStatus {
+            not_before_epoch_micros,
+            not_after_epoch_micros,
+            oldest_revocation_info_epoch_micros,
+            revocation_info_missing,
+            signature_count: csi.signature_count.load(Ordering::Relaxed),
+            signature_self_test: csi.signature_self_test.clone(),
         })
     }
 
+    /// Return the most recently fetched revocation information of the current
+    /// signing certificate chain.
+    pub fn current_revocation_infos(self: &Arc<Self>) -> Vec<RevocationInfo> {
+        let Some(csi) = self.get_current_signing_info() else {
+            return vec![];
+        };
+        csi.signing_certificate_chain
+            .get_parsed_certificate_chain()
+            .iter()
+            .map(CertificateParser::fingerprint)
+            .filter_map(
+                |fp| match csi.signing_certificate_chain.get_revocation_info(fp) {
+                    MonitoredRevocationInfo::Crl { encoded } => Some(RevocationInfo::Crl(encoded)),
+                    MonitoredRevocationInfo::OcspResponse { encoded } => {
+                        Some(RevocationInfo::OcspResponse(encoded))
+                    }
+                    MonitoredRevocationInfo::NotDefinedInCertificate
+                    | MonitoredRevocationInfo::Missing => None,
+                },
+            )
+            .collect()
+    }
+
     /// Get a snapshot of the current info
     pub fn get_dynamic_singing_info(self: &Arc<Self>) -> Option<TimeStampTokenSigner> {
+        let _span = Span::start("TimeStampTokenSigningInfo::get_dynamic_singing_info");
         self.get_current_signing_info().and_then(|csi|{
             let digest_algo_oid = csi.digest_algorithm_oid.to_vec();
             let sign_algo_oid = csi.signing_algorithm_oid.to_vec();
@@ -216,6 +523,7 @@ impl TimeStampTokenSigningInfo {
                     }
                 }
             }
+            csi.signature_count.fetch_add(1, Ordering::Relaxed);
             Some(TimeStampTokenSigner::new(
                 digest_algo_oid,
                 sign_algo_oid,
@@ -0,0 +1,124 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Hex, base64url and digest helpers shared by the encoders and parsers, and
+//! the shared outgoing HTTP client.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+use tyst::Tyst;
+
+/// SHA-256 object identifier.
+pub const OID_SHA256: &[u32] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+
+/// Decode lower or upper case hex.
+pub fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Encode as unpadded base64url.
+pub fn base64url_encode(data: &[u8]) -> String {
+    tyst::encdec::base64::encode(data)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+/// Decode unpadded base64url.
+pub fn base64url_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let mut encoded = encoded.replace('-', "+").replace('_', "/");
+    while encoded.len() % 4 != 0 {
+        encoded.push('=');
+    }
+    tyst::encdec::base64::decode(&encoded).map_err(|e| format!("Malformed base64url: {e:?}"))
+}
+
+/// Return the hash of `data` or `None` if the digest algorithm is unknown.
+pub fn digest(digest_algorithm_oid: &[u32], data: &[u8]) -> Option<Vec<u8>> {
+    Tyst::instance()
+        .digests()
+        .by_oid(&tyst::encdec::oid::as_string(digest_algorithm_oid))
+        .map(|mut digest| digest.hash(data))
+}
+
+/// Return the size of a hash value in bytes or `None` if the digest algorithm
+/// is unknown.
+pub fn digest_size(digest_algorithm_oid: &[u32]) -> Option<usize> {
+    Tyst::instance()
+        .digests()
+        .by_oid(&tyst::encdec::oid::as_string(digest_algorithm_oid))
+        .map(|digest| digest.get_digest_size_bits() / 8)
+}
+
+/// Return the HTTP client used for fetching the JWKS and exporting traces.
+pub fn http_agent() -> &'static ureq::Agent {
+    static HTTP_AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    HTTP_AGENT.get_or_init(|| {
+        ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tyst::encdec::hex::ToHex;
+
+    #[test]
+    fn hex_round_trip() {
+        let data = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(decode_hex(&data.to_hex()), Some(data));
+        assert_eq!(decode_hex("00aBcD"), Some(vec![0x00, 0xab, 0xcd]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+    }
+
+    #[test]
+    fn malformed_hex_is_rejected() {
+        for value in ["0", "0g", "+f", "é", " 00"] {
+            assert_eq!(decode_hex(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn base64url_round_trip() {
+        for len in 0..8 {
+            let data = (0..len).map(|i| 0xf8 | i).collect::<Vec<u8>>();
+            let encoded = base64url_encode(&data);
+            assert!(!encoded.contains(['=', '+', '/']));
+            assert_eq!(base64url_decode(&encoded), Ok(data));
+        }
+    }
+
+    #[test]
+    fn sha256_known_answer() {
+        assert_eq!(digest_size(OID_SHA256), Some(32));
+        assert_eq!(
+            digest(OID_SHA256, b"abc").unwrap().to_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(digest(&[1, 2, 3], b"abc"), None);
+        assert_eq!(digest_size(&[1, 2, 3]), None);
+    }
+}
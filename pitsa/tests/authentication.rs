@@ -0,0 +1,129 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Requests without a bearer token to every `/api/v1` resource when bearer
+//! authentication is configured.
+
+use pitsa::TimeStamper;
+use pitsa::conf::AppConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tyst_api_rest_health::AppHealth;
+
+/// Resources that are expected to be available without authentication.
+const ANONYMOUS_PATHS: [&str; 9] = [
+    "/api/v1/openapi.json",
+    "/api/v1/status",
+    "/api/v1/tsa/certs",
+    "/api/v1/tsa/info",
+    "/api/v1/transparency/sth",
+    "/api/v1/transparency/proof",
+    "/api/v1/transparency/consistency",
+    "/api/v1/transparency/entries",
+    "/api/v1/tsp/verify",
+];
+
+/// Health that never fails, since only authentication is tested.
+struct AlwaysHealthy;
+
+impl AppHealth for AlwaysHealthy {
+    fn is_health_started(&self) -> bool {
+        true
+    }
+    fn is_health_ready(&self) -> bool {
+        true
+    }
+    fn is_health_live(&self) -> bool {
+        true
+    }
+}
+
+/// Return the method and path of every `/api/v1` operation in the OpenAPI
+/// document with path parameters filled in.
+fn api_operations() -> Vec<(String, String)> {
+    let openapi =
+        serde_json::from_str::<serde_json::Value>(&pitsa::rest_api::openapi_as_string()).unwrap();
+    let mut operations = vec![("get".to_string(), "/api/v1/openapi.json".to_string())];
+    for (path, methods) in openapi["paths"].as_object().unwrap() {
+        if !path.starts_with("/api/v1/") {
+            continue;
+        }
+        let path = path.replace("{serial}", "00");
+        for method in methods.as_object().unwrap().keys() {
+            operations.push((method.to_owned(), path.to_owned()));
+        }
+    }
+    operations
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_requires_bearer_token_except_anonymous_resources() {
+    let api_keys_path =
+        std::env::temp_dir().join(format!("pitsa-api-keys-{}.json", std::process::id()));
+    std::fs::write(
+        &api_keys_path,
+        r#"{"keys":[{"identity":"billing","sha256":"5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"}]}"#,
+    )
+    .unwrap();
+    // SAFETY: No other thread reads the environment while it is modified.
+    unsafe {
+        std::env::set_var("PITSA_AUTH_APIKEYS", api_keys_path.to_str().unwrap());
+    }
+    let app_config = Arc::new(AppConfig::new("pitsa"));
+    let app = TimeStamper::new(&app_config).await.unwrap();
+    let app_health: Arc<dyn AppHealth> = Arc::new(AlwaysHealthy);
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    tokio::spawn(async move {
+        pitsa::rest_api::run_http_server(1, "127.0.0.1", port, None, &app_health, &app)
+            .await
+            .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let operations = api_operations();
+    assert!(operations.len() > ANONYMOUS_PATHS.len());
+    let statuses = tokio::task::spawn_blocking(move || {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        operations
+            .into_iter()
+            .map(|(method, path)| {
+                let url = format!("http://127.0.0.1:{port}{path}");
+                let response = match method.as_str() {
+                    "get" => agent.get(&url).call(),
+                    _ => agent.post(&url).send_empty(),
+                };
+                (path, response.unwrap().status().as_u16())
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap();
+    let _ = std::fs::remove_file(&api_keys_path);
+    for (path, status) in statuses {
+        if ANONYMOUS_PATHS.contains(&path.as_str()) {
+            assert_ne!(status, 401, "{path} should be anonymous");
+        } else {
+            assert_eq!(status, 401, "{path} should require authentication");
+        }
+    }
+}
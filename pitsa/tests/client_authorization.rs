@@ -0,0 +1,136 @@
+/*
+    Copyright 2025 MydriaTech AB
+
+    Licensed under the Apache License 2.0 with Free world makers exception
+    1.0.0 (the "License"); you may not use this file except in compliance with
+    the License. You should have obtained a copy of the License with the source
+    or binary distribution in file named
+
+        LICENSE-Apache-2.0-with-FWM-Exception-1.0.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Requests from an authenticated client without a client rule to the
+//! resources that issue time-stamps in other formats than RFC 3161 or hash
+//! uploaded content.
+
+use pitsa::TimeStamper;
+use pitsa::conf::AppConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tyst_api_rest_health::AppHealth;
+
+/// Base64 encoded Authenticode `TimeStampRequest` over the content "sig".
+const AUTHENTICODE_REQUEST: &str = "MCAGCisGAQQBgjcDAgEwEgYJKoZIhvcNAQcBoAUEA3NpZw==";
+
+/// Hex encoded DVCS `ccpd` request over an all-zero SHA-256 message imprint.
+const DVCS_REQUEST: &str = "303630030a0104302f300b060960864801650304020104200000000000000000000000000000000000000000000000000000000000000000";
+
+/// Health that never fails, since only authorization is tested.
+struct AlwaysHealthy;
+
+impl AppHealth for AlwaysHealthy {
+    fn is_health_started(&self) -> bool {
+        true
+    }
+    fn is_health_ready(&self) -> bool {
+        true
+    }
+    fn is_health_live(&self) -> bool {
+        true
+    }
+}
+
+fn from_hex(encoded: &str) -> Vec<u8> {
+    (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_without_rule_is_refused() {
+    let api_keys_path =
+        std::env::temp_dir().join(format!("pitsa-client-keys-{}.json", std::process::id()));
+    std::fs::write(
+        &api_keys_path,
+        r#"{"keys":[{"identity":"billing","sha256":"5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"}]}"#,
+    )
+    .unwrap();
+    let client_rules_path =
+        std::env::temp_dir().join(format!("pitsa-client-rules-{}.json", std::process::id()));
+    std::fs::write(
+        &client_rules_path,
+        r#"{"clients":[{"identity":"archive"}]}"#,
+    )
+    .unwrap();
+    // SAFETY: No other thread reads the environment while it is modified.
+    unsafe {
+        std::env::set_var("PITSA_AUTH_APIKEYS", api_keys_path.to_str().unwrap());
+        std::env::set_var("PITSA_API_CLIENTS", client_rules_path.to_str().unwrap());
+    }
+    let app_config = Arc::new(AppConfig::new("pitsa"));
+    let app = TimeStamper::new(&app_config).await.unwrap();
+    let app_health: Arc<dyn AppHealth> = Arc::new(AlwaysHealthy);
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    tokio::spawn(async move {
+        pitsa::rest_api::run_http_server(1, "127.0.0.1", port, None, &app_health, &app)
+            .await
+            .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let requests = vec![
+        (
+            "/api/v1/authenticode",
+            "application/octet-stream",
+            AUTHENTICODE_REQUEST.as_bytes().to_vec(),
+        ),
+        ("/api/v1/dvcs", "application/dvcs", from_hex(DVCS_REQUEST)),
+        // Refused before the upload is hashed
+        (
+            "/api/v1/tsp/hash?hashAlgorithm=SHA256",
+            "application/octet-stream",
+            b"content".to_vec(),
+        ),
+    ];
+    let responses = tokio::task::spawn_blocking(move || {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        requests
+            .into_iter()
+            .map(|(path, content_type, body)| {
+                let mut response = agent
+                    .post(format!("http://127.0.0.1:{port}{path}"))
+                    .header("Authorization", "Bearer password")
+                    .header("Content-Type", content_type)
+                    .send(&body[..])
+                    .unwrap();
+                let status = response.status().as_u16();
+                let body = response.body_mut().read_to_string().unwrap();
+                (path, status, body)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap();
+    let _ = std::fs::remove_file(&api_keys_path);
+    let _ = std::fs::remove_file(&client_rules_path);
+    for (path, status, body) in responses {
+        assert_eq!(status, 400, "{path} should refuse the client");
+        assert!(
+            body.contains("'billing' is not authorized"),
+            "{path} should refuse the client without a rule: {body}"
+        );
+    }
+}